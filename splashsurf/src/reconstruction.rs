@@ -4,13 +4,18 @@ use clap::value_parser;
 use indicatif::{ProgressBar, ProgressStyle};
 use log::info;
 use rayon::prelude::*;
-use splashsurf_lib::mesh::{AttributeData, Mesh3d, MeshAttribute, MeshWithData};
-use splashsurf_lib::nalgebra::{Unit, Vector3};
-use splashsurf_lib::sph_interpolation::SphInterpolator;
-use splashsurf_lib::{profile, Aabb3d, Index, Real};
+use splashsurf_lib::mesh::MeshWithData;
+use splashsurf_lib::postprocessing::{
+    AdvectedUvParameters, PostprocessingParameters, RemeshingProjection, UvGeneration,
+};
+use splashsurf_lib::sph_interpolation::{CategoricalInterpolation, InterpolationMode};
+use splashsurf_lib::{profile, Index, Real, StageTiming};
 use std::borrow::Cow;
 use std::convert::TryFrom;
-use std::path::PathBuf;
+use std::fs;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use arguments::*;
 
@@ -131,6 +136,26 @@ pub struct ReconstructSubcommandArgs {
     /// Each subdomain will be a cube consisting of this number of MC cube cells along each coordinate axis
     #[arg(help_heading = ARGS_OCTREE, long, default_value="64")]
     pub subdomain_cubes: u32,
+    /// Enable processing each subdomain in single precision coordinates relative to its own origin, only the global grid and the stitching use the precision of the input (useful for double precision reconstructions far away from the origin, has no effect without double precision)
+    #[arg(
+        help_heading = ARGS_OCTREE,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub subdomain_local_f32: Switch,
+    /// Enable selecting the subdomain size (overrides '--subdomain-cubes') and the sparse subdomain limit automatically based on an estimate of the reconstruction cost of the subdomains
+    #[arg(
+        help_heading = ARGS_OCTREE,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub subdomain_auto_tuning: Switch,
 
     /// Enable omputing surface normals at the mesh vertices and write them to the output file
     #[arg(
@@ -165,9 +190,49 @@ pub struct ReconstructSubcommandArgs {
         require_equals = true
     )]
     pub output_raw_normals: Switch,
+    /// Enable computing the mean, Gaussian and principal curvatures of the mesh and write them as point attributes to the output file
+    #[arg(
+        help_heading = ARGS_INTERP,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub curvature: Switch,
+    /// Enable additionally computing the mean curvature using SPH interpolation if curvature computation is enabled
+    #[arg(
+        help_heading = ARGS_INTERP,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub sph_curvature: Switch,
     /// List of point attribute field names from the input file that should be interpolated to the reconstructed surface. Currently this is only supported for VTK and VTU input files.
     #[arg(help_heading = ARGS_INTERP, long)]
     pub interpolate_attributes: Vec<String>,
+    /// Interpolation scheme for SPH normals and attributes: plain SPH sum, Shepard-normalized SPH sum or moving least squares with a linear basis (reduces the bias at the free surface)
+    #[arg(
+        help_heading = ARGS_INTERP,
+        long,
+        default_value = "shepard",
+        value_name = "sph|shepard|mls",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub interpolation_mode: InterpolationModeArg,
+    /// Interpolation scheme for integer attributes like IDs and labels: value of the nearest particle or SPH-weighted majority vote
+    #[arg(
+        help_heading = ARGS_INTERP,
+        long,
+        default_value = "majority-vote",
+        value_name = "nearest|majority-vote",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub integer_interpolation: IntegerInterpolationArg,
 
     /// Enable MC specific mesh decimation/simplification which removes bad quality triangles typically generated by MC
     #[arg(
@@ -189,6 +254,18 @@ pub struct ReconstructSubcommandArgs {
         require_equals = true
     )]
     pub decimate_barnacles: Switch,
+    /// Target number of triangles for the quadric error metric (QEM) decimation of the final mesh (enables QEM decimation)
+    #[arg(help_heading = ARGS_POSTPROC, long)]
+    pub qem_target_triangles: Option<usize>,
+    /// Maximum quadric error (sum of squared distances to the original triangle planes) of a single edge collapse in the QEM decimation (enables QEM decimation)
+    #[arg(help_heading = ARGS_POSTPROC, long)]
+    pub qem_max_error: Option<f64>,
+    /// Maximum allowed angle (in degrees) by which a triangle normal may rotate due to a single edge collapse in the QEM decimation
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "45")]
+    pub qem_max_normal_angle: f64,
+    /// Maximum allowed factor by which the aspect ratio of a triangle may grow due to a single edge collapse in the QEM decimation
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "4.0")]
+    pub qem_max_aspect_ratio_change: f64,
     /// Enable keeping vertices without connectivity during decimation instead of filtering them out (faster and helps with debugging)
     #[arg(
         help_heading = ARGS_POSTPROC,
@@ -202,6 +279,33 @@ pub struct ReconstructSubcommandArgs {
     /// Number of smoothing iterations to run on the reconstructed mesh
     #[arg(help_heading = ARGS_POSTPROC, long)]
     pub mesh_smoothing_iters: Option<usize>,
+    /// Smoothing scheme for the mesh smoothing: uniform Laplacian, cotangent-weighted Laplacian, Taubin λ/μ or Humphrey's Classes (HC) smoothing (the latter two avoid shrinkage)
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "laplacian",
+        value_name = "laplacian|cotangent|taubin|hc",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub mesh_smoothing_method: MeshSmoothingMethodArg,
+    /// Factor of the shrinking step of Taubin smoothing (only used with mesh-smoothing-method=taubin)
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "0.5")]
+    pub taubin_lambda: f64,
+    /// Factor of the inflating step of Taubin smoothing, has to be negative with a larger magnitude than lambda (only used with mesh-smoothing-method=taubin)
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "-0.53",
+        allow_negative_numbers = true
+    )]
+    pub taubin_mu: f64,
+    /// Weight of the original vertex positions for the push-back of HC smoothing (only used with mesh-smoothing-method=hc)
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "0.1")]
+    pub hc_alpha: f64,
+    /// Weight of the vertex itself (in contrast to its neighbors) for the push-back of HC smoothing (only used with mesh-smoothing-method=hc)
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "0.6")]
+    pub hc_beta: f64,
     /// Enable feature weights for mesh smoothing if mesh smoothing enabled. Preserves isolated particles even under strong smoothing.
     #[arg(
         help_heading = ARGS_POSTPROC,
@@ -212,6 +316,48 @@ pub struct ReconstructSubcommandArgs {
         require_equals = true
     )]
     pub mesh_smoothing_weights: Switch,
+    /// Enable restoring the volume of the mesh before smoothing by displacing the vertices along their normals (weighted by the smoothing weights if enabled)
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub mesh_smoothing_volume_correction: Switch,
+    /// Relative tolerance of the mesh volume after the volume correction
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "1e-4")]
+    pub volume_correction_tolerance: f64,
+    /// Target edge length of the isotropic remeshing applied after the mesh smoothing in multiplies of the particle radius (enables remeshing)
+    #[arg(help_heading = ARGS_POSTPROC, long)]
+    pub remesh_edge_length: Option<f64>,
+    /// Number of iterations of the isotropic remeshing
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "5")]
+    pub remesh_iters: usize,
+    /// Surface onto which the vertices are projected during the isotropic remeshing: no projection, the mesh before remeshing or the SPH level set of the particles
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "mesh",
+        value_name = "none|mesh|level-set",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub remesh_projection: RemeshingProjectionArg,
+    /// Number of Loop subdivision steps applied after the mesh smoothing and remeshing, every step quadruples the number of triangles (enables subdivision)
+    #[arg(help_heading = ARGS_POSTPROC, long)]
+    pub subdivision_iters: Option<usize>,
+    /// Enable projecting all vertices onto the SPH level set of the particles after every subdivision step
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub subdivision_level_set_projection: Switch,
     /// Normalization value from weighted number of neighbors to mesh smoothing weights
     #[arg(help_heading = ARGS_POSTPROC, long, default_value = "13.0")]
     pub mesh_smoothing_weights_normalization: f64,
@@ -246,6 +392,33 @@ pub struct ReconstructSubcommandArgs {
     #[arg(help_heading = ARGS_POSTPROC, long, default_value = "135")]
     pub quad_max_interior_angle: f64,
 
+    /// Enable writing the index of the connected component of each vertex as a point attribute ("component_id") to the output mesh file
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub output_component_ids: Switch,
+    /// Connected components of the mesh with an enclosed volume below this threshold are removed, in multiplies of the particle volume (4/3 pi r^3)
+    #[arg(help_heading = ARGS_POSTPROC, long)]
+    pub min_component_volume: Option<f64>,
+    /// Connected components of the mesh with fewer triangles than this threshold are removed
+    #[arg(help_heading = ARGS_POSTPROC, long)]
+    pub min_component_triangles: Option<usize>,
+    /// Enable writing the removed small components as particles (located at their centroids with "volume", "radius" and "area" attributes) to a separate VTK file with the prefix "droplets_"
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub small_components_to_particles: Switch,
+
     /// Lower corner of the bounding-box for the surface mesh, triangles completely outside are removed (requires mesh-aabb-max to be specified)
     #[arg(
         help_heading = ARGS_POSTPROC,
@@ -276,6 +449,64 @@ pub struct ReconstructSubcommandArgs {
         require_equals = true
     )]
     pub mesh_aabb_clamp_verts: Switch,
+    /// Enable clipping the mesh at the faces of the specified mesh AABB and closing the cuts with caps to keep the mesh watertight (instead of removing triangles outside and clamping, only has an effect if mesh-aabb-min/max are specified)
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub mesh_aabb_clip: Switch,
+
+    /// Enable repairing the final mesh (removal of duplicate, degenerate and non-manifold triangles, splitting of non-manifold vertices and hole filling), fails if the result is not a closed manifold mesh (checked on the triangle mesh before an optional quad conversion)
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub mesh_repair: Switch,
+
+    /// Strategy to generate texture coordinates written as point attribute ("uv") to the output mesh: triplanar projection or triplanar projection advected with the particle velocity (additionally writes "uv_1" for the second advected layer, the blend weight of the layers only depends on the frame and is logged)
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "none",
+        value_name = "none|triplanar|advected",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub uv_generation: UvGenerationArg,
+    /// Size of one texture tile of the generated texture coordinates in multiplies of the particle radius
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "20.0")]
+    pub uv_tile_size: f64,
+    /// Name of the particle attribute with the velocities for advected texture coordinates (interpolated to the surface automatically)
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "velocity")]
+    pub uv_velocity_attribute: String,
+    /// Simulation time between two consecutive files of the input sequence for advected texture coordinates (the time of a frame is its index multiplied by this value)
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "0.01")]
+    pub uv_frame_time: f64,
+    /// Number of frames after which each of the two layers of the advected texture coordinates is reset
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "50")]
+    pub uv_reset_frames: usize,
+
+    /// Enable computing and writing the originating subdomain and the nearest contributing particles of each vertex as point attributes (requires the subdomain grid)
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub output_vertex_provenance: Switch,
+    /// Number of nearest contributing particles to write per vertex if vertex provenance output is enabled
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "4")]
+    pub vertex_provenance_particles: usize,
 
     /// Enable writing the raw reconstructed mesh before applying any post-processing steps
     #[arg(
@@ -288,7 +519,7 @@ pub struct ReconstructSubcommandArgs {
     )]
     pub output_raw_mesh: Switch,
 
-    /// Enable checking the final mesh for holes and non-manifold edges and vertices
+    /// Enable checking the final mesh for holes and non-manifold edges and vertices (the triangle mesh is checked before an optional quad conversion)
     #[arg(
         help_heading = ARGS_DEBUG,
         long,
@@ -328,6 +559,16 @@ pub struct ReconstructSubcommandArgs {
         require_equals = true
     )]
     pub check_mesh_debug: Switch,
+    /// Enable writing the reconstruction statistics and per-stage timings of each frame to a JSON file next to the output mesh ("<output file stem>.stats.json")
+    #[arg(
+        help_heading = ARGS_DEBUG,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub output_stats: Switch,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
@@ -345,6 +586,70 @@ impl Switch {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum InterpolationModeArg {
+    Sph,
+    Shepard,
+    Mls,
+}
+
+impl From<InterpolationModeArg> for InterpolationMode {
+    fn from(mode: InterpolationModeArg) -> Self {
+        match mode {
+            InterpolationModeArg::Sph => InterpolationMode::Sph,
+            InterpolationModeArg::Shepard => InterpolationMode::Shepard,
+            InterpolationModeArg::Mls => InterpolationMode::MovingLeastSquares,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum IntegerInterpolationArg {
+    Nearest,
+    MajorityVote,
+}
+
+impl From<IntegerInterpolationArg> for CategoricalInterpolation {
+    fn from(scheme: IntegerInterpolationArg) -> Self {
+        match scheme {
+            IntegerInterpolationArg::Nearest => CategoricalInterpolation::Nearest,
+            IntegerInterpolationArg::MajorityVote => CategoricalInterpolation::MajorityVote,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum MeshSmoothingMethodArg {
+    Laplacian,
+    Cotangent,
+    Taubin,
+    Hc,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum RemeshingProjectionArg {
+    None,
+    Mesh,
+    LevelSet,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum UvGenerationArg {
+    None,
+    Triplanar,
+    Advected,
+}
+
+impl From<RemeshingProjectionArg> for RemeshingProjection {
+    fn from(projection: RemeshingProjectionArg) -> Self {
+        match projection {
+            RemeshingProjectionArg::None => RemeshingProjection::None,
+            RemeshingProjectionArg::Mesh => RemeshingProjection::InputMesh,
+            RemeshingProjectionArg::LevelSet => RemeshingProjection::LevelSet,
+        }
+    }
+}
+
 /// Executes the `reconstruct` subcommand
 pub fn reconstruct_subcommand(cmd_args: &ReconstructSubcommandArgs) -> Result<(), anyhow::Error> {
     profile!("reconstruct subcommand");
@@ -408,12 +713,17 @@ pub fn reconstruct_subcommand(cmd_args: &ReconstructSubcommandArgs) -> Result<()
 
 /// Conversion and validation of command line arguments
 mod arguments {
-    use super::ReconstructSubcommandArgs;
+    use super::{MeshSmoothingMethodArg, ReconstructSubcommandArgs, UvGenerationArg};
     use crate::io;
     use anyhow::{anyhow, Context};
-    use log::info;
+    use log::{info, warn};
     use regex::{escape, Regex};
     use splashsurf_lib::nalgebra::Vector3;
+    use splashsurf_lib::postprocessing::{
+        AdvectedUvParameters, IsotropicRemeshingParameters, LoopSubdivisionParameters,
+        MeshRepairParameters, MeshSmoothingMethod, PostprocessingParameters,
+        QuadricDecimationParameters, UvGeneration,
+    };
     use splashsurf_lib::Aabb3d;
     use std::convert::TryFrom;
     use std::fs;
@@ -421,35 +731,29 @@ mod arguments {
     use std::str::FromStr;
     use walkdir::WalkDir;
 
+    /// Post-processing arguments that are handled by the CLI itself and not by the library pipeline
     pub struct ReconstructionRunnerPostprocessingArgs {
         pub check_mesh_closed: bool,
         pub check_mesh_manifold: bool,
         pub check_mesh_debug: bool,
-        pub mesh_cleanup: bool,
-        pub decimate_barnacles: bool,
-        pub keep_vertices: bool,
-        pub compute_normals: bool,
-        pub sph_normals: bool,
-        pub normals_smoothing_iters: Option<usize>,
+        pub mesh_repair: bool,
         pub interpolate_attributes: Vec<String>,
-        pub mesh_smoothing_iters: Option<usize>,
-        pub mesh_smoothing_weights: bool,
-        pub mesh_smoothing_weights_normalization: f64,
+        pub uv_frame_time: f64,
         pub generate_quads: bool,
         pub quad_max_edge_diag_ratio: f64,
         pub quad_max_normal_angle: f64,
         pub quad_max_interior_angle: f64,
-        pub output_mesh_smoothing_weights: bool,
-        pub output_raw_normals: bool,
+        pub small_components_to_particles: bool,
         pub output_raw_mesh: bool,
-        pub mesh_aabb: Option<Aabb3d<f64>>,
-        pub mesh_aabb_clamp_vertices: bool,
+        pub output_stats: bool,
     }
 
     /// All arguments that can be supplied to the surface reconstruction tool converted to useful types
     pub struct ReconstructionRunnerArgs {
         /// Parameters passed directly to the surface reconstruction
         pub params: splashsurf_lib::Parameters<f64>,
+        /// Parameters passed directly to the post-processing pipeline of the library
+        pub postprocessing_params: PostprocessingParameters<f64>,
         pub use_double_precision: bool,
         pub io_params: io::FormatParameters,
         pub postprocessing: ReconstructionRunnerPostprocessingArgs,
@@ -519,6 +823,8 @@ mod arguments {
                 Some(splashsurf_lib::SpatialDecomposition::UniformGrid(
                     splashsurf_lib::GridDecompositionParameters {
                         subdomain_num_cubes_per_dim: args.subdomain_cubes,
+                        local_f32_coordinates: args.subdomain_local_f32.into_bool(),
+                        auto_tuning: args.subdomain_auto_tuning.into_bool(),
                         ..Default::default()
                     },
                 ))
@@ -537,6 +843,13 @@ mod arguments {
                 enable_multi_threading: args.parallelize_over_particles.into_bool(),
                 spatial_decomposition,
                 global_neighborhood_list: args.mesh_smoothing_weights.into_bool(),
+                vertex_provenance: args.output_vertex_provenance.into_bool().then(|| {
+                    splashsurf_lib::VertexProvenanceParameters {
+                        num_particles: args.vertex_provenance_particles,
+                    }
+                }),
+                tabulated_kernel: None,
+                particle_reordering: None,
             };
 
             // Optionally initialize thread pool
@@ -544,35 +857,145 @@ mod arguments {
                 splashsurf_lib::initialize_thread_pool(num_threads)?;
             }
 
-            let postprocessing = ReconstructionRunnerPostprocessingArgs {
-                check_mesh_closed: args.check_mesh.into_bool()
-                    || args.check_mesh_closed.into_bool(),
-                check_mesh_manifold: args.check_mesh.into_bool()
-                    || args.check_mesh_manifold.into_bool(),
-                check_mesh_debug: args.check_mesh_debug.into_bool(),
+            let quadric_decimation = (args.qem_target_triangles.is_some()
+                || args.qem_max_error.is_some())
+            .then(|| QuadricDecimationParameters {
+                target_triangle_count: args.qem_target_triangles,
+                max_error: args.qem_max_error,
+                max_normal_change: args.qem_max_normal_angle.to_radians(),
+                max_aspect_ratio_change: args.qem_max_aspect_ratio_change,
+            });
+
+            if args.subdomain_local_f32.into_bool() && !args.double_precision.into_bool() {
+                warn!("Local f32 subdomain coordinates have no effect for single precision reconstructions (enable double precision to use them)");
+            }
+
+            if args.mesh_smoothing_method == MeshSmoothingMethodArg::Taubin
+                && !(0.0 < args.taubin_lambda && args.taubin_lambda < -args.taubin_mu)
+            {
+                return Err(anyhow!(
+                    "Taubin smoothing requires 0 < lambda < -mu (got lambda: {}, mu: {})",
+                    args.taubin_lambda,
+                    args.taubin_mu
+                ));
+            }
+
+            let mesh_smoothing_method = match args.mesh_smoothing_method {
+                MeshSmoothingMethodArg::Laplacian => MeshSmoothingMethod::Laplacian,
+                MeshSmoothingMethodArg::Cotangent => MeshSmoothingMethod::Cotangent,
+                MeshSmoothingMethodArg::Taubin => MeshSmoothingMethod::Taubin {
+                    lambda: args.taubin_lambda,
+                    mu: args.taubin_mu,
+                },
+                MeshSmoothingMethodArg::Hc => MeshSmoothingMethod::Hc {
+                    alpha: args.hc_alpha,
+                    beta: args.hc_beta,
+                },
+            };
+
+            let remeshing =
+                args.remesh_edge_length
+                    .map(|edge_length| IsotropicRemeshingParameters {
+                        iterations: args.remesh_iters,
+                        projection: args.remesh_projection.into(),
+                        ..IsotropicRemeshingParameters::new(args.particle_radius * edge_length)
+                    });
+            let subdivision = args
+                .subdivision_iters
+                .map(|iterations| LoopSubdivisionParameters {
+                    iterations,
+                    level_set_projection: args.subdivision_level_set_projection.into_bool(),
+                });
+
+            if args.uv_generation == UvGenerationArg::Advected
+                && (args.uv_reset_frames == 0 || args.uv_frame_time <= 0.0)
+            {
+                return Err(anyhow!(
+                    "The reset period of advected texture coordinates (uv-reset-frames times uv-frame-time) has to be positive"
+                ));
+            }
+
+            let uv_scale = 1.0 / (args.uv_tile_size * args.particle_radius);
+            let uv_generation = match args.uv_generation {
+                UvGenerationArg::None => None,
+                UvGenerationArg::Triplanar => Some(UvGeneration::Triplanar { scale: uv_scale }),
+                // The time of the frame is set per input file
+                UvGenerationArg::Advected => Some(UvGeneration::Advected(AdvectedUvParameters {
+                    scale: uv_scale,
+                    time: 0.0,
+                    reset_period: args.uv_reset_frames as f64 * args.uv_frame_time,
+                    velocity_attribute: args.uv_velocity_attribute.clone(),
+                })),
+            };
+
+            // The velocities for advected texture coordinates have to be interpolated to the surface
+            let mut interpolate_attributes = args.interpolate_attributes.clone();
+            if args.uv_generation == UvGenerationArg::Advected
+                && !interpolate_attributes.contains(&args.uv_velocity_attribute)
+            {
+                interpolate_attributes.push(args.uv_velocity_attribute.clone());
+            }
+
+            // Assemble all parameters for the post-processing pipeline
+            let postprocessing_params = PostprocessingParameters {
                 mesh_cleanup: args.mesh_cleanup.into_bool(),
                 decimate_barnacles: args.decimate_barnacles.into_bool(),
+                quadric_decimation,
                 keep_vertices: args.keep_verts.into_bool(),
                 compute_normals: args.normals.into_bool(),
                 sph_normals: args.sph_normals.into_bool(),
+                compute_curvature: args.curvature.into_bool(),
+                sph_curvature: args.sph_curvature.into_bool(),
                 normals_smoothing_iters: args.normals_smoothing_iters,
-                interpolate_attributes: args.interpolate_attributes.clone(),
+                interpolation_mode: args.interpolation_mode.into(),
+                integer_interpolation: args.integer_interpolation.into(),
                 mesh_smoothing_iters: args.mesh_smoothing_iters,
+                mesh_smoothing_method,
+                mesh_smoothing_volume_correction: args.mesh_smoothing_volume_correction.into_bool(),
+                volume_correction_tolerance: args.volume_correction_tolerance,
+                remeshing,
+                subdivision,
                 mesh_smoothing_weights: args.mesh_smoothing_weights.into_bool(),
                 mesh_smoothing_weights_normalization: args.mesh_smoothing_weights_normalization,
+                output_mesh_smoothing_weights: args.output_smoothing_weights.into_bool(),
+                output_raw_normals: args.output_raw_normals.into_bool(),
+                mesh_aabb,
+                mesh_aabb_clamp_vertices: args.mesh_aabb_clamp_verts.into_bool(),
+                mesh_aabb_clip: args.mesh_aabb_clip.into_bool(),
+                mesh_repair: args
+                    .mesh_repair
+                    .into_bool()
+                    .then(MeshRepairParameters::default),
+                uv_generation,
+                output_vertex_provenance: args.output_vertex_provenance.into_bool(),
+                min_component_volume: args
+                    .min_component_volume
+                    .map(|v| v * 4.0 / 3.0 * std::f64::consts::PI * args.particle_radius.powi(3)),
+                min_component_triangles: args.min_component_triangles,
+                output_component_ids: args.output_component_ids.into_bool(),
+            };
+
+            let postprocessing = ReconstructionRunnerPostprocessingArgs {
+                check_mesh_closed: args.check_mesh.into_bool()
+                    || args.check_mesh_closed.into_bool(),
+                check_mesh_manifold: args.check_mesh.into_bool()
+                    || args.check_mesh_manifold.into_bool(),
+                check_mesh_debug: args.check_mesh_debug.into_bool(),
+                mesh_repair: args.mesh_repair.into_bool(),
+                interpolate_attributes,
+                uv_frame_time: args.uv_frame_time,
                 generate_quads: args.generate_quads.into_bool(),
                 quad_max_edge_diag_ratio: args.quad_max_edge_diag_ratio,
                 quad_max_normal_angle: args.quad_max_normal_angle,
                 quad_max_interior_angle: args.quad_max_interior_angle,
-                output_mesh_smoothing_weights: args.output_smoothing_weights.into_bool(),
-                output_raw_normals: args.output_raw_normals.into_bool(),
+                small_components_to_particles: args.small_components_to_particles.into_bool(),
                 output_raw_mesh: args.output_raw_mesh.into_bool(),
-                mesh_aabb,
-                mesh_aabb_clamp_vertices: args.mesh_aabb_clamp_verts.into_bool(),
+                output_stats: args.output_stats.into_bool(),
             };
 
             Ok(ReconstructionRunnerArgs {
                 params,
+                postprocessing_params,
                 use_double_precision: args.double_precision.into_bool(),
                 io_params: io::FormatParameters::default(),
                 postprocessing,
@@ -716,7 +1139,11 @@ mod arguments {
                         let output_filename_i = output_pattern.replace("{}", index);
                         let output_file_i = output_dir.join(output_filename_i);
 
-                        paths.push(ReconstructionRunnerPaths::new(input_file_i, output_file_i));
+                        paths.push(ReconstructionRunnerPaths::new(
+                            input_file_i,
+                            output_file_i,
+                            Some(index_usize),
+                        ));
                     }
                 }
 
@@ -739,6 +1166,7 @@ mod arguments {
                     ReconstructionRunnerPaths::new(
                         self.input_file.clone(),
                         self.output_file.clone(),
+                        None,
                     );
                     1
                 ]
@@ -845,13 +1273,16 @@ mod arguments {
     pub(crate) struct ReconstructionRunnerPaths {
         pub input_file: PathBuf,
         pub output_file: PathBuf,
+        /// Index of the input file in the input sequence (`None` if no sequence is processed)
+        pub frame_index: Option<usize>,
     }
 
     impl ReconstructionRunnerPaths {
-        fn new(input_file: PathBuf, output_file: PathBuf) -> Self {
+        fn new(input_file: PathBuf, output_file: PathBuf, frame_index: Option<usize>) -> Self {
             ReconstructionRunnerPaths {
                 input_file,
                 output_file,
+                frame_index,
             }
         }
     }
@@ -867,6 +1298,7 @@ pub(crate) fn reconstruction_pipeline(
         reconstruction_pipeline_generic::<i64, f64>(
             paths,
             &args.params,
+            &args.postprocessing_params,
             &args.io_params,
             &args.postprocessing,
         )?;
@@ -877,6 +1309,9 @@ pub(crate) fn reconstruction_pipeline(
             &args.params.try_convert().ok_or(anyhow!(
                 "Unable to convert surface reconstruction parameters from f64 to f32."
             ))?,
+            &args.postprocessing_params.try_convert().ok_or(anyhow!(
+                "Unable to convert post-processing parameters from f64 to f32."
+            ))?,
             &args.io_params,
             &args.postprocessing,
         )?;
@@ -889,12 +1324,15 @@ pub(crate) fn reconstruction_pipeline(
 pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
     paths: &ReconstructionRunnerPaths,
     params: &splashsurf_lib::Parameters<R>,
+    postprocessing_params: &PostprocessingParameters<R>,
     io_params: &io::FormatParameters,
     postprocessing: &ReconstructionRunnerPostprocessingArgs,
 ) -> Result<(), anyhow::Error> {
     profile!("surface reconstruction");
+    let mut pipeline_timings = Vec::new();
 
     // Load particle positions and attributes to interpolate
+    let stage_start = Instant::now();
     let (particle_positions, attributes) = io::read_particle_positions_with_attributes(
         &paths.input_file,
         &postprocessing.interpolate_attributes,
@@ -906,17 +1344,23 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
             paths.input_file.display()
         )
     })?;
+    pipeline_timings.push(("read input", stage_start.elapsed()));
 
     // Perform the surface reconstruction
+    let stage_start = Instant::now();
     let reconstruction =
         splashsurf_lib::reconstruct_surface::<I, R>(particle_positions.as_slice(), params)?;
+    pipeline_timings.push(("reconstruction", stage_start.elapsed()));
 
-    let grid = reconstruction.grid();
-    let mut mesh_with_data = MeshWithData::new(Cow::Borrowed(reconstruction.mesh()));
+    // The post-processing consumes the reconstruction
+    let grid = reconstruction.grid().clone();
+    let reconstruction_stats = reconstruction.stats().clone();
 
     if postprocessing.output_raw_mesh {
         profile!("write surface mesh to file");
 
+        let mesh_with_data = MeshWithData::new(Cow::Borrowed(reconstruction.mesh()));
+
         let output_path = paths
             .output_file
             .parent()
@@ -942,308 +1386,92 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
         })?;
     }
 
-    // Perform post-processing
-    {
-        profile!("postprocessing");
-
-        let mut vertex_connectivity = None;
-
-        if postprocessing.mesh_cleanup {
-            info!("Post-processing: Performing mesh cleanup");
-            let tris_before = mesh_with_data.mesh.triangles.len();
-            let verts_before = mesh_with_data.mesh.vertices.len();
-            vertex_connectivity = Some(splashsurf_lib::postprocessing::marching_cubes_cleanup(
-                mesh_with_data.mesh.to_mut(),
-                reconstruction.grid(),
-                5,
-                postprocessing.keep_vertices,
-            ));
-            let tris_after = mesh_with_data.mesh.triangles.len();
-            let verts_after = mesh_with_data.mesh.vertices.len();
-            info!("Post-processing: Cleanup reduced number of vertices to {:.2}% and number of triangles to {:.2}% of original mesh.", (verts_after as f64 / verts_before as f64) * 100.0, (tris_after as f64 / tris_before as f64) * 100.0)
-        }
-
-        // Decimate mesh if requested
-        if postprocessing.decimate_barnacles {
-            info!("Post-processing: Performing decimation");
-            vertex_connectivity = Some(splashsurf_lib::postprocessing::decimation(
-                mesh_with_data.mesh.to_mut(),
-                postprocessing.keep_vertices,
-            ));
-        }
-
-        // Initialize SPH interpolator if required later
-        let interpolator_required = postprocessing.mesh_smoothing_weights
-            || postprocessing.sph_normals
-            || !attributes.is_empty();
-        let interpolator = if interpolator_required {
-            profile!("initialize interpolator");
-            info!("Post-processing: Initializing interpolator...");
-
-            info!(
-                "Constructing global acceleration structure for SPH interpolation to {} vertices...",
-                mesh_with_data.vertices().len()
-            );
-
-            let particle_rest_density = params.rest_density;
-            let particle_rest_volume = R::from_f64((4.0 / 3.0) * std::f64::consts::PI).unwrap()
-                * params.particle_radius.powi(3);
-            let particle_rest_mass = particle_rest_volume * particle_rest_density;
-
-            let particle_densities = reconstruction
-                .particle_densities()
-                .ok_or_else(|| anyhow::anyhow!("Particle densities were not returned by surface reconstruction but are required for SPH normal computation"))?
-                .as_slice();
-            assert_eq!(
-                particle_positions.len(),
-                particle_densities.len(),
-                "There has to be one density value per particle"
-            );
-
-            Some(SphInterpolator::new(
-                &particle_positions,
-                particle_densities,
-                particle_rest_mass,
-                params.compact_support_radius,
-            ))
-        } else {
-            None
-        };
-
-        // Compute mesh vertex-vertex connectivity map if required later
-        let vertex_connectivity_required = postprocessing.normals_smoothing_iters.is_some()
-            || postprocessing.mesh_smoothing_iters.is_some();
-        if vertex_connectivity.is_none() && vertex_connectivity_required {
-            vertex_connectivity = Some(mesh_with_data.mesh.vertex_vertex_connectivity());
-        }
-
-        // Compute smoothing weights if requested
-        let smoothing_weights = if postprocessing.mesh_smoothing_weights {
-            profile!("compute smoothing weights");
-            info!("Post-processing: Computing smoothing weights...");
-
-            // TODO: Switch between parallel/single threaded
-            // TODO: Re-use data from reconstruction?
-
-            // Global neighborhood search
-            let nl = reconstruction
-                .particle_neighbors()
-                .map(|nl| Cow::Borrowed(nl))
-                .unwrap_or_else(||
-                    {
-                        let search_radius = params.compact_support_radius;
-
-                        let mut domain = Aabb3d::from_points(particle_positions.as_slice());
-                        domain.grow_uniformly(search_radius);
-
-                        let mut nl = Vec::new();
-                        splashsurf_lib::neighborhood_search::neighborhood_search_spatial_hashing_parallel::<I, R>(
-                            &domain,
-                            particle_positions.as_slice(),
-                            search_radius,
-                            &mut nl,
-                        );
-                        assert_eq!(nl.len(), particle_positions.len());
-                        Cow::Owned(nl)
-                    }
-                );
-
-            // Compute weighted neighbor count
-            let squared_r = params.compact_support_radius * params.compact_support_radius;
-            let weighted_ncounts = nl
-                .par_iter()
-                .enumerate()
-                .map(|(i, nl)| {
-                    nl.iter()
-                        .copied()
-                        .map(|j| {
-                            let dist =
-                                (particle_positions[i] - particle_positions[j]).norm_squared();
-                            let weight = R::one() - (dist / squared_r).clamp(R::zero(), R::one());
-                            return weight;
-                        })
-                        .fold(R::zero(), R::add)
-                })
-                .collect::<Vec<_>>();
-
-            let vertex_weighted_num_neighbors = {
-                profile!("interpolate weighted neighbor counts");
-                interpolator
-                    .as_ref()
-                    .expect("interpolator is required")
-                    .interpolate_scalar_quantity(
-                        weighted_ncounts.as_slice(),
-                        &mesh_with_data.vertices(),
-                        true,
-                    )
-            };
-
-            let smoothing_weights = {
-                let offset = R::zero();
-                let normalization =
-                    R::from_f64(postprocessing.mesh_smoothing_weights_normalization).expect(
-                        "smoothing weight normalization value cannot be represented as Real type",
-                    ) - offset;
-
-                // Normalize number of neighbors
-                let smoothing_weights = vertex_weighted_num_neighbors
-                    .par_iter()
-                    .copied()
-                    .map(|n| (n - offset).max(R::zero()))
-                    .map(|n| (n / normalization).min(R::one()))
-                    // Smooth-Step function
-                    .map(|x| x.powi(5).times(6) - x.powi(4).times(15) + x.powi(3).times(10))
-                    .collect::<Vec<_>>();
-
-                if postprocessing.output_mesh_smoothing_weights {
-                    // Raw distance-weighted number of neighbors value per vertex (can be used to determine normalization value)
-                    mesh_with_data.point_attributes.push(MeshAttribute::new(
-                        "wnn".to_string(),
-                        AttributeData::ScalarReal(vertex_weighted_num_neighbors),
-                    ));
-                    // Final smoothing weights per vertex
-                    mesh_with_data.point_attributes.push(MeshAttribute::new(
-                        "sw".to_string(),
-                        AttributeData::ScalarReal(smoothing_weights.clone()),
-                    ));
-                }
-
-                smoothing_weights
-            };
-
-            Some(smoothing_weights)
-        } else {
-            None
-        };
-
-        // Perform smoothing if requested
-        if let Some(mesh_smoothing_iters) = postprocessing.mesh_smoothing_iters {
-            profile!("mesh smoothing");
-            info!("Post-processing: Smoothing mesh...");
-
-            // TODO: Switch between parallel/single threaded
-
-            let smoothing_weights = smoothing_weights
-                .unwrap_or_else(|| vec![R::one(); mesh_with_data.vertices().len()]);
-
-            splashsurf_lib::postprocessing::par_laplacian_smoothing_inplace(
-                mesh_with_data.mesh.to_mut(),
-                vertex_connectivity
-                    .as_ref()
-                    .expect("vertex connectivity is required"),
-                mesh_smoothing_iters,
-                R::one(),
-                &smoothing_weights,
-            );
+    // Advected texture coordinates depend on the time of the frame
+    let postprocessing_params = match &postprocessing_params.uv_generation {
+        Some(UvGeneration::Advected(uv_parameters)) => {
+            let frame_time = R::from_f64(postprocessing.uv_frame_time).unwrap();
+            let time = R::from_usize(paths.frame_index.unwrap_or(0)).unwrap() * frame_time;
+            Cow::Owned(PostprocessingParameters {
+                uv_generation: Some(UvGeneration::Advected(AdvectedUvParameters {
+                    time,
+                    ..uv_parameters.clone()
+                })),
+                ..postprocessing_params.clone()
+            })
         }
+        _ => Cow::Borrowed(postprocessing_params),
+    };
 
-        // Add normals to mesh if requested
-        if postprocessing.compute_normals {
-            profile!("compute normals");
-            info!("Post-processing: Computing surface normals...");
-
-            // Compute normals
-            let normals = if postprocessing.sph_normals {
-                info!("Using SPH interpolation to compute surface normals");
-
-                let sph_normals = interpolator
-                    .as_ref()
-                    .expect("interpolator is required")
-                    .interpolate_normals(mesh_with_data.vertices());
-                bytemuck::allocation::cast_vec::<Unit<Vector3<R>>, Vector3<R>>(sph_normals)
-            } else {
-                info!("Using area weighted triangle normals for surface normals");
-                profile!("mesh.par_vertex_normals");
-                let tri_normals = mesh_with_data.mesh.par_vertex_normals();
-
-                // Convert unit vectors to plain vectors
-                bytemuck::allocation::cast_vec::<Unit<Vector3<R>>, Vector3<R>>(tri_normals)
-            };
+    // Perform post-processing
+    let stage_start = Instant::now();
+    let (mesh_with_data, removed_components) = splashsurf_lib::postprocessing::postprocess_surface(
+        reconstruction,
+        particle_positions.as_slice(),
+        attributes,
+        params,
+        &postprocessing_params,
+    )?;
+
+    // Write the removed small components as particles
+    if postprocessing.small_components_to_particles {
+        let particles =
+            splashsurf_lib::postprocessing::components_to_particles(&removed_components);
 
-            // Smooth normals
-            if let Some(smoothing_iters) = postprocessing.normals_smoothing_iters {
-                info!("Post-processing: Smoothing normals...");
-
-                let mut smoothed_normals = normals.clone();
-                splashsurf_lib::postprocessing::par_laplacian_smoothing_normals_inplace(
-                    &mut smoothed_normals,
-                    vertex_connectivity
-                        .as_ref()
-                        .expect("vertex connectivity is required"),
-                    smoothing_iters,
-                );
+        let output_path = paths
+            .output_file
+            .parent()
+            // Add a trailing separator if the parent is non-empty
+            .map(|p| p.join(""))
+            .unwrap_or_else(PathBuf::new);
+        let output_filename = format!(
+            "droplets_{}.vtk",
+            paths.output_file.file_stem().unwrap().to_string_lossy()
+        );
+        let droplets_output_file = output_path.join(output_filename);
 
-                mesh_with_data.point_attributes.push(MeshAttribute::new(
-                    "normals".to_string(),
-                    AttributeData::Vector3Real(smoothed_normals),
-                ));
-                if postprocessing.output_raw_normals {
-                    mesh_with_data.point_attributes.push(MeshAttribute::new(
-                        "raw_normals".to_string(),
-                        AttributeData::Vector3Real(normals),
-                    ));
-                }
-            } else {
-                mesh_with_data.point_attributes.push(MeshAttribute::new(
-                    "normals".to_string(),
-                    AttributeData::Vector3Real(normals),
-                ));
-            }
-        }
+        info!(
+            "Writing {} removed components as particles to \"{}\"...",
+            removed_components.len(),
+            droplets_output_file.display()
+        );
 
-        // Interpolate attributes if requested
-        if !attributes.is_empty() {
-            profile!("interpolate attributes");
-            info!("Post-processing: Interpolating attributes...");
-            let interpolator = interpolator.as_ref().expect("interpolator is required");
-
-            for attribute in attributes.into_iter() {
-                info!("Interpolating attribute \"{}\"...", attribute.name);
-
-                match attribute.data {
-                    AttributeData::ScalarReal(values) => {
-                        let interpolated_values = interpolator.interpolate_scalar_quantity(
-                            values.as_slice(),
-                            mesh_with_data.vertices(),
-                            true,
-                        );
-                        mesh_with_data.point_attributes.push(MeshAttribute::new(
-                            attribute.name,
-                            AttributeData::ScalarReal(interpolated_values),
-                        ));
-                    }
-                    AttributeData::Vector3Real(values) => {
-                        let interpolated_values = interpolator.interpolate_vector_quantity(
-                            values.as_slice(),
-                            mesh_with_data.vertices(),
-                            true,
-                        );
-                        mesh_with_data.point_attributes.push(MeshAttribute::new(
-                            attribute.name,
-                            AttributeData::Vector3Real(interpolated_values),
-                        ));
-                    }
-                    _ => unimplemented!("Interpolation of this attribute type not implemented"),
-                }
-            }
-        }
+        io::write_mesh(&particles, &droplets_output_file, &io_params.output).with_context(
+            || {
+                anyhow!(
+                    "Failed to write removed components to file \"{}\"",
+                    droplets_output_file.display()
+                )
+            },
+        )?;
     }
 
-    // Remove and clamp cells outside of AABB
-    let mesh_with_data = if let Some(mesh_aabb) = &postprocessing.mesh_aabb {
-        profile!("clamp mesh to aabb");
-        info!("Post-processing: Clamping mesh to AABB...");
-
-        mesh_with_data.par_clamp_with_aabb(
-            &mesh_aabb
-                .try_convert()
-                .ok_or_else(|| anyhow!("Failed to convert mesh AABB"))?,
-            postprocessing.mesh_aabb_clamp_vertices,
-            postprocessing.keep_vertices,
-        )
-    } else {
-        mesh_with_data
-    };
+    // The repaired mesh has to be closed and manifold, checked before the quad conversion but reported after writing the mesh
+    let mesh_repair_error = postprocessing
+        .mesh_repair
+        .then(|| mesh_with_data.mesh.compute_manifold_information())
+        .filter(|manifold_info| !manifold_info.is_closed() || !manifold_info.is_manifold())
+        .map(|manifold_info| {
+            anyhow!(
+                "Mesh repair failed to produce a closed manifold mesh: {} boundary edges, {} non-manifold edges and {} non-manifold vertices remain (see log for holes that could not be filled)",
+                manifold_info.boundary_edges.len(),
+                manifold_info.non_manifold_edges.len(),
+                manifold_info.non_manifold_vertices.len()
+            )
+        });
+
+    // Check the triangle mesh before the quad conversion, problems are reported after writing the mesh
+    let mesh_check_result = (postprocessing.check_mesh_closed
+        || postprocessing.check_mesh_manifold
+        || postprocessing.check_mesh_debug)
+        .then(|| {
+            splashsurf_lib::marching_cubes::check_mesh_consistency(
+                &grid,
+                &mesh_with_data.mesh,
+                postprocessing.check_mesh_closed,
+                postprocessing.check_mesh_manifold,
+                postprocessing.check_mesh_debug,
+            )
+        });
 
     // Convert triangles to quads
     let (tri_mesh, tri_quad_mesh) = if postprocessing.generate_quads {
@@ -1265,8 +1493,10 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
     } else {
         (Some(mesh_with_data), None)
     };
+    pipeline_timings.push(("postprocessing", stage_start.elapsed()));
 
     // Store the surface mesh
+    let stage_start = Instant::now();
     {
         profile!("write surface mesh to file");
         info!(
@@ -1292,30 +1522,82 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
         })?;
         info!("Done.");
     }
+    pipeline_timings.push(("write output", stage_start.elapsed()));
 
-    if postprocessing.check_mesh_closed
-        || postprocessing.check_mesh_manifold
-        || postprocessing.check_mesh_debug
-    {
-        if let Err(err) = match (&tri_mesh, &tri_quad_mesh) {
-            (Some(mesh), None) => splashsurf_lib::marching_cubes::check_mesh_consistency(
-                grid,
-                &mesh.mesh,
-                postprocessing.check_mesh_closed,
-                postprocessing.check_mesh_manifold,
-                postprocessing.check_mesh_debug,
-            ),
-            (None, Some(_mesh)) => {
-                info!("Checking for mesh consistency not implemented for quad mesh at the moment.");
-                return Ok(());
-            }
+    if postprocessing.output_stats {
+        let (num_vertices, num_triangles) = match (&tri_mesh, &tri_quad_mesh) {
+            (Some(mesh), None) => (mesh.mesh.vertices.len(), mesh.mesh.triangles.len()),
+            (None, Some(mesh)) => (mesh.mesh.vertices.len(), mesh.mesh.cells.len()),
             _ => unreachable!(),
-        } {
-            return Err(anyhow!("{}", err));
-        } else {
-            info!("Checked mesh for problems (holes: {}, non-manifold edges/vertices: {}), no problems were found.", postprocessing.check_mesh_closed, postprocessing.check_mesh_manifold);
-        }
+        };
+        write_stats_json(
+            paths,
+            &reconstruction_stats,
+            &pipeline_timings,
+            num_vertices,
+            num_triangles,
+        )?;
+    }
+
+    if let Some(err) = mesh_repair_error {
+        return Err(err);
+    }
+
+    match mesh_check_result {
+        Some(Err(err)) => return Err(anyhow!("{}", err)),
+        Some(Ok(())) => info!("Checked mesh for problems (holes: {}, non-manifold edges/vertices: {}), no problems were found.", postprocessing.check_mesh_closed, postprocessing.check_mesh_manifold),
+        None => {}
     }
 
     Ok(())
 }
+
+/// Statistics of a single frame written to a JSON file next to the output mesh
+#[derive(serde::Serialize)]
+struct FrameStats<'a> {
+    input_file: &'a Path,
+    output_file: &'a Path,
+    output_num_vertices: usize,
+    output_num_cells: usize,
+    reconstruction: &'a splashsurf_lib::ReconstructionStats,
+    pipeline_timings: Vec<StageTiming>,
+}
+
+/// Writes the statistics of the reconstruction of a single frame to a JSON file next to the output mesh
+fn write_stats_json(
+    paths: &ReconstructionRunnerPaths,
+    stats: &splashsurf_lib::ReconstructionStats,
+    pipeline_timings: &[(&'static str, Duration)],
+    output_num_vertices: usize,
+    output_num_cells: usize,
+) -> Result<(), anyhow::Error> {
+    let frame_stats = FrameStats {
+        input_file: &paths.input_file,
+        output_file: &paths.output_file,
+        output_num_vertices,
+        output_num_cells,
+        reconstruction: stats,
+        pipeline_timings: pipeline_timings
+            .iter()
+            .map(|&(stage, duration)| StageTiming { stage, duration })
+            .collect(),
+    };
+
+    let stats_file = paths.output_file.with_extension("stats.json");
+    info!(
+        "Writing reconstruction statistics to \"{}\"...",
+        stats_file.display()
+    );
+    let file = fs::File::create(&stats_file).with_context(|| {
+        anyhow!(
+            "Failed to create reconstruction statistics file \"{}\"",
+            stats_file.display()
+        )
+    })?;
+    serde_json::to_writer_pretty(BufWriter::new(file), &frame_stats).with_context(|| {
+        anyhow!(
+            "Failed to write reconstruction statistics to file \"{}\"",
+            stats_file.display()
+        )
+    })
+}
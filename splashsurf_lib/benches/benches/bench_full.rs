@@ -5,7 +5,7 @@ use splashsurf_lib::io::particles_from_file;
 use splashsurf_lib::io::vtk_format::write_vtk;
 use splashsurf_lib::{
     reconstruct_surface, reconstruct_surface_inplace, GridDecompositionParameters, Parameters,
-    SpatialDecomposition, SurfaceReconstruction,
+    SpatialDecomposition, SurfaceReconstruction, TabulatedKernelParameters,
 };
 use std::time::Duration;
 
@@ -104,6 +104,9 @@ pub fn surface_reconstruction_dam_break(c: &mut Criterion) {
         enable_multi_threading: true,
         spatial_decomposition: None,
         global_neighborhood_list: false,
+        vertex_provenance: None,
+        tabulated_kernel: None,
+        particle_reordering: None,
     };
 
     let mut group = c.benchmark_group("full surface reconstruction");
@@ -126,6 +129,9 @@ pub fn surface_reconstruction_dam_break(c: &mut Criterion) {
             parameters.spatial_decomposition = Some(SpatialDecomposition::UniformGrid(
                 GridDecompositionParameters {
                     subdomain_num_cubes_per_dim: 64,
+                    enable_simd: true,
+                    local_f32_coordinates: false,
+                    auto_tuning: false,
                 },
             ));
             reconstruction =
@@ -163,6 +169,9 @@ pub fn surface_reconstruction_double_dam_break(c: &mut Criterion) {
         enable_multi_threading: true,
         spatial_decomposition: None,
         global_neighborhood_list: false,
+        vertex_provenance: None,
+        tabulated_kernel: None,
+        particle_reordering: None,
     };
 
     let mut group = c.benchmark_group("full surface reconstruction");
@@ -185,6 +194,9 @@ pub fn surface_reconstruction_double_dam_break(c: &mut Criterion) {
             parameters.spatial_decomposition = Some(SpatialDecomposition::UniformGrid(
                 GridDecompositionParameters {
                     subdomain_num_cubes_per_dim: 64,
+                    enable_simd: true,
+                    local_f32_coordinates: false,
+                    auto_tuning: false,
                 },
             ));
             reconstruction =
@@ -222,6 +234,9 @@ pub fn surface_reconstruction_double_dam_break_inplace(c: &mut Criterion) {
         enable_multi_threading: true,
         spatial_decomposition: None,
         global_neighborhood_list: false,
+        vertex_provenance: None,
+        tabulated_kernel: None,
+        particle_reordering: None,
     };
 
     let mut group = c.benchmark_group("full surface reconstruction");
@@ -253,6 +268,9 @@ pub fn surface_reconstruction_double_dam_break_inplace(c: &mut Criterion) {
                 parameters.spatial_decomposition = Some(SpatialDecomposition::UniformGrid(
                     GridDecompositionParameters {
                         subdomain_num_cubes_per_dim: 64,
+                        enable_simd: true,
+                        local_f32_coordinates: false,
+                        auto_tuning: false,
                     },
                 ));
                 reconstruct_surface_inplace::<i64, _>(
@@ -275,10 +293,146 @@ pub fn surface_reconstruction_double_dam_break_inplace(c: &mut Criterion) {
     .unwrap();
 }
 
+pub fn surface_reconstruction_grid_simd(c: &mut Criterion) {
+    let datasets = [
+        (
+            "dam_break",
+            "../data/dam_break_frame_23_24389_particles.bgeo",
+        ),
+        (
+            "double_dam_break",
+            "../data/double_dam_break_frame_26_4732_particles.vtk",
+        ),
+    ];
+
+    let particle_radius = 0.025;
+    let compact_support_radius = 4.0 * particle_radius;
+    let cube_size = 0.3 * particle_radius;
+
+    let parameters = Parameters {
+        particle_radius,
+        rest_density: 1000.0,
+        compact_support_radius,
+        cube_size,
+        iso_surface_threshold: 0.6,
+        particle_aabb: None,
+        enable_multi_threading: true,
+        spatial_decomposition: None,
+        global_neighborhood_list: false,
+        vertex_provenance: None,
+        tabulated_kernel: None,
+        particle_reordering: None,
+    };
+
+    let mut group = c.benchmark_group("full surface reconstruction");
+    group.sample_size(20);
+    group.warm_up_time(Duration::from_secs(5));
+    group.measurement_time(Duration::from_secs(15));
+
+    for (name, path) in datasets {
+        let particle_positions: &Vec<Vector3<f32>> = &particles_from_file(path).unwrap();
+
+        for enable_simd in [false, true] {
+            let mut parameters = parameters.clone();
+            parameters.spatial_decomposition = Some(SpatialDecomposition::UniformGrid(
+                GridDecompositionParameters {
+                    subdomain_num_cubes_per_dim: 64,
+                    enable_simd,
+                    local_f32_coordinates: false,
+                    auto_tuning: false,
+                },
+            ));
+
+            let simd = if enable_simd { "simd" } else { "scalar" };
+            group.bench_function(
+                format!("surface_reconstruction_{}_par_grid_64_{}", name, simd),
+                |b| {
+                    b.iter(|| {
+                        reconstruct_surface::<i64, _>(particle_positions.as_slice(), &parameters)
+                            .unwrap()
+                    })
+                },
+            );
+        }
+    }
+
+    group.finish();
+}
+
+pub fn surface_reconstruction_grid_tabulated_kernel(c: &mut Criterion) {
+    let particle_positions: &Vec<Vector3<f32>> =
+        &particles_from_file("../data/dam_break_frame_23_24389_particles.bgeo").unwrap();
+
+    let particle_radius = 0.025;
+    let compact_support_radius = 4.0 * particle_radius;
+    let cube_size = 0.3 * particle_radius;
+
+    let parameters = Parameters {
+        particle_radius,
+        rest_density: 1000.0,
+        compact_support_radius,
+        cube_size,
+        iso_surface_threshold: 0.6,
+        particle_aabb: None,
+        enable_multi_threading: true,
+        spatial_decomposition: Some(SpatialDecomposition::UniformGrid(
+            GridDecompositionParameters {
+                subdomain_num_cubes_per_dim: 64,
+                enable_simd: true,
+                local_f32_coordinates: false,
+                auto_tuning: false,
+            },
+        )),
+        global_neighborhood_list: false,
+        vertex_provenance: None,
+        tabulated_kernel: None,
+        particle_reordering: None,
+    };
+
+    let mut group = c.benchmark_group("full surface reconstruction");
+    group.sample_size(20);
+    group.warm_up_time(Duration::from_secs(5));
+    group.measurement_time(Duration::from_secs(15));
+
+    group.bench_function(
+        "surface_reconstruction_dam_break_par_grid_64_exact_kernel",
+        |b| {
+            b.iter(|| {
+                reconstruct_surface::<i64, _>(particle_positions.as_slice(), &parameters).unwrap()
+            })
+        },
+    );
+
+    for resolution in [256, 1024, 4096] {
+        let mut parameters = parameters.clone();
+        parameters.tabulated_kernel = Some(TabulatedKernelParameters {
+            resolution,
+            max_relative_error: None,
+        });
+
+        group.bench_function(
+            format!(
+                "surface_reconstruction_dam_break_par_grid_64_tabulated_kernel_{}",
+                resolution
+            ),
+            |b| {
+                b.iter(|| {
+                    reconstruct_surface::<i64, _>(particle_positions.as_slice(), &parameters)
+                        .unwrap()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
 criterion_group!(
     bench_full,
     surface_reconstruction_double_dam_break,
     surface_reconstruction_double_dam_break_inplace,
     surface_reconstruction_dam_break,
+    surface_reconstruction_grid_simd,
+    surface_reconstruction_grid_tabulated_kernel,
     //surface_reconstruction_canyon
 );
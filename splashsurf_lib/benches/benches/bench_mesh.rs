@@ -27,6 +27,9 @@ fn reconstruct_particles<P: AsRef<Path>>(particle_file: P) -> SurfaceReconstruct
             GridDecompositionParameters::default(),
         )),
         global_neighborhood_list: false,
+        vertex_provenance: None,
+        tabulated_kernel: None,
+        particle_reordering: None,
     };
 
     reconstruct_surface::<i64, _>(particle_positions.as_slice(), &parameters).unwrap()
@@ -27,6 +27,9 @@ fn parameters_canyon() -> Parameters<f32> {
             },
         )),
         global_neighborhood_list: false,
+        vertex_provenance: None,
+        tabulated_kernel: None,
+        particle_reordering: None,
     };
 
     parameters
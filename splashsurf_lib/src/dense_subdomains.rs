@@ -1,18 +1,20 @@
-use anyhow::{anyhow, Context};
 use arrayvec::ArrayVec;
 use itertools::Itertools;
 use log::{info, trace};
 use nalgebra::Vector3;
 use num_integer::Integer;
-use num_traits::{FromPrimitive, NumCast};
+use num_traits::{FromPrimitive, NumCast, ToPrimitive};
 use parking_lot::Mutex;
 use rayon::prelude::*;
+use std::any::TypeId;
 use std::cell::RefCell;
+use std::collections::hash_map::Entry;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use thiserror::Error as ThisError;
 use thread_local::ThreadLocal;
 
 use crate::density_map::sequential_compute_particle_densities_filtered;
-use crate::kernel::{CubicSplineKernel, SymmetricKernel3d};
+use crate::kernel::{CubicSplineKernel, SymmetricKernel3d, TabulatedKernel};
 use crate::marching_cubes::marching_cubes_lut::marching_cubes_triangulation_iter;
 use crate::mesh::{HexMesh3d, TriMesh3d};
 use crate::neighborhood_search::{
@@ -22,34 +24,99 @@ use crate::neighborhood_search::{
 use crate::uniform_grid::{EdgeIndex, GridConstructionError, UniformCartesianCubeGrid3d};
 use crate::{
     new_map, new_parallel_map, profile, Aabb3d, MapType, Parameters, SpatialDecomposition,
-    SurfaceReconstruction,
+    SubdomainGridStats, SurfaceReconstruction, TabulatedKernelParameters, VertexProvenance,
 };
-use crate::{Index, Real};
+use crate::{Index, Real, RealConvert};
+
+use cost_model::{auto_subdomain_cubes, par_map_by_descending_cost, SubdomainCostModel};
+use density_splatting::{DensitySplatter, SplattingGrid};
+pub(crate) use incremental::{incremental_reconstruction, SurfacePatchCache};
+
+mod cost_model;
+mod density_splatting;
+mod incremental;
 
 // TODO: Implement single-threaded processing
 
 type GlobalIndex = u64;
 
-/// Converts any literal or expression to the Index type I (panics if value does not fit)
+/// Converts any literal or expression to the Index type I (returns a [`SubdomainGridError::IndexConversion`] from the enclosing function if the value does not fit)
 macro_rules! to_index {
-    ($value:literal) => {
-        <I as NumCast>::from($value).expect("literal has to fit in index type")
-    };
     ($value:expr) => {
-        <I as NumCast>::from($value).expect("value has to fit in index type")
+        to_index!($value, stringify!($value))
+    };
+    ($value:expr, $what:expr) => {
+        <I as NumCast>::from($value).ok_or(SubdomainGridError::IndexConversion($what))?
     };
 }
 
-/// Converts any literal or expression to the Real type R (panics if value does not fit)
+/// Converts any literal or expression to the Real type R (returns a [`SubdomainGridError::RealConversion`] from the enclosing function if the value does not fit)
 macro_rules! to_real {
-    ($value:literal) => {
-        <R as NumCast>::from($value).expect("literal has to fit in real type")
+    ($value:expr) => {
+        to_real!($value, stringify!($value))
+    };
+    ($value:expr, $what:expr) => {
+        <R as NumCast>::from($value).ok_or(SubdomainGridError::RealConversion($what))?
     };
+}
+
+/// Converts any literal or expression to the Real type L used inside of subdomains (returns a [`SubdomainGridError::RealConversion`] from the enclosing function if the value does not fit)
+macro_rules! to_local {
     ($value:expr) => {
-        <R as NumCast>::from($value).expect("value has to fit in real type")
+        to_local!($value, stringify!($value))
+    };
+    ($value:expr, $what:expr) => {
+        <L as NumCast>::from($value).ok_or(SubdomainGridError::RealConversion($what))?
     };
 }
 
+/// Error type returned when the surface reconstruction using the uniform subdomain grid fails
+#[rustfmt::skip]
+#[non_exhaustive]
+#[derive(Clone, PartialEq, Debug, ThisError)]
+pub enum SubdomainGridError<I: Index, R: Real> {
+    /// The parameters of the uniform grid spatial decomposition are missing
+    #[error("spatial decomposition parameters for uniform grid are missing")]
+    MissingParameters,
+    /// A value cannot be represented by one of the index types used by the reconstruction
+    #[error("{0} is out of range of the index type")]
+    IndexConversion(&'static str),
+    /// A value cannot be represented by the real type used by the reconstruction
+    #[error("{0} cannot be represented by the real type")]
+    RealConversion(&'static str),
+    /// The ghost particle margin is thicker than half of the extents of a subdomain
+    #[error("the ghost margin is {ghost_margin_cubes} cubes thick (rounded up), while a subdomain only has an extent of {subdomain_cubes} cubes, the subdomain has to have at least twice the number of cubes of the ghost margin")]
+    GhostMarginTooThick {
+        /// Thickness of the ghost margin in marching cubes cells (rounded up)
+        ghost_margin_cubes: I,
+        /// Extent of a subdomain in marching cubes cells
+        subdomain_cubes: I,
+    },
+    /// Error during the construction of the subdomain grid or the marching cubes grid of a subdomain
+    #[error("subdomain grid construction")]
+    GridConstruction(
+        #[source]
+        #[from]
+        GridConstructionError<I, R>,
+    ),
+    /// Error during the construction of the global marching cubes grid
+    #[error("global marching cubes grid construction")]
+    GlobalGridConstruction(
+        #[source]
+        GridConstructionError<GlobalIndex, R>,
+    ),
+    /// A particle with non-finite coordinates was encountered that cannot be classified into a subdomain
+    #[error("particle {0} has non-finite coordinates")]
+    NonFiniteParticle(usize),
+    /// A flat subdomain index that is not part of the subdomain grid was encountered
+    #[error("subdomain with flat index {0} is not part of the subdomain grid")]
+    InvalidSubdomain(I),
+    /// A cell, point or edge index that is not part of the corresponding grid was encountered
+    #[error("invalid index of {0}")]
+    InvalidGridIndex(&'static str),
+}
+
+#[derive(Clone)]
 pub(crate) struct ParametersSubdomainGrid<I: Index, R: Real> {
     /// SPH particle radius (in simulation units)
     #[allow(unused)]
@@ -66,6 +133,8 @@ pub(crate) struct ParametersSubdomainGrid<I: Index, R: Real> {
     subdomain_cubes: I,
     /// Margin for ghost particles around each subdomain
     ghost_particle_margin: R,
+    /// Volume of the ghost margin relative to the volume of a subdomain
+    ghost_margin_volume_fraction: R,
     /// Implicit global MC background grid (required to compute consistent float coordinates at domain boundaries)
     global_marching_cubes_grid: UniformCartesianCubeGrid3d<GlobalIndex, R>,
     /// Implicit subdomain grid
@@ -74,9 +143,66 @@ pub(crate) struct ParametersSubdomainGrid<I: Index, R: Real> {
     chunk_size: usize,
     /// Whether to return the global particle neighborhood list instead of only using per-domain lists internally
     global_neighborhood_list: bool,
+    /// Whether to use explicit SIMD instructions for the level-set evaluation in dense subdomains
+    enable_simd: bool,
+    /// Whether the subdomain size and the sparse subdomain limit were chosen automatically
+    auto_tuning: bool,
+    /// Cost estimate of the reconstruction of a subdomain (for the sparse limit in auto tuning mode and the scheduling)
+    cost_model: SubdomainCostModel,
+    /// Parameters of the tabulated kernel for the level-set evaluation (exact kernel evaluation if `None`)
+    tabulated_kernel: Option<TabulatedKernelParameters>,
+    /// Number of nearest contributing particles to store per vertex for the vertex provenance (no provenance if `None`)
+    vertex_provenance_particles: Option<usize>,
+    /// Mapping from global coordinates to the coordinates used inside of the subdomains
+    coordinates: SubdomainCoordinates<R>,
 }
 
 impl<I: Index, R: Real> ParametersSubdomainGrid<I, R> {
+    pub(crate) fn vertex_provenance_particles(&self) -> Option<usize> {
+        self.vertex_provenance_particles
+    }
+
+    /// Returns the maximum number of particles such that a subdomain will be considered "sparse"
+    fn sparse_particle_limit(
+        &self,
+        subdomains: &Subdomains<I>,
+    ) -> Result<usize, SubdomainGridError<I, R>> {
+        if self.auto_tuning {
+            Ok(self.cost_model.sparse_limit())
+        } else {
+            sparse_particle_limit(largest_subdomain_particles(subdomains))
+        }
+    }
+
+    /// Returns whether the subdomains are processed in local `f32` coordinates
+    pub(crate) fn uses_local_coordinates(&self) -> bool {
+        self.coordinates.quantum.is_some()
+    }
+
+    /// Returns the coordinate frame of the given subdomain
+    fn subdomain_frame(
+        &self,
+        subdomain_ijk: &[I; 3],
+    ) -> Result<SubdomainFrame<'_, R>, SubdomainGridError<I, R>> {
+        let to_global = |i: I| <GlobalIndex as NumCast>::from(i);
+        let first_point = (|| -> Option<_> {
+            let subdomain_cubes = to_global(self.subdomain_cubes)?;
+            Some([
+                to_global(subdomain_ijk[0])?.checked_mul(subdomain_cubes)?,
+                to_global(subdomain_ijk[1])?.checked_mul(subdomain_cubes)?,
+                to_global(subdomain_ijk[2])?.checked_mul(subdomain_cubes)?,
+            ])
+        })()
+        .ok_or(SubdomainGridError::IndexConversion(
+            "global marching cubes point index",
+        ))?;
+        self.coordinates
+            .frame(first_point)
+            .ok_or(SubdomainGridError::RealConversion(
+                "origin of the local subdomain coordinates",
+            ))
+    }
+
     pub(crate) fn global_marching_cubes_grid(
         &self,
     ) -> Result<UniformCartesianCubeGrid3d<I, R>, GridConstructionError<I, R>> {
@@ -93,6 +219,181 @@ impl<I: Index, R: Real> ParametersSubdomainGrid<I, R> {
     }
 }
 
+/// Mapping between global coordinates and the coordinates used for the computations inside of subdomains
+///
+/// By default, subdomains are processed in global coordinates. With local coordinates, all positions in a
+/// subdomain are relative to its first marching cubes grid point and quantized to integer multiples of a
+/// power of two (the quantum), such that they are exactly representable by `f32`. As all subdomains share
+/// the same quantization lattice, the differences between particle and grid point coordinates are exact
+/// and identical in all subdomains. Therefore, neighboring subdomains still compute identical level-set
+/// values at the grid points of their shared boundary.
+#[derive(Clone, PartialEq, Debug)]
+struct SubdomainCoordinates<R: Real> {
+    /// Lower corner of the global marching cubes grid
+    global_origin: Vector3<R>,
+    /// Edge length of the marching cubes cells
+    cube_size: R,
+    /// Quantization step of the local coordinates (global coordinates are used if `None`)
+    quantum: Option<R>,
+}
+
+/// Coordinate frame of a single subdomain, see [`SubdomainCoordinates`]
+struct SubdomainFrame<'a, R: Real> {
+    coordinates: &'a SubdomainCoordinates<R>,
+    /// Position of the first grid point of the subdomain on the quantization lattice (only used for local coordinates)
+    origin_lattice: [i64; 3],
+    /// Global coordinates of the origin of the local coordinates (only used for local coordinates)
+    origin: Vector3<R>,
+}
+
+impl<R: Real> SubdomainCoordinates<R> {
+    /// Number of bits of the local coordinates on the quantization lattice (one bit less than the `f32` mantissa)
+    const LOCAL_LATTICE_BITS: i32 = 23;
+
+    /// Subdomains are processed in global coordinates
+    fn global(global_grid: &UniformCartesianCubeGrid3d<GlobalIndex, R>) -> Self {
+        Self {
+            global_origin: *global_grid.aabb().min(),
+            cube_size: global_grid.cell_size(),
+            quantum: None,
+        }
+    }
+
+    /// Subdomains are processed in local coordinates that are exactly representable by `f32` up to the given magnitude
+    fn local(
+        global_grid: &UniformCartesianCubeGrid3d<GlobalIndex, R>,
+        max_local_extent: R,
+    ) -> Option<Self> {
+        let quantum_exponent = (max_local_extent.to_f64()?
+            / 2.0_f64.powi(Self::LOCAL_LATTICE_BITS))
+        .log2()
+        .ceil();
+        let quantum = R::from_f64(2.0_f64.powi(quantum_exponent.to_i32()?))?;
+        (quantum > R::zero()).then_some(Self {
+            global_origin: *global_grid.aabb().min(),
+            cube_size: global_grid.cell_size(),
+            quantum: Some(quantum),
+        })
+    }
+
+    /// Returns the position of a global coordinate along the given axis on the quantization lattice
+    #[inline(always)]
+    fn lattice_coordinate(&self, quantum: R, axis: usize, x: R) -> Option<i64> {
+        ((x - self.global_origin[axis]) / quantum).round().to_i64()
+    }
+
+    /// Returns the position of a global grid point along the given axis on the quantization lattice
+    #[inline(always)]
+    fn lattice_grid_point(&self, quantum: R, global_point: GlobalIndex) -> Option<i64> {
+        (global_point.to_real::<R>()? * self.cube_size / quantum)
+            .round()
+            .to_i64()
+    }
+
+    /// Returns the coordinate frame of the subdomain with the given global index of its first grid point
+    fn frame(&self, first_point: [GlobalIndex; 3]) -> Option<SubdomainFrame<'_, R>> {
+        let (origin_lattice, origin) = match self.quantum {
+            None => ([0; 3], Vector3::zeros()),
+            Some(quantum) => {
+                let origin_lattice = [
+                    self.lattice_grid_point(quantum, first_point[0])?,
+                    self.lattice_grid_point(quantum, first_point[1])?,
+                    self.lattice_grid_point(quantum, first_point[2])?,
+                ];
+                let origin = self.global_origin
+                    + Vector3::new(
+                        R::from_i64(origin_lattice[0])?,
+                        R::from_i64(origin_lattice[1])?,
+                        R::from_i64(origin_lattice[2])?,
+                    ) * quantum;
+                (origin_lattice, origin)
+            }
+        };
+
+        Some(SubdomainFrame {
+            coordinates: self,
+            origin_lattice,
+            origin,
+        })
+    }
+}
+
+impl<'a, R: Real> SubdomainFrame<'a, R> {
+    /// Converts global coordinates to the coordinates used inside of the subdomain
+    #[inline(always)]
+    fn to_local<L: Real>(&self, point: &Vector3<R>) -> Option<Vector3<L>> {
+        match self.coordinates.quantum {
+            None => (*point).try_convert(),
+            Some(quantum) => {
+                let local_quantum = L::from_f64(quantum.to_f64()?)?;
+                let mut local = Vector3::zeros();
+                for axis in 0..3 {
+                    let lattice =
+                        self.coordinates
+                            .lattice_coordinate(quantum, axis, point[axis])?;
+                    local[axis] = L::from_i64(lattice.checked_sub(self.origin_lattice[axis])?)?
+                        * local_quantum;
+                }
+                Some(local)
+            }
+        }
+    }
+
+    /// Returns the coordinate of a global grid point along the given axis used inside of the subdomain
+    #[inline(always)]
+    fn grid_point_to_local<L: Real>(&self, axis: usize, global_point: GlobalIndex) -> Option<L> {
+        match self.coordinates.quantum {
+            // Same calculation as `UniformCartesianCubeGrid3d::point_coordinates`
+            None => (&(self.coordinates.global_origin[axis]
+                + global_point.to_real::<R>()? * self.coordinates.cube_size))
+                .try_convert(),
+            Some(quantum) => {
+                let lattice = self.coordinates.lattice_grid_point(quantum, global_point)?;
+                Some(
+                    L::from_i64(lattice.checked_sub(self.origin_lattice[axis])?)?
+                        * L::from_f64(quantum.to_f64()?)?,
+                )
+            }
+        }
+    }
+
+    /// Converts coordinates used inside of the subdomain back to global coordinates
+    #[inline(always)]
+    fn to_global<L: Real>(&self, point: &Vector3<L>) -> Option<Vector3<R>> {
+        let point = (*point).try_convert::<R>()?;
+        match self.coordinates.quantum {
+            None => Some(point),
+            Some(_) => Some(self.origin + point),
+        }
+    }
+
+    /// Returns the lower corner of the subdomain marching cubes grid in the coordinates used inside of the subdomain
+    fn local_grid_origin<L: Real>(&self, subdomain_aabb: &Aabb3d<R>) -> Option<Vector3<L>> {
+        match self.coordinates.quantum {
+            None => (*subdomain_aabb.min()).try_convert(),
+            Some(_) => Some(Vector3::zeros()),
+        }
+    }
+}
+
+/// Converts an error of the construction of a subdomain grid in local coordinates to the real type of the reconstruction
+fn local_grid_error<I: Index, L: Real, R: Real>(
+    error: GridConstructionError<I, L>,
+) -> SubdomainGridError<I, R> {
+    use GridConstructionError as E;
+    SubdomainGridError::GridConstruction(match error {
+        E::InvalidCellSize(cell_size) => {
+            E::InvalidCellSize((&cell_size).try_convert().unwrap_or(R::zero()))
+        }
+        E::DegenerateAabb => E::DegenerateAabb,
+        E::InconsistentAabb => E::InconsistentAabb,
+        E::IndexTypeTooSmallCellsPerDim => E::IndexTypeTooSmallCellsPerDim,
+        E::IndexTypeTooSmallPointsPerDim => E::IndexTypeTooSmallPointsPerDim,
+        E::IndexTypeTooSmallTotalPoints(nx, ny, nz) => E::IndexTypeTooSmallTotalPoints(nx, ny, nz),
+        E::RealTypeTooSmallDomainSize => E::RealTypeTooSmallDomainSize,
+    })
+}
+
 /// Result of the subdomain decomposition procedure
 pub(crate) struct Subdomains<I: Index> {
     // Flat subdomain coordinate indices (same order as the particle list)
@@ -103,26 +404,17 @@ pub(crate) struct Subdomains<I: Index> {
 
 pub(crate) fn initialize_parameters<'a, I: Index, R: Real>(
     parameters: &Parameters<R>,
-    _particles: &[Vector3<R>],
+    particles: &[Vector3<R>],
     output_surface: &'a SurfaceReconstruction<I, R>,
-) -> Result<ParametersSubdomainGrid<I, R>, anyhow::Error> {
+) -> Result<ParametersSubdomainGrid<I, R>, SubdomainGridError<I, R>> {
     let chunk_size = 500;
 
     let Some(SpatialDecomposition::UniformGrid(grid_parameters)) =
         &parameters.spatial_decomposition
     else {
-        return Err(anyhow!(
-            "spatial decomposition parameters for uniform grid are missing"
-        ));
+        return Err(SubdomainGridError::MissingParameters);
     };
 
-    // A subdomain will be a cube consisting of this number of MC cubes along each coordinate axis
-    let subdomain_cubes_in = grid_parameters.subdomain_num_cubes_per_dim;
-    let subdomain_cubes = I::from_u32(subdomain_cubes_in)
-        .expect("number of subdomain cubes has to fit in index type");
-    let subdomain_cubes_global = GlobalIndex::from_u32(subdomain_cubes_in)
-        .expect("number of subdomain cubes has to fit in global index type");
-
     // Physical particle properties
     let particle_radius = parameters.particle_radius;
     let particle_rest_density = parameters.rest_density;
@@ -135,25 +427,61 @@ pub(crate) fn initialize_parameters<'a, I: Index, R: Real>(
 
     let ghost_particle_margin =
         (compact_support_radius / cube_size).ceil() * cube_size * to_real!(1.01);
+    // Radius of the kernel support in MC cubes
+    let kernel_cube_radius = (compact_support_radius / cube_size)
+        .ceil()
+        .to_usize()
+        .ok_or(SubdomainGridError::IndexConversion(
+            "kernel radius in cubes",
+        ))?;
+
+    // A subdomain will be a cube consisting of this number of MC cubes along each coordinate axis
+    let subdomain_cubes_in = if grid_parameters.auto_tuning {
+        auto_subdomain_cubes(
+            particles,
+            output_surface.grid.aabb().min(),
+            cube_size,
+            kernel_cube_radius,
+            (ghost_particle_margin / cube_size)
+                .ceil()
+                .to_usize()
+                .ok_or(SubdomainGridError::IndexConversion(
+                    "ghost margin cube count",
+                ))?,
+            rayon::current_num_threads(),
+        )
+    } else {
+        grid_parameters.subdomain_num_cubes_per_dim
+    };
+    let subdomain_cubes = to_index!(subdomain_cubes_in, "number of subdomain cubes");
+    let subdomain_cubes_global = GlobalIndex::from_u32(subdomain_cubes_in).ok_or(
+        SubdomainGridError::IndexConversion("number of subdomain cubes (global index)"),
+    )?;
 
     // Compute information of ghost margin volume for debugging
-    {
-        let ghost_margin_cubes = I::from((ghost_particle_margin / cube_size).ceil())
-            .expect("ghost margin cube count has to fit in index type");
+    let ghost_margin_volume_fraction = {
+        let ghost_margin_cubes = to_index!(
+            (ghost_particle_margin / cube_size).ceil(),
+            "ghost margin cube count"
+        );
 
-        let vol_subdomain = subdomain_cubes
-            .checked_cubed()
-            .expect("number of cubes per subdomain has to be representable in index type");
+        let vol_subdomain =
+            subdomain_cubes
+                .checked_cubed()
+                .ok_or(SubdomainGridError::IndexConversion(
+                    "number of cubes per subdomain",
+                ))?;
         let vol_margin = (ghost_margin_cubes * to_index!(2) + subdomain_cubes)
             .checked_cubed()
-            .expect(
-                "number of cubes per subdomain with margin has to be representable in index type",
-            )
+            .ok_or(SubdomainGridError::IndexConversion(
+                "number of cubes per subdomain with margin",
+            ))?
             - vol_subdomain;
 
+        let ghost_margin_volume_fraction = to_real!(vol_margin) / to_real!(vol_subdomain);
         info!(
             "The ghost margin volume is {:.2}% of the subdomain volume",
-            (to_real!(vol_margin) / to_real!(vol_subdomain)) * to_real!(100.0)
+            ghost_margin_volume_fraction * to_real!(100.0)
         );
         info!(
             "The ghost margin is {:.2} MC cells or {:.2} subdomains thick",
@@ -162,22 +490,36 @@ pub(crate) fn initialize_parameters<'a, I: Index, R: Real>(
         );
 
         if ghost_margin_cubes > subdomain_cubes / to_index!(2) {
-            panic!("The ghost margin is {ghost_margin_cubes} cubes thick (rounded up), while the subdomain only has an extent of {subdomain_cubes} cubes. The subdomain has to have at least twice the number of cubes ({})!", ghost_margin_cubes.times(2));
+            return Err(SubdomainGridError::GhostMarginTooThick {
+                ghost_margin_cubes,
+                subdomain_cubes,
+            });
         }
-    }
+
+        ghost_margin_volume_fraction
+    };
 
     // AABB of the particles
     let aabb = output_surface.grid.aabb();
 
+    let global_cells_per_dim = (|| -> Option<_> {
+        let [nx, ny, nz] = output_surface.grid.cells_per_dim();
+        Some([
+            <GlobalIndex as NumCast>::from(*nx)?,
+            <GlobalIndex as NumCast>::from(*ny)?,
+            <GlobalIndex as NumCast>::from(*nz)?,
+        ])
+    })()
+    .ok_or(SubdomainGridError::IndexConversion(
+        "number of global marching cubes cells per dimension",
+    ))?;
+
     let global_mc_grid = UniformCartesianCubeGrid3d::<GlobalIndex, R>::new(
         aabb.min(),
-        &output_surface
-            .grid
-            .cells_per_dim()
-            .map(|c| <GlobalIndex as NumCast>::from(c).unwrap()),
+        &global_cells_per_dim,
         cube_size,
     )
-    .context("construct initial global marching cubes cell grid")?;
+    .map_err(SubdomainGridError::GlobalGridConstruction)?;
     trace!("Initial global MC Grid: {:?}", global_mc_grid);
 
     // MC cubes along each coordinate axis of the entire global MC background grid
@@ -196,14 +538,16 @@ pub(crate) fn initialize_parameters<'a, I: Index, R: Real>(
             num_subdomains[2].checked_mul(subdomain_cubes_global)?,
         ])
     })()
-    .context("compute global number of marching cubes cells per dimension")?;
+    .ok_or(SubdomainGridError::IndexConversion(
+        "global number of marching cubes cells per dimension",
+    ))?;
 
     let global_mc_grid = UniformCartesianCubeGrid3d::<GlobalIndex, R>::new(
         &global_mc_grid.aabb().min(),
         &num_global_mc_cells,
         cube_size,
     )
-    .context("construct final global marching cubes cell grid")?;
+    .map_err(SubdomainGridError::GlobalGridConstruction)?;
     trace!("Global MC Grid: {:?}", global_mc_grid);
 
     // Convert number of subdomains back to local index type
@@ -214,15 +558,19 @@ pub(crate) fn initialize_parameters<'a, I: Index, R: Real>(
             I::from(num_subdomains[2])?,
         ])
     })()
-    .context("convert number of subdomains per dimension to local index type")?;
+    .ok_or(SubdomainGridError::IndexConversion(
+        "number of subdomains per dimension",
+    ))?;
 
     // Compute total number of subdomains
     let subdomain_count: I = (|| -> Option<_> {
         num_subdomains[0].checked_mul(&num_subdomains[1].checked_mul(&num_subdomains[2])?)
     })()
-    .context("compute total number of subdomains")?;
+    .ok_or(SubdomainGridError::IndexConversion(
+        "total number of subdomains",
+    ))?;
     // Edge length of a subdomain in absolute units
-    let subdomain_size = cube_size * subdomain_cubes.to_real_unchecked();
+    let subdomain_size = cube_size * to_real!(subdomain_cubes, "number of subdomain cubes");
     // Background grid of the subdomains
     let subdomain_grid = UniformCartesianCubeGrid3d::<I, R>::new(
         &global_mc_grid.aabb().min(),
@@ -230,6 +578,27 @@ pub(crate) fn initialize_parameters<'a, I: Index, R: Real>(
         subdomain_size,
     )?;
 
+    // Coordinates used for the computations inside of the subdomains (local coordinates would only quantize f32 positions)
+    let coordinates = if grid_parameters.local_f32_coordinates
+        && TypeId::of::<R>() != TypeId::of::<f32>()
+    {
+        // Upper bound for the local coordinates of all particles (including ghost particles) and grid points of a subdomain
+        let max_local_extent = subdomain_size + ghost_particle_margin * to_real!(4);
+        let coordinates = SubdomainCoordinates::local(&global_mc_grid, max_local_extent).ok_or(
+            SubdomainGridError::RealConversion("quantization step of local subdomain coordinates"),
+        )?;
+        info!(
+            "Using local f32 coordinates in subdomains (quantization step: {:.3e}).",
+            coordinates
+                .quantum
+                .and_then(|q| q.to_f64())
+                .unwrap_or(f64::NAN)
+        );
+        coordinates
+    } else {
+        SubdomainCoordinates::global(&global_mc_grid)
+    };
+
     {
         let nc = subdomain_cubes;
         let [nx, ny, nz] = subdomain_grid.cells_per_dim();
@@ -254,10 +623,20 @@ pub(crate) fn initialize_parameters<'a, I: Index, R: Real>(
         cube_size,
         subdomain_cubes,
         ghost_particle_margin,
+        ghost_margin_volume_fraction,
         global_marching_cubes_grid: global_mc_grid,
         subdomain_grid,
         chunk_size,
         global_neighborhood_list: parameters.global_neighborhood_list,
+        enable_simd: grid_parameters.enable_simd,
+        auto_tuning: grid_parameters.auto_tuning,
+        cost_model: SubdomainCostModel::new(subdomain_cubes_in as usize, kernel_cube_radius),
+        tabulated_kernel: parameters.tabulated_kernel.clone(),
+        vertex_provenance_particles: parameters
+            .vertex_provenance
+            .as_ref()
+            .map(|p| p.num_particles),
+        coordinates,
     })
 }
 
@@ -271,7 +650,7 @@ pub(crate) fn extract_narrow_band<I: Index, R: Real>(
     let compact_support_radius = parameters.compact_support_radius;
     let ghost_particle_margin = (compact_support_radius / parameters.cube_size).ceil()
         * parameters.cube_size
-        * to_real!(1.01);
+        * R::from_f64(1.01).unwrap();
 
     // AABB of the particles
     let aabb = {
@@ -373,10 +752,18 @@ pub(crate) fn decomposition<
 >(
     parameters: &ParametersSubdomainGrid<I, R>,
     particles: &[Vector3<R>],
-) -> Result<Subdomains<I>, anyhow::Error> {
+) -> Result<Subdomains<I>, SubdomainGridError<I, R>> {
     profile!("decomposition");
     info!("Starting classification of particles into subdomains.");
 
+    // Particles with non-finite coordinates cannot be assigned to any subdomain
+    if let Some(particle_idx) = particles
+        .par_iter()
+        .position_any(|p| !p.iter().all(|c| c.is_finite()))
+    {
+        return Err(SubdomainGridError::NonFiniteParticle(particle_idx));
+    }
+
     // Count the number of particles and ghost particles per subdomain (with thread local counters)
     let per_subdomain_counter_tls = ThreadLocal::<RefCell<MapType<I, usize>>>::new();
     {
@@ -513,14 +900,18 @@ pub(crate) fn decomposition<
     })
 }
 
-pub(crate) fn compute_global_densities_and_neighbors<I: Index, R: Real>(
+/// Computes the densities (and optionally the neighborhood lists) of all particles, the subdomains are processed using the real type `L`
+pub(crate) fn compute_global_densities_and_neighbors<I: Index, R: Real, L: Real>(
     parameters: &ParametersSubdomainGrid<I, R>,
     global_particles: &[Vector3<R>],
     subdomains: &Subdomains<I>,
-) -> (Vec<R>, Vec<Vec<usize>>) {
+) -> Result<(Vec<R>, Vec<Vec<usize>>), SubdomainGridError<I, R>> {
     profile!(parent, "compute_global_density_vector");
     info!("Starting computation of global density vector.");
 
+    let compact_support_radius: L = to_local!(parameters.compact_support_radius);
+    let particle_rest_mass: L = to_local!(parameters.particle_rest_mass);
+
     let global_particle_densities = Mutex::new(vec![R::zero(); global_particles.len()]);
     let global_neighbors = Mutex::new(vec![Vec::new(); global_particles.len()]);
 
@@ -536,14 +927,14 @@ pub(crate) fn compute_global_densities_and_neighbors<I: Index, R: Real>(
         is_inside: Vec<bool>,
     }
 
-    let workspace_tls = ThreadLocal::<RefCell<SubdomainWorkspace<R>>>::new();
+    let workspace_tls = ThreadLocal::<RefCell<SubdomainWorkspace<L>>>::new();
 
     subdomains
         .flat_subdomain_indices
         .par_iter()
         .copied()
         .zip(subdomains.per_subdomain_particles.par_iter())
-        .for_each(|(flat_subdomain_idx, subdomain_particle_indices)| {
+        .try_for_each(|(flat_subdomain_idx, subdomain_particle_indices)| {
             profile!("subdomain density computation", parent = parent);
 
             // Obtain thread local workspace and clear it
@@ -559,54 +950,65 @@ pub(crate) fn compute_global_densities_and_neighbors<I: Index, R: Real>(
             let flat_subdomain_idx: I = flat_subdomain_idx;
             let subdomain_particle_indices: &[usize] = subdomain_particle_indices.as_slice();
 
+            // Get the cell index and AABB of the subdomain
+            let subdomain_idx = parameters
+                .subdomain_grid
+                .try_unflatten_cell_index(flat_subdomain_idx)
+                .ok_or(SubdomainGridError::InvalidSubdomain(flat_subdomain_idx))?;
+            let subdomain_aabb = parameters.subdomain_grid.cell_aabb(&subdomain_idx);
+            let frame = parameters.subdomain_frame(subdomain_idx.index())?;
+
             // Collect all particle positions of this subdomain
             {
                 profile!("collect subdomain data");
-                gather_subdomain_data(
+                gather_subdomain_positions(
+                    &frame,
                     global_particles,
                     subdomain_particle_indices,
                     subdomain_particles,
-                );
+                )?;
             }
 
-            // Get the cell index and AABB of the subdomain
-            let subdomain_idx = parameters
-                .subdomain_grid
-                .try_unflatten_cell_index(flat_subdomain_idx)
-                .expect("Subdomain cell does not exist");
-            let subdomain_aabb = parameters.subdomain_grid.cell_aabb(&subdomain_idx);
-
             let margin_aabb = {
                 let mut margin_aabb = subdomain_aabb.clone();
                 // TODO: Verify if we can omit this extra margin?
                 margin_aabb.grow_uniformly(parameters.ghost_particle_margin * to_real!(1.5));
-                margin_aabb
+                (|| {
+                    Some(Aabb3d::new(
+                        frame.to_local(margin_aabb.min())?,
+                        frame.to_local(margin_aabb.max())?,
+                    ))
+                })()
+                .ok_or(SubdomainGridError::RealConversion(
+                    "local coordinates of the subdomain AABB",
+                ))?
             };
 
             {
                 profile!("initialize particle filter");
                 is_inside.clear();
                 reserve_total(is_inside, subdomain_particle_indices.len());
+                // Classify the particles in global coordinates for consistency with the decomposition
                 is_inside.extend(
-                    subdomain_particles
+                    subdomain_particle_indices
                         .iter()
-                        .map(|p| subdomain_aabb.contains_point(p)),
+                        .map(|&i| subdomain_aabb.contains_point(&global_particles[i])),
                 );
             }
 
-            neighborhood_search_spatial_hashing_flat_filtered::<I, R>(
+            neighborhood_search_spatial_hashing_flat_filtered::<I, L>(
                 &margin_aabb,
                 &subdomain_particles,
-                parameters.compact_support_radius,
+                compact_support_radius,
                 neighborhood_lists,
                 |i| is_inside[i],
             );
 
-            sequential_compute_particle_densities_filtered::<I, R, _>(
+            sequential_compute_particle_densities_filtered::<I, L, _>(
                 &subdomain_particles,
                 neighborhood_lists,
-                parameters.compact_support_radius,
-                parameters.particle_rest_mass,
+                compact_support_radius,
+                particle_rest_mass,
                 particle_densities,
                 |i| is_inside[i],
             );
@@ -627,9 +1029,11 @@ pub(crate) fn compute_global_densities_and_neighbors<I: Index, R: Real>(
                     )
                     // Update density values only for particles inside of the subdomain (ghost particles have wrong values)
                     .filter(|(is_inside, _)| *is_inside)
-                    .for_each(|(_, (particle_idx, density))| {
-                        global_particle_densities[particle_idx] = density;
-                    });
+                    .try_for_each(|(_, (particle_idx, density))| {
+                        global_particle_densities[particle_idx] =
+                            to_real!(density, "particle density");
+                        Ok::<_, SubdomainGridError<I, R>>(())
+                    })?;
             }
 
             // Write particle neighbor lists into global storage
@@ -656,7 +1060,9 @@ pub(crate) fn compute_global_densities_and_neighbors<I: Index, R: Real>(
                             .collect();
                     });
             }
-        });
+
+            Ok::<_, SubdomainGridError<I, R>>(())
+        })?;
 
     let global_particle_densities = global_particle_densities.into_inner();
     let global_neighbors = global_neighbors.into_inner();
@@ -672,10 +1078,12 @@ pub(crate) fn compute_global_densities_and_neighbors<I: Index, R: Real>(
     }
     */
 
-    (global_particle_densities, global_neighbors)
+    Ok((global_particle_densities, global_neighbors))
 }
 
+#[derive(Clone)]
 pub(crate) struct SurfacePatch<I: Index, R: Real> {
+    pub subdomain_index: I,
     pub vertices: Vec<Vector3<R>>,
     pub triangles: Vec<[usize; 3]>,
     pub vertex_inside_count: usize,
@@ -683,49 +1091,248 @@ pub(crate) struct SurfacePatch<I: Index, R: Real> {
     pub vertex_inside_flags: Vec<bool>,
     pub triangle_inside_flags: Vec<bool>,
     pub exterior_vertex_edge_indices: Vec<(I, EdgeIndex<I>)>,
+    /// Global indices and level-set contributions of the nearest particles per vertex (empty if provenance is disabled)
+    pub vertex_provenance: Vec<(usize, R)>,
+}
+
+/// Spatial hash of the particles of a single subdomain to find the nearest particles contributing to mesh vertices
+#[derive(Default)]
+struct ProvenanceLookup<R: Real> {
+    /// Origin of the hash grid
+    origin: Vector3<R>,
+    /// Cell size of the hash grid (equal to the compact support radius)
+    cell_size: R,
+    /// Squared compact support radius
+    squared_support: R,
+    /// Map from hash grid cell to the local indices of the enclosed particles
+    cells: MapType<[i64; 3], Vec<usize>>,
+    /// Buffer for the contributing particles of a vertex (squared distance, local particle index)
+    candidates: Vec<(R, usize)>,
+}
+
+impl<R: Real> ProvenanceLookup<R> {
+    fn cell_of(&self, point: &Vector3<R>) -> [i64; 3] {
+        let relative = (point - self.origin).unscale(self.cell_size);
+        [relative.x, relative.y, relative.z].map(|c| c.floor().to_i64().unwrap_or(i64::MAX))
+    }
+
+    /// Rebuilds the spatial hash for the given particles of a subdomain
+    fn rebuild(&mut self, origin: Vector3<R>, compact_support_radius: R, particles: &[Vector3<R>]) {
+        self.origin = origin;
+        self.cell_size = compact_support_radius;
+        self.squared_support = compact_support_radius * compact_support_radius;
+        self.cells.clear();
+        for (i, p) in particles.iter().enumerate() {
+            let cell = self.cell_of(p);
+            self.cells.entry(cell).or_default().push(i);
+        }
+    }
+
+    /// Appends exactly `k` entries with the nearest particles contributing to the given vertex to the output (padded with `usize::MAX`)
+    fn append_nearest(
+        &mut self,
+        vertex: &Vector3<R>,
+        k: usize,
+        particles: &[Vector3<R>],
+        global_particle_indices: &[usize],
+        weight: impl Fn(usize, R) -> R,
+        output: &mut Vec<(usize, R)>,
+    ) {
+        self.candidates.clear();
+        let [i, j, l] = self.cell_of(vertex);
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for dl in -1..=1 {
+                    let cell = [
+                        i.saturating_add(di),
+                        j.saturating_add(dj),
+                        l.saturating_add(dl),
+                    ];
+                    for &p in self.cells.get(&cell).into_iter().flatten() {
+                        let dist_sq = (particles[p] - vertex).norm_squared();
+                        if dist_sq < self.squared_support {
+                            self.candidates.push((dist_sq, p));
+                        }
+                    }
+                }
+            }
+        }
+
+        self.candidates.sort_unstable_by(|a, b| {
+            a.0.partial_cmp(&b.0)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.1.cmp(&b.1))
+        });
+
+        output.extend(
+            self.candidates
+                .iter()
+                .take(k)
+                .map(|&(dist_sq, p)| (global_particle_indices[p], weight(p, dist_sq))),
+        );
+        output.extend(
+            std::iter::repeat((usize::MAX, R::zero()))
+                .take(k.saturating_sub(self.candidates.len())),
+        );
+    }
+}
+
+/// Returns the number of particles (including ghost particles) of the largest subdomain
+fn largest_subdomain_particles<I: Index>(subdomains: &Subdomains<I>) -> usize {
+    subdomains
+        .per_subdomain_particles
+        .iter()
+        .map(|p| p.len())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Returns the maximum number of particles such that a subdomain will be considered "sparse" (relative to the largest subdomain)
+fn sparse_particle_limit<I: Index, R: Real>(
+    max_particles: usize,
+) -> Result<usize, SubdomainGridError<I, R>> {
+    Ok((to_real!(max_particles) * to_real!(0.05))
+        .ceil()
+        .to_usize()
+        .ok_or(SubdomainGridError::IndexConversion(
+            "sparse subdomain limit",
+        ))?
+        .max(100))
+}
+
+/// Collects statistics of the subdomain decomposition
+pub(crate) fn subdomain_stats<I: Index, R: Real>(
+    parameters: &ParametersSubdomainGrid<I, R>,
+    subdomains: &Subdomains<I>,
+) -> Result<SubdomainGridStats, SubdomainGridError<I, R>> {
+    let max_particles = largest_subdomain_particles(subdomains);
+    let sparse_limit = parameters.sparse_particle_limit(subdomains)?;
+    let num_sparse_subdomains = subdomains
+        .per_subdomain_particles
+        .iter()
+        .filter(|p| p.len() <= sparse_limit)
+        .count();
+
+    Ok(SubdomainGridStats {
+        subdomain_cubes: parameters.subdomain_cubes.to_usize().unwrap_or(usize::MAX),
+        num_subdomains: parameters
+            .subdomain_grid
+            .cells_per_dim()
+            .iter()
+            .map(|n| n.to_usize().unwrap_or(usize::MAX))
+            .fold(1, usize::saturating_mul),
+        num_nonempty_subdomains: subdomains.flat_subdomain_indices.len(),
+        num_dense_subdomains: subdomains.flat_subdomain_indices.len() - num_sparse_subdomains,
+        num_sparse_subdomains,
+        num_reused_subdomains: 0,
+        sparse_particle_limit: sparse_limit,
+        largest_subdomain_particles: max_particles,
+        total_subdomain_particles: subdomains
+            .per_subdomain_particles
+            .iter()
+            .map(|p| p.len())
+            .sum(),
+        ghost_margin_cubes: (parameters.ghost_particle_margin / parameters.cube_size)
+            .to_f64()
+            .unwrap_or(f64::NAN),
+        ghost_margin_volume_fraction: parameters
+            .ghost_margin_volume_fraction
+            .to_f64()
+            .unwrap_or(f64::NAN),
+    })
 }
 
 // TODO: Reduce code duplication between dense and sparse
-pub(crate) fn reconstruction<I: Index, R: Real>(
+/// Reconstructs the surface patches of all subdomains, the subdomains are processed using the real type `L`
+///
+/// If a `selection` flag per subdomain is given, only the patches of the selected subdomains are returned (in the same order).
+pub(crate) fn reconstruction<I: Index, R: Real, L: Real>(
     parameters: &ParametersSubdomainGrid<I, R>,
     global_particles: &[Vector3<R>],
     global_particle_densities: &[R],
     subdomains: &Subdomains<I>,
-) -> Vec<SurfacePatch<I, R>> {
+    selection: Option<&[bool]>,
+) -> Result<Vec<SurfacePatch<I, R>>, SubdomainGridError<I, R>> {
     profile!(parent, "reconstruction");
 
     let squared_support = parameters.compact_support_radius * parameters.compact_support_radius;
     // Add 1% so that we don't exclude grid points that are just on the kernel boundary
     let squared_support_with_margin = squared_support * to_real!(1.01);
     // Compute radial distance in terms of grid points we have to evaluate for each particle
-    let cube_radius = I::from((parameters.compact_support_radius / parameters.cube_size).ceil())
-        .expect("kernel radius in cubes has to fit in index type");
-    // Kernel
-    let kernel = CubicSplineKernel::new(parameters.compact_support_radius);
-    //let kernel = DiscreteSquaredDistanceCubicKernel::new::<f64>(1000, parameters.compact_support_radius);
+    let cube_radius = to_index!(
+        (parameters.compact_support_radius / parameters.cube_size).ceil(),
+        "kernel radius in cubes"
+    );
 
-    let mc_total_cells = parameters.subdomain_cubes.cubed();
-    let mc_total_points = (parameters.subdomain_cubes + I::one()).cubed();
+    // Parameters in the real type used inside of the subdomains
+    let compact_support_radius: L = to_local!(parameters.compact_support_radius);
+    let squared_support_with_margin: L = to_local!(squared_support_with_margin);
+    let cube_size: L = to_local!(parameters.cube_size);
+    let particle_rest_mass: L = to_local!(parameters.particle_rest_mass);
+    let surface_threshold: L = to_local!(parameters.surface_threshold);
+    let local_particle_densities = global_particle_densities
+        .par_iter()
+        .map(|rho| rho.try_convert::<L>())
+        .collect::<Option<Vec<_>>>()
+        .ok_or(SubdomainGridError::RealConversion("particle density"))?;
 
-    assert!(
-        mc_total_points.to_usize().is_some(),
-        "number of mc cubes per subdomain must be fit into usize"
+    // Kernel
+    let kernel = CubicSplineKernel::new(compact_support_radius);
+    // Optional lookup table for the kernel evaluation of the level-set function
+    let tabulated_kernel = match &parameters.tabulated_kernel {
+        Some(table_parameters) => {
+            let h = compact_support_radius;
+            let tabulated_kernel = match table_parameters.max_relative_error {
+                Some(max_relative_error) => TabulatedKernel::with_estimated_max_error(
+                    &kernel,
+                    h,
+                    table_parameters.resolution,
+                    to_local!(max_relative_error) * kernel.evaluate(L::zero()),
+                ),
+                None => TabulatedKernel::new(&kernel, h, table_parameters.resolution),
+            };
+            info!(
+                "Using tabulated kernel with resolution {} (estimated max. relative error: {:.3e}).",
+                tabulated_kernel.resolution(),
+                (tabulated_kernel.estimated_max_error() / kernel.evaluate(L::zero()))
+                    .to_f64()
+                    .unwrap_or(f64::NAN)
+            );
+            Some(tabulated_kernel)
+        }
+        None => None,
+    };
+    // Kernel splatting for the level-set evaluation of dense subdomains
+    let splatter = DensitySplatter::new(
+        compact_support_radius,
+        squared_support_with_margin,
+        parameters.enable_simd,
+        tabulated_kernel,
+    );
+    info!(
+        "Using {:?} backend for the level-set evaluation of dense subdomains.",
+        splatter.backend()
     );
 
-    let max_particles = subdomains
-        .per_subdomain_particles
-        .iter()
-        .map(|p| p.len())
-        .max()
-        .unwrap_or(0);
+    let mc_total_cells =
+        parameters
+            .subdomain_cubes
+            .checked_cubed()
+            .ok_or(SubdomainGridError::IndexConversion(
+                "number of mc cubes per subdomain",
+            ))?;
+    let mc_total_points = (parameters.subdomain_cubes + I::one())
+        .checked_cubed()
+        .and_then(|n| n.to_usize())
+        .ok_or(SubdomainGridError::IndexConversion(
+            "number of mc points per subdomain",
+        ))?;
+
+    let max_particles = largest_subdomain_particles(subdomains);
     info!("Largest subdomain has {} particles.", max_particles);
 
     // Maximum number of particles such that a subdomain will be considered "sparse"
-    let sparse_limit = (to_real!(max_particles) * to_real!(0.05))
-        .ceil()
-        .to_usize()
-        .unwrap()
-        .max(100);
+    let sparse_limit = parameters.sparse_particle_limit(subdomains)?;
     info!(
         "Subdomains with {} or less particles will be considered sparse.",
         sparse_limit
@@ -734,11 +1341,11 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
     info!("Starting reconstruction (level-set evaluation and local triangulation).");
 
     // Returns a unique identifier for any edge index of a subdomain that can be later used for stitching
-    let globalize_local_edge = |mc_grid: &UniformCartesianCubeGrid3d<I, R>,
+    let globalize_local_edge = |mc_grid: &UniformCartesianCubeGrid3d<I, L>,
                                 subdomain_grid: &UniformCartesianCubeGrid3d<I, R>,
                                 subdomain_index: I,
                                 local_edge: &EdgeIndex<I>|
-     -> (I, EdgeIndex<I>) {
+     -> Result<(I, EdgeIndex<I>), SubdomainGridError<I, R>> {
         // We globalize the boundary edge index by translating the local edge index to the subdomain
         // where it lies on the lower boundary of that domain.
 
@@ -762,12 +1369,12 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
 
         if !is_max[0] && !is_max[1] {
             // Edge is already in the correct subdomain
-            (subdomain_index, local_edge.clone())
+            Ok((subdomain_index, local_edge.clone()))
         } else {
             // We have to translate to the neighboring subdomain (+1 in all directions where is_max == true)
             let subdomain_cell = subdomain_grid
                 .try_unflatten_cell_index(subdomain_index)
-                .expect("invalid subdomain index");
+                .ok_or(SubdomainGridError::InvalidSubdomain(subdomain_index))?;
 
             let mut target_subdomain_ijk = subdomain_cell.index().clone();
             let mut target_local_origin_ijk = local_edge.origin().index().clone();
@@ -790,18 +1397,20 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
                 }
             }
 
-            let target_subdomain = subdomain_grid
-                .get_cell(target_subdomain_ijk)
-                .expect("target subdomain has to exist");
+            let target_subdomain = subdomain_grid.get_cell(target_subdomain_ijk).ok_or(
+                SubdomainGridError::InvalidGridIndex("target subdomain of a boundary edge"),
+            )?;
             let flat_target_subdomain = subdomain_grid.flatten_cell_index(&target_subdomain);
 
             // We re-use the same marching cubes domain here because the domain is anyway rectangular,
             // therefore this shift gives the same result
             let new_local_edge = mc_grid
                 .get_edge(target_local_origin_ijk, local_edge.axis())
-                .expect("failed to translate edge");
+                .ok_or(SubdomainGridError::InvalidGridIndex(
+                    "translated boundary edge",
+                ))?;
 
-            (flat_target_subdomain, new_local_edge)
+            Ok((flat_target_subdomain, new_local_edge))
         }
     };
 
@@ -815,11 +1424,75 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
         levelset_grid: Vec<R>,
         // Cache for indices
         index_cache: Vec<I>,
+        // Coordinates of the subdomain grid points along each axis
+        grid_coordinates: [Vec<R>; 3],
+        // Spatial hash of the subdomain particles for the vertex provenance
+        provenance_lookup: ProvenanceLookup<R>,
     }
 
-    let workspace_tls = ThreadLocal::<RefCell<SubdomainWorkspace<I, R>>>::new();
+    let workspace_tls = ThreadLocal::<RefCell<SubdomainWorkspace<I, L>>>::new();
+
+    // Computes the coordinates of the grid points of a subdomain along each axis
+    let compute_grid_coordinates = |frame: &SubdomainFrame<R>,
+                                    subdomain_ijk: &[I; 3],
+                                    points_per_dim: [usize; 3],
+                                    grid_coordinates: &mut [Vec<L>; 3]|
+     -> Result<(), SubdomainGridError<I, R>> {
+        // Use global coordinate calculation for consistency with neighboring domains
+        let global_points_per_dim = parameters.global_marching_cubes_grid.points_per_dim();
+        for (axis, coordinates) in grid_coordinates.iter_mut().enumerate() {
+            let offset = (|| -> Option<GlobalIndex> {
+                let to_global = |i: I| <GlobalIndex as NumCast>::from(i);
+                to_global(subdomain_ijk[axis])?.checked_mul(to_global(parameters.subdomain_cubes)?)
+            })()
+            .ok_or(SubdomainGridError::IndexConversion(
+                "global marching cubes point index",
+            ))?;
+
+            coordinates.clear();
+            for local_point in 0..points_per_dim[axis] {
+                let global_point = offset + local_point as GlobalIndex;
+                if global_point >= global_points_per_dim[axis] {
+                    return Err(SubdomainGridError::InvalidGridIndex(
+                        "point of the global marching cubes grid",
+                    ));
+                }
+                coordinates.push(frame.grid_point_to_local(axis, global_point).ok_or(
+                    SubdomainGridError::RealConversion("local coordinates of a grid point"),
+                )?);
+            }
+        }
+        Ok(())
+    };
+
+    /// Vertices and vertex provenance of a surface patch in global coordinates
+    type GlobalPatchData<R> = (Vec<Vector3<R>>, Vec<(usize, R)>);
 
-    let reconstruct_dense = |flat_subdomain_idx: I, subdomain_particle_indices: &Vec<usize>| {
+    // Converts the vertices and provenance data of a surface patch from the coordinates used inside of the subdomain
+    let globalize_patch_data = |frame: &SubdomainFrame<R>,
+                                vertices: Vec<Vector3<L>>,
+                                vertex_provenance: Vec<(usize, L)>|
+     -> Result<GlobalPatchData<R>, SubdomainGridError<I, R>> {
+        let vertices = vertices
+            .iter()
+            .map(|v| frame.to_global(v))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(SubdomainGridError::RealConversion(
+                "global coordinates of a vertex",
+            ))?;
+        let vertex_provenance = vertex_provenance
+            .iter()
+            .map(|(p, w)| Some((*p, w.try_convert()?)))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(SubdomainGridError::RealConversion(
+                "vertex provenance weight",
+            ))?;
+        Ok((vertices, vertex_provenance))
+    };
+
+    let reconstruct_dense = |flat_subdomain_idx: I,
+                             subdomain_particle_indices: &Vec<usize>|
+     -> Result<SurfacePatch<I, R>, SubdomainGridError<I, R>> {
         // Obtain thread local workspace and clear it
         let mut workspace = workspace_tls.get_or_default().borrow_mut();
 
@@ -828,47 +1501,80 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
             subdomain_particle_densities,
             levelset_grid,
             index_cache: _index_cache,
+            grid_coordinates,
+            provenance_lookup,
         } = &mut *workspace;
 
         let flat_subdomain_idx: I = flat_subdomain_idx;
         let subdomain_particle_indices: &[usize] = subdomain_particle_indices.as_slice();
 
+        // Get the cell index and AABB of the subdomain
+        let subdomain_idx = parameters
+            .subdomain_grid
+            .try_unflatten_cell_index(flat_subdomain_idx)
+            .ok_or(SubdomainGridError::InvalidSubdomain(flat_subdomain_idx))?;
+        let subdomain_aabb = parameters.subdomain_grid.cell_aabb(&subdomain_idx);
+        let frame = parameters.subdomain_frame(subdomain_idx.index())?;
+
         // Collect all particle positions and densities of this subdomain
         {
             //profile!("collect subdomain data");
-            gather_subdomain_data(
+            gather_subdomain_positions(
+                &frame,
                 global_particles,
                 subdomain_particle_indices,
                 subdomain_particles,
-            );
+            )?;
             gather_subdomain_data(
-                global_particle_densities,
+                &local_particle_densities,
                 subdomain_particle_indices,
                 subdomain_particle_densities,
             );
         }
 
-        // Get the cell index and AABB of the subdomain
-        let subdomain_idx = parameters
-            .subdomain_grid
-            .try_unflatten_cell_index(flat_subdomain_idx)
-            .expect("Subdomain cell does not exist");
-        let subdomain_aabb = parameters.subdomain_grid.cell_aabb(&subdomain_idx);
-
-        let mc_grid = UniformCartesianCubeGrid3d::new(
-            subdomain_aabb.min(),
-            &[parameters.subdomain_cubes; 3],
-            parameters.cube_size,
-        )
-        .unwrap();
+        let mc_grid =
+            UniformCartesianCubeGrid3d::new(
+                &frame.local_grid_origin(&subdomain_aabb).ok_or(
+                    SubdomainGridError::RealConversion("local coordinates of the subdomain origin"),
+                )?,
+                &[parameters.subdomain_cubes; 3],
+                cube_size,
+            )
+            .map_err(local_grid_error)?;
 
-        levelset_grid.fill(R::zero());
-        levelset_grid.resize(mc_total_points.to_usize().unwrap(), R::zero());
+        levelset_grid.fill(L::zero());
+        levelset_grid.resize(mc_total_points, L::zero());
 
         {
             profile!("density grid loop");
 
             let extents = mc_grid.points_per_dim();
+            let points_per_dim = (|| -> Option<_> {
+                Some([
+                    extents[0].to_usize()?,
+                    extents[1].to_usize()?,
+                    extents[2].to_usize()?,
+                ])
+            })()
+            .ok_or(SubdomainGridError::IndexConversion(
+                "number of points of the subdomain marching cubes grid",
+            ))?;
+
+            compute_grid_coordinates(
+                &frame,
+                subdomain_idx.index(),
+                points_per_dim,
+                grid_coordinates,
+            )?;
+
+            let grid = SplattingGrid {
+                points_per_dim,
+                coordinates: [
+                    &grid_coordinates[0],
+                    &grid_coordinates[1],
+                    &grid_coordinates[2],
+                ],
+            };
 
             for (p_i, rho_i) in subdomain_particles
                 .iter()
@@ -880,90 +1586,37 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
 
                 // Compute lower and upper bounds of the grid points possibly affected by the particle
                 // We want to loop over the vertices of the enclosing cells plus all points in `cube_radius` distance from the cell
+                // (the splatter clamps the upper bounds to the grid, negative bounds result in empty ranges)
 
-                let lower = [
-                    (particle_cell[0] - cube_radius).max(I::zero()),
-                    (particle_cell[1] - cube_radius).max(I::zero()),
-                    (particle_cell[2] - cube_radius).max(I::zero()),
-                ];
+                let lower = [0, 1, 2].map(|d| {
+                    (particle_cell[d] - cube_radius)
+                        .max(I::zero())
+                        .to_usize()
+                        .unwrap_or(0)
+                });
 
-                let upper = [
+                let upper = [0, 1, 2].map(|d| {
                     // We add 2 because
                     //  - we want to loop over all grid points of the cell (+1 for upper points) + the radius
                     //  - the upper range limit is exclusive (+1)
-                    (particle_cell[0] + cube_radius + I::two()).min(extents[0]),
-                    (particle_cell[1] + cube_radius + I::two()).min(extents[1]),
-                    (particle_cell[2] + cube_radius + I::two()).min(extents[2]),
-                ];
-
-                // Loop over all grid points around the enclosing cell
-                for i in I::range(lower[0], upper[0]).iter() {
-                    for j in I::range(lower[1], upper[1]).iter() {
-                        for k in I::range(lower[2], upper[2]).iter() {
-                            let point_ijk = [i, j, k];
-                            let local_point = mc_grid
-                                .get_point(point_ijk)
-                                .expect("point has to be part of the subdomain grid");
-                            //let point_coordinates = mc_grid.point_coordinates(&point);
-
-                            let subdomain_ijk = subdomain_idx.index();
-                            let mc_cells_per_subdomain = mc_grid.cells_per_dim();
-
-                            fn local_to_global_point_ijk<I: Index>(
-                                local_point_ijk: [I; 3],
-                                subdomain_ijk: [I; 3],
-                                cells_per_subdomain: [I; 3],
-                            ) -> [GlobalIndex; 3] {
-                                let local_point_ijk = local_point_ijk
-                                    .map(|i| <GlobalIndex as NumCast>::from(i).unwrap());
-                                let subdomain_ijk = subdomain_ijk
-                                    .map(|i| <GlobalIndex as NumCast>::from(i).unwrap());
-                                let cells_per_subdomain = cells_per_subdomain
-                                    .map(|i| <GlobalIndex as NumCast>::from(i).unwrap());
-                                let [i, j, k] = local_point_ijk;
-
-                                [
-                                    subdomain_ijk[0] * cells_per_subdomain[0] + i,
-                                    subdomain_ijk[1] * cells_per_subdomain[1] + j,
-                                    subdomain_ijk[2] * cells_per_subdomain[2] + k,
-                                ]
-                            }
-
-                            // Use global coordinate calculation for consistency with neighboring domains
-                            let global_point_ijk = local_to_global_point_ijk(
-                                point_ijk,
-                                subdomain_ijk.clone(),
-                                mc_cells_per_subdomain.clone(),
-                            );
-                            let global_point = parameters
-                                .global_marching_cubes_grid
-                                .get_point(global_point_ijk)
-                                .expect("point has to be part of the global mc grid");
-                            let point_coordinates = parameters
-                                .global_marching_cubes_grid
-                                .point_coordinates(&global_point);
-
-                            let dx = p_i - point_coordinates;
-                            let dx_norm_sq = dx.norm_squared();
-
-                            if dx_norm_sq < squared_support_with_margin {
-                                let v_i = parameters.particle_rest_mass / rho_i;
-                                let r = dx_norm_sq.sqrt();
-                                let w_ij = kernel.evaluate(r);
-                                //let w_ij = kernel.evaluate(dx_norm_sq);
-
-                                let interpolated_value = v_i * w_ij;
+                    (particle_cell[d] + cube_radius + I::two())
+                        .to_usize()
+                        .unwrap_or(0)
+                });
 
-                                let flat_point_idx = mc_grid.flatten_point_index(&local_point);
-                                let flat_point_idx = flat_point_idx.to_usize().unwrap();
-                                levelset_grid[flat_point_idx] += interpolated_value;
-                            }
-                        }
-                    }
-                }
+                let v_i = particle_rest_mass / rho_i;
+                splatter.splat_particle(&grid, levelset_grid, lower, upper, &p_i, v_i);
             }
         }
 
+        if parameters.vertex_provenance_particles.is_some() {
+            provenance_lookup.rebuild(
+                *mc_grid.aabb().min(),
+                compact_support_radius,
+                subdomain_particles,
+            );
+        }
+
         let mut vertices = Vec::new();
         let mut triangles = Vec::new();
 
@@ -974,6 +1627,7 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
         let mut triangle_inside_flags = Vec::new();
 
         let mut exterior_vertex_edge_indices = Vec::new();
+        let mut vertex_provenance = Vec::new();
 
         let mut edge_to_vertex = new_map();
 
@@ -981,68 +1635,96 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
             profile!("mc triangulation loop");
 
             for flat_cell_idx in I::range(I::zero(), mc_total_cells).iter() {
-                let cell = mc_grid.try_unflatten_cell_index(flat_cell_idx).unwrap();
+                let cell = mc_grid.try_unflatten_cell_index(flat_cell_idx).ok_or(
+                    SubdomainGridError::InvalidGridIndex(
+                        "cell of the subdomain marching cubes grid",
+                    ),
+                )?;
 
                 let mut vertices_inside = [true; 8];
                 for local_point_index in 0..8 {
-                    let point = cell.global_point_index_of(local_point_index).unwrap();
+                    let point = cell.global_point_index_of(local_point_index).ok_or(
+                        SubdomainGridError::InvalidGridIndex("point of a marching cubes cell"),
+                    )?;
                     let flat_point_idx = mc_grid.flatten_point_index(&point);
-                    let flat_point_idx = flat_point_idx.to_usize().unwrap();
+                    let flat_point_idx = flat_point_idx
+                        .to_usize()
+                        .ok_or(SubdomainGridError::IndexConversion("flat point index"))?;
                     // Get value of density map
                     let density_value = levelset_grid[flat_point_idx];
                     // Update inside/outside surface flag
-                    vertices_inside[local_point_index] =
-                        density_value > parameters.surface_threshold;
+                    vertices_inside[local_point_index] = density_value > surface_threshold;
                 }
 
                 for triangle in marching_cubes_triangulation_iter(&vertices_inside) {
                     let mut global_triangle = [0; 3];
                     for (v_idx, local_edge_index) in triangle.iter().copied().enumerate() {
-                        let edge = cell
-                            .global_edge_index_of(local_edge_index as usize)
-                            .unwrap();
-                        let vertex_index = *edge_to_vertex.entry(edge).or_insert_with(|| {
-                            // TODO: Nonlinear interpolation
-
-                            let origin_coords = mc_grid.point_coordinates(&edge.origin());
-                            let target_coords = mc_grid.point_coordinates(&edge.target());
-
-                            let flat_origin_idx = mc_grid
-                                .flatten_point_index(&edge.origin())
-                                .to_usize()
-                                .unwrap();
-                            let flat_target_idx = mc_grid
-                                .flatten_point_index(&edge.target())
-                                .to_usize()
-                                .unwrap();
-
-                            let origin_value = levelset_grid[flat_origin_idx];
-                            let target_value = levelset_grid[flat_target_idx];
-
-                            let alpha = (parameters.surface_threshold - origin_value)
-                                / (target_value - origin_value);
-                            let interpolated_coords =
-                                origin_coords * (R::one() - alpha) + target_coords * alpha;
-                            let vertex_coords = interpolated_coords;
-
-                            vertices.push(vertex_coords);
-                            let vertex_index = vertices.len() - 1;
-
-                            let is_interior_vertex = !mc_grid.is_boundary_edge(&edge);
-                            vertex_inside_count += is_interior_vertex as usize;
-                            vertex_inside_flags.push(is_interior_vertex);
-
-                            if !is_interior_vertex {
-                                exterior_vertex_edge_indices.push(globalize_local_edge(
-                                    &mc_grid,
-                                    &parameters.subdomain_grid,
-                                    flat_subdomain_idx,
-                                    &edge,
-                                ));
-                            }
+                        let edge = cell.global_edge_index_of(local_edge_index as usize).ok_or(
+                            SubdomainGridError::InvalidGridIndex("edge of a marching cubes cell"),
+                        )?;
+                        let vertex_index = match edge_to_vertex.entry(edge) {
+                            Entry::Occupied(entry) => *entry.get(),
+                            Entry::Vacant(entry) => {
+                                // TODO: Nonlinear interpolation
+
+                                let origin_coords = mc_grid.point_coordinates(&edge.origin());
+                                let target_coords = mc_grid.point_coordinates(&edge.target());
+
+                                let flat_origin_idx = mc_grid
+                                    .flatten_point_index(&edge.origin())
+                                    .to_usize()
+                                    .ok_or(SubdomainGridError::IndexConversion(
+                                        "flat point index",
+                                    ))?;
+                                let flat_target_idx = mc_grid
+                                    .flatten_point_index(&edge.target())
+                                    .to_usize()
+                                    .ok_or(SubdomainGridError::IndexConversion(
+                                        "flat point index",
+                                    ))?;
+
+                                let origin_value = levelset_grid[flat_origin_idx];
+                                let target_value = levelset_grid[flat_target_idx];
+
+                                let alpha = (surface_threshold - origin_value)
+                                    / (target_value - origin_value);
+                                let interpolated_coords =
+                                    origin_coords * (L::one() - alpha) + target_coords * alpha;
+                                let vertex_coords = interpolated_coords;
+
+                                vertices.push(vertex_coords);
+                                let vertex_index = vertices.len() - 1;
+
+                                if let Some(k) = parameters.vertex_provenance_particles {
+                                    provenance_lookup.append_nearest(
+                                        &vertex_coords,
+                                        k,
+                                        subdomain_particles,
+                                        subdomain_particle_indices,
+                                        |p, dist_sq| {
+                                            (particle_rest_mass / subdomain_particle_densities[p])
+                                                * kernel.evaluate(dist_sq.sqrt())
+                                        },
+                                        &mut vertex_provenance,
+                                    );
+                                }
 
-                            vertex_index
-                        });
+                                let is_interior_vertex = !mc_grid.is_boundary_edge(&edge);
+                                vertex_inside_count += is_interior_vertex as usize;
+                                vertex_inside_flags.push(is_interior_vertex);
+
+                                if !is_interior_vertex {
+                                    exterior_vertex_edge_indices.push(globalize_local_edge(
+                                        &mc_grid,
+                                        &parameters.subdomain_grid,
+                                        flat_subdomain_idx,
+                                        &edge,
+                                    )?);
+                                }
+
+                                *entry.insert(vertex_index)
+                            }
+                        };
 
                         global_triangle[v_idx] = vertex_index;
                     }
@@ -1059,7 +1741,11 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
             }
         }
 
-        SurfacePatch {
+        let (vertices, vertex_provenance) =
+            globalize_patch_data(&frame, vertices, vertex_provenance)?;
+
+        Ok(SurfacePatch {
+            subdomain_index: flat_subdomain_idx,
             vertices,
             triangles,
             vertex_inside_count,
@@ -1067,10 +1753,13 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
             vertex_inside_flags,
             triangle_inside_flags,
             exterior_vertex_edge_indices,
-        }
+            vertex_provenance,
+        })
     };
 
-    let reconstruct_sparse = |flat_subdomain_idx: I, subdomain_particle_indices: &Vec<usize>| {
+    let reconstruct_sparse = |flat_subdomain_idx: I,
+                              subdomain_particle_indices: &Vec<usize>|
+     -> Result<SurfacePatch<I, R>, SubdomainGridError<I, R>> {
         // Obtain thread local workspace and clear it
         let mut workspace = workspace_tls.get_or_default().borrow_mut();
 
@@ -1079,42 +1768,49 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
             subdomain_particle_densities,
             levelset_grid,
             index_cache,
+            grid_coordinates,
+            provenance_lookup,
         } = &mut *workspace;
 
         let flat_subdomain_idx: I = flat_subdomain_idx;
         let subdomain_particle_indices: &[usize] = subdomain_particle_indices.as_slice();
 
+        // Get the cell index and AABB of the subdomain
+        let subdomain_idx = parameters
+            .subdomain_grid
+            .try_unflatten_cell_index(flat_subdomain_idx)
+            .ok_or(SubdomainGridError::InvalidSubdomain(flat_subdomain_idx))?;
+        let subdomain_aabb = parameters.subdomain_grid.cell_aabb(&subdomain_idx);
+        let frame = parameters.subdomain_frame(subdomain_idx.index())?;
+
         // Collect all particle positions and densities of this subdomain
         {
             //profile!("collect subdomain data");
-            gather_subdomain_data(
+            gather_subdomain_positions(
+                &frame,
                 global_particles,
                 subdomain_particle_indices,
                 subdomain_particles,
-            );
+            )?;
             gather_subdomain_data(
-                global_particle_densities,
+                &local_particle_densities,
                 subdomain_particle_indices,
                 subdomain_particle_densities,
             );
         }
 
-        // Get the cell index and AABB of the subdomain
-        let subdomain_idx = parameters
-            .subdomain_grid
-            .try_unflatten_cell_index(flat_subdomain_idx)
-            .expect("Subdomain cell does not exist");
-        let subdomain_aabb = parameters.subdomain_grid.cell_aabb(&subdomain_idx);
-
-        let mc_grid = UniformCartesianCubeGrid3d::new(
-            subdomain_aabb.min(),
-            &[parameters.subdomain_cubes; 3],
-            parameters.cube_size,
-        )
-        .unwrap();
+        let mc_grid =
+            UniformCartesianCubeGrid3d::new(
+                &frame.local_grid_origin(&subdomain_aabb).ok_or(
+                    SubdomainGridError::RealConversion("local coordinates of the subdomain origin"),
+                )?,
+                &[parameters.subdomain_cubes; 3],
+                cube_size,
+            )
+            .map_err(local_grid_error)?;
 
-        levelset_grid.fill(R::zero());
-        levelset_grid.resize(mc_total_points.to_usize().unwrap(), R::zero());
+        levelset_grid.fill(L::zero());
+        levelset_grid.resize(mc_total_points, L::zero());
 
         index_cache.clear();
 
@@ -1122,6 +1818,23 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
             profile!("density grid loop");
 
             let extents = mc_grid.points_per_dim();
+            let points_per_dim = (|| -> Option<_> {
+                Some([
+                    extents[0].to_usize()?,
+                    extents[1].to_usize()?,
+                    extents[2].to_usize()?,
+                ])
+            })()
+            .ok_or(SubdomainGridError::IndexConversion(
+                "number of points of the subdomain marching cubes grid",
+            ))?;
+
+            compute_grid_coordinates(
+                &frame,
+                subdomain_idx.index(),
+                points_per_dim,
+                grid_coordinates,
+            )?;
 
             for (p_i, rho_i) in subdomain_particles
                 .iter()
@@ -1154,64 +1867,43 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
                     for j in I::range(lower[1], upper[1]).iter() {
                         for k in I::range(lower[2], upper[2]).iter() {
                             let point_ijk = [i, j, k];
-                            let local_point = mc_grid
-                                .get_point(point_ijk)
-                                .expect("point has to be part of the subdomain grid");
-                            //let point_coordinates = mc_grid.point_coordinates(&point);
-
-                            let subdomain_ijk = subdomain_idx.index();
-                            let mc_cells_per_subdomain = mc_grid.cells_per_dim();
-
-                            fn local_to_global_point_ijk<I: Index>(
-                                local_point_ijk: [I; 3],
-                                subdomain_ijk: [I; 3],
-                                cells_per_subdomain: [I; 3],
-                            ) -> [GlobalIndex; 3] {
-                                let local_point_ijk = local_point_ijk
-                                    .map(|i| <GlobalIndex as NumCast>::from(i).unwrap());
-                                let subdomain_ijk = subdomain_ijk
-                                    .map(|i| <GlobalIndex as NumCast>::from(i).unwrap());
-                                let cells_per_subdomain = cells_per_subdomain
-                                    .map(|i| <GlobalIndex as NumCast>::from(i).unwrap());
-                                let [i, j, k] = local_point_ijk;
-
-                                [
-                                    subdomain_ijk[0] * cells_per_subdomain[0] + i,
-                                    subdomain_ijk[1] * cells_per_subdomain[1] + j,
-                                    subdomain_ijk[2] * cells_per_subdomain[2] + k,
-                                ]
-                            }
-
-                            // Use global coordinate calculation for consistency with neighboring domains
-                            let global_point_ijk = local_to_global_point_ijk(
-                                point_ijk,
-                                subdomain_ijk.clone(),
-                                mc_cells_per_subdomain.clone(),
-                            );
-                            let global_point = parameters
-                                .global_marching_cubes_grid
-                                .get_point(global_point_ijk)
-                                .expect("point has to be part of the global mc grid");
-                            let point_coordinates = parameters
-                                .global_marching_cubes_grid
-                                .point_coordinates(&global_point);
+                            let local_point = mc_grid.get_point(point_ijk).ok_or(
+                                SubdomainGridError::InvalidGridIndex(
+                                    "point of the subdomain marching cubes grid",
+                                ),
+                            )?;
+
+                            // Use the grid point coordinates of the global grid for consistency with neighboring domains
+                            let point_coordinates = (|| -> Option<_> {
+                                Some(Vector3::new(
+                                    grid_coordinates[0][i.to_usize()?],
+                                    grid_coordinates[1][j.to_usize()?],
+                                    grid_coordinates[2][k.to_usize()?],
+                                ))
+                            })()
+                            .ok_or(
+                                SubdomainGridError::IndexConversion(
+                                    "point of the subdomain marching cubes grid",
+                                ),
+                            )?;
 
                             let dx = p_i - point_coordinates;
                             let dx_norm_sq = dx.norm_squared();
 
                             if dx_norm_sq < squared_support_with_margin {
-                                let v_i = parameters.particle_rest_mass / rho_i;
-                                let r = dx_norm_sq.sqrt();
-                                let w_ij = kernel.evaluate(r);
-                                //let w_ij = kernel.evaluate(dx_norm_sq);
+                                let v_i = particle_rest_mass / rho_i;
+                                // Evaluate the kernel like the splatting of dense subdomains for consistency
+                                let w_ij = splatter.evaluate_squared(dx_norm_sq);
 
                                 let interpolated_value = v_i * w_ij;
 
                                 let flat_point_idx = mc_grid.flatten_point_index(&local_point);
-                                let flat_point_idx = flat_point_idx.to_usize().unwrap();
+                                let flat_point_idx = flat_point_idx.to_usize().ok_or(
+                                    SubdomainGridError::IndexConversion("flat point index"),
+                                )?;
                                 levelset_grid[flat_point_idx] += interpolated_value;
 
-                                if levelset_grid[flat_point_idx] > parameters.surface_threshold {
+                                if levelset_grid[flat_point_idx] > surface_threshold {
                                     for c in mc_grid
                                         .cells_adjacent_to_point(
                                             &mc_grid.get_point_neighborhood(&local_point),
@@ -1230,6 +1922,14 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
             }
         }
 
+        if parameters.vertex_provenance_particles.is_some() {
+            provenance_lookup.rebuild(
+                *mc_grid.aabb().min(),
+                compact_support_radius,
+                subdomain_particles,
+            );
+        }
+
         let mut vertices = Vec::new();
         let mut triangles = Vec::new();
 
@@ -1240,6 +1940,7 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
         let mut triangle_inside_flags = Vec::new();
 
         let mut exterior_vertex_edge_indices = Vec::new();
+        let mut vertex_provenance = Vec::new();
 
         let mut edge_to_vertex = new_map();
 
@@ -1248,68 +1949,96 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
 
             index_cache.sort_unstable();
             for flat_cell_idx in index_cache.iter().copied().dedup() {
-                let cell = mc_grid.try_unflatten_cell_index(flat_cell_idx).unwrap();
+                let cell = mc_grid.try_unflatten_cell_index(flat_cell_idx).ok_or(
+                    SubdomainGridError::InvalidGridIndex(
+                        "cell of the subdomain marching cubes grid",
+                    ),
+                )?;
 
                 let mut vertices_inside = [true; 8];
                 for local_point_index in 0..8 {
-                    let point = cell.global_point_index_of(local_point_index).unwrap();
+                    let point = cell.global_point_index_of(local_point_index).ok_or(
+                        SubdomainGridError::InvalidGridIndex("point of a marching cubes cell"),
+                    )?;
                     let flat_point_idx = mc_grid.flatten_point_index(&point);
-                    let flat_point_idx = flat_point_idx.to_usize().unwrap();
+                    let flat_point_idx = flat_point_idx
+                        .to_usize()
+                        .ok_or(SubdomainGridError::IndexConversion("flat point index"))?;
                     // Get value of density map
                     let density_value = levelset_grid[flat_point_idx];
                     // Update inside/outside surface flag
-                    vertices_inside[local_point_index] =
-                        density_value > parameters.surface_threshold;
+                    vertices_inside[local_point_index] = density_value > surface_threshold;
                 }
 
                 for triangle in marching_cubes_triangulation_iter(&vertices_inside) {
                     let mut global_triangle = [0; 3];
                     for (v_idx, local_edge_index) in triangle.iter().copied().enumerate() {
-                        let edge = cell
-                            .global_edge_index_of(local_edge_index as usize)
-                            .unwrap();
-                        let vertex_index = *edge_to_vertex.entry(edge).or_insert_with(|| {
-                            // TODO: Nonlinear interpolation
-
-                            let origin_coords = mc_grid.point_coordinates(&edge.origin());
-                            let target_coords = mc_grid.point_coordinates(&edge.target());
-
-                            let flat_origin_idx = mc_grid
-                                .flatten_point_index(&edge.origin())
-                                .to_usize()
-                                .unwrap();
-                            let flat_target_idx = mc_grid
-                                .flatten_point_index(&edge.target())
-                                .to_usize()
-                                .unwrap();
-
-                            let origin_value = levelset_grid[flat_origin_idx];
-                            let target_value = levelset_grid[flat_target_idx];
-
-                            let alpha = (parameters.surface_threshold - origin_value)
-                                / (target_value - origin_value);
-                            let interpolated_coords =
-                                origin_coords * (R::one() - alpha) + target_coords * alpha;
-                            let vertex_coords = interpolated_coords;
-
-                            vertices.push(vertex_coords);
-                            let vertex_index = vertices.len() - 1;
-
-                            let is_interior_vertex = !mc_grid.is_boundary_edge(&edge);
-                            vertex_inside_count += is_interior_vertex as usize;
-                            vertex_inside_flags.push(is_interior_vertex);
-
-                            if !is_interior_vertex {
-                                exterior_vertex_edge_indices.push(globalize_local_edge(
-                                    &mc_grid,
-                                    &parameters.subdomain_grid,
-                                    flat_subdomain_idx,
-                                    &edge,
-                                ));
-                            }
+                        let edge = cell.global_edge_index_of(local_edge_index as usize).ok_or(
+                            SubdomainGridError::InvalidGridIndex("edge of a marching cubes cell"),
+                        )?;
+                        let vertex_index = match edge_to_vertex.entry(edge) {
+                            Entry::Occupied(entry) => *entry.get(),
+                            Entry::Vacant(entry) => {
+                                // TODO: Nonlinear interpolation
+
+                                let origin_coords = mc_grid.point_coordinates(&edge.origin());
+                                let target_coords = mc_grid.point_coordinates(&edge.target());
+
+                                let flat_origin_idx = mc_grid
+                                    .flatten_point_index(&edge.origin())
+                                    .to_usize()
+                                    .ok_or(SubdomainGridError::IndexConversion(
+                                        "flat point index",
+                                    ))?;
+                                let flat_target_idx = mc_grid
+                                    .flatten_point_index(&edge.target())
+                                    .to_usize()
+                                    .ok_or(SubdomainGridError::IndexConversion(
+                                        "flat point index",
+                                    ))?;
+
+                                let origin_value = levelset_grid[flat_origin_idx];
+                                let target_value = levelset_grid[flat_target_idx];
+
+                                let alpha = (surface_threshold - origin_value)
+                                    / (target_value - origin_value);
+                                let interpolated_coords =
+                                    origin_coords * (L::one() - alpha) + target_coords * alpha;
+                                let vertex_coords = interpolated_coords;
+
+                                vertices.push(vertex_coords);
+                                let vertex_index = vertices.len() - 1;
+
+                                if let Some(k) = parameters.vertex_provenance_particles {
+                                    provenance_lookup.append_nearest(
+                                        &vertex_coords,
+                                        k,
+                                        subdomain_particles,
+                                        subdomain_particle_indices,
+                                        |p, dist_sq| {
+                                            (particle_rest_mass / subdomain_particle_densities[p])
+                                                * kernel.evaluate(dist_sq.sqrt())
+                                        },
+                                        &mut vertex_provenance,
+                                    );
+                                }
+
+                                let is_interior_vertex = !mc_grid.is_boundary_edge(&edge);
+                                vertex_inside_count += is_interior_vertex as usize;
+                                vertex_inside_flags.push(is_interior_vertex);
+
+                                if !is_interior_vertex {
+                                    exterior_vertex_edge_indices.push(globalize_local_edge(
+                                        &mc_grid,
+                                        &parameters.subdomain_grid,
+                                        flat_subdomain_idx,
+                                        &edge,
+                                    )?);
+                                }
 
-                            vertex_index
-                        });
+                                *entry.insert(vertex_index)
+                            }
+                        };
 
                         global_triangle[v_idx] = vertex_index;
                     }
@@ -1326,7 +2055,11 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
             }
         }
 
-        SurfacePatch {
+        let (vertices, vertex_provenance) =
+            globalize_patch_data(&frame, vertices, vertex_provenance)?;
+
+        Ok(SurfacePatch {
+            subdomain_index: flat_subdomain_idx,
             vertices,
             triangles,
             vertex_inside_count,
@@ -1334,32 +2067,43 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
             vertex_inside_flags,
             triangle_inside_flags,
             exterior_vertex_edge_indices,
-        }
+            vertex_provenance,
+        })
     };
 
-    let mut surface_patches = Vec::with_capacity(subdomains.flat_subdomain_indices.len());
-    subdomains
-        .flat_subdomain_indices
-        .par_iter()
-        .copied()
-        .zip(subdomains.per_subdomain_particles.par_iter())
-        .map(|(flat_subdomain_idx, subdomain_particle_indices)| {
-            if subdomain_particle_indices.len() <= sparse_limit {
-                profile!("subdomain reconstruction (sparse)", parent = parent);
-                reconstruct_sparse(flat_subdomain_idx, subdomain_particle_indices)
-            } else {
-                profile!("subdomain reconstruction (dense)", parent = parent);
-                reconstruct_dense(flat_subdomain_idx, subdomain_particle_indices)
-            }
+    let selected_subdomains = (0..subdomains.flat_subdomain_indices.len())
+        .filter(|&i| selection.map_or(true, |selection| selection[i]))
+        .collect::<Vec<_>>();
+    // Process the most expensive subdomains first to avoid a single large subdomain at the end of the reconstruction
+    let estimated_costs = selected_subdomains
+        .iter()
+        .map(|&i| {
+            parameters
+                .cost_model
+                .cost(subdomains.per_subdomain_particles[i].len(), sparse_limit)
         })
-        .collect_into_vec(&mut surface_patches);
+        .collect::<Vec<_>>();
 
-    surface_patches
+    par_map_by_descending_cost(&estimated_costs, |j| {
+        let i = selected_subdomains[j];
+        let flat_subdomain_idx = subdomains.flat_subdomain_indices[i];
+        let subdomain_particle_indices = &subdomains.per_subdomain_particles[i];
+        if subdomain_particle_indices.len() <= sparse_limit {
+            profile!("subdomain reconstruction (sparse)", parent = parent);
+            reconstruct_sparse(flat_subdomain_idx, subdomain_particle_indices)
+        } else {
+            profile!("subdomain reconstruction (dense)", parent = parent);
+            reconstruct_dense(flat_subdomain_idx, subdomain_particle_indices)
+        }
+    })
+    .into_iter()
+    .collect()
 }
 
 pub(crate) fn stitching<I: Index, R: Real>(
-    surface_patches: Vec<SurfacePatch<I, R>>,
-) -> TriMesh3d<R> {
+    surface_patches: &[SurfacePatch<I, R>],
+    vertex_provenance_particles: Option<usize>,
+) -> (TriMesh3d<R>, Option<VertexProvenance<R>>) {
     profile!("stitching");
     info!("Starting stitching of subdomains to global mesh.");
 
@@ -1377,10 +2121,8 @@ pub(crate) fn stitching<I: Index, R: Real>(
             .collect::<Vec<_>>()
     };
 
-    let (total_interior_vert_count, total_interior_tri_count) = vert_and_tri_offsets
-        .last()
-        .copied()
-        .expect("there has to be at least one entry in the offset list");
+    let (total_interior_vert_count, total_interior_tri_count) =
+        vert_and_tri_offsets.last().copied().unwrap_or((0, 0));
 
     let mut interior_vertices = vec![Vector3::<R>::zeros(); total_interior_vert_count];
     let mut interior_triangles = vec![[0, 0, 0]; total_interior_tri_count];
@@ -1389,6 +2131,20 @@ pub(crate) fn stitching<I: Index, R: Real>(
     let mut exterior_triangles = Vec::new();
     let mut exterior_vertex_mapping = new_map();
 
+    // Provenance data: subdomain index per vertex and `k` nearest particles per vertex
+    let k = vertex_provenance_particles.unwrap_or(0);
+    let (mut interior_subdomains, mut interior_particles) = if vertex_provenance_particles.is_some()
+    {
+        (
+            vec![I::zero(); total_interior_vert_count],
+            vec![(usize::MAX, R::zero()); total_interior_vert_count * k],
+        )
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    let mut exterior_subdomains = Vec::new();
+    let mut exterior_particles = Vec::new();
+
     {
         profile!("copy interior verts/tris and deduplicate exterior verts");
 
@@ -1423,6 +2179,27 @@ pub(crate) fn stitching<I: Index, R: Real>(
                                     local_to_global_vertex_mapping[old_local_idx] =
                                         global_vertex_offset + new_local_idx;
                                 });
+
+                            // Copy provenance data of interior vertices
+                            if vertex_provenance_particles.is_some() {
+                                interior_subdomains[start_verts..end_verts]
+                                    .fill(patch.subdomain_index);
+                                let out_particles =
+                                    &mut interior_particles[start_verts * k..end_verts * k];
+                                patch
+                                    .vertex_inside_flags
+                                    .iter()
+                                    .enumerate()
+                                    .filter_map(|(i, is_interior)| is_interior.then_some(i))
+                                    .enumerate()
+                                    .for_each(|(new_local_idx, old_local_idx)| {
+                                        out_particles[new_local_idx * k..(new_local_idx + 1) * k]
+                                            .copy_from_slice(
+                                                &patch.vertex_provenance
+                                                    [old_local_idx * k..(old_local_idx + 1) * k],
+                                            );
+                                    });
+                            }
                         }
 
                         // Copy interior triangles
@@ -1470,6 +2247,13 @@ pub(crate) fn stitching<I: Index, R: Real>(
                                                 let global_index = total_interior_vert_count
                                                     + exterior_vertices.len();
                                                 exterior_vertices.push(*vert);
+                                                if vertex_provenance_particles.is_some() {
+                                                    exterior_subdomains.push(patch.subdomain_index);
+                                                    exterior_particles.extend_from_slice(
+                                                        &patch.vertex_provenance[old_local_idx * k
+                                                            ..(old_local_idx + 1) * k],
+                                                    );
+                                                }
                                                 global_index
                                             });
                                         local_to_global_vertex_mapping[old_local_idx] =
@@ -1503,10 +2287,34 @@ pub(crate) fn stitching<I: Index, R: Real>(
     let mut triangles = interior_triangles;
     triangles.append(&mut exterior_triangles);
 
-    TriMesh3d {
-        vertices,
-        triangles,
-    }
+    let vertex_provenance = vertex_provenance_particles.map(|num_particles| {
+        profile!("collect vertex provenance");
+
+        let subdomain_indices = interior_subdomains
+            .into_iter()
+            .chain(exterior_subdomains)
+            .map(|s| s.to_u64().unwrap_or(u64::MAX))
+            .collect();
+        let (particle_indices, particle_weights) = interior_particles
+            .into_iter()
+            .chain(exterior_particles)
+            .unzip();
+
+        VertexProvenance {
+            num_particles,
+            subdomain_indices,
+            particle_indices,
+            particle_weights,
+        }
+    });
+
+    (
+        TriMesh3d {
+            vertices,
+            triangles,
+        },
+        vertex_provenance,
+    )
 }
 
 pub(crate) mod subdomain_classification {
@@ -1615,16 +2423,12 @@ pub(crate) mod subdomain_classification {
         // Find the owning subdomain of the particle
         let subdomain_ijk = subdomain_grid.enclosing_cell(particle);
         // Make sure particle is part of computational domain
-        if subdomain_grid.get_cell(subdomain_ijk).is_none() {
+        let Some(subdomain_cell) = subdomain_grid.get_cell(subdomain_ijk) else {
             return;
-        }
+        };
 
         // Get corner points spanning the owning subdomain
-        let subdomain_aabb = subdomain_grid.cell_aabb(
-            &subdomain_grid
-                .get_cell(subdomain_ijk)
-                .expect("Subdomain has to be part of grid"),
-        );
+        let subdomain_aabb = subdomain_grid.cell_aabb(&subdomain_cell);
         let min_corner = subdomain_aabb.min();
         let max_corner = subdomain_aabb.max();
 
@@ -1638,6 +2442,16 @@ pub(crate) mod subdomain_classification {
             }
         };
 
+        // Steps the subdomain index along one axis, returns `None` if the index type cannot represent the neighbor
+        let step_index = |index: I, step: i8| -> Option<I> {
+            match step {
+                -1 => index.checked_sub(&I::one()),
+                0 => Some(index),
+                1 => index.checked_add(&I::one()),
+                _ => unreachable!("subdomain steps are always in -1..=1"),
+            }
+        };
+
         // Checks whether the current particle is within the ghost particle margin of the neighbor subdomain reached by the given steps
         let is_in_ghost_margin = |x_step: i8, y_step: i8, z_step: i8| -> bool {
             is_in_ghost_margin_single_dim(x_step, 0)
@@ -1653,13 +2467,15 @@ pub(crate) mod subdomain_classification {
                     let in_ghost_margin = is_in_ghost_margin(i, j, k);
 
                     if in_ghost_margin {
-                        let neighbor_subdomain_ijk = [
-                            subdomain_ijk[0] + to_index!(i),
-                            subdomain_ijk[1] + to_index!(j),
-                            subdomain_ijk[2] + to_index!(k),
-                        ];
+                        let (Some(ni), Some(nj), Some(nk)) = (
+                            step_index(subdomain_ijk[0], i),
+                            step_index(subdomain_ijk[1], j),
+                            step_index(subdomain_ijk[2], k),
+                        ) else {
+                            continue;
+                        };
                         // The potential neighbor subdomain might not even be part of our computation domain
-                        if let Some(cell) = subdomain_grid.get_cell(neighbor_subdomain_ijk) {
+                        if let Some(cell) = subdomain_grid.get_cell([ni, nj, nk]) {
                             // If it is, it can be added as a subdomain of the particle
                             subdomains.push(subdomain_grid.flatten_cell_index(&cell));
                         }
@@ -1804,7 +2620,11 @@ pub(crate) mod debug {
                     hexmesh.vertices.push(
                         subdomain_grid.point_coordinates(
                             &subdomain_grid
-                                .get_point([i + to_index!(a), j + to_index!(b), k + to_index!(c)])
+                                .get_point([
+                                    i + I::from_i32(a).unwrap(),
+                                    j + I::from_i32(b).unwrap(),
+                                    k + I::from_i32(c).unwrap(),
+                                ])
                                 .unwrap(),
                         ),
                     );
@@ -1898,6 +2718,23 @@ fn reserve_total<T>(vec: &mut Vec<T>, total_capacity: usize) {
     }
 }
 
+/// Gathers particle positions from global storage to subdomain storage and converts them to the coordinates used inside of the subdomain
+fn gather_subdomain_positions<I: Index, R: Real, L: Real>(
+    frame: &SubdomainFrame<R>,
+    global_positions: &[Vector3<R>],
+    subdomain_particle_indices: &[usize],
+    subdomain_positions: &mut Vec<Vector3<L>>,
+) -> Result<(), SubdomainGridError<I, R>> {
+    subdomain_positions.clear();
+    reserve_total(subdomain_positions, subdomain_particle_indices.len());
+    for &idx in subdomain_particle_indices {
+        subdomain_positions.push(frame.to_local(&global_positions[idx]).ok_or(
+            SubdomainGridError::RealConversion("local coordinates of a particle"),
+        )?);
+    }
+    Ok(())
+}
+
 /// Gathers particle related data from global storage to subdomain storage
 fn gather_subdomain_data<T: Copy>(
     global_data: &[T],
@@ -0,0 +1,178 @@
+//! Cost estimates of the subdomain reconstruction for the automatic subdomain size selection and load balancing
+
+use super::*;
+
+/// Estimate of the computational cost of the reconstruction of a single subdomain
+///
+/// All costs are relative to the evaluation of the kernel of one particle at one point of a dense grid.
+#[derive(Clone, Debug)]
+pub(super) struct SubdomainCostModel {
+    /// Number of grid points in the kernel support of a particle
+    kernel_points: f64,
+    /// Number of grid points of a dense subdomain
+    dense_points: f64,
+}
+
+impl SubdomainCostModel {
+    /// Relative cost of a kernel evaluation in a sparse subdomain (includes the hash map access)
+    const SPARSE_EVALUATION_COST: f64 = 4.0;
+    /// Relative cost per grid point of a dense subdomain (initialization of the level-set grid and marching cubes)
+    const DENSE_POINT_COST: f64 = 1.5;
+    /// Fixed cost per subdomain (workspace setup and stitching of the patch)
+    const SUBDOMAIN_COST: f64 = 20_000.0;
+
+    pub(super) fn new(subdomain_cubes: usize, kernel_cube_radius: usize) -> Self {
+        Self {
+            kernel_points: ((2 * kernel_cube_radius + 1) as f64).powi(3),
+            dense_points: ((subdomain_cubes + 1) as f64).powi(3),
+        }
+    }
+
+    /// Estimated cost of the dense reconstruction of a subdomain with the given number of particles
+    fn dense_cost(&self, num_particles: usize) -> f64 {
+        Self::SUBDOMAIN_COST
+            + num_particles as f64 * self.kernel_points
+            + self.dense_points * Self::DENSE_POINT_COST
+    }
+
+    /// Estimated cost of the sparse reconstruction of a subdomain with the given number of particles
+    fn sparse_cost(&self, num_particles: usize) -> f64 {
+        Self::SUBDOMAIN_COST
+            + num_particles as f64 * self.kernel_points * Self::SPARSE_EVALUATION_COST
+    }
+
+    /// Estimated cost of the reconstruction of a subdomain with the given number of particles
+    pub(super) fn cost(&self, num_particles: usize, sparse_limit: usize) -> f64 {
+        if num_particles <= sparse_limit {
+            self.sparse_cost(num_particles)
+        } else {
+            self.dense_cost(num_particles)
+        }
+    }
+
+    /// Largest number of particles for which the sparse reconstruction is estimated to be cheaper than the dense reconstruction
+    pub(super) fn sparse_limit(&self) -> usize {
+        (self.dense_points * Self::DENSE_POINT_COST
+            / (self.kernel_points * (Self::SPARSE_EVALUATION_COST - 1.0)))
+            .floor() as usize
+    }
+}
+
+/// Selects the number of marching cubes per subdomain with the lowest estimated reconstruction time for the given particles
+///
+/// For every candidate size, the particles are binned into the subdomains (using a subset of the particles for large
+/// inputs) and the reconstruction time is estimated as the maximum of the total cost distributed over all threads and
+/// the cost of the most expensive subdomain.
+pub(super) fn auto_subdomain_cubes<R: Real>(
+    particles: &[Vector3<R>],
+    grid_min: &Vector3<R>,
+    cube_size: R,
+    kernel_cube_radius: usize,
+    ghost_margin_cubes: usize,
+    num_threads: usize,
+) -> u32 {
+    profile!("auto subdomain size");
+
+    const CANDIDATES: [usize; 7] = [16, 24, 32, 48, 64, 96, 128];
+    const MAX_SAMPLES: usize = 100_000;
+
+    // Global marching cubes cells of a subset of the particles
+    let stride = (particles.len() / MAX_SAMPLES).max(1);
+    let particle_weight = stride as f64;
+    let particle_cells = particles
+        .par_iter()
+        .step_by(stride)
+        .filter_map(|p| {
+            let cell = (p - grid_min) / cube_size;
+            Some([
+                cell.x.floor().to_i64()?,
+                cell.y.floor().to_i64()?,
+                cell.z.floor().to_i64()?,
+            ])
+        })
+        .collect::<Vec<_>>();
+
+    // The ghost margin may not be thicker than half of a subdomain
+    let min_subdomain_cubes = 2 * ghost_margin_cubes;
+    let mut candidates = CANDIDATES.map(|n| n.max(min_subdomain_cubes)).to_vec();
+    candidates.dedup();
+
+    let num_threads = num_threads.max(1) as f64;
+    let (subdomain_cubes, estimated_cost) = candidates
+        .into_iter()
+        .map(|subdomain_cubes| {
+            let cost_model = SubdomainCostModel::new(subdomain_cubes, kernel_cube_radius);
+            let sparse_limit = cost_model.sparse_limit();
+            let n = subdomain_cubes as i64;
+            // Ratio of particles including the ghost particles to the particles inside of a subdomain
+            let ghost_factor = ((subdomain_cubes + 2 * ghost_margin_cubes) as f64
+                / subdomain_cubes as f64)
+                .powi(3);
+
+            let mut subdomain_particles: MapType<[i64; 3], f64> = new_map();
+            for cell in &particle_cells {
+                *subdomain_particles
+                    .entry(cell.map(|c| c.div_euclid(n)))
+                    .or_default() += particle_weight;
+            }
+
+            let (total_cost, max_cost) = subdomain_particles
+                .values()
+                .map(|&num_particles| {
+                    cost_model.cost((num_particles * ghost_factor) as usize, sparse_limit)
+                })
+                .fold((0.0, 0.0_f64), |(total, max), cost| {
+                    (total + cost, max.max(cost))
+                });
+            trace!(
+                "Estimated cost with {} cubes per subdomain: {:.3e} (total: {:.3e}, largest subdomain: {:.3e})",
+                subdomain_cubes,
+                (total_cost / num_threads).max(max_cost),
+                total_cost,
+                max_cost
+            );
+
+            (subdomain_cubes, (total_cost / num_threads).max(max_cost))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap_or((64, 0.0));
+
+    info!(
+        "Automatically selected {} cubes per subdomain (estimated cost: {:.3e}).",
+        subdomain_cubes, estimated_cost
+    );
+    subdomain_cubes.try_into().unwrap_or(u32::MAX)
+}
+
+/// Maps all items in parallel starting with the items of the highest cost, the results are returned in the original order
+///
+/// The items are distributed over the threads one by one in order of descending cost (longest processing time first),
+/// such that expensive items do not end up at the end of the schedule of a single thread.
+pub(super) fn par_map_by_descending_cost<T, F>(costs: &[f64], f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Sync,
+{
+    let mut schedule = (0..costs.len()).collect::<Vec<_>>();
+    schedule.sort_by(|&a, &b| costs[b].total_cmp(&costs[a]));
+
+    let next_item = AtomicUsize::new(0);
+    let mut results = (0..rayon::current_num_threads().min(costs.len()))
+        .into_par_iter()
+        .with_max_len(1)
+        .flat_map_iter(|_| {
+            let mut results = Vec::new();
+            loop {
+                let scheduled = next_item.fetch_add(1, Ordering::Relaxed);
+                let Some(&item) = schedule.get(scheduled) else {
+                    break;
+                };
+                results.push((item, f(item)));
+            }
+            results
+        })
+        .collect::<Vec<_>>();
+
+    results.sort_unstable_by_key(|(item, _)| *item);
+    results.into_iter().map(|(_, result)| result).collect()
+}
@@ -0,0 +1,637 @@
+//! Explicitly vectorized splatting of particle kernel contributions onto the level-set grid of a dense subdomain
+//!
+//! All implementations perform exactly the same sequence of floating point operations per grid
+//! point as the scalar evaluation using the [`CubicSplineKernel`](crate::kernel::CubicSplineKernel)
+//! (no fused multiply-add, branches are replaced by masked selects). Therefore, the resulting
+//! level-set values are bitwise identical for all backends, which is required to obtain consistent
+//! surfaces at the boundaries between dense and sparse subdomains.
+//! If a [`TabulatedKernel`] is used, the splatting always uses the portable scalar implementation.
+
+use crate::kernel::TabulatedKernel;
+use crate::Real;
+use nalgebra::Vector3;
+#[cfg(target_arch = "x86_64")]
+use std::any::TypeId;
+
+/// Implementation used for splatting the kernel contributions onto the level-set grid
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum SplattingBackend {
+    /// Portable scalar implementation
+    Scalar,
+    /// Portable scalar implementation using a tabulated kernel
+    Tabulated,
+    /// AVX implementation processing 8 (`f32`) or 4 (`f64`) grid points at once
+    #[cfg(target_arch = "x86_64")]
+    Avx,
+}
+
+impl SplattingBackend {
+    /// Returns the fastest backend that is supported by the current CPU for the given float type
+    fn detect<R: Real>() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            let is_f32_or_f64 = TypeId::of::<R>() == TypeId::of::<f32>()
+                || TypeId::of::<R>() == TypeId::of::<f64>();
+            if is_f32_or_f64 && std::is_x86_feature_detected!("avx") {
+                return SplattingBackend::Avx;
+            }
+        }
+
+        SplattingBackend::Scalar
+    }
+}
+
+/// Dense level-set grid of a subdomain that is the target of the splatting
+pub(crate) struct SplattingGrid<'a, R> {
+    /// Number of grid points along each axis, the grid points are stored with the z-axis being contiguous
+    pub points_per_dim: [usize; 3],
+    /// Coordinates of the grid points along each axis
+    pub coordinates: [&'a [R]; 3],
+}
+
+/// Constants required for the evaluation of the cubic spline kernel
+#[derive(Copy, Clone, Debug)]
+struct KernelConstants<R> {
+    compact_support_radius: R,
+    normalization: R,
+    squared_support_with_margin: R,
+    inner_factor: R,
+    outer_factor: R,
+    two_thirds: R,
+    half: R,
+    two: R,
+}
+
+impl<R: Real> KernelConstants<R> {
+    fn new(compact_support_radius: R, squared_support_with_margin: R) -> Self {
+        let c = |v: f64| R::from_f64(v).expect("Literal must fit in R");
+        let h = compact_support_radius;
+        Self {
+            compact_support_radius,
+            normalization: c(8.0) / (h * h * h),
+            squared_support_with_margin,
+            inner_factor: c(3.0) / (c(2.0) * R::pi()),
+            outer_factor: R::one() / (c(4.0) * R::pi()),
+            two_thirds: c(2.0) / c(3.0),
+            half: c(0.5),
+            two: c(2.0),
+        }
+    }
+
+    /// Reinterprets the constants as constants of the float type `T`, requires that `T` and `R` are the same type
+    #[cfg(target_arch = "x86_64")]
+    fn reinterpret<T: Real>(&self) -> KernelConstants<T> {
+        debug_assert_eq!(TypeId::of::<R>(), TypeId::of::<T>());
+        let cast = |v: R| bytemuck::cast::<R, T>(v);
+        KernelConstants {
+            compact_support_radius: cast(self.compact_support_radius),
+            normalization: cast(self.normalization),
+            squared_support_with_margin: cast(self.squared_support_with_margin),
+            inner_factor: cast(self.inner_factor),
+            outer_factor: cast(self.outer_factor),
+            two_thirds: cast(self.two_thirds),
+            half: cast(self.half),
+            two: cast(self.two),
+        }
+    }
+
+    /// Evaluates the cubic spline kernel at the radial distance `r`
+    #[inline(always)]
+    fn evaluate(&self, r: R) -> R {
+        let q = (r + r) / self.compact_support_radius;
+        let cubic = if q < R::one() {
+            self.inner_factor * ((self.two_thirds - q * q) + self.half * q * q * q)
+        } else if q < self.two {
+            let x = self.two - q;
+            self.outer_factor * x * x * x
+        } else {
+            R::zero()
+        };
+        self.normalization * cubic
+    }
+}
+
+/// Kernel that can be evaluated at squared distances, used by the scalar splatting
+trait SquaredDistanceKernel<R: Real> {
+    /// Squared distance from which on the kernel is not evaluated anymore
+    fn squared_cutoff(&self) -> R;
+    /// Evaluates the kernel at the squared distance `r_squared`
+    fn evaluate_squared(&self, r_squared: R) -> R;
+}
+
+impl<R: Real> SquaredDistanceKernel<R> for KernelConstants<R> {
+    #[inline(always)]
+    fn squared_cutoff(&self) -> R {
+        self.squared_support_with_margin
+    }
+
+    #[inline(always)]
+    fn evaluate_squared(&self, r_squared: R) -> R {
+        self.evaluate(r_squared.sqrt())
+    }
+}
+
+impl<R: Real> SquaredDistanceKernel<R> for TabulatedKernel<R> {
+    #[inline(always)]
+    fn squared_cutoff(&self) -> R {
+        self.compact_support_radius() * self.compact_support_radius()
+    }
+
+    #[inline(always)]
+    fn evaluate_squared(&self, r_squared: R) -> R {
+        self.evaluate_squared(r_squared)
+    }
+}
+
+/// Splats the contribution of a single particle onto all grid points in the given range
+#[inline(always)]
+fn splat_particle_scalar<R: Real, K: SquaredDistanceKernel<R>>(
+    kernel: &K,
+    grid: &SplattingGrid<R>,
+    levelset_grid: &mut [R],
+    lower: [usize; 3],
+    upper: [usize; 3],
+    particle: [R; 3],
+    volume: R,
+) {
+    let [_, ny, nz] = grid.points_per_dim;
+    let [xs, ys, zs] = grid.coordinates;
+    let [px, py, pz] = particle;
+    let squared_cutoff = kernel.squared_cutoff();
+
+    for (i, &x) in xs.iter().enumerate().take(upper[0]).skip(lower[0]) {
+        let dx = px - x;
+        for (j, &y) in ys.iter().enumerate().take(upper[1]).skip(lower[1]) {
+            let dy = py - y;
+            let dxy_sq = dx * dx + dy * dy;
+            if dxy_sq >= squared_cutoff {
+                continue;
+            }
+
+            let row_offset = (i * ny + j) * nz;
+            let row = &mut levelset_grid[row_offset + lower[2]..row_offset + upper[2]];
+            for (value, &z) in row.iter_mut().zip(&zs[lower[2]..upper[2]]) {
+                let dz = pz - z;
+                let d_sq = dxy_sq + dz * dz;
+                if d_sq < squared_cutoff {
+                    *value += volume * kernel.evaluate_squared(d_sq);
+                }
+            }
+        }
+    }
+}
+
+/// Splats the weighted kernel contributions of particles onto the dense level-set grid of a subdomain
+pub(crate) struct DensitySplatter<R: Real> {
+    backend: SplattingBackend,
+    constants: KernelConstants<R>,
+    tabulated_kernel: Option<TabulatedKernel<R>>,
+}
+
+impl<R: Real> DensitySplatter<R> {
+    /// Creates a splatter for the cubic spline kernel, uses explicit SIMD instructions if enabled and supported by the CPU
+    ///
+    /// If a tabulated kernel is provided, it replaces the exact evaluation of the cubic spline kernel.
+    pub(crate) fn new(
+        compact_support_radius: R,
+        squared_support_with_margin: R,
+        enable_simd: bool,
+        tabulated_kernel: Option<TabulatedKernel<R>>,
+    ) -> Self {
+        let backend = if tabulated_kernel.is_some() {
+            SplattingBackend::Tabulated
+        } else if enable_simd {
+            SplattingBackend::detect::<R>()
+        } else {
+            SplattingBackend::Scalar
+        };
+
+        Self {
+            backend,
+            constants: KernelConstants::new(compact_support_radius, squared_support_with_margin),
+            tabulated_kernel,
+        }
+    }
+
+    /// Returns the backend used by this splatter
+    pub(crate) fn backend(&self) -> SplattingBackend {
+        self.backend
+    }
+
+    /// Evaluates the kernel at the squared distance `r_squared` exactly like the splatting (for level-set values that are computed individually)
+    #[inline(always)]
+    pub(crate) fn evaluate_squared(&self, r_squared: R) -> R {
+        match &self.tabulated_kernel {
+            Some(tabulated_kernel) => tabulated_kernel.evaluate_squared(r_squared),
+            None => self.constants.evaluate(r_squared.sqrt()),
+        }
+    }
+
+    /// Adds `volume * W(|particle - x|)` to all grid points `x` with indices in the range `lower..upper` along each axis that are within the kernel support
+    pub(crate) fn splat_particle(
+        &self,
+        grid: &SplattingGrid<R>,
+        levelset_grid: &mut [R],
+        lower: [usize; 3],
+        upper: [usize; 3],
+        particle: &Vector3<R>,
+        volume: R,
+    ) {
+        let upper = [0, 1, 2].map(|d| upper[d].min(grid.points_per_dim[d]));
+        if (0..3).any(|d| lower[d] >= upper[d]) {
+            return;
+        }
+        let particle = [particle.x, particle.y, particle.z];
+
+        if let Some(tabulated_kernel) = &self.tabulated_kernel {
+            return splat_particle_scalar(
+                tabulated_kernel,
+                grid,
+                levelset_grid,
+                lower,
+                upper,
+                particle,
+                volume,
+            );
+        }
+
+        match self.backend {
+            #[cfg(target_arch = "x86_64")]
+            SplattingBackend::Avx if TypeId::of::<R>() == TypeId::of::<f32>() => {
+                let grid = reinterpret_grid::<R, f32>(grid);
+                let constants = self.constants.reinterpret::<f32>();
+                let levelset_grid = bytemuck::cast_slice_mut::<R, f32>(levelset_grid);
+                let particle = particle.map(bytemuck::cast::<R, f32>);
+                let volume = bytemuck::cast::<R, f32>(volume);
+                // Safety: the backend is only selected if AVX is supported by the CPU
+                unsafe {
+                    avx::f32x8::splat_particle(
+                        &constants,
+                        &grid,
+                        levelset_grid,
+                        lower,
+                        upper,
+                        particle,
+                        volume,
+                    )
+                }
+            }
+            #[cfg(target_arch = "x86_64")]
+            SplattingBackend::Avx if TypeId::of::<R>() == TypeId::of::<f64>() => {
+                let grid = reinterpret_grid::<R, f64>(grid);
+                let constants = self.constants.reinterpret::<f64>();
+                let levelset_grid = bytemuck::cast_slice_mut::<R, f64>(levelset_grid);
+                let particle = particle.map(bytemuck::cast::<R, f64>);
+                let volume = bytemuck::cast::<R, f64>(volume);
+                // Safety: the backend is only selected if AVX is supported by the CPU
+                unsafe {
+                    avx::f64x4::splat_particle(
+                        &constants,
+                        &grid,
+                        levelset_grid,
+                        lower,
+                        upper,
+                        particle,
+                        volume,
+                    )
+                }
+            }
+            _ => splat_particle_scalar(
+                &self.constants,
+                grid,
+                levelset_grid,
+                lower,
+                upper,
+                particle,
+                volume,
+            ),
+        }
+    }
+}
+
+/// Reinterprets the coordinates of the grid as coordinates of the float type `T`, requires that `T` and `R` are the same type
+#[cfg(target_arch = "x86_64")]
+fn reinterpret_grid<'a, R: Real, T: Real>(grid: &SplattingGrid<'a, R>) -> SplattingGrid<'a, T> {
+    let [xs, ys, zs] = grid.coordinates;
+    SplattingGrid {
+        points_per_dim: grid.points_per_dim,
+        coordinates: [
+            bytemuck::cast_slice(xs),
+            bytemuck::cast_slice(ys),
+            bytemuck::cast_slice(zs),
+        ],
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx {
+    /// Generates a module with the AVX implementation of the particle splatting for the given float type and its intrinsics
+    macro_rules! impl_avx_splatting {
+        (
+            $module:ident, $t:ty, $vt:ty, $mask_t:ty, $lanes:expr,
+            $set1:ident, $loadu:ident, $storeu:ident, $maskload:ident, $maskstore:ident,
+            $add:ident, $sub:ident, $mul:ident, $div:ident, $sqrt:ident,
+            $cmp:ident, $and:ident, $blendv:ident
+        ) => {
+            pub(super) mod $module {
+                use super::super::{KernelConstants, SplattingGrid};
+                use std::arch::x86_64::{__m256i, _mm256_loadu_si256, _CMP_LT_OQ};
+                use std::arch::x86_64::{
+                    $add as add, $and as and, $blendv as blendv, $cmp as cmp, $div as div,
+                    $loadu as loadu, $maskload as maskload, $maskstore as maskstore, $mul as mul,
+                    $set1 as set1, $sqrt as sqrt, $storeu as storeu, $sub as sub,
+                };
+
+                const LANES: usize = $lanes;
+
+                /// Masks for loading and storing the first `n` lanes starting at index `LANES - n`
+                static TAIL_MASKS: [$mask_t; 2 * LANES] = {
+                    let mut masks = [0; 2 * LANES];
+                    let mut i = 0;
+                    while i < LANES {
+                        masks[i] = -1;
+                        i += 1;
+                    }
+                    masks
+                };
+
+                /// Kernel constants and particle data broadcast to all lanes
+                struct Broadcast {
+                    compact_support_radius: $vt,
+                    normalization: $vt,
+                    squared_support_with_margin: $vt,
+                    inner_factor: $vt,
+                    outer_factor: $vt,
+                    two_thirds: $vt,
+                    half: $vt,
+                    one: $vt,
+                    two: $vt,
+                    pz: $vt,
+                    volume: $vt,
+                }
+
+                /// Computes the weighted kernel values for grid points with the given z-coordinates (zero outside of the kernel support)
+                #[inline]
+                #[target_feature(enable = "avx")]
+                unsafe fn weighted_kernel(c: &Broadcast, dxy_sq: $vt, z: $vt) -> $vt {
+                    let dz = sub(c.pz, z);
+                    let d_sq = add(dxy_sq, mul(dz, dz));
+                    let r = sqrt(d_sq);
+                    let q = div(add(r, r), c.compact_support_radius);
+
+                    // Inner part of the cubic spline (q < 1)
+                    let inner = mul(
+                        c.inner_factor,
+                        add(sub(c.two_thirds, mul(q, q)), mul(mul(mul(c.half, q), q), q)),
+                    );
+                    // Outer part of the cubic spline (1 <= q < 2)
+                    let x = sub(c.two, q);
+                    let outer = mul(mul(mul(c.outer_factor, x), x), x);
+
+                    let cubic = blendv(
+                        and(outer, cmp::<_CMP_LT_OQ>(q, c.two)),
+                        inner,
+                        cmp::<_CMP_LT_OQ>(q, c.one),
+                    );
+                    let value = mul(c.volume, mul(c.normalization, cubic));
+                    and(
+                        value,
+                        cmp::<_CMP_LT_OQ>(d_sq, c.squared_support_with_margin),
+                    )
+                }
+
+                /// AVX implementation of the scalar particle splatting with the exact cubic spline kernel
+                ///
+                /// # Safety
+                /// The CPU has to support AVX instructions.
+                #[target_feature(enable = "avx")]
+                pub(in super::super) unsafe fn splat_particle(
+                    c: &KernelConstants<$t>,
+                    grid: &SplattingGrid<$t>,
+                    levelset_grid: &mut [$t],
+                    lower: [usize; 3],
+                    upper: [usize; 3],
+                    particle: [$t; 3],
+                    volume: $t,
+                ) {
+                    let [_, ny, nz] = grid.points_per_dim;
+                    let [xs, ys, zs] = grid.coordinates;
+                    let [px, py, pz] = particle;
+
+                    let broadcast = Broadcast {
+                        compact_support_radius: set1(c.compact_support_radius),
+                        normalization: set1(c.normalization),
+                        squared_support_with_margin: set1(c.squared_support_with_margin),
+                        inner_factor: set1(c.inner_factor),
+                        outer_factor: set1(c.outer_factor),
+                        two_thirds: set1(c.two_thirds),
+                        half: set1(c.half),
+                        one: set1(1.0),
+                        two: set1(c.two),
+                        pz: set1(pz),
+                        volume: set1(volume),
+                    };
+
+                    let zs = &zs[lower[2]..upper[2]];
+                    let n = zs.len();
+                    let n_vectorized = n - n % LANES;
+                    let tail_mask = _mm256_loadu_si256(
+                        TAIL_MASKS.as_ptr().add(LANES - n % LANES) as *const __m256i
+                    );
+
+                    for (i, &x) in xs.iter().enumerate().take(upper[0]).skip(lower[0]) {
+                        let dx = px - x;
+                        for (j, &y) in ys.iter().enumerate().take(upper[1]).skip(lower[1]) {
+                            let dy = py - y;
+                            let dxy_sq = dx * dx + dy * dy;
+                            if dxy_sq >= c.squared_support_with_margin {
+                                continue;
+                            }
+
+                            let row_offset = (i * ny + j) * nz;
+                            let row =
+                                &mut levelset_grid[row_offset + lower[2]..row_offset + upper[2]];
+                            let row_ptr = row.as_mut_ptr();
+                            let zs_ptr = zs.as_ptr();
+                            let dxy_sq = set1(dxy_sq);
+
+                            // Safety: all loads and stores are within the first `n` elements of `row` and `zs`
+                            let mut k = 0;
+                            while k < n_vectorized {
+                                let value =
+                                    weighted_kernel(&broadcast, dxy_sq, loadu(zs_ptr.add(k)));
+                                let target = row_ptr.add(k);
+                                storeu(target, add(loadu(target), value));
+                                k += LANES;
+                            }
+
+                            if k < n {
+                                let z = maskload(zs_ptr.add(k), tail_mask);
+                                let value = weighted_kernel(&broadcast, dxy_sq, z);
+                                let target = row_ptr.add(k);
+                                maskstore(
+                                    target,
+                                    tail_mask,
+                                    add(maskload(target, tail_mask), value),
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        };
+    }
+
+    impl_avx_splatting!(
+        f32x8,
+        f32,
+        std::arch::x86_64::__m256,
+        i32,
+        8,
+        _mm256_set1_ps,
+        _mm256_loadu_ps,
+        _mm256_storeu_ps,
+        _mm256_maskload_ps,
+        _mm256_maskstore_ps,
+        _mm256_add_ps,
+        _mm256_sub_ps,
+        _mm256_mul_ps,
+        _mm256_div_ps,
+        _mm256_sqrt_ps,
+        _mm256_cmp_ps,
+        _mm256_and_ps,
+        _mm256_blendv_ps
+    );
+
+    impl_avx_splatting!(
+        f64x4,
+        f64,
+        std::arch::x86_64::__m256d,
+        i64,
+        4,
+        _mm256_set1_pd,
+        _mm256_loadu_pd,
+        _mm256_storeu_pd,
+        _mm256_maskload_pd,
+        _mm256_maskstore_pd,
+        _mm256_add_pd,
+        _mm256_sub_pd,
+        _mm256_mul_pd,
+        _mm256_div_pd,
+        _mm256_sqrt_pd,
+        _mm256_cmp_pd,
+        _mm256_and_pd,
+        _mm256_blendv_pd
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::{CubicSplineKernel, SymmetricKernel3d};
+
+    /// Splats particles at pseudo-random positions with all backends and compares the results with the direct kernel evaluation
+    fn test_splatting_consistency<R: Real>() {
+        let h = R::from_f64(0.1).unwrap();
+        let squared_support_with_margin = h * h * R::from_f64(1.01).unwrap();
+        let kernel = CubicSplineKernel::new(h);
+
+        let n = 23;
+        let cube_size = R::from_f64(0.0173).unwrap();
+        let coordinates: Vec<R> = (0..n)
+            .map(|i| R::from_f64(-0.2).unwrap() + R::from_usize(i).unwrap() * cube_size)
+            .collect();
+        let grid = SplattingGrid {
+            points_per_dim: [n; 3],
+            coordinates: [&coordinates, &coordinates, &coordinates],
+        };
+
+        // Reference: direct evaluation of the kernel for every grid point
+        let reference = |particles: &[(Vector3<R>, R)], kernel: &dyn Fn(R) -> R| {
+            let mut levelset = vec![R::zero(); n * n * n];
+            for (p, volume) in particles {
+                for i in 0..n {
+                    for j in 0..n {
+                        for k in 0..n {
+                            let x = Vector3::new(coordinates[i], coordinates[j], coordinates[k]);
+                            let d_sq = (p - x).norm_squared();
+                            if d_sq < squared_support_with_margin {
+                                levelset[(i * n + j) * n + k] += *volume * kernel(d_sq);
+                            }
+                        }
+                    }
+                }
+            }
+            levelset
+        };
+
+        let mut state = 12345u64;
+        let mut random = || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            R::from_f64((state >> 11) as f64 / (1u64 << 53) as f64).unwrap()
+        };
+        let particles: Vec<_> = (0..50)
+            .map(|_| {
+                let p = Vector3::new(random(), random(), random()) * R::from_f64(0.38).unwrap()
+                    - Vector3::repeat(R::from_f64(0.19).unwrap());
+                (p, random() + R::from_f64(0.5).unwrap())
+            })
+            .collect();
+
+        for (enable_simd, tabulated_kernel) in [
+            (false, None),
+            (true, None),
+            (true, Some(TabulatedKernel::new(&kernel, h, 256))),
+        ] {
+            let expected = match &tabulated_kernel {
+                Some(tabulated_kernel) => {
+                    reference(&particles, &|d_sq| tabulated_kernel.evaluate_squared(d_sq))
+                }
+                None => reference(&particles, &|d_sq| kernel.evaluate(d_sq.sqrt())),
+            };
+
+            let splatter = DensitySplatter::new(
+                h,
+                squared_support_with_margin,
+                enable_simd,
+                tabulated_kernel,
+            );
+            let mut levelset = vec![R::zero(); n * n * n];
+            for (p, volume) in &particles {
+                // Use a range that is not a multiple of the SIMD width and exceeds the grid
+                splatter.splat_particle(&grid, &mut levelset, [1, 0, 2], [n, n + 3, n], p, *volume);
+            }
+
+            for i in 0..n {
+                for j in 0..n {
+                    for k in 0..n {
+                        let idx = (i * n + j) * n + k;
+                        if i >= 1 && k >= 2 {
+                            assert_eq!(
+                                levelset[idx].to_f64().unwrap().to_bits(),
+                                expected[idx].to_f64().unwrap().to_bits(),
+                                "mismatch at {:?} with backend {:?}",
+                                [i, j, k],
+                                splatter.backend()
+                            );
+                        } else {
+                            assert_eq!(levelset[idx], R::zero());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_splatting_consistency_f32() {
+        test_splatting_consistency::<f32>();
+    }
+
+    #[test]
+    fn test_splatting_consistency_f64() {
+        test_splatting_consistency::<f64>();
+    }
+}
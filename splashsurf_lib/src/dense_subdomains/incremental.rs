@@ -0,0 +1,351 @@
+//! Reuse of the surface patches of unchanged subdomains in subsequent reconstructions
+
+use super::*;
+use crate::IncrementalParameters;
+use std::fmt;
+
+/// Surface patches of a subdomain grid reconstruction that can be reused by subsequent incremental reconstructions
+#[derive(Clone)]
+pub(crate) struct SurfacePatchCache<I: Index, R: Real> {
+    /// Parameters of the reconstruction the patches were computed with
+    parameters: ParametersSubdomainGrid<I, R>,
+    /// Surface patches of all non-empty subdomains (in the order of the subdomain decomposition)
+    patches: Vec<SurfacePatch<I, R>>,
+    /// Particles each of the patches was reconstructed from
+    patch_particles: Vec<PatchParticles<R>>,
+}
+
+/// Particles of a subdomain (including ghost particles) at the time its surface patch was reconstructed
+#[derive(Clone)]
+struct PatchParticles<R: Real> {
+    /// Indices of the particles
+    indices: Vec<usize>,
+    /// Positions of the particles
+    positions: Vec<Vector3<R>>,
+    /// Densities of the particles
+    densities: Vec<R>,
+}
+
+impl<I: Index, R: Real> SurfacePatchCache<I, R> {
+    /// Returns the cached surface patches (in the order of the subdomain decomposition)
+    pub(crate) fn patches(&self) -> &[SurfacePatch<I, R>] {
+        &self.patches
+    }
+}
+
+impl<I: Index, R: Real> fmt::Debug for SurfacePatchCache<I, R> {
+    /// Only print the number of cached patches to the formatter
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SurfacePatchCache")
+            .field("num_patches", &self.patches.len())
+            .finish()
+    }
+}
+
+impl<R: Real> PatchParticles<R> {
+    fn gather(
+        subdomain_particle_indices: &[usize],
+        global_particles: &[Vector3<R>],
+        global_particle_densities: &[R],
+    ) -> Self {
+        let mut positions = Vec::new();
+        let mut densities = Vec::new();
+        gather_subdomain_data(global_particles, subdomain_particle_indices, &mut positions);
+        gather_subdomain_data(
+            global_particle_densities,
+            subdomain_particle_indices,
+            &mut densities,
+        );
+        Self {
+            indices: subdomain_particle_indices.to_vec(),
+            positions,
+            densities,
+        }
+    }
+
+    /// Returns whether the given particles of the subdomain are the same particles (within the tolerances) as the cached ones
+    fn is_unchanged(
+        &self,
+        subdomain_particle_indices: &[usize],
+        global_particles: &[Vector3<R>],
+        global_particle_densities: &[R],
+        incremental_parameters: &IncrementalParameters<R>,
+    ) -> bool {
+        let squared_position_tolerance = incremental_parameters.position_tolerance.powi(2);
+        let density_tolerance = incremental_parameters.density_tolerance;
+
+        self.indices == subdomain_particle_indices
+            && subdomain_particle_indices
+                .iter()
+                .copied()
+                .zip(self.positions.iter().zip(self.densities.iter()))
+                .all(|(idx, (x_cached, rho_cached))| {
+                    (global_particles[idx] - x_cached).norm_squared() <= squared_position_tolerance
+                        && (global_particle_densities[idx] - *rho_cached).abs()
+                            <= density_tolerance * rho_cached.abs()
+                })
+    }
+}
+
+impl<I: Index, R: Real> ParametersSubdomainGrid<I, R> {
+    /// Returns whether the subdomains are reconstructed exactly like with the other parameters (given the same particles)
+    fn reconstructs_same_patches(&self, other: &Self) -> bool {
+        // The SIMD and the scalar level-set evaluation give identical results
+        self.particle_rest_mass == other.particle_rest_mass
+            && self.compact_support_radius == other.compact_support_radius
+            && self.surface_threshold == other.surface_threshold
+            && self.cube_size == other.cube_size
+            && self.subdomain_cubes == other.subdomain_cubes
+            && self.ghost_particle_margin == other.ghost_particle_margin
+            && self.global_marching_cubes_grid == other.global_marching_cubes_grid
+            && self.subdomain_grid == other.subdomain_grid
+            && self.tabulated_kernel == other.tabulated_kernel
+            && self.vertex_provenance_particles == other.vertex_provenance_particles
+            && self.coordinates == other.coordinates
+    }
+}
+
+/// Reconstructs the surface patches of all subdomains and reuses the cached patches of subdomains whose particles did not change
+///
+/// Returns the updated cache with the patches of all subdomains and the number of reused patches.
+pub(crate) fn incremental_reconstruction<I: Index, R: Real, L: Real>(
+    parameters: &ParametersSubdomainGrid<I, R>,
+    incremental_parameters: &IncrementalParameters<R>,
+    global_particles: &[Vector3<R>],
+    global_particle_densities: &[R],
+    subdomains: &Subdomains<I>,
+    cache: Option<SurfacePatchCache<I, R>>,
+) -> Result<(SurfacePatchCache<I, R>, usize), SubdomainGridError<I, R>> {
+    profile!("incremental reconstruction");
+
+    let num_subdomains = subdomains.flat_subdomain_indices.len();
+    let mut patches: Vec<Option<SurfacePatch<I, R>>> = vec![None; num_subdomains];
+    let mut patch_particles: Vec<Option<PatchParticles<R>>> = vec![None; num_subdomains];
+
+    // Take over the cached patches of all subdomains with unchanged particles
+    if let Some(cache) =
+        cache.filter(|cache| cache.parameters.reconstructs_same_patches(parameters))
+    {
+        profile!("find unchanged subdomains");
+
+        let mut cached_patch_index = new_map();
+        cached_patch_index.extend(
+            cache
+                .patches
+                .iter()
+                .enumerate()
+                .map(|(i, patch)| (patch.subdomain_index, i)),
+        );
+
+        let reusable_patches = subdomains
+            .flat_subdomain_indices
+            .par_iter()
+            .zip(subdomains.per_subdomain_particles.par_iter())
+            .map(|(flat_subdomain_idx, subdomain_particle_indices)| {
+                cached_patch_index
+                    .get(flat_subdomain_idx)
+                    .copied()
+                    .filter(|&i| {
+                        cache.patch_particles[i].is_unchanged(
+                            subdomain_particle_indices,
+                            global_particles,
+                            global_particle_densities,
+                            incremental_parameters,
+                        )
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let mut cached_patches = cache.patches.into_iter().map(Some).collect::<Vec<_>>();
+        let mut cached_particles = cache
+            .patch_particles
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+        for (i, cached_idx) in reusable_patches.into_iter().enumerate() {
+            if let Some(cached_idx) = cached_idx {
+                patches[i] = cached_patches[cached_idx].take();
+                patch_particles[i] = cached_particles[cached_idx].take();
+            }
+        }
+    }
+
+    let mut is_reused = patches.iter().map(Option::is_some).collect::<Vec<_>>();
+    let num_unchanged = is_reused.iter().filter(|r| **r).count();
+
+    // Reconstruct all subdomains without reusable patch until the reused patches match their recomputed neighbors
+    let mut selection = is_reused.iter().map(|r| !r).collect::<Vec<_>>();
+    loop {
+        let new_patches = reconstruction::<I, R, L>(
+            parameters,
+            global_particles,
+            global_particle_densities,
+            subdomains,
+            Some(&selection),
+        )?;
+        let new_particles = selection
+            .par_iter()
+            .zip(subdomains.per_subdomain_particles.par_iter())
+            .filter(|(is_selected, _)| **is_selected)
+            .map(|(_, subdomain_particle_indices)| {
+                PatchParticles::gather(
+                    subdomain_particle_indices,
+                    global_particles,
+                    global_particle_densities,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        selection
+            .iter()
+            .enumerate()
+            .filter_map(|(i, is_selected)| is_selected.then_some(i))
+            .zip(new_patches.into_iter().zip(new_particles))
+            .for_each(|(i, (patch, particles))| {
+                patches[i] = Some(patch);
+                patch_particles[i] = Some(particles);
+            });
+
+        let inconsistent =
+            inconsistent_reused_patches(parameters, subdomains, &patches, &is_reused)?;
+        if inconsistent.is_empty() {
+            break;
+        }
+
+        selection.fill(false);
+        for i in inconsistent {
+            is_reused[i] = false;
+            selection[i] = true;
+        }
+    }
+
+    let num_reused = is_reused.iter().filter(|r| **r).count();
+    info!(
+        "Reused the surface patches of {} of {} subdomains ({} unchanged subdomains had to be reconstructed to match their neighbors).",
+        num_reused,
+        num_subdomains,
+        num_unchanged - num_reused
+    );
+
+    // All patches were either reused or reconstructed at this point
+    let cache = SurfacePatchCache {
+        parameters: parameters.clone(),
+        patches: patches.into_iter().flatten().collect(),
+        patch_particles: patch_particles.into_iter().flatten().collect(),
+    };
+    debug_assert_eq!(cache.patches.len(), num_subdomains);
+
+    Ok((cache, num_reused))
+}
+
+/// Returns the positions of the reused patches whose exterior vertices do not match the adjacent patches
+///
+/// A vertex on a boundary edge between subdomains is generated by the patches of all subdomains adjacent to the edge.
+/// If the level-set values at the shared grid points of a reused and a recomputed patch change sign (within the
+/// tolerances of the reuse), only one of the patches has a vertex on the edge and the stitched mesh would contain holes.
+fn inconsistent_reused_patches<I: Index, R: Real>(
+    parameters: &ParametersSubdomainGrid<I, R>,
+    subdomains: &Subdomains<I>,
+    patches: &[Option<SurfacePatch<I, R>>],
+    is_reused: &[bool],
+) -> Result<Vec<usize>, SubdomainGridError<I, R>> {
+    profile!("check reused patch boundaries");
+
+    if !is_reused.contains(&true) || !is_reused.contains(&false) {
+        return Ok(Vec::new());
+    }
+
+    let mut subdomain_position = new_map();
+    subdomain_position.extend(
+        subdomains
+            .flat_subdomain_indices
+            .iter()
+            .copied()
+            .enumerate()
+            .map(|(i, flat_subdomain_idx)| (flat_subdomain_idx, i)),
+    );
+
+    // Patches that generated a vertex on each globalized boundary edge
+    let mut edge_patches: MapType<(I, EdgeIndex<I>), ArrayVec<usize, 4>> = new_map();
+    for (i, patch) in patches.iter().enumerate() {
+        for edge in patch
+            .iter()
+            .flat_map(|p| p.exterior_vertex_edge_indices.iter())
+        {
+            let reporting_patches = edge_patches.entry(*edge).or_default();
+            if !reporting_patches.contains(&i) {
+                // An edge cannot be adjacent to more than four subdomains
+                let _ = reporting_patches.try_push(i);
+            }
+        }
+    }
+
+    let mut is_inconsistent = vec![false; patches.len()];
+    for ((flat_subdomain_idx, edge), reporting_patches) in edge_patches.iter() {
+        let adjacent_patches = adjacent_subdomains(parameters, *flat_subdomain_idx, edge)?
+            .into_iter()
+            .map(|s| subdomain_position.get(&s).copied())
+            .collect::<ArrayVec<_, 4>>();
+
+        let is_consistent = adjacent_patches.len() == reporting_patches.len()
+            && adjacent_patches
+                .iter()
+                .all(|p| p.is_some_and(|p| reporting_patches.contains(&p)));
+        if !is_consistent {
+            reporting_patches
+                .iter()
+                .chain(adjacent_patches.iter().flatten())
+                .filter(|&&i| is_reused[i])
+                .for_each(|&i| is_inconsistent[i] = true);
+        }
+    }
+
+    Ok(is_inconsistent
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, is_inconsistent)| is_inconsistent.then_some(i))
+        .collect())
+}
+
+/// Returns the flat indices of all subdomains that contain cells adjacent to the given globalized boundary edge
+fn adjacent_subdomains<I: Index, R: Real>(
+    parameters: &ParametersSubdomainGrid<I, R>,
+    flat_subdomain_idx: I,
+    edge: &EdgeIndex<I>,
+) -> Result<ArrayVec<I, 4>, SubdomainGridError<I, R>> {
+    let subdomain_grid = &parameters.subdomain_grid;
+    let subdomain_ijk = *subdomain_grid
+        .try_unflatten_cell_index(flat_subdomain_idx)
+        .ok_or(SubdomainGridError::InvalidSubdomain(flat_subdomain_idx))?
+        .index();
+
+    // Globalized edges on the lower boundary of a subdomain are shared with the preceding subdomain along that axis
+    let orthogonal_axes = edge.axis().orthogonal_axes();
+    let candidates = orthogonal_axes.map(|axis| {
+        let dim = axis.dim();
+        let mut candidates = ArrayVec::<I, 2>::new();
+        if edge.origin().index()[dim] == I::zero() && subdomain_ijk[dim] > I::zero() {
+            candidates.push(subdomain_ijk[dim] - I::one());
+        }
+        candidates.push(subdomain_ijk[dim]);
+        candidates
+    });
+
+    let mut adjacent = ArrayVec::new();
+    for &i in &candidates[0] {
+        for &j in &candidates[1] {
+            let mut ijk = subdomain_ijk;
+            ijk[orthogonal_axes[0].dim()] = i;
+            ijk[orthogonal_axes[1].dim()] = j;
+            let subdomain =
+                subdomain_grid
+                    .get_cell(ijk)
+                    .ok_or(SubdomainGridError::InvalidGridIndex(
+                        "adjacent subdomain of a boundary edge",
+                    ))?;
+            adjacent.push(subdomain_grid.flatten_cell_index(&subdomain));
+        }
+    }
+
+    Ok(adjacent)
+}
@@ -21,7 +21,7 @@
 use crate::aabb::Aabb3d;
 use crate::kernel::DiscreteSquaredDistanceCubicKernel;
 use crate::mesh::{HexMesh3d, MeshAttribute, MeshWithData};
-use crate::neighborhood_search::NeighborhoodList;
+use crate::neighborhood_search::{FlatNeighborhoodList, NeighborhoodList};
 use crate::uniform_grid::UniformGrid;
 use crate::utils::{ChunkSize, ParallelPolicy};
 use crate::{new_map, profile, HashState, Index, MapType, ParallelMapType, Real};
@@ -214,6 +214,46 @@ pub fn parallel_compute_particle_densities<I: Index, R: Real>(
         );
 }
 
+/// Computes the individual densities of particles using a standard SPH sum from precomputed squared neighbor distances, multi-threaded implementation
+///
+/// The squared distances have to be stored in the same order as the neighbors of the flat neighborhood list
+/// (e.g. as computed by [`neighborhood_search_spatial_hashing_flat_parallel`](crate::neighborhood_search::neighborhood_search_spatial_hashing_flat_parallel)).
+#[inline(never)]
+pub fn parallel_compute_particle_densities_from_squared_distances<R: Real>(
+    particle_neighbor_lists: &FlatNeighborhoodList,
+    neighbor_squared_distances: &[R],
+    compact_support_radius: R,
+    particle_rest_mass: R,
+    particle_densities: &mut Vec<R>,
+) {
+    profile!("parallel_compute_particle_densities_from_squared_distances");
+    assert_eq!(
+        particle_neighbor_lists.neighbors.len(),
+        neighbor_squared_distances.len()
+    );
+
+    init_density_storage(particle_densities, particle_neighbor_lists.len());
+
+    // Pre-compute the kernel which can be queried using squared distances
+    let kernel = DiscreteSquaredDistanceCubicKernel::new::<f64>(1000, compact_support_radius);
+
+    particle_densities
+        .par_iter_mut()
+        .with_min_len(8)
+        .zip_eq(particle_neighbor_lists.neighbor_ptr.par_windows(2))
+        .for_each(|(particle_i_density, range)| {
+            let mut density = kernel.evaluate(R::zero());
+            for r_squared in neighbor_squared_distances[range[0]..range[1]]
+                .iter()
+                .copied()
+            {
+                density += kernel.evaluate(r_squared);
+            }
+            density *= particle_rest_mass;
+            *particle_i_density = density;
+        });
+}
+
 /// A sparse density map
 ///
 /// The density map contains values for all points of the background grid where the density is not
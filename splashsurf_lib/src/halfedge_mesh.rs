@@ -2,7 +2,7 @@
 //!
 //! See [`HalfEdgeTriMesh`] for more information.
 
-use crate::mesh::{Mesh3d, TriMesh3d, TriMesh3dExt};
+use crate::mesh::{compute_connected_components, Mesh3d, MeshComponents, TriMesh3d, TriMesh3dExt};
 use crate::{profile, Real, SetType};
 use nalgebra::Vector3;
 use rayon::prelude::*;
@@ -85,6 +85,28 @@ pub enum IllegalHalfEdgeCollapse {
     FacelessEdge,
 }
 
+/// Error indicating why a specific edge flip is illegal
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ThisError)]
+pub enum IllegalEdgeFlip {
+    /// Trying to flip an edge that is not adjacent to two faces
+    #[error("trying to flip an edge that is not adjacent to two faces")]
+    BoundaryEdge,
+    /// Trying to flip an edge whose flipped counterpart already exists in the mesh
+    #[error("trying to flip an edge whose flipped counterpart already exists in the mesh")]
+    ExistingEdge,
+    /// Trying to flip an edge with a vertex of valence three or less at one of its ends
+    #[error("trying to flip an edge with a vertex of valence three or less at one of its ends")]
+    MinimalValence,
+}
+
+/// Error indicating why a specific edge split is illegal
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ThisError)]
+pub enum IllegalEdgeSplit {
+    /// Trying to split an edge that is not adjacent to two faces
+    #[error("trying to split an edge that is not adjacent to two faces")]
+    BoundaryEdge,
+}
+
 impl<R: Real> HalfEdgeTriMesh<R> {
     /// Converts this mesh into a simple triangle mesh and a vertex-vertex connectivity map
     pub fn into_parts(mut self, keep_vertices: bool) -> (TriMesh3d<R>, Vec<Vec<usize>>) {
@@ -175,6 +197,18 @@ impl<R: Real> HalfEdgeTriMesh<R> {
         !self.removed_half_edges.contains(&half_edge_idx)
     }
 
+    /// Labels the connected components of the valid triangles of the mesh and computes their statistics (see [`TriMesh3d::connected_components`])
+    ///
+    /// Vertices and triangles that are marked as removed are assigned the component index `usize::MAX`.
+    pub fn connected_components(&self) -> MeshComponents<R> {
+        compute_connected_components(
+            &self.vertices,
+            &self.triangles,
+            |v| self.is_valid_vertex(v),
+            |t| self.is_valid_triangle(t),
+        )
+    }
+
     /// Returns the next half-edge in the loop of the given half-edge, panics if there is none
     pub fn next(&self, half_edge: HalfEdge) -> HalfEdge {
         self.half_edges[half_edge
@@ -427,6 +461,211 @@ impl<R: Real> HalfEdgeTriMesh<R> {
         max_aspect_ratio_change
     }
 
+    /// Checks if the flip of the edge of the given half-edge is topologically legal
+    pub fn is_flip_ok(&self, half_edge: HalfEdge) -> Result<(), IllegalEdgeFlip> {
+        let he = half_edge;
+        let he_o = self.opposite(he);
+
+        if self.is_boundary_edge(he) {
+            return Err(IllegalEdgeFlip::BoundaryEdge);
+        }
+
+        // Flipping reduces the valence of both edge vertices by one
+        if self.vertex_one_ring_len(he.to) <= 3 || self.vertex_one_ring_len(he_o.to) <= 3 {
+            return Err(IllegalEdgeFlip::MinimalValence);
+        }
+
+        // Vertices opposite to the edge
+        let v_pos = self.next(he).to;
+        let v_neg = self.next(he_o).to;
+
+        if v_pos == v_neg || self.half_edge(v_pos, v_neg).is_some() {
+            return Err(IllegalEdgeFlip::ExistingEdge);
+        }
+
+        Ok(())
+    }
+
+    /// Flips the edge of the given half-edge if it is topologically legal
+    pub fn try_edge_flip(&mut self, half_edge: HalfEdge) -> Result<(), IllegalEdgeFlip> {
+        self.is_flip_ok(half_edge)?;
+
+        self.edge_flip(half_edge);
+        Ok(())
+    }
+
+    /// Flips the edge of the given half-edge, i.e. replaces it by the edge connecting the two vertices opposite to it
+    ///
+    /// The two adjacent faces and the two half-edges of the edge keep their indices.
+    /// Assumes that the flip is legal (see [`is_flip_ok`](HalfEdgeTriMesh::is_flip_ok)).
+    pub fn edge_flip(&mut self, half_edge: HalfEdge) {
+        // Notation: he = (a -> b) in face f0 = (a, b, c), he_o = (b -> a) in face f1 = (b, a, d)
+        let he = half_edge;
+        let he_o = self.opposite(he);
+
+        let he_n = self.next(he);
+        let he_nn = self.next(he_n);
+        let he_on = self.next(he_o);
+        let he_onn = self.next(he_on);
+
+        let f0 = he.face.expect("half-edge to flip must have a face");
+        let f1 = he_o.face.expect("half-edge to flip must have a face");
+
+        let a = he_o.to;
+        let b = he.to;
+        let c = he_n.to;
+        let d = he_on.to;
+
+        // New face f0 = (a, d, c): he_on -> he -> he_nn
+        self.half_edges[he.idx].to = c;
+        self.half_edges[he_on.idx].face = Some(f0);
+        self.half_edges[he_on.idx].next = Some(he.idx);
+        self.half_edges[he.idx].next = Some(he_nn.idx);
+        self.half_edges[he_nn.idx].next = Some(he_on.idx);
+        self.triangles[f0] = [a, d, c];
+
+        // New face f1 = (d, b, c): he_onn -> he_n -> he_o
+        self.half_edges[he_o.idx].to = d;
+        self.half_edges[he_n.idx].face = Some(f1);
+        self.half_edges[he_onn.idx].next = Some(he_n.idx);
+        self.half_edges[he_n.idx].next = Some(he_o.idx);
+        self.half_edges[he_o.idx].next = Some(he_onn.idx);
+        self.triangles[f1] = [d, b, c];
+
+        // The half-edges of the flipped edge now start at the opposite vertices
+        self.vertex_half_edge_map[a].retain(|he_i| *he_i != he.idx);
+        self.vertex_half_edge_map[b].retain(|he_i| *he_i != he_o.idx);
+        self.vertex_half_edge_map[d].push(he.idx);
+        self.vertex_half_edge_map[c].push(he_o.idx);
+    }
+
+    /// Computes the largest angle in radians between the face normals before and after the flip of the given half-edge, assumes that the given half-edge is not a boundary edge
+    ///
+    /// Returns pi if one of the flipped triangles is degenerate.
+    pub fn edge_flip_max_normal_change(&self, half_edge: HalfEdge) -> R {
+        let he = half_edge;
+        let he_o = self.opposite(he);
+
+        let a = he_o.to;
+        let b = he.to;
+        let c = self.next(he).to;
+        let d = self.next(he_o).to;
+
+        let tris_old = [[a, b, c], [b, a, d]];
+        let tris_new = [[a, d, c], [d, b, c]];
+
+        let mut max_normal_change_angle = R::zero();
+        for tri_new in &tris_new {
+            if self.tri_area_ijk::<R>(tri_new) <= R::default_epsilon() {
+                return R::pi();
+            }
+
+            let new_normal = self.tri_normal_ijk::<R>(tri_new);
+            for tri_old in &tris_old {
+                let old_normal = self.tri_normal_ijk::<R>(tri_old);
+                let alpha = old_normal.dot(&new_normal).min(R::one()).acos();
+                max_normal_change_angle = max_normal_change_angle.max(alpha);
+            }
+        }
+
+        max_normal_change_angle
+    }
+
+    /// Splits the edge of the given half-edge at its midpoint if it is not a boundary edge, returns the index of the new vertex
+    pub fn try_edge_split(&mut self, half_edge: HalfEdge) -> Result<usize, IllegalEdgeSplit> {
+        if self.is_boundary_edge(half_edge) {
+            return Err(IllegalEdgeSplit::BoundaryEdge);
+        }
+
+        Ok(self.edge_split(half_edge))
+    }
+
+    /// Splits the edge of the given half-edge at its midpoint, returns the index of the new vertex
+    ///
+    /// The two faces adjacent to the edge are split into four faces by connecting the new vertex to the two vertices
+    /// opposite to the edge. The existing faces and half-edges keep their indices, two new faces and six new half-edges
+    /// are appended to the mesh. Assumes that the given half-edge is not a boundary edge.
+    pub fn edge_split(&mut self, half_edge: HalfEdge) -> usize {
+        // Notation: he = (a -> b) in face f0 = (a, b, c), he_o = (b -> a) in face f1 = (b, a, d)
+        let he = half_edge;
+        let he_o = self.opposite(he);
+
+        let he_n = self.next(he);
+        let he_nn = self.next(he_n);
+        let he_on = self.next(he_o);
+        let he_onn = self.next(he_on);
+
+        let f0 = he.face.expect("half-edge to split must have a face");
+        let f1 = he_o.face.expect("half-edge to split must have a face");
+
+        let a = he_o.to;
+        let b = he.to;
+        let c = he_n.to;
+        let d = he_on.to;
+
+        // Add the new vertex
+        let m = self.vertices.len();
+        let midpoint = (self.vertices[a] + self.vertices[b]).unscale(R::one() + R::one());
+        self.vertices.push(midpoint);
+
+        // Add the new faces
+        let f2 = self.triangles.len();
+        let f3 = f2 + 1;
+        self.triangles[f0] = [a, m, c];
+        self.triangles[f1] = [m, a, d];
+        self.triangles.push([m, b, c]);
+        self.triangles.push([b, m, d]);
+
+        // Add the new half-edges
+        let h_mb = self.half_edges.len();
+        let h_ma = h_mb + 1;
+        let h_mc = h_mb + 2;
+        let h_cm = h_mb + 3;
+        let h_md = h_mb + 4;
+        let h_dm = h_mb + 5;
+        let new_half_edge = |idx, to, face, next, opposite| HalfEdge {
+            idx,
+            to,
+            face: Some(face),
+            next: Some(next),
+            opposite,
+        };
+        self.half_edges.extend([
+            new_half_edge(h_mb, b, f2, he_n.idx, he_o.idx),
+            new_half_edge(h_ma, a, f1, he_on.idx, he.idx),
+            new_half_edge(h_mc, c, f0, he_nn.idx, h_cm),
+            new_half_edge(h_cm, m, f2, h_mb, h_mc),
+            new_half_edge(h_md, d, f3, he_onn.idx, h_dm),
+            new_half_edge(h_dm, m, f1, h_ma, h_md),
+        ]);
+
+        // Face f0 = (a, m, c): he -> h_mc -> he_nn
+        self.half_edges[he.idx].to = m;
+        self.half_edges[he.idx].next = Some(h_mc);
+        self.half_edges[he.idx].opposite = h_ma;
+
+        // Face f2 = (m, b, c): h_mb -> he_n -> h_cm
+        self.half_edges[he_n.idx].face = Some(f2);
+        self.half_edges[he_n.idx].next = Some(h_cm);
+
+        // Face f1 = (m, a, d): h_ma -> he_on -> h_dm
+        self.half_edges[he_on.idx].next = Some(h_dm);
+
+        // Face f3 = (b, m, d): he_o -> h_md -> he_onn
+        self.half_edges[he_o.idx].to = m;
+        self.half_edges[he_o.idx].face = Some(f3);
+        self.half_edges[he_o.idx].next = Some(h_md);
+        self.half_edges[he_o.idx].opposite = h_mb;
+        self.half_edges[he_onn.idx].face = Some(f3);
+
+        // Update the vertex connectivity
+        self.vertex_half_edge_map.push(vec![h_mb, h_ma, h_mc, h_md]);
+        self.vertex_half_edge_map[c].push(h_cm);
+        self.vertex_half_edge_map[d].push(h_dm);
+
+        m
+    }
+
     fn compute_vertex_vertex_connectivity(
         vertex_half_edge_map: &mut [Vec<usize>],
         half_edges: &[HalfEdge],
@@ -4,8 +4,9 @@ use crate::mesh::{
     AttributeData, CellConnectivity, Mesh3d, MeshAttribute, MeshWithData, TriMesh3d,
 };
 use crate::{utils, Real};
-use anyhow::Context;
-use nalgebra::Vector3;
+use anyhow::{anyhow, Context};
+use log::warn;
+use nalgebra::{Vector2, Vector3};
 use std::fs;
 use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::Path;
@@ -13,7 +14,9 @@ use std::str::FromStr;
 
 // TODO: Support for other mesh data (interpolated fields)?
 
-/// Writes the given mesh to an OBJ file, supports outputting normals
+/// Writes the given mesh to an OBJ file, supports outputting normals and texture coordinates
+///
+/// Normals are taken from the 3D vector point attribute `"normals"`, texture coordinates from the 2D vector point attribute `"uv"`.
 pub fn mesh_to_obj<R: Real, M: Mesh3d<R>, P: AsRef<Path>>(
     mesh: &MeshWithData<R, M>,
     filename: P,
@@ -38,40 +41,54 @@ pub fn mesh_to_obj<R: Real, M: Mesh3d<R>, P: AsRef<Path>>(
         .iter()
         .find(|attrib| attrib.name == "normals");
 
-    if let Some(normals) = normals {
-        match &normals.data {
-            AttributeData::Vector3Real(normals) => {
-                for n in normals {
-                    write!(&mut writer, "vn {} {} {}\n", n.x, n.y, n.z)?;
-                }
+    let normals = match normals.map(|attrib| &attrib.data) {
+        Some(AttributeData::Vector3Real(normals)) => {
+            for n in normals {
+                write!(&mut writer, "vn {} {} {}\n", n.x, n.y, n.z)?;
             }
-            _ => {}
+            true
         }
-    }
+        _ => false,
+    };
 
-    if normals.is_some() {
-        for f in mesh_vertices.cells() {
-            write!(writer, "f")?;
-            f.vertices()
-                .iter()
-                .copied()
-                .try_for_each(|v| write!(writer, " {}//{}", v + 1, v + 1))?;
-            write!(writer, "\n")?;
-        }
-    } else {
-        for f in mesh_vertices.cells() {
-            write!(writer, "f")?;
-            f.vertices()
-                .iter()
-                .copied()
-                .try_for_each(|v| write!(writer, " {}", v + 1))?;
-            write!(writer, "\n")?;
+    let uvs = mesh
+        .point_attributes
+        .iter()
+        .find(|attrib| attrib.name == "uv");
+
+    let uvs = match uvs.map(|attrib| &attrib.data) {
+        Some(AttributeData::Vector2Real(uvs)) => {
+            for uv in uvs {
+                write!(&mut writer, "vt {} {}\n", uv.x, uv.y)?;
+            }
+            true
         }
+        _ => false,
+    };
+
+    // Normals and texture coordinates are stored per vertex, i.e. they use the same indices as the vertices
+    for f in mesh_vertices.cells() {
+        write!(writer, "f")?;
+        f.vertices()
+            .iter()
+            .copied()
+            .try_for_each(|v| match (uvs, normals) {
+                (true, true) => write!(writer, " {}/{}/{}", v + 1, v + 1, v + 1),
+                (true, false) => write!(writer, " {}/{}", v + 1, v + 1),
+                (false, true) => write!(writer, " {}//{}", v + 1, v + 1),
+                (false, false) => write!(writer, " {}", v + 1),
+            })?;
+        write!(writer, "\n")?;
     }
 
     Ok(())
 }
 
+/// Reads a triangle mesh from an OBJ file, supports reading normals and texture coordinates
+///
+/// Normals are attached as point attribute `"normals"`, texture coordinates as point attribute `"uv"`. Texture
+/// coordinates are only attached if every face corner uses the same texture coordinate index as its vertex index
+/// (as written by [`mesh_to_obj`]), otherwise (e.g. for meshes with texture seams) they are skipped with a warning.
 pub fn surface_mesh_from_obj<R: Real, P: AsRef<Path>>(
     obj_path: P,
 ) -> Result<MeshWithData<R, TriMesh3d<R>>, anyhow::Error> {
@@ -81,6 +98,9 @@ pub fn surface_mesh_from_obj<R: Real, P: AsRef<Path>>(
     let mut vertices = Vec::new();
     let mut triangles = Vec::new();
     let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    // Whether all face corners use the texture coordinate with the same index as their vertex
+    let mut uvs_per_vertex = true;
 
     let buffer_to_vec3 = |buffer: &[&str]| -> Result<Vector3<R>, anyhow::Error> {
         Ok(Vector3::new(
@@ -109,12 +129,17 @@ pub fn surface_mesh_from_obj<R: Real, P: AsRef<Path>>(
             vertices.push(buffer_to_vec3(&buffer)?);
         } else if let Some(face_string) = line.strip_prefix("f ") {
             // TODO: Support mixed tri/quad meshes?
-            buffer.extend(
-                face_string
-                    .split(' ')
-                    // Support "v1/vt1", "v1/vt1/vn1" and "v1//vn1" formats (ignore everything after '/')
-                    .map(|f| f.split_once('/').map(|(f, _)| f).unwrap_or(f)),
-            );
+            // Support "v1/vt1", "v1/vt1/vn1" and "v1//vn1" formats (normal indices are ignored)
+            for corner in face_string.split(' ') {
+                let mut indices = corner.split('/');
+                let v = indices.next().unwrap_or_default();
+                let vt = indices.next().filter(|vt| !vt.is_empty());
+                uvs_per_vertex &= match vt {
+                    Some(vt) => usize::from_str(vt)? == usize::from_str(v)?,
+                    None => false,
+                };
+                buffer.push(v);
+            }
             assert_eq!(
                 buffer.len(),
                 3,
@@ -134,6 +159,21 @@ pub fn surface_mesh_from_obj<R: Real, P: AsRef<Path>>(
                 "expected three normal components per vertex"
             );
             normals.push(buffer_to_vec3(&buffer)?);
+        } else if let Some(uv_string) = line.strip_prefix("vt ") {
+            buffer.extend(uv_string.split(' '));
+            // The second texture coordinate is optional (defaults to zero), the optional third one is ignored
+            if buffer.is_empty() || buffer.len() > 3 {
+                return Err(anyhow!(
+                    "expected one to three texture coordinates per vertex, got \"{}\"",
+                    line
+                ));
+            }
+            let u = f64::from_str(buffer[0])?;
+            let v = buffer.get(1).map(|v| f64::from_str(v)).transpose()?;
+            uvs.push(Vector2::new(
+                R::from_f64(u).unwrap(),
+                R::from_f64(v.unwrap_or(0.0)).unwrap(),
+            ));
         }
 
         outer_buffer = utils::recycle(buffer);
@@ -157,6 +197,19 @@ pub fn surface_mesh_from_obj<R: Real, P: AsRef<Path>>(
         ));
     }
 
+    if !uvs.is_empty() {
+        if uvs_per_vertex && uvs.len() == mesh.vertices().len() {
+            mesh.point_attributes
+                .push(MeshAttribute::new("uv", AttributeData::Vector2Real(uvs)));
+        } else {
+            warn!(
+                "Skipping texture coordinates of OBJ file: {} texture coordinates for {} vertices, only texture coordinates indexed like the vertices are supported",
+                uvs.len(),
+                mesh.vertices().len()
+            );
+        }
+    }
+
     Ok(mesh)
 }
 
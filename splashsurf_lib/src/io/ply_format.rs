@@ -6,7 +6,7 @@ use crate::mesh::{
 use crate::utils::IteratorExt;
 use crate::Real;
 use anyhow::{anyhow, Context};
-use nalgebra::Vector3;
+use nalgebra::{Vector2, Vector3};
 use num_traits::ToPrimitive;
 use ply_rs::parser::Parser as PlyParser;
 use ply_rs::ply::{DefaultElement, Ply, Property};
@@ -88,6 +88,7 @@ fn parse_particles_from_ply<R: Real>(
 /// element face *
 /// property list uchar uint vertex_indices
 /// ```
+/// Normals (`nx`, `ny`, `nz`) and texture coordinates (`u`, `v` or `s`, `t`) are optional and loaded as the point attributes `"normals"` and `"uv"`.
 fn parse_mesh_from_ply<R: Real>(
     ply_file: &Ply<DefaultElement>,
 ) -> Result<MeshWithData<R, TriMesh3d<R>>, anyhow::Error> {
@@ -182,12 +183,36 @@ fn parse_mesh_from_ply<R: Real>(
                 MeshAttribute::new("normals".to_string(), AttributeData::Vector3Real(normals));
             mesh.point_attributes.push(normals);
         }
+
+        let uv_names = [("u", "v"), ("s", "t")].into_iter().find(|(u, v)| {
+            vertex_properties.contains_key(*u) && vertex_properties.contains_key(*v)
+        });
+
+        if let Some((u_name, v_name)) = uv_names {
+            let uvs = vertices_normals
+                .iter()
+                .map(|e| match (e.get(u_name).unwrap(), e.get(v_name).unwrap()) {
+                    (Property::Float(u), Property::Float(v)) => Ok(Vector2::new(
+                        R::from_f32(*u).unwrap(),
+                        R::from_f32(*v).unwrap(),
+                    )),
+                    _ => Err(anyhow!(
+                        "Texture coordinate properties have wrong PLY data type (expected float)"
+                    )),
+                })
+                .try_collect_with_capacity(vertices_normals.len())?;
+
+            mesh.point_attributes
+                .push(MeshAttribute::new("uv", AttributeData::Vector2Real(uvs)));
+        }
     }
 
     Ok(mesh)
 }
 
 /// Tries to write a mesh with attributes into a little endian PLY file
+///
+/// The point attribute `"normals"` is written as the properties `nx`, `ny`, `nz` and a 2D vector point attribute `"uv"` as the texture coordinates `u`, `v`.
 #[rustfmt::skip]
 pub fn mesh_to_ply<R: Real, M: Mesh3d<R>, P: AsRef<Path>>(
     mesh: &MeshWithData<R, M>,
@@ -215,14 +240,25 @@ pub fn mesh_to_ply<R: Real, M: Mesh3d<R>, P: AsRef<Path>>(
             write!(&mut writer, "property float nx\n")?;
             write!(&mut writer, "property float ny\n")?;
             write!(&mut writer, "property float nz\n")?;
+        } else if let ("uv", AttributeData::Vector2Real(_)) = (p_attr.name.as_str(), &p_attr.data) {
+            write!(&mut writer, "property float u\n")?;
+            write!(&mut writer, "property float v\n")?;
         } else {
             match p_attr.data {
                 AttributeData::ScalarU64(_) => write!(&mut writer, "property uint {}\n", p_attr.name)?,
+                AttributeData::ScalarI64(_) => write!(&mut writer, "property int {}\n", p_attr.name)?,
                 AttributeData::ScalarReal(_) => write!(&mut writer, "property float {}\n", p_attr.name)?,
-                AttributeData::Vector3Real(_) => {
-                    write!(&mut writer, "property float {}_x\n", p_attr.name)?;
-                    write!(&mut writer, "property float {}_y\n", p_attr.name)?;
-                    write!(&mut writer, "property float {}_z\n", p_attr.name)?;
+                AttributeData::Vector2Real(_) | AttributeData::Vector3Real(_) | AttributeData::Vector4Real(_) => {
+                    for c in ["x", "y", "z", "w"].iter().take(p_attr.data.num_components()) {
+                        write!(&mut writer, "property float {}_{}\n", p_attr.name, c)?;
+                    }
+                },
+                AttributeData::Matrix3Real(_) => {
+                    for i in 0..3 {
+                        for j in 0..3 {
+                            write!(&mut writer, "property float {}_{}{}\n", p_attr.name, i, j)?;
+                        }
+                    }
                 },
             }
         }
@@ -231,6 +267,13 @@ pub fn mesh_to_ply<R: Real, M: Mesh3d<R>, P: AsRef<Path>>(
     write!(&mut writer, "property list uchar uint vertex_indices\n")?;
     write!(&mut writer, "end_header\n")?;
 
+    let write_reals = |writer: &mut BufWriter<_>, values: &[R]| -> Result<(), anyhow::Error> {
+        for v in values {
+            writer.write_all(&v.to_f32().expect("failed to convert attribute to f32").to_le_bytes())?;
+        }
+        Ok(())
+    };
+
     for (i, v) in mesh.vertices().iter().enumerate() {
         writer.write_all(&v.x.to_f32().expect("failed to convert coordinate to f32").to_le_bytes())?;
         writer.write_all(&v.y.to_f32().expect("failed to convert coordinate to f32").to_le_bytes())?;
@@ -242,16 +285,16 @@ pub fn mesh_to_ply<R: Real, M: Mesh3d<R>, P: AsRef<Path>>(
                     let val = data[i].to_u32().expect("failed to convert attribute to u32");
                     writer.write_all(&val.to_le_bytes())?;
                 },
-                AttributeData::ScalarReal(data) => {
-                    let val = data[i].to_f32().expect("failed to convert attribute to f32");
+                AttributeData::ScalarI64(data) => {
+                    let val = data[i].to_i32().expect("failed to convert attribute to i32");
                     writer.write_all(&val.to_le_bytes())?;
                 },
-                AttributeData::Vector3Real(data) => {
-                    let val = &data[i];
-                    writer.write_all(&val.x.to_f32().expect("failed to convert attribute to f32").to_le_bytes())?;
-                    writer.write_all(&val.y.to_f32().expect("failed to convert attribute to f32").to_le_bytes())?;
-                    writer.write_all(&val.z.to_f32().expect("failed to convert attribute to f32").to_le_bytes())?;
-                },
+                AttributeData::ScalarReal(data) => write_reals(&mut writer, &[data[i]])?,
+                AttributeData::Vector2Real(data) => write_reals(&mut writer, data[i].as_slice())?,
+                AttributeData::Vector3Real(data) => write_reals(&mut writer, data[i].as_slice())?,
+                AttributeData::Vector4Real(data) => write_reals(&mut writer, data[i].as_slice())?,
+                // Tensors are written in row-major order
+                AttributeData::Matrix3Real(data) => write_reals(&mut writer, data[i].transpose().as_slice())?,
             }
         }
     }
@@ -4,7 +4,7 @@ use crate::mesh::{AttributeData, IntoVtkDataSet, MeshAttribute, MeshWithData, Tr
 use crate::utils::IteratorExt;
 use crate::{Real, RealConvert};
 use anyhow::{anyhow, Context};
-use nalgebra::Vector3;
+use nalgebra::{Matrix3, Vector2, Vector3, Vector4};
 use std::borrow::Cow;
 use std::fs::create_dir_all;
 use std::path::{Path, PathBuf};
@@ -307,18 +307,22 @@ fn surface_mesh_from_unstructured_grid<R: Real>(
 }
 
 /// Converts a VTK IOBuffer to the corresponding supported AttributeData
+///
+/// Integer scalars are loaded as integer attributes, floating point data with 1, 2, 3, 4 or 9 (3x3 tensor in row-major order) components as real attributes.
 fn try_convert_io_buffer_to_attribute<R: Real>(
     io_buffer: &vtkio::model::IOBuffer,
     num_comp: usize,
 ) -> Result<AttributeData<R>, anyhow::Error> {
     match num_comp {
         1 => match &io_buffer {
-            IOBuffer::U32(vec) => try_map_scalars_to_real(&vec, |val| {
-                R::from_u32(val).ok_or_else(|| {
-                    anyhow!("Cannot convert an attribute value from u32 to Real type")
-                })
-            })
-            .map(|v| AttributeData::ScalarReal(v)),
+            IOBuffer::U8(vec) => Ok(AttributeData::ScalarU64(map_integers(vec))),
+            IOBuffer::U16(vec) => Ok(AttributeData::ScalarU64(map_integers(vec))),
+            IOBuffer::U32(vec) => Ok(AttributeData::ScalarU64(map_integers(vec))),
+            IOBuffer::U64(vec) => Ok(AttributeData::ScalarU64(vec.clone())),
+            IOBuffer::I8(vec) => Ok(AttributeData::ScalarI64(map_integers(vec))),
+            IOBuffer::I16(vec) => Ok(AttributeData::ScalarI64(map_integers(vec))),
+            IOBuffer::I32(vec) => Ok(AttributeData::ScalarI64(map_integers(vec))),
+            IOBuffer::I64(vec) => Ok(AttributeData::ScalarI64(vec.clone())),
             IOBuffer::F32(vec) => try_map_scalars_to_real(&vec, |val| {
                 R::from_f32(val).ok_or_else(|| {
                     anyhow!("Cannot convert an attribute value from f32 to Real type")
@@ -333,6 +337,9 @@ fn try_convert_io_buffer_to_attribute<R: Real>(
             .map(|v| AttributeData::ScalarReal(v)),
             _ => Err(anyhow!("Unsupported IOBuffer scalar data type")),
         },
+        2 => try_map_components_to_real(io_buffer, 2).map(|v| {
+            AttributeData::Vector2Real(v.chunks_exact(2).map(Vector2::from_column_slice).collect())
+        }),
         3 => match &io_buffer {
             IOBuffer::F32(coords) => {
                 particles_from_coords(coords).map(|p| AttributeData::Vector3Real(p))
@@ -342,6 +349,12 @@ fn try_convert_io_buffer_to_attribute<R: Real>(
             }
             _ => Err(anyhow!("Unsupported IOBuffer vector data type")),
         },
+        4 => try_map_components_to_real(io_buffer, 4).map(|v| {
+            AttributeData::Vector4Real(v.chunks_exact(4).map(Vector4::from_column_slice).collect())
+        }),
+        9 => try_map_components_to_real(io_buffer, 9).map(|v| {
+            AttributeData::Matrix3Real(v.chunks_exact(9).map(Matrix3::from_row_slice).collect())
+        }),
         _ => Err(anyhow!(
             "Unsupported number of components ({}) in VTK IO buffer",
             num_comp
@@ -349,6 +362,38 @@ fn try_convert_io_buffer_to_attribute<R: Real>(
     }
 }
 
+/// Converts integer values to the integer type of an integer attribute
+fn map_integers<T: Copy + Into<U>, U>(io_buffer: &[T]) -> Vec<U> {
+    io_buffer.iter().copied().map(Into::into).collect()
+}
+
+/// Converts floating point data with the given number of components per entry to a flat vector of reals
+fn try_map_components_to_real<R: Real>(
+    io_buffer: &vtkio::model::IOBuffer,
+    num_comp: usize,
+) -> Result<Vec<R>, anyhow::Error> {
+    let values = match io_buffer {
+        IOBuffer::F32(vec) => try_map_scalars_to_real(vec, |val| {
+            R::from_f32(val)
+                .ok_or_else(|| anyhow!("Cannot convert an attribute value from f32 to Real type"))
+        }),
+        IOBuffer::F64(vec) => try_map_scalars_to_real(vec, |val| {
+            R::from_f64(val)
+                .ok_or_else(|| anyhow!("Cannot convert an attribute value from f64 to Real type"))
+        }),
+        _ => Err(anyhow!("Unsupported IOBuffer vector data type")),
+    }?;
+
+    if values.len() % num_comp != 0 {
+        return Err(anyhow!(
+            "Number of values ({}) in VTK IO buffer is not divisible by the number of components ({})",
+            values.len(),
+            num_comp
+        ));
+    }
+    Ok(values)
+}
+
 fn try_map_scalars_to_real<R: Real, T: Copy, F: Fn(T) -> Result<R, anyhow::Error>>(
     io_buffer: &[T],
     f: F,
@@ -62,6 +62,28 @@ impl<R: Real> CubicSplineKernel<R> {
             return 0.0;
         }
     }
+
+    /// The second derivative of the cubic spline function used by the cubic spline kernel w.r.t to the parameter `q`
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn cubic_function_dq2(q: R) -> R {
+        if q < 1.0 {
+            (3.0 / (4.0 * R::pi())) * (-4.0 + 6.0 * q)
+        } else if q < 2.0 {
+            (3.0 / (2.0 * R::pi())) * (2.0 - q)
+        } else {
+            0.0
+        }
+    }
+
+    /// Evaluates the second derivative of the cubic spline kernel w.r.t. the radial distance `r`
+    pub fn evaluate_second_derivative(&self, r: R) -> R {
+        let q = (r + r) / self.compact_support_radius;
+
+        let d2fdq2 = Self::cubic_function_dq2(q);
+        let dqdr = (R::one() + R::one()) / self.compact_support_radius;
+
+        self.normalization * d2fdq2 * dqdr * dqdr
+    }
 }
 
 impl<R: Real> SymmetricKernel3d<R> for CubicSplineKernel<R> {
@@ -144,6 +166,31 @@ fn test_cubic_kernel_r_integral() {
     }
 }
 
+#[test]
+fn test_cubic_kernel_r_second_derivative() {
+    let hs = [0.025, 0.1, 2.0];
+    let n = 100;
+
+    for &h in hs.iter() {
+        let kernel = CubicSplineKernel::new(h);
+        let dr = 1e-6 * h;
+
+        // Compare to central differences of the first derivative (except at the kinks of the second derivative)
+        for i in 1..n {
+            let r = h * (i as f64) / (n as f64);
+            if (r - 0.5 * h).abs() < 2.0 * dr {
+                continue;
+            }
+            let fd = (kernel.evaluate_gradient_norm(r + dr)
+                - kernel.evaluate_gradient_norm(r - dr))
+                / (2.0 * dr);
+            let d2 = kernel.evaluate_second_derivative(r);
+            assert!((fd - d2).abs() <= 1e-4 * kernel.evaluate_second_derivative(0.0).abs());
+        }
+        assert_eq!(kernel.evaluate_second_derivative(h), 0.0);
+    }
+}
+
 /// Accelerator for efficient evaluation of a precomputed cubic kernel
 ///
 /// This structure is used to pre-compute a discrete representation of the cubic kernel function.
@@ -241,3 +288,229 @@ fn test_discrete_kernel() {
         }
     }
 }
+
+/// Lookup table for the efficient evaluation of any [`SymmetricKernel3d`] using squared distances
+///
+/// On a quadratic scale, the compact support `[0, h*h]` of the kernel is divided into `n` segments of
+/// equal width `ds`. The kernel values and gradient norms are pre-computed at the segment boundaries
+/// `r_i = sqrt(i * ds)` for `i ∈ [0, n]`. At runtime, the kernel is evaluated at a squared radius `s`
+/// by linear interpolation between the two table entries of the segment containing `s`, which avoids
+/// taking the square root. All evaluations outside of the compact support return zero.
+///
+/// In contrast to the [`DiscreteSquaredDistanceCubicKernel`], the linear interpolation results in a
+/// continuous approximation of the kernel. The maximum absolute error of the interpolated kernel values
+/// is estimated during the construction by sampling every segment (see [`TabulatedKernel::estimated_max_error`]),
+/// i.e. it is not a guaranteed bound.
+/// The error is usually dominated by the first segment, because kernels are not smooth as a function of
+/// the squared distance at the origin. For the [`CubicSplineKernel`], it decreases with `n^(-3/2)` and is
+/// about `3e-5` (`2e-4`) of the kernel value at the origin for `n = 1024` (`n = 256`). The tabulated
+/// gradient norms are less accurate close to the origin. Use [`TabulatedKernel::with_estimated_max_error`] to
+/// automatically choose a resolution for a given estimated error.
+#[derive(Clone, Debug)]
+pub struct TabulatedKernel<R: Real> {
+    /// Compact support radius of the tabulated kernel
+    compact_support_radius: R,
+    /// Squared compact support radius of the tabulated kernel
+    compact_support_squared: R,
+    /// Inverse of the width of one segment on the quadratic scale
+    inv_segment_width: R,
+    /// Kernel values at the segment boundaries (with one additional padding entry)
+    values: Vec<R>,
+    /// Kernel gradient norms at the segment boundaries (with one additional padding entry)
+    gradient_norms: Vec<R>,
+    /// Estimated maximum absolute error of the interpolated kernel values
+    estimated_max_error: R,
+}
+
+impl<R: Real> TabulatedKernel<R> {
+    /// Largest resolution that is used by [`TabulatedKernel::with_estimated_max_error`]
+    pub const MAX_RESOLUTION: usize = 1 << 20;
+    /// Number of samples per segment used to estimate the interpolation error
+    const ERROR_SAMPLES_PER_SEGMENT: usize = 4;
+
+    /// Tabulates the given kernel with compact support radius `h`, the squared radius `h * h` is divided into `resolution` segments
+    pub fn new<K: SymmetricKernel3d<R>>(kernel: &K, h: R, resolution: usize) -> Self {
+        let n = resolution.max(1);
+        let to_real = |i: usize| {
+            R::from_usize(i).expect("Table resolution has to fit into the kernel type `R`")
+        };
+
+        let compact_support_squared = h * h;
+        let segment_width = compact_support_squared / to_real(n);
+
+        let (mut values, mut gradient_norms): (Vec<_>, Vec<_>) = (0..=n)
+            .map(|i| {
+                let r = (segment_width * to_real(i)).sqrt();
+                (kernel.evaluate(r), kernel.evaluate_gradient_norm(r))
+            })
+            .unzip();
+        // Padding such that the interpolation at the end of the table does not need a special case
+        values.push(values[n]);
+        gradient_norms.push(gradient_norms[n]);
+
+        let mut table = Self {
+            compact_support_radius: h,
+            compact_support_squared,
+            inv_segment_width: segment_width.recip(),
+            values,
+            gradient_norms,
+            estimated_max_error: R::zero(),
+        };
+
+        // Estimate the interpolation error by sampling the interior of every segment
+        let samples = Self::ERROR_SAMPLES_PER_SEGMENT;
+        let sample_width = segment_width / to_real(samples + 1);
+        table.estimated_max_error = (0..n)
+            .flat_map(|i| (1..=samples).map(move |j| (i, j)))
+            .map(|(i, j)| {
+                let r_squared = segment_width * to_real(i) + sample_width * to_real(j);
+                (table.evaluate_squared(r_squared) - kernel.evaluate(r_squared.sqrt())).abs()
+            })
+            .fold(R::zero(), |a, b| a.max(b));
+
+        table
+    }
+
+    /// Tabulates the given kernel with compact support radius `h` with a resolution of at least `min_resolution` such that the estimated maximum error is at most `max_error` (limited by [`TabulatedKernel::MAX_RESOLUTION`])
+    pub fn with_estimated_max_error<K: SymmetricKernel3d<R>>(
+        kernel: &K,
+        h: R,
+        min_resolution: usize,
+        max_error: R,
+    ) -> Self {
+        let mut table = Self::new(kernel, h, min_resolution);
+        while table.estimated_max_error > max_error && table.resolution() < Self::MAX_RESOLUTION {
+            let resolution = (table.resolution() * 2).min(Self::MAX_RESOLUTION);
+            table = Self::new(kernel, h, resolution);
+        }
+        table
+    }
+
+    /// Returns the number of segments of the table
+    pub fn resolution(&self) -> usize {
+        self.values.len() - 2
+    }
+
+    /// Returns the compact support radius of the tabulated kernel
+    pub fn compact_support_radius(&self) -> R {
+        self.compact_support_radius
+    }
+
+    /// Returns the estimated maximum absolute error of the interpolated kernel values compared to the exact kernel
+    ///
+    /// The error is estimated from a few samples in the interior of every segment, so the actual maximum error may be slightly larger.
+    pub fn estimated_max_error(&self) -> R {
+        self.estimated_max_error
+    }
+
+    /// Linearly interpolates the given table at the specified squared radius
+    #[inline(always)]
+    fn interpolate(&self, table: &[R], r_squared: R) -> R {
+        if r_squared >= self.compact_support_squared {
+            return R::zero();
+        }
+
+        let x = r_squared * self.inv_segment_width;
+        // Truncation is equivalent to rounding down as the squared radius is non-negative
+        let i = x.to_usize().unwrap_or(0);
+        let t = x - R::from_usize(i).unwrap();
+        // Rounding errors can only result in the index of the padding entry
+        let i = i.min(table.len() - 2);
+
+        let a = table[i];
+        let b = table[i + 1];
+        a + t * (b - a)
+    }
+
+    /// Evaluates the tabulated kernel at the specified squared radius, i.e. returns an approximate kernel value at the radius `sqrt(r_squared)`
+    #[inline(always)]
+    pub fn evaluate_squared(&self, r_squared: R) -> R {
+        self.interpolate(&self.values, r_squared)
+    }
+
+    /// Evaluates the tabulated kernel gradient norm at the specified squared radius, i.e. returns an approximate gradient norm at the radius `sqrt(r_squared)`
+    #[inline(always)]
+    pub fn evaluate_gradient_norm_squared(&self, r_squared: R) -> R {
+        self.interpolate(&self.gradient_norms, r_squared)
+    }
+}
+
+impl<R: Real> SymmetricKernel3d<R> for TabulatedKernel<R> {
+    /// Evaluates the tabulated kernel at the radial distance `r`
+    fn evaluate(&self, r: R) -> R {
+        self.evaluate_squared(r * r)
+    }
+
+    /// Evaluates the gradient of the tabulated kernel at the position `x`
+    fn evaluate_gradient(&self, x: Vector3<R>) -> Vector3<R> {
+        let r_squared = x.norm_squared();
+        if r_squared == R::zero() {
+            return Vector3::zeros();
+        }
+
+        // Normalize the position vector: points into direction of gradient due to symmetry
+        let drdx = x.unscale(r_squared.sqrt());
+        drdx.scale(self.evaluate_gradient_norm_squared(r_squared))
+    }
+
+    /// Evaluates the norm of the gradient of the tabulated kernel at the radial distance `r`
+    fn evaluate_gradient_norm(&self, r: R) -> R {
+        self.evaluate_gradient_norm_squared(r * r)
+    }
+}
+
+#[test]
+fn test_tabulated_kernel() {
+    let h: f64 = 0.025;
+    let kernel = CubicSplineKernel::new(h);
+    let peak = kernel.evaluate(0.0);
+    // The kernel gradient norm has its maximum at q = 2/3
+    let peak_gradient = kernel.evaluate_gradient_norm(h / 3.0).abs();
+
+    let mut previous_error = f64::INFINITY;
+    for resolution in [64, 256, 1024, 4096] {
+        let tabulated = TabulatedKernel::new(&kernel, h, resolution);
+        assert_eq!(tabulated.resolution(), resolution);
+        assert!(tabulated.estimated_max_error() < previous_error);
+        previous_error = tabulated.estimated_max_error();
+
+        // The tabulated kernel is exact at the origin and vanishes outside of the compact support
+        assert_eq!(tabulated.evaluate(0.0), peak);
+        assert_eq!(tabulated.evaluate(h), 0.0);
+        assert_eq!(tabulated.evaluate(2.0 * h), 0.0);
+
+        // Check the sampled error estimate with a linear stepping that is independent of the table segments (small slack as it is not a strict bound)
+        let n = 10 * resolution + 7;
+        let dr = h / (n as f64);
+        for i in 0..n {
+            let r = (i as f64) * dr;
+            let diff = (tabulated.evaluate_squared(r * r) - kernel.evaluate(r)).abs();
+            assert!(
+                diff <= 1.1 * tabulated.estimated_max_error(),
+                "error at r={r} with resolution {resolution}: {diff} > {}",
+                tabulated.estimated_max_error()
+            );
+
+            let diff_grad =
+                (tabulated.evaluate_gradient_norm(r) - kernel.evaluate_gradient_norm(r)).abs();
+            // The gradient norm is not smooth as a function of the squared distance close to the origin
+            assert!(diff_grad <= 2.0 * peak_gradient / (resolution as f64).sqrt());
+        }
+    }
+
+    assert!(previous_error < 1e-3 * peak);
+
+    // Accuracy relative to the kernel value at the origin (see the documentation of the table)
+    for (resolution, relative_error) in [(256, 2.5e-4), (1024, 4e-5)] {
+        let tabulated = TabulatedKernel::new(&kernel, h, resolution);
+        let estimated_relative_error = tabulated.estimated_max_error() / peak;
+        assert!(
+            estimated_relative_error < relative_error,
+            "relative error with resolution {resolution}: {estimated_relative_error:.3e} >= {relative_error:.0e}"
+        );
+    }
+
+    let tabulated = TabulatedKernel::with_estimated_max_error(&kernel, h, 16, 1e-4 * peak);
+    assert!(tabulated.estimated_max_error() <= 1e-4 * peak);
+    assert!(tabulated.resolution() > 16);
+}
@@ -29,19 +29,23 @@ pub use nalgebra;
 use nalgebra::Vector3;
 use std::borrow::Cow;
 use std::hash::Hash;
+use std::time::{Duration, Instant};
 use thiserror::Error as ThisError;
 /// Re-export the version of `vtkio` used by this crate, if vtk support is enabled
 #[cfg(feature = "vtk_extras")]
 pub use vtkio;
 
 pub use crate::aabb::{Aabb2d, Aabb3d, AxisAlignedBoundingBox};
+pub use crate::dense_subdomains::SubdomainGridError;
 pub use crate::density_map::DensityMap;
 pub use crate::traits::{Index, Real, RealConvert, ThreadSafe};
 pub use crate::uniform_grid::UniformGrid;
 
+use crate::dense_subdomains::SurfacePatchCache;
 use crate::density_map::DensityMapError;
 use crate::marching_cubes::MarchingCubesError;
 use crate::mesh::TriMesh3d;
+use crate::space_filling_curve::{ParticleOrdering, SpaceFillingCurve};
 use crate::uniform_grid::GridConstructionError;
 use crate::workspace::ReconstructionWorkspace;
 
@@ -65,6 +69,8 @@ pub mod mesh;
 pub mod neighborhood_search;
 pub mod postprocessing;
 pub(crate) mod reconstruction;
+pub mod space_filling_curve;
+pub mod spatial_index;
 pub mod sph_interpolation;
 pub mod topology;
 mod traits;
@@ -137,12 +143,69 @@ impl Default for SpatialDecomposition {
 pub struct GridDecompositionParameters {
     /// Each uniform subdomain will be a cube consisting of this number of MC cube cells along each coordinate axis
     pub subdomain_num_cubes_per_dim: u32,
+    /// Whether to use explicit SIMD instructions (if supported by the CPU) to evaluate the level-set function in dense subdomains, otherwise a portable scalar implementation is used (the results are identical)
+    pub enable_simd: bool,
+    /// Whether to process each subdomain in single precision (`f32`) coordinates relative to the origin of the subdomain
+    ///
+    /// Only the global marching cubes grid and the stitching of the subdomain meshes use the real type of the
+    /// reconstruction. With double precision particle positions, this allows precise reconstructions of particles
+    /// far away from the origin while the performance and memory usage of the subdomain computations is similar to
+    /// single precision. The local coordinates are quantized to roughly `1e-5` of the marching cubes cell size.
+    /// Has no effect if the reconstruction already uses single precision.
+    pub local_f32_coordinates: bool,
+    /// Whether to select the subdomain size and the particle limit for sparse subdomains automatically (`subdomain_num_cubes_per_dim` is ignored)
+    ///
+    /// The reconstruction cost of each subdomain is estimated from its number of particles and the number of
+    /// marching cubes points in the kernel support. The subdomain size with the lowest estimated reconstruction
+    /// time for the number of available threads is selected and subdomains are only reconstructed sparsely if
+    /// this is estimated to be cheaper than a dense reconstruction.
+    pub auto_tuning: bool,
 }
 
 impl Default for GridDecompositionParameters {
     fn default() -> Self {
         Self {
             subdomain_num_cubes_per_dim: 64,
+            enable_simd: true,
+            local_f32_coordinates: false,
+            auto_tuning: false,
+        }
+    }
+}
+
+/// Parameters for computing the provenance of the vertices of the reconstructed mesh, see [`VertexProvenance`]
+#[derive(Clone, Debug)]
+pub struct VertexProvenanceParameters {
+    /// Maximum number of nearest contributing particles that are stored per vertex
+    pub num_particles: usize,
+}
+
+impl Default for VertexProvenanceParameters {
+    fn default() -> Self {
+        Self { num_particles: 4 }
+    }
+}
+
+/// Parameters for evaluating the kernel in the level-set computation using a lookup table, see [`kernel::TabulatedKernel`]
+///
+/// The table avoids the square root and the exact kernel evaluation per grid point at the cost of a small
+/// approximation error of the level-set function. Note that the tabulated kernel is evaluated without
+/// explicit SIMD instructions, so depending on the CPU it can be slower than the exact evaluation (see
+/// the `surface_reconstruction_grid_tabulated_kernel` benchmark).
+#[derive(Clone, PartialEq, Debug)]
+pub struct TabulatedKernelParameters {
+    /// Number of segments the squared compact support radius is divided into
+    pub resolution: usize,
+    /// Upper bound for the estimated maximum error of the tabulated kernel relative to the kernel value at the origin.
+    /// If provided, the resolution is increased until the bound is satisfied.
+    pub max_relative_error: Option<f64>,
+}
+
+impl Default for TabulatedKernelParameters {
+    fn default() -> Self {
+        Self {
+            resolution: 1024,
+            max_relative_error: None,
         }
     }
 }
@@ -176,6 +239,15 @@ pub struct Parameters<R: Real> {
     /// Depending on the settings of the reconstruction, neighborhood lists are only computed locally
     /// in subdomains. Enabling this flag joins this data over all particles which can add a small overhead.
     pub global_neighborhood_list: bool,
+    /// Parameters for computing per-vertex provenance data of the reconstructed mesh (see [`SurfaceReconstruction::vertex_provenance`])
+    /// If not provided, no provenance data is computed. Currently, this is only supported by the uniform grid spatial decomposition.
+    pub vertex_provenance: Option<VertexProvenanceParameters>,
+    /// Parameters for evaluating the kernel of the level-set function using a lookup table (see [`TabulatedKernelParameters`])
+    /// If not provided, the kernel is evaluated exactly. Currently, this is only supported by the uniform grid spatial decomposition.
+    pub tabulated_kernel: Option<TabulatedKernelParameters>,
+    /// Space-filling curve used to reorder the particles before the neighborhood search and density computation to improve cache locality
+    /// If not provided, the particles are processed in the input order. Per-particle outputs are always returned in the input order.
+    pub particle_reordering: Option<SpaceFillingCurve>,
 }
 
 impl<R: Real> Parameters<R> {
@@ -191,10 +263,32 @@ impl<R: Real> Parameters<R> {
             enable_multi_threading: self.enable_multi_threading,
             spatial_decomposition: self.spatial_decomposition.clone(),
             global_neighborhood_list: self.global_neighborhood_list,
+            vertex_provenance: self.vertex_provenance.clone(),
+            tabulated_kernel: self.tabulated_kernel.clone(),
+            particle_reordering: self.particle_reordering,
         })
     }
 }
 
+/// Parameters for incremental surface reconstructions that reuse unchanged parts of a previous reconstruction, see [`reconstruct_surface_incremental`]
+///
+/// The surface patch of a subdomain is reused if the subdomain contains exactly the same particles (including
+/// its ghost particles) as when the patch was reconstructed and if none of these particles moved or changed its
+/// density by more than the given tolerances. With tolerances of zero, the result is identical to a full
+/// reconstruction on the same background grid. Note that an incremental reconstruction keeps the grid of the
+/// previous reconstruction as long as it encloses all particles. As the grid of a full reconstruction is fitted
+/// to the current particles, its origin and subdomain layout may differ, so the meshes can differ slightly
+/// (on the order of the cube size) even with tolerances of zero.
+#[derive(Clone, Debug)]
+pub struct IncrementalParameters<R: Real> {
+    /// Maximum distance a particle may have moved since the reconstruction of a reused surface patch (in distance units)
+    pub position_tolerance: R,
+    /// Maximum change of the density of a particle since the reconstruction of a reused surface patch (relative to the previous density)
+    ///
+    /// The densities have to be checked as well because they also depend on particles outside of the ghost margin of a subdomain.
+    pub density_tolerance: R,
+}
+
 /// Result data returned when the surface reconstruction was successful
 #[derive(Clone, Debug)]
 pub struct SurfaceReconstruction<I: Index, R: Real> {
@@ -208,6 +302,12 @@ pub struct SurfaceReconstruction<I: Index, R: Real> {
     particle_neighbors: Option<Vec<Vec<usize>>>,
     /// Surface mesh that is the result of the surface reconstruction
     mesh: TriMesh3d<R>,
+    /// Per vertex provenance data of the surface mesh
+    vertex_provenance: Option<VertexProvenance<R>>,
+    /// Statistics and timings of the last reconstruction
+    stats: ReconstructionStats,
+    /// Surface patches of the subdomains that can be reused by an incremental reconstruction
+    patch_cache: Option<SurfacePatchCache<I, R>>,
     /// Workspace with allocated memory for subsequent surface reconstructions
     workspace: ReconstructionWorkspace<R>,
 }
@@ -221,6 +321,9 @@ impl<I: Index, R: Real> Default for SurfaceReconstruction<I, R> {
             particle_neighbors: None,
             particle_inside_aabb: None,
             mesh: TriMesh3d::default(),
+            vertex_provenance: None,
+            stats: ReconstructionStats::default(),
+            patch_cache: None,
             workspace: ReconstructionWorkspace::default(),
         }
     }
@@ -242,6 +345,16 @@ impl<I: Index, R: Real> SurfaceReconstruction<I, R> {
         self.particle_neighbors.as_ref()
     }
 
+    /// Returns a reference to the per-vertex provenance data of the mesh if it was requested in the [`Parameters`] and is supported by the selected spatial decomposition
+    pub fn vertex_provenance(&self) -> Option<&VertexProvenance<R>> {
+        self.vertex_provenance.as_ref()
+    }
+
+    /// Returns a reference to the statistics and per-stage timings collected during the reconstruction
+    pub fn stats(&self) -> &ReconstructionStats {
+        &self.stats
+    }
+
     /// Returns a reference to the virtual background grid that was used as a basis for discretization of the density map for marching cubes, can be used to convert the density map to a hex mesh (using [`density_map::sparse_density_map_to_hex_mesh`])
     pub fn grid(&self) -> &UniformGrid<I, R> {
         &self.grid
@@ -255,6 +368,173 @@ impl<I: Index, R: Real> From<SurfaceReconstruction<I, R>> for TriMesh3d<R> {
     }
 }
 
+/// Per-vertex provenance data of a reconstructed mesh, i.e. which subdomain and particles produced each vertex
+///
+/// For every vertex, the flat index of the subdomain that generated the vertex and the indices of up to
+/// `num_particles` nearest particles contributing to the level-set at the vertex position are stored.
+/// Particle indices refer to the particle positions passed to the reconstruction (before any filtering with an AABB).
+#[derive(Clone, Debug, Default)]
+pub struct VertexProvenance<R: Real> {
+    /// Number of particle entries stored per vertex
+    pub num_particles: usize,
+    /// Flat index of the subdomain that generated each vertex
+    pub subdomain_indices: Vec<u64>,
+    /// Indices of the nearest contributing particles per vertex (`num_particles` entries per vertex sorted by distance, unused entries are `usize::MAX`)
+    pub particle_indices: Vec<usize>,
+    /// Contribution `m_j/rho_j * W(|x - x_j|)` of the particles in `particle_indices` to the level-set at the vertex (unused entries are zero)
+    pub particle_weights: Vec<R>,
+}
+
+impl<R: Real> VertexProvenance<R> {
+    /// Returns the number of vertices this provenance data is stored for
+    pub fn len(&self) -> usize {
+        self.subdomain_indices.len()
+    }
+
+    /// Returns whether provenance data is stored for any vertex
+    pub fn is_empty(&self) -> bool {
+        self.subdomain_indices.is_empty()
+    }
+
+    /// Returns the indices of the nearest contributing particles of the given vertex
+    pub fn particles(&self, vertex: usize) -> &[usize] {
+        &self.particle_indices[vertex * self.num_particles..(vertex + 1) * self.num_particles]
+    }
+
+    /// Returns the level-set contributions of the nearest contributing particles of the given vertex
+    pub fn weights(&self, vertex: usize) -> &[R] {
+        &self.particle_weights[vertex * self.num_particles..(vertex + 1) * self.num_particles]
+    }
+
+    /// Converts the provenance data to mesh point attributes
+    ///
+    /// Returns the attribute `"provenance_subdomain"` followed by the attributes `"provenance_particle_{i}"`
+    /// and `"provenance_weight_{i}"` for every stored particle `i` (unused particle indices are `u64::MAX`).
+    pub fn to_attributes(&self) -> Vec<mesh::MeshAttribute<R>> {
+        let mut attributes = Vec::with_capacity(1 + 2 * self.num_particles);
+        attributes.push(mesh::MeshAttribute::new(
+            "provenance_subdomain",
+            mesh::AttributeData::ScalarU64(self.subdomain_indices.clone()),
+        ));
+        for i in 0..self.num_particles {
+            let indices = (0..self.len())
+                .map(|v| self.particles(v)[i])
+                .map(|p| if p == usize::MAX { u64::MAX } else { p as u64 })
+                .collect::<Vec<_>>();
+            let weights = (0..self.len())
+                .map(|v| self.weights(v)[i])
+                .collect::<Vec<_>>();
+            attributes.push(mesh::MeshAttribute::new(
+                format!("provenance_particle_{}", i),
+                mesh::AttributeData::ScalarU64(indices),
+            ));
+            attributes.push(mesh::MeshAttribute::new(
+                format!("provenance_weight_{}", i),
+                mesh::AttributeData::ScalarReal(weights),
+            ));
+        }
+        attributes
+    }
+}
+
+/// Statistics and per-stage timings collected during a surface reconstruction
+///
+/// The timings are always recorded (independent of the `profiling` feature) and refer to the
+/// stages of the reconstruction in the order they were executed. With the `io` feature, the statistics
+/// can be serialized with `serde` (durations are serialized in seconds).
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "io", derive(serde::Serialize))]
+pub struct ReconstructionStats {
+    /// Number of particles passed to the reconstruction
+    pub num_input_particles: usize,
+    /// Number of particles that were actually used for the reconstruction (e.g. after filtering with an AABB)
+    pub num_particles: usize,
+    /// Number of vertices of the reconstructed mesh
+    pub num_vertices: usize,
+    /// Number of triangles of the reconstructed mesh
+    pub num_triangles: usize,
+    /// Statistics of the subdomain decomposition (only available if the uniform grid spatial decomposition was used)
+    pub subdomains: Option<SubdomainGridStats>,
+    /// Wall clock time of each stage of the reconstruction
+    pub timings: Vec<StageTiming>,
+    /// Total wall clock time of the reconstruction
+    #[cfg_attr(
+        feature = "io",
+        serde(rename = "total_seconds", serialize_with = "serialize_duration_secs")
+    )]
+    pub total_duration: Duration,
+}
+
+/// Statistics of the subdomain decomposition used by the uniform grid spatial decomposition
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "io", derive(serde::Serialize))]
+pub struct SubdomainGridStats {
+    /// Number of marching cubes cells per subdomain along each coordinate axis
+    pub subdomain_cubes: usize,
+    /// Total number of subdomains of the subdomain grid
+    pub num_subdomains: usize,
+    /// Number of subdomains that contain at least one (ghost) particle
+    pub num_nonempty_subdomains: usize,
+    /// Number of subdomains that were processed with the dense (full grid) reconstruction
+    pub num_dense_subdomains: usize,
+    /// Number of subdomains that were processed with the sparse reconstruction
+    pub num_sparse_subdomains: usize,
+    /// Number of subdomains whose surface patch was reused from the previous reconstruction (only for incremental reconstructions)
+    pub num_reused_subdomains: usize,
+    /// Subdomains with this number of particles or less are considered sparse
+    pub sparse_particle_limit: usize,
+    /// Number of particles (including ghost particles) of the largest subdomain
+    pub largest_subdomain_particles: usize,
+    /// Sum of the particle counts of all subdomains (including ghost particles)
+    pub total_subdomain_particles: usize,
+    /// Thickness of the ghost particle margin in marching cubes cells
+    pub ghost_margin_cubes: f64,
+    /// Volume of the ghost margin relative to the volume of a subdomain
+    pub ghost_margin_volume_fraction: f64,
+}
+
+/// Wall clock time spent in a stage of the reconstruction
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "io", derive(serde::Serialize))]
+pub struct StageTiming {
+    /// Name of the stage
+    pub stage: &'static str,
+    /// Wall clock time of the stage
+    #[cfg_attr(
+        feature = "io",
+        serde(rename = "seconds", serialize_with = "serialize_duration_secs")
+    )]
+    pub duration: Duration,
+}
+
+/// Serializes a duration as floating point number of seconds
+#[cfg(feature = "io")]
+fn serialize_duration_secs<S: serde::Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+impl ReconstructionStats {
+    /// Returns the total duration of all stages with the given name or `None` if the stage was not recorded
+    pub fn timing(&self, stage: &str) -> Option<Duration> {
+        self.timings
+            .iter()
+            .filter(|t| t.stage == stage)
+            .map(|t| t.duration)
+            .reduce(|a, b| a + b)
+    }
+
+    /// Records the time elapsed since `start` for the given stage
+    pub(crate) fn record(&mut self, stage: &'static str, start: Instant) {
+        self.timings.push(StageTiming {
+            stage,
+            duration: start.elapsed(),
+        });
+    }
+}
+
 /// Error type returned when the surface reconstruction fails
 #[non_exhaustive]
 #[derive(Debug, ThisError)]
@@ -280,6 +560,13 @@ pub enum ReconstructionError<I: Index, R: Real> {
         #[from]
         MarchingCubesError,
     ),
+    /// Error that occurred during the reconstruction using the uniform subdomain grid spatial decomposition
+    #[error("subdomain grid reconstruction")]
+    SubdomainGridError(
+        #[source]
+        #[from]
+        SubdomainGridError<I, R>,
+    ),
     /// Any error that is not represented by some other explicit variant
     #[error(transparent)]
     Unknown(#[from] anyhow::Error),
@@ -314,12 +601,61 @@ pub fn reconstruct_surface_inplace<'a, I: Index, R: Real>(
     parameters: &Parameters<R>,
     output_surface: &'a mut SurfaceReconstruction<I, R>,
 ) -> Result<(), ReconstructionError<I, R>> {
+    reconstruct_surface_inplace_impl(particle_positions, parameters, None, output_surface)
+}
+
+/// Performs a marching cubes surface construction that only reconstructs the parts of the surface that changed since the previous reconstruction, inplace
+///
+/// The `previous_surface` has to be the result of a previous call of this function (otherwise, a full
+/// reconstruction is performed) and is replaced by the new reconstruction. The surface patches of all
+/// subdomains whose particles did not change according to the [`IncrementalParameters`] are reused from the
+/// previous reconstruction, only the remaining subdomains are reconstructed before all patches are stitched
+/// again. The particle densities are always recomputed.
+///
+/// Patches can only be reused if the background grid of the previous reconstruction still encloses all
+/// particles and if the particles have the same indices as before. Therefore, this is intended for sequences of
+/// simulation frames with a constant number of particles and without [`Parameters::particle_reordering`].
+/// Currently, this is only supported by the uniform grid spatial decomposition, otherwise a full reconstruction
+/// is performed.
+pub fn reconstruct_surface_incremental<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    parameters: &Parameters<R>,
+    incremental_parameters: &IncrementalParameters<R>,
+    previous_surface: &mut SurfaceReconstruction<I, R>,
+) -> Result<(), ReconstructionError<I, R>> {
+    reconstruct_surface_inplace_impl(
+        particle_positions,
+        parameters,
+        Some(incremental_parameters),
+        previous_surface,
+    )
+}
+
+fn reconstruct_surface_inplace_impl<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    parameters: &Parameters<R>,
+    incremental_parameters: Option<&IncrementalParameters<R>>,
+    output_surface: &mut SurfaceReconstruction<I, R>,
+) -> Result<(), ReconstructionError<I, R>> {
+    let reconstruction_start = Instant::now();
+
+    // Only incremental reconstructions keep the surface patches of the previous reconstruction
+    if incremental_parameters.is_none() {
+        output_surface.patch_cache = None;
+    }
+
     // Clear the existing mesh
     output_surface.mesh.clear();
+    output_surface.vertex_provenance = None;
+    output_surface.stats = ReconstructionStats {
+        num_input_particles: particle_positions.len(),
+        ..Default::default()
+    };
 
     // Filter out particles
     let filtered_particle_positions = if let Some(particle_aabb) = &parameters.particle_aabb {
         profile!("filtering particles");
+        let stage_start = Instant::now();
 
         use rayon::prelude::*;
         let mut particle_inside = output_surface
@@ -350,14 +686,19 @@ pub fn reconstruct_surface_inplace<'a, I: Index, R: Real>(
         );
 
         output_surface.particle_inside_aabb = Some(particle_inside);
+        output_surface
+            .stats
+            .record("filtering particles", stage_start);
         Cow::Owned(filtered_particles)
     } else {
         Cow::Borrowed(particle_positions)
     };
     let particle_positions = filtered_particle_positions.as_ref();
+    output_surface.stats.num_particles = particle_positions.len();
 
     // Initialize grid for the reconstruction
-    output_surface.grid = grid_for_reconstruction(
+    let stage_start = Instant::now();
+    let grid = grid_for_reconstruction(
         particle_positions,
         parameters.particle_radius,
         parameters.compact_support_radius,
@@ -365,24 +706,131 @@ pub fn reconstruct_surface_inplace<'a, I: Index, R: Real>(
         parameters.particle_aabb.as_ref(),
         parameters.enable_multi_threading,
     )?;
+    // Keep the grid of an incremental reconstruction as long as possible, otherwise no patches can be reused
+    let keep_previous_grid = output_surface.patch_cache.is_some() && {
+        let previous_aabb = output_surface.grid.aabb();
+        let aabb = grid.aabb();
+        output_surface.grid.cell_size() == grid.cell_size()
+            && previous_aabb
+                .min()
+                .iter()
+                .zip(aabb.min().iter())
+                .all(|(a, b)| a <= b)
+            && previous_aabb
+                .max()
+                .iter()
+                .zip(aabb.max().iter())
+                .all(|(a, b)| a >= b)
+    };
+    if !keep_previous_grid {
+        output_surface.grid = grid;
+    }
 
     output_surface.grid.log_grid_info();
+    output_surface
+        .stats
+        .record("grid construction", stage_start);
+
+    // Reorder particles along a space-filling curve
+    let reordered_particle_positions = if let Some(curve) = parameters.particle_reordering {
+        profile!("particle reordering");
+        let stage_start = Instant::now();
+
+        let ordering = ParticleOrdering::new(
+            particle_positions,
+            output_surface.grid.aabb(),
+            curve,
+            parameters.enable_multi_threading,
+        );
+
+        // Take temporary storage for reordered particles from workspace
+        let mut reordered_particles =
+            std::mem::take(output_surface.workspace.reordered_particles_mut());
+        ordering.apply_into(particle_positions, &mut reordered_particles);
+        // Neighbor lists from a previous reconstruction cannot be mapped back to the input order
+        output_surface.particle_neighbors = None;
+
+        info!("Reordered particles along {:?} curve.", curve);
+        output_surface
+            .stats
+            .record("particle reordering", stage_start);
+        Some((ordering, reordered_particles))
+    } else {
+        None
+    };
+    let particle_positions = reordered_particle_positions
+        .as_ref()
+        .map(|(_, reordered_particles)| reordered_particles.as_slice())
+        .unwrap_or(particle_positions);
 
     match &parameters.spatial_decomposition {
         Some(SpatialDecomposition::UniformGrid(_)) => {
             reconstruction::reconstruct_surface_subdomain_grid::<I, R>(
+                particle_positions,
+                parameters,
+                incremental_parameters,
+                output_surface,
+            )?
+        }
+        None => {
+            if incremental_parameters.is_some() {
+                info!("Incremental reconstruction requires the subdomain grid, performing a full reconstruction instead.");
+            }
+            reconstruction::reconstruct_surface_global(
                 particle_positions,
                 parameters,
                 output_surface,
             )?
         }
-        None => reconstruction::reconstruct_surface_global(
-            particle_positions,
-            parameters,
-            output_surface,
-        )?,
     }
 
+    // Restore the original particle order of all per-particle outputs
+    if let Some((ordering, mut reordered_particles)) = reordered_particle_positions {
+        profile!("restore particle order");
+
+        if let Some(particle_densities) = output_surface.particle_densities.as_mut() {
+            *particle_densities = ordering.restore(particle_densities);
+        }
+        if let Some(particle_neighbors) = output_surface.particle_neighbors.take() {
+            output_surface.particle_neighbors =
+                Some(ordering.restore_neighbor_lists(particle_neighbors));
+        }
+        if let Some(provenance) = output_surface.vertex_provenance.as_mut() {
+            provenance
+                .particle_indices
+                .iter_mut()
+                .filter(|p| **p != usize::MAX)
+                .for_each(|p| *p = ordering.original_index(*p));
+        }
+
+        // Put back temporary storage for reordered particles for next reconstruction
+        reordered_particles.clear();
+        *output_surface.workspace.reordered_particles_mut() = reordered_particles;
+    }
+
+    // Map provenance particle indices from the filtered particles back to the input particles
+    if let (Some(provenance), Some(particle_inside), Some(_)) = (
+        output_surface.vertex_provenance.as_mut(),
+        output_surface.particle_inside_aabb.as_ref(),
+        parameters.particle_aabb.as_ref(),
+    ) {
+        let filtered_to_input = particle_inside
+            .iter()
+            .copied()
+            .enumerate()
+            .filter_map(|(i, is_inside)| is_inside.then_some(i))
+            .collect::<Vec<_>>();
+        provenance
+            .particle_indices
+            .iter_mut()
+            .filter(|p| **p != usize::MAX)
+            .for_each(|p| *p = filtered_to_input[*p]);
+    }
+
+    output_surface.stats.num_vertices = output_surface.mesh.vertices.len();
+    output_surface.stats.num_triangles = output_surface.mesh.triangles.len();
+    output_surface.stats.total_duration = reconstruction_start.elapsed();
+
     // Put back temporary storage for filtered particles for next reconstruction
     if let Cow::Owned(mut filtered_particles) = filtered_particle_positions {
         filtered_particles.clear();
@@ -15,16 +15,17 @@
 //!  - [`IntoVtkUnstructuredGridPiece`] to convert basic meshes and meshes with attached attributes to the
 //!  - [`IntoVtkDataSet`] for all meshes implementing [`IntoVtkUnstructuredGridPiece`] to directly save a mesh as a VTK file
 
+use crate::utils::UnionFind;
 use crate::{new_map, profile, Aabb3d, MapType, Real, RealConvert};
 use bytemuck_derive::{Pod, Zeroable};
-use nalgebra::{Unit, Vector3};
+use nalgebra::{Matrix3, Unit, Vector2, Vector3, Vector4};
 use rayon::prelude::*;
 use std::cell::RefCell;
 use std::collections::BTreeSet;
 use std::fmt::Debug;
 use thread_local::ThreadLocal;
 #[cfg(feature = "vtk_extras")]
-use vtkio::model::{Attribute, UnstructuredGridPiece};
+use vtkio::model::{Attribute, DataArray, ElementType, UnstructuredGridPiece};
 
 #[cfg(feature = "vtk_extras")]
 pub use crate::mesh::vtk_helper::{IntoVtkDataSet, IntoVtkUnstructuredGridPiece};
@@ -166,11 +167,16 @@ pub struct MeshAttribute<R: Real> {
 /// Data of an [`MeshAttribute`] that can be attached to the vertices or cells of a mesh
 ///
 /// One value in the data-set corresponds is associated to a point or cell of the mesh.
+/// Integer data is typically used for IDs and labels, tensors are stored as 3x3 matrices.
 #[derive(Clone, Debug)]
 pub enum AttributeData<R: Real> {
     ScalarU64(Vec<u64>),
+    ScalarI64(Vec<i64>),
     ScalarReal(Vec<R>),
+    Vector2Real(Vec<Vector2<R>>),
     Vector3Real(Vec<Vector3<R>>),
+    Vector4Real(Vec<Vector4<R>>),
+    Matrix3Real(Vec<Matrix3<R>>),
 }
 
 /// A triangle (surface) mesh in 3D
@@ -733,6 +739,137 @@ impl MeshManifoldInformation {
     }
 }
 
+/// Discrete curvatures per vertex of a triangle mesh (see [`TriMesh3d::vertex_curvatures`])
+#[derive(Clone, Debug, Default)]
+pub struct VertexCurvatures<R: Real> {
+    /// Mean curvature `H = (k1 + k2) / 2` per vertex (positive for convex regions, e.g. `1/r` on a sphere of radius `r`)
+    pub mean: Vec<R>,
+    /// Gaussian curvature `K = k1 * k2` per vertex
+    pub gaussian: Vec<R>,
+    /// Principal curvatures `[k1, k2]` with `k1 >= k2` per vertex
+    pub principal: Vec<[R; 2]>,
+}
+
+/// Connected components of a triangle mesh with statistics per component (see [`TriMesh3d::connected_components`])
+#[derive(Clone, Debug, Default)]
+pub struct MeshComponents<R: Real> {
+    /// Index of the component of every vertex
+    pub vertex_components: Vec<usize>,
+    /// Index of the component of every triangle
+    pub triangle_components: Vec<usize>,
+    /// Statistics of every component
+    pub components: Vec<MeshComponentInfo<R>>,
+}
+
+/// Statistics of a single connected component of a triangle mesh
+#[derive(Clone, Debug, Default)]
+pub struct MeshComponentInfo<R: Real> {
+    /// Number of vertices of the component
+    pub num_vertices: usize,
+    /// Number of triangles of the component
+    pub num_triangles: usize,
+    /// Total area of all triangles of the component
+    pub area: R,
+    /// Signed volume enclosed by the component (only meaningful for closed components, see [`TriMesh3d::signed_volume`])
+    pub volume: R,
+    /// Centroid of the enclosed volume (mean vertex position if the enclosed volume vanishes)
+    pub centroid: Vector3<R>,
+}
+
+impl<R: Real> MeshComponents<R> {
+    /// Returns the number of connected components
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    /// Returns whether there are no components (i.e. the mesh has no vertices)
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty()
+    }
+}
+
+/// Labels the connected components of the valid triangles of a mesh and computes their statistics
+///
+/// Invalid vertices and triangles are assigned the component index `usize::MAX`, valid vertices without
+/// valid triangles form their own components.
+pub(crate) fn compute_connected_components<R: Real>(
+    vertices: &[Vector3<R>],
+    triangles: &[[usize; 3]],
+    is_valid_vertex: impl Fn(usize) -> bool,
+    is_valid_triangle: impl Fn(usize) -> bool,
+) -> MeshComponents<R> {
+    profile!("compute_connected_components");
+
+    // Union-find over the vertices connected by triangles
+    let mut union_find = UnionFind::new(vertices.len());
+    for (tri_idx, tri) in triangles.iter().enumerate() {
+        if !is_valid_triangle(tri_idx) {
+            continue;
+        }
+        for &v in &tri[1..] {
+            union_find.union(tri[0], v);
+        }
+    }
+
+    // Number the components in the order of their first vertex
+    let mut root_components = vec![usize::MAX; vertices.len()];
+    let mut vertex_components = vec![usize::MAX; vertices.len()];
+    let mut components = Vec::new();
+    for v in 0..vertices.len() {
+        if !is_valid_vertex(v) {
+            continue;
+        }
+        let root = union_find.find(v);
+        if root_components[root] == usize::MAX {
+            root_components[root] = components.len();
+            components.push(MeshComponentInfo::default());
+        }
+        vertex_components[v] = root_components[root];
+
+        let component = &mut components[vertex_components[v]];
+        component.num_vertices += 1;
+        component.centroid += vertices[v];
+    }
+
+    // Accumulate the triangle statistics (the centroid is accumulated as the first moment of the volume)
+    let mut triangle_components = vec![usize::MAX; triangles.len()];
+    let mut volume_moments = vec![Vector3::zeros(); components.len()];
+    let four = R::from_i32(4).unwrap();
+    let six = R::from_i32(6).unwrap();
+    for (tri_idx, tri) in triangles.iter().enumerate() {
+        if !is_valid_triangle(tri_idx) {
+            continue;
+        }
+        let c = vertex_components[tri[0]];
+        triangle_components[tri_idx] = c;
+
+        let [v0, v1, v2] = tri.map(|i| &vertices[i]);
+        let volume = v0.dot(&v1.cross(v2)) / six;
+        let component = &mut components[c];
+        component.num_triangles += 1;
+        component.area += tri_area::<R, R>(v0, v1, v2);
+        component.volume += volume;
+        volume_moments[c] += (v0 + v1 + v2).scale(volume / four);
+    }
+
+    for (component, moment) in components.iter_mut().zip(volume_moments) {
+        let mean_vertex = component
+            .centroid
+            .unscale(R::from_usize(component.num_vertices).unwrap());
+        component.centroid = if component.volume.abs() > R::default_epsilon() {
+            moment.unscale(component.volume)
+        } else {
+            mean_vertex
+        };
+    }
+
+    MeshComponents {
+        vertex_components,
+        triangle_components,
+        components,
+    }
+}
+
 impl<R: Real> TriMesh3d<R> {
     /// Returns a slice of all triangles of the mesh as `TriangleCell`s
     pub fn triangle_cells(&self) -> &[TriangleCell] {
@@ -1077,6 +1214,127 @@ impl<R: Real> TriMesh3d<R> {
         non_manifold_verts
     }
 
+    /// Computes the signed volume enclosed by the mesh
+    ///
+    /// The volume is only meaningful for closed meshes. It is positive if the triangles are oriented counter-clockwise
+    /// when viewed from the outside (i.e. if the triangle normals point outwards).
+    pub fn signed_volume(&self) -> R {
+        let six = R::from_i32(6).unwrap();
+        self.triangles
+            .par_iter()
+            .map(|tri| {
+                let [v0, v1, v2] = tri.map(|i| &self.vertices[i]);
+                v0.dot(&v1.cross(v2))
+            })
+            .reduce(R::zero, |a, b| a + b)
+            / six
+    }
+
+    /// Labels the connected components of the mesh (vertices connected by triangles) and computes their vertex and triangle counts, area and enclosed volume
+    ///
+    /// Components are numbered in the order of their vertex with the smallest index, vertices without triangles form
+    /// their own components.
+    pub fn connected_components(&self) -> MeshComponents<R> {
+        compute_connected_components(&self.vertices, &self.triangles, |_| true, |_| true)
+    }
+
+    /// Computes discrete mean, Gaussian and principal curvatures of all vertices of the mesh
+    ///
+    /// Implements the estimators of "Discrete Differential-Geometry Operators for Triangulated 2-Manifolds"
+    /// (Meyer, Desbrun, Schröder, Barr 2003): the mean curvature is obtained from the cotangent Laplacian
+    /// and the Gaussian curvature from the angle deficit, both normalized by the mixed Voronoi area of the vertex.
+    /// The sign of the mean curvature is determined by the area weighted vertex normals, i.e. the mesh has to be
+    /// consistently oriented. The principal curvatures are derived from the mean and Gaussian curvature.
+    ///
+    /// Vertices on the boundary of the mesh and vertices without incident triangles get zero curvature.
+    pub fn vertex_curvatures(&self) -> VertexCurvatures<R> {
+        profile!("vertex_curvatures");
+
+        let n = self.vertices.len();
+        let two = R::one() + R::one();
+        let eight = two * two * two;
+
+        let mut laplacian = vec![Vector3::zeros(); n];
+        let mut angle_sums = vec![R::zero(); n];
+        let mut mixed_areas = vec![R::zero(); n];
+
+        for tri in self.triangles.iter() {
+            let x = [
+                &self.vertices[tri[0]],
+                &self.vertices[tri[1]],
+                &self.vertices[tri[2]],
+            ];
+            let area: R = tri_area(x[0], x[1], x[2]);
+            if area <= R::zero() {
+                continue;
+            }
+
+            // Interior angles and their cotangents at the three corners
+            let angles: [R; 3] =
+                std::array::from_fn(|i| angle_between(x[(i + 1) % 3], x[i], x[(i + 2) % 3]));
+            let cotangents = angles.map(|alpha| alpha.cos() / alpha.sin());
+            let is_obtuse = angles.map(|alpha| alpha > R::frac_pi_2());
+
+            for i in 0..3 {
+                let (j, k) = ((i + 1) % 3, (i + 2) % 3);
+
+                // Cotangent weight of the edge opposite to corner `i`
+                let edge = x[k] - x[j];
+                laplacian[tri[j]] += edge.scale(cotangents[i]);
+                laplacian[tri[k]] -= edge.scale(cotangents[i]);
+
+                angle_sums[tri[i]] += angles[i];
+
+                // Mixed Voronoi area of corner `i`
+                mixed_areas[tri[i]] += if is_obtuse.iter().any(|&o| o) {
+                    if is_obtuse[i] {
+                        area / two
+                    } else {
+                        area / (two * two)
+                    }
+                } else {
+                    ((x[i] - x[j]).norm_squared() * cotangents[k]
+                        + (x[i] - x[k]).norm_squared() * cotangents[j])
+                        / eight
+                };
+            }
+        }
+
+        let mut is_boundary = vec![false; n];
+        for [a, b] in self.compute_edge_information().boundary_edges() {
+            is_boundary[a] = true;
+            is_boundary[b] = true;
+        }
+
+        let normals = self.vertex_normal_directions();
+        let two_pi = two * R::pi();
+
+        let mut curvatures = VertexCurvatures {
+            mean: vec![R::zero(); n],
+            gaussian: vec![R::zero(); n],
+            principal: vec![[R::zero(); 2]; n],
+        };
+
+        for i in 0..n {
+            let area = mixed_areas[i];
+            let normal_norm = normals[i].norm();
+            if is_boundary[i] || area <= R::zero() || normal_norm <= R::zero() {
+                continue;
+            }
+
+            // The mean curvature normal is `-2 H n`
+            let mean = -(laplacian[i] / (two * area)).dot(&normals[i]) / (two * normal_norm);
+            let gaussian = (two_pi - angle_sums[i]) / area;
+            let delta = (mean * mean - gaussian).max(R::zero()).sqrt();
+
+            curvatures.mean[i] = mean;
+            curvatures.gaussian[i] = gaussian;
+            curvatures.principal[i] = [mean + delta, mean - delta];
+        }
+
+        curvatures
+    }
+
     /// Returns a struct with lists of all boundary edges, non-manifold edges and non-manifold vertices
     ///
     /// Note that the output order is not necessarily deterministic due to the internal use of hashmaps.
@@ -1372,6 +1630,8 @@ impl<R: Real> MeshAttribute<R> {
     }
 
     /// Converts the mesh attribute to a [`vtkio::model::Attribute`])
+    ///
+    /// A 2D vector attribute with the name `"uv"` is converted to texture coordinates.
     #[cfg(feature = "vtk_extras")]
     #[cfg_attr(doc_cfg, doc(cfg(feature = "vtk_extras")))]
     fn to_vtk_attribute(&self) -> Attribute {
@@ -1379,42 +1639,90 @@ impl<R: Real> MeshAttribute<R> {
             AttributeData::ScalarU64(u64_vec) => {
                 Attribute::scalars(&self.name, 1).with_data(u64_vec.clone())
             }
+            AttributeData::ScalarI64(i64_vec) => {
+                Attribute::scalars(&self.name, 1).with_data(i64_vec.clone())
+            }
             AttributeData::ScalarReal(real_vec) => {
                 Attribute::scalars(&self.name, 1).with_data(real_vec.clone())
             }
+            AttributeData::Vector2Real(vec2r_vec) if self.name == "uv" => {
+                Attribute::DataArray(DataArray {
+                    name: self.name.clone(),
+                    elem: ElementType::TCoords(2),
+                    data: vec2r_vec
+                        .iter()
+                        .flatten()
+                        .copied()
+                        .collect::<Vec<R>>()
+                        .into(),
+                })
+            }
+            AttributeData::Vector2Real(vec2r_vec) => Attribute::scalars(&self.name, 2)
+                .with_data(vec2r_vec.iter().flatten().copied().collect::<Vec<R>>()),
             AttributeData::Vector3Real(vec3r_vec) => Attribute::scalars(&self.name, 3)
                 .with_data(vec3r_vec.iter().flatten().copied().collect::<Vec<R>>()),
+            AttributeData::Vector4Real(vec4r_vec) => Attribute::scalars(&self.name, 4)
+                .with_data(vec4r_vec.iter().flatten().copied().collect::<Vec<R>>()),
+            // Tensors are written in row-major order
+            AttributeData::Matrix3Real(mat3r_vec) => Attribute::scalars(&self.name, 9).with_data(
+                mat3r_vec
+                    .iter()
+                    .flat_map(|m| m.transpose().iter().copied().collect::<Vec<_>>())
+                    .collect::<Vec<R>>(),
+            ),
         }
     }
 
     /// Returns a new attribute keeping only the entries with the given index
     fn keep_indices(&self, indices: &[usize]) -> Self {
-        let data = match &self.data {
-            AttributeData::ScalarU64(d) => {
-                AttributeData::ScalarU64(indices.iter().copied().map(|i| d[i].clone()).collect())
-            }
-            AttributeData::ScalarReal(d) => {
-                AttributeData::ScalarReal(indices.iter().copied().map(|i| d[i].clone()).collect())
-            }
-            AttributeData::Vector3Real(d) => {
-                AttributeData::Vector3Real(indices.iter().copied().map(|i| d[i].clone()).collect())
-            }
-        };
-
         Self {
             name: self.name.clone(),
-            data,
+            data: self.data.select(indices),
         }
     }
 }
 
 impl<R: Real> AttributeData<R> {
     /// Returns the number of entries in the data set
-    fn len(&self) -> usize {
+    pub fn len(&self) -> usize {
         match self {
             AttributeData::ScalarU64(v) => v.len(),
+            AttributeData::ScalarI64(v) => v.len(),
             AttributeData::ScalarReal(v) => v.len(),
+            AttributeData::Vector2Real(v) => v.len(),
             AttributeData::Vector3Real(v) => v.len(),
+            AttributeData::Vector4Real(v) => v.len(),
+            AttributeData::Matrix3Real(v) => v.len(),
+        }
+    }
+
+    /// Returns the number of components of a single entry of the data set
+    pub fn num_components(&self) -> usize {
+        match self {
+            AttributeData::ScalarU64(_) => 1,
+            AttributeData::ScalarI64(_) => 1,
+            AttributeData::ScalarReal(_) => 1,
+            AttributeData::Vector2Real(_) => 2,
+            AttributeData::Vector3Real(_) => 3,
+            AttributeData::Vector4Real(_) => 4,
+            AttributeData::Matrix3Real(_) => 9,
+        }
+    }
+
+    /// Returns a new data set with the entries at the given indices (in the given order, indices can be repeated)
+    pub fn select(&self, indices: &[usize]) -> Self {
+        fn select<T: Clone>(data: &[T], indices: &[usize]) -> Vec<T> {
+            indices.iter().map(|&i| data[i].clone()).collect()
+        }
+
+        match self {
+            AttributeData::ScalarU64(d) => AttributeData::ScalarU64(select(d, indices)),
+            AttributeData::ScalarI64(d) => AttributeData::ScalarI64(select(d, indices)),
+            AttributeData::ScalarReal(d) => AttributeData::ScalarReal(select(d, indices)),
+            AttributeData::Vector2Real(d) => AttributeData::Vector2Real(select(d, indices)),
+            AttributeData::Vector3Real(d) => AttributeData::Vector3Real(select(d, indices)),
+            AttributeData::Vector4Real(d) => AttributeData::Vector4Real(select(d, indices)),
+            AttributeData::Matrix3Real(d) => AttributeData::Matrix3Real(select(d, indices)),
         }
     }
 }
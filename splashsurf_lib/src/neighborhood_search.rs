@@ -2,7 +2,8 @@
 //!
 //! This module provides basic sequential and parallel neighborhood search implementations using
 //! spatial hashing. The algorithms return per-particle neighborhood list with indices of all particles
-//! that are within the given radius of the particle.
+//! that are within the given radius of the particle. For repeated searches of moving particles (e.g. over
+//! the frames of a sequence) the persistent [`NeighborhoodSearch`] can be used.
 
 use crate::uniform_grid::UniformGrid;
 use crate::utils::UnsafeSlice;
@@ -10,6 +11,10 @@ use crate::{new_map, profile, Aabb3d, HashState, Index, MapType, ParallelMapType
 use nalgebra::Vector3;
 use rayon::prelude::*;
 
+pub use persistent::NeighborhoodSearch;
+
+mod persistent;
+
 // TODO: Replace some unwrap() calls with errors, e.g. if domain excludes some particles that are neighbors
 // TODO: Check if input parameters are valid (valid domain, valid search radius)
 // TODO: Write tests with sample data for neighborhood search
@@ -290,6 +295,92 @@ impl FlatNeighborhoodList {
     }
 }
 
+/// Builds a [`FlatNeighborhoodList`] by calling the given closure to append the neighbors of each particle (optionally in parallel)
+pub(crate) fn build_flat_neighborhood_list(
+    num_particles: usize,
+    enable_multi_threading: bool,
+    neighborhood_list: &mut FlatNeighborhoodList,
+    append_neighbors: impl Fn(usize, &mut Vec<usize>) + Sync,
+) {
+    build_flat_neighborhood_list_with_distances::<f32>(
+        num_particles,
+        enable_multi_threading,
+        neighborhood_list,
+        None,
+        |i, neighbors, _| append_neighbors(i, neighbors),
+    )
+}
+
+/// Builds a [`FlatNeighborhoodList`] and optionally the squared distances of all neighbors by calling the given closure to append the neighbors of each particle (optionally in parallel)
+///
+/// The closure has to append exactly one squared distance per neighbor if `squared_distances` is `Some`, otherwise the distances are ignored.
+fn build_flat_neighborhood_list_with_distances<R: Real>(
+    num_particles: usize,
+    enable_multi_threading: bool,
+    neighborhood_list: &mut FlatNeighborhoodList,
+    mut squared_distances: Option<&mut Vec<R>>,
+    append_neighbors: impl Fn(usize, &mut Vec<usize>, &mut Vec<R>) + Sync,
+) {
+    // Number of particles processed at once, the neighbors of a chunk are collected in a contiguous buffer
+    const CHUNK_SIZE: usize = 1024;
+
+    let store_distances = squared_distances.is_some();
+    let collect_chunk = |chunk: usize| {
+        let particles = chunk * CHUNK_SIZE..((chunk + 1) * CHUNK_SIZE).min(num_particles);
+        let mut neighbor_counts = Vec::with_capacity(particles.len());
+        let mut neighbors = Vec::new();
+        let mut distances = Vec::new();
+        for particle_i in particles {
+            let start = neighbors.len();
+            append_neighbors(particle_i, &mut neighbors, &mut distances);
+            if !store_distances {
+                distances.clear();
+            }
+            neighbor_counts.push(neighbors.len() - start);
+        }
+        (neighbor_counts, neighbors, distances)
+    };
+
+    let num_chunks = num_particles.div_ceil(CHUNK_SIZE);
+    let chunks = if enable_multi_threading {
+        (0..num_chunks)
+            .into_par_iter()
+            .map(collect_chunk)
+            .collect::<Vec<_>>()
+    } else {
+        (0..num_chunks).map(collect_chunk).collect::<Vec<_>>()
+    };
+
+    let total_neighbors = chunks.iter().map(|(_, neighbors, _)| neighbors.len()).sum();
+    neighborhood_list.neighbor_ptr.clear();
+    neighborhood_list.neighbor_ptr.reserve(num_particles + 1);
+    neighborhood_list.neighbor_ptr.push(0);
+    neighborhood_list.neighbors.clear();
+    neighborhood_list.neighbors.reserve(total_neighbors);
+    if let Some(squared_distances) = squared_distances.as_deref_mut() {
+        squared_distances.clear();
+        squared_distances.reserve(total_neighbors);
+    }
+    for (neighbor_counts, neighbors, distances) in chunks {
+        let mut offset = neighborhood_list.neighbors.len();
+        neighborhood_list
+            .neighbor_ptr
+            .extend(neighbor_counts.into_iter().map(|count| {
+                offset += count;
+                offset
+            }));
+        neighborhood_list.neighbors.extend_from_slice(&neighbors);
+        if let Some(squared_distances) = squared_distances.as_deref_mut() {
+            assert_eq!(
+                distances.len(),
+                neighbors.len(),
+                "Exactly one squared distance has to be stored per neighbor"
+            );
+            squared_distances.extend_from_slice(&distances);
+        }
+    }
+}
+
 /// Trait unifying different particle neighborhood list storage formats
 pub trait NeighborhoodList {
     /// Returns the total number of particles this list has neighborhood information about
@@ -585,6 +676,75 @@ pub fn neighborhood_search_spatial_hashing_parallel<I: Index, R: Real>(
     }
 }
 
+/// Performs a neighborhood search (multi-threaded implementation, returning a [`FlatNeighborhoodList`])
+///
+/// Returns the indices of all neighboring particles in the given search radius per particle as a [`FlatNeighborhoodList`].
+/// In contrast to [`neighborhood_search_spatial_hashing_parallel`], the neighbors are written into a single contiguous
+/// storage which avoids one allocation per particle. If `squared_distances` is provided, it is filled with the squared
+/// distance of every neighbor, i.e. `squared_distances[k]` belongs to `neighborhood_list.neighbors[k]`.
+#[inline(never)]
+pub fn neighborhood_search_spatial_hashing_flat_parallel<I: Index, R: Real>(
+    domain: &Aabb3d<R>,
+    particle_positions: &[Vector3<R>],
+    search_radius: R,
+    neighborhood_list: &mut FlatNeighborhoodList,
+    squared_distances: Option<&mut Vec<R>>,
+) {
+    profile!("neighborhood_search_spatial_hashing_flat_parallel");
+
+    assert!(
+        search_radius > R::zero(),
+        "Search radius for neighborhood search has to be positive!"
+    );
+    assert!(
+        domain.is_consistent(),
+        "Domain for neighborhood search has to be consistent!"
+    );
+    assert!(
+        !domain.is_degenerate(),
+        "Domain for neighborhood search cannot be degenerate!"
+    );
+
+    let search_radius_squared = search_radius * search_radius;
+
+    // Create a new grid for neighborhood search
+    let grid = UniformGrid::from_aabb(&domain, search_radius)
+        .expect("Failed to construct grid for neighborhood search!");
+
+    // Map for spatially hashed storage of all particles (map from cell -> enclosed particles)
+    let particles_per_cell =
+        parallel_generate_cell_to_particle_map::<I, R>(&grid, particle_positions).into_read_only();
+
+    {
+        profile!("calculate_particle_neighbors_flat_par");
+        build_flat_neighborhood_list_with_distances(
+            particle_positions.len(),
+            true,
+            neighborhood_list,
+            squared_distances,
+            |particle_i, neighbors, distances| {
+                let pos_i = &particle_positions[particle_i];
+                let current_cell = grid.get_cell(grid.enclosing_cell(pos_i)).unwrap();
+
+                // Check all particles of the adjacent cells and the cell itself
+                grid.cells_adjacent_to_cell(&current_cell)
+                    .chain(std::iter::once(current_cell))
+                    .filter_map(|c| particles_per_cell.get(&grid.flatten_cell_index(&c)))
+                    .flatten()
+                    .copied()
+                    .for_each(|particle_j| {
+                        let r_squared = (particle_positions[particle_j] - pos_i).norm_squared();
+                        if particle_i != particle_j && r_squared < search_radius_squared {
+                            // A neighbor was found
+                            neighbors.push(particle_j);
+                            distances.push(r_squared);
+                        }
+                    });
+            },
+        );
+    }
+}
+
 /// Stats of a neighborhood list
 #[derive(Clone, Debug)]
 pub struct NeighborhoodStats {
@@ -0,0 +1,262 @@
+//! Persistent neighborhood search with incremental updates of the particle cell assignments
+
+use super::{build_flat_neighborhood_list, FlatNeighborhoodList};
+use crate::{new_map, profile, MapType, Real};
+use nalgebra::Vector3;
+use rayon::prelude::*;
+
+/// Integer coordinates of a cell of the (unbounded) spatial hashing grid
+type CellKey = [i64; 3];
+
+/// Reference to a point in a cell of the spatial hashing grid (`(point set, point index)`)
+type PointRef = (usize, usize);
+
+/// A set of points registered in a [`NeighborhoodSearch`]
+#[derive(Clone, Debug, Default)]
+struct PointSet<R: Real> {
+    /// Positions of the points at the time of the last update
+    positions: Vec<Vector3<R>>,
+    /// Cell of the spatial hashing grid per point (`None` for points with non-finite coordinates)
+    cells: Vec<Option<CellKey>>,
+}
+
+/// Persistent neighborhood search based on spatial hashing that supports multiple point sets and incremental updates
+///
+/// In contrast to the functions of the [`neighborhood_search`](super) module, the assignment of the points to the cells
+/// of the spatial hashing grid is kept between queries. When the positions of a point set are updated, only the points
+/// that moved to another cell are re-assigned. This makes the search suitable for sequences of frames and for
+/// algorithms that repeatedly query neighborhoods of the same particles. The grid is unbounded, i.e. no domain has to
+/// be specified and points are allowed to move anywhere. Points with non-finite coordinates never have neighbors.
+///
+/// The neighborhoods can be queried between any pair of point sets (see [`NeighborhoodSearch::find_neighbors`]),
+/// a point is never considered to be its own neighbor.
+#[derive(Clone, Debug)]
+pub struct NeighborhoodSearch<R: Real> {
+    /// Search radius (and cell size of the spatial hashing grid)
+    search_radius: R,
+    /// Whether the queries and updates are parallelized
+    enable_multi_threading: bool,
+    /// All registered point sets
+    point_sets: Vec<PointSet<R>>,
+    /// Map from cells to the points of all point sets in the cell
+    cells: MapType<CellKey, Vec<PointRef>>,
+}
+
+impl<R: Real> NeighborhoodSearch<R> {
+    /// Creates an empty neighborhood search with the given search radius
+    pub fn new(search_radius: R, enable_multi_threading: bool) -> Self {
+        assert!(
+            search_radius > R::zero(),
+            "Search radius for neighborhood search has to be positive!"
+        );
+
+        Self {
+            search_radius,
+            enable_multi_threading,
+            point_sets: Vec::new(),
+            cells: new_map(),
+        }
+    }
+
+    /// Returns the search radius of the neighborhood search
+    pub fn search_radius(&self) -> R {
+        self.search_radius
+    }
+
+    /// Changes the search radius, this requires a re-assignment of all points to the cells
+    pub fn set_search_radius(&mut self, search_radius: R) {
+        assert!(
+            search_radius > R::zero(),
+            "Search radius for neighborhood search has to be positive!"
+        );
+
+        if search_radius != self.search_radius {
+            self.search_radius = search_radius;
+            self.cells.clear();
+            for point_set in 0..self.point_sets.len() {
+                let positions = std::mem::take(&mut self.point_sets[point_set].positions);
+                self.point_sets[point_set].cells.clear();
+                self.update_point_set(point_set, &positions);
+            }
+        }
+    }
+
+    /// Returns the number of registered point sets
+    pub fn num_point_sets(&self) -> usize {
+        self.point_sets.len()
+    }
+
+    /// Returns the positions of the given point set at the time of its last update
+    pub fn point_set_positions(&self, point_set: usize) -> &[Vector3<R>] {
+        self.point_sets[point_set].positions.as_slice()
+    }
+
+    /// Registers a new point set with the given positions and returns its index
+    pub fn add_point_set(&mut self, positions: &[Vector3<R>]) -> usize {
+        self.point_sets.push(PointSet::default());
+        let point_set = self.point_sets.len() - 1;
+        self.update_point_set(point_set, positions);
+        point_set
+    }
+
+    /// Updates the positions of the points of the given point set, returns the number of points that moved to another cell
+    ///
+    /// If the number of points did not change, only the points that left their cell are re-assigned. Otherwise,
+    /// all points of the set are re-assigned (and the number of points of the set is returned).
+    pub fn update_point_set(&mut self, point_set: usize, positions: &[Vector3<R>]) -> usize {
+        profile!("NeighborhoodSearch::update_point_set");
+
+        let new_cells = {
+            let cell_key = |p: &Vector3<R>| cell_key(p, self.search_radius);
+            if self.enable_multi_threading {
+                positions.par_iter().map(cell_key).collect::<Vec<_>>()
+            } else {
+                positions.iter().map(cell_key).collect::<Vec<_>>()
+            }
+        };
+
+        let set = &mut self.point_sets[point_set];
+        let num_moved = if set.cells.len() == new_cells.len() {
+            // Only re-assign points that moved to another cell
+            let mut num_moved = 0;
+            for (i, (old_cell, new_cell)) in set.cells.iter().zip(new_cells.iter()).enumerate() {
+                if old_cell != new_cell {
+                    remove_point(&mut self.cells, old_cell, (point_set, i));
+                    insert_point(&mut self.cells, new_cell, (point_set, i));
+                    num_moved += 1;
+                }
+            }
+            num_moved
+        } else {
+            // Remove all points of the set and insert them again
+            self.cells.retain(|_, points| {
+                points.retain(|&(s, _)| s != point_set);
+                !points.is_empty()
+            });
+            for (i, new_cell) in new_cells.iter().enumerate() {
+                insert_point(&mut self.cells, new_cell, (point_set, i));
+            }
+            new_cells.len()
+        };
+
+        set.cells = new_cells;
+        set.positions.clear();
+        set.positions.extend_from_slice(positions);
+        num_moved
+    }
+
+    /// Computes the neighbors of all points of `point_set` in `neighbor_set` and stores them in the given list
+    ///
+    /// The neighbor indices refer to the points of `neighbor_set`. If both sets are identical, the points themselves
+    /// are excluded from their neighbor lists.
+    pub fn find_neighbors(
+        &self,
+        point_set: usize,
+        neighbor_set: usize,
+        neighborhood_list: &mut FlatNeighborhoodList,
+    ) {
+        profile!("NeighborhoodSearch::find_neighbors");
+
+        let set = &self.point_sets[point_set];
+        build_flat_neighborhood_list(
+            set.positions.len(),
+            self.enable_multi_threading,
+            neighborhood_list,
+            |i, neighbors| {
+                let exclude = (point_set == neighbor_set).then_some(i);
+                self.neighbors_in_set(
+                    &set.positions[i],
+                    set.cells[i],
+                    neighbor_set,
+                    exclude,
+                    |j| neighbors.push(j),
+                );
+            },
+        );
+    }
+
+    /// Appends the neighbors in `neighbor_set` of an arbitrary point to the given vector
+    pub fn find_neighbors_of_point(
+        &self,
+        point: &Vector3<R>,
+        neighbor_set: usize,
+        neighbors: &mut Vec<usize>,
+    ) {
+        let cell = cell_key(point, self.search_radius);
+        self.neighbors_in_set(point, cell, neighbor_set, None, |j| neighbors.push(j));
+    }
+
+    /// Calls the given closure for all points of `neighbor_set` within the search radius of the given point
+    fn neighbors_in_set(
+        &self,
+        point: &Vector3<R>,
+        cell: Option<CellKey>,
+        neighbor_set: usize,
+        exclude: Option<usize>,
+        mut neighbor_found: impl FnMut(usize),
+    ) {
+        let Some([i, j, k]) = cell else {
+            return;
+        };
+
+        let search_radius_squared = self.search_radius * self.search_radius;
+        let neighbor_positions = &self.point_sets[neighbor_set].positions;
+        for di in -1..=1 {
+            for dj in -1..=1 {
+                for dk in -1..=1 {
+                    let Some(points) = self.cells.get(&[i + di, j + dj, k + dk]) else {
+                        continue;
+                    };
+                    for &(s, p) in points {
+                        if s == neighbor_set
+                            && Some(p) != exclude
+                            && (neighbor_positions[p] - point).norm_squared()
+                                < search_radius_squared
+                        {
+                            neighbor_found(p);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the cell of the spatial hashing grid enclosing the given point
+fn cell_key<R: Real>(point: &Vector3<R>, cell_size: R) -> Option<CellKey> {
+    let cell = |x: R| {
+        (x / cell_size)
+            .floor()
+            .to_i64()
+            // Avoid overflows when computing the adjacent cells
+            .filter(|c| c.checked_abs().is_some_and(|c| c < i64::MAX))
+    };
+    Some([cell(point.x)?, cell(point.y)?, cell(point.z)?])
+}
+
+fn insert_point(
+    cells: &mut MapType<CellKey, Vec<PointRef>>,
+    cell: &Option<CellKey>,
+    point: PointRef,
+) {
+    if let Some(cell) = cell {
+        cells.entry(*cell).or_default().push(point);
+    }
+}
+
+fn remove_point(
+    cells: &mut MapType<CellKey, Vec<PointRef>>,
+    cell: &Option<CellKey>,
+    point: PointRef,
+) {
+    if let Some(cell) = cell {
+        if let Some(points) = cells.get_mut(cell) {
+            if let Some(pos) = points.iter().position(|&p| p == point) {
+                points.swap_remove(pos);
+            }
+            if points.is_empty() {
+                cells.remove(cell);
+            }
+        }
+    }
+}
@@ -1,54 +1,908 @@
 //! Functions for post-processing of surface meshes (decimation, smoothing, etc.)
 
 use crate::halfedge_mesh::{HalfEdgeTriMesh, IllegalHalfEdgeCollapse};
-use crate::mesh::{Mesh3d, MixedTriQuadMesh3d, TriMesh3d, TriMesh3dExt, TriangleOrQuadCell};
+use crate::mesh::{
+    AttributeData, Mesh3d, MeshAttribute, MeshComponentInfo, MeshWithData, MixedTriQuadMesh3d,
+    PointCloud3d, TriMesh3d, TriMesh3dExt, TriangleOrQuadCell,
+};
+use crate::neighborhood_search::FlatNeighborhoodList;
+use crate::sph_interpolation::{
+    CategoricalInterpolation, InterpolationMode, SphInterpolator, TensorAveraging,
+};
 use crate::topology::{Axis, DirectedAxis, Direction};
 use crate::uniform_grid::UniformCartesianCubeGrid3d;
-use crate::{profile, Index, MapType, Real, SetType};
+use crate::{
+    neighborhood_search, profile, Aabb3d, Index, MapType, Parameters, Real, RealConvert, SetType,
+    SurfaceReconstruction,
+};
+use anyhow::anyhow;
 use log::{info, warn};
-use nalgebra::Vector3;
+use nalgebra::{Matrix3, Unit, Vector2, Vector3};
 use rayon::prelude::*;
 
-/// Laplacian Smoothing with feature weights
+pub use clipping::{clip_with_aabb, clip_with_plane};
+pub use decimation::{
+    quadric_decimation, quadric_decimation_he, quadric_decimation_with_data,
+    QuadricDecimationParameters,
+};
+pub use remeshing::{
+    isotropic_remeshing, isotropic_remeshing_he, IsotropicRemeshingParameters, RemeshingProjection,
+};
+pub use repair::{
+    fill_holes, remove_degenerate_triangles, remove_duplicate_triangles, remove_non_manifold_edges,
+    repair_mesh, split_non_manifold_vertices, MeshRepairParameters, MeshRepairReport,
+};
+pub use smoothing::{
+    par_cotangent_laplacian_smoothing_inplace, par_hc_smoothing_inplace,
+    par_laplacian_smoothing_inplace, par_smoothing_inplace, par_taubin_smoothing_inplace,
+    par_volume_correction_inplace, MeshSmoothingMethod,
+};
+pub use subdivision::{loop_subdivision, LoopSubdivisionParameters};
+pub use uv::{
+    advected_uv_coordinates, triplanar_uv_coordinates, AdvectedUvCoordinates, AdvectedUvParameters,
+    UvGeneration,
+};
+
+mod clipping;
+mod decimation;
+mod remeshing;
+mod repair;
+mod smoothing;
+mod subdivision;
+mod uv;
+
+/// Parameters for the post-processing pipeline that is applied to a reconstructed surface by [`postprocess_surface`]
+#[derive(Clone, Debug)]
+pub struct PostprocessingParameters<R: Real> {
+    /// Whether to perform the marching cubes mesh cleanup (see [`marching_cubes_cleanup`])
+    pub mesh_cleanup: bool,
+    /// Whether to merge barnacle configurations of the mesh (see [`decimation`])
+    pub decimate_barnacles: bool,
+    /// Parameters of the quadric error metric simplification applied after all attributes were computed (no simplification if `None`, see [`quadric_decimation_with_data`])
+    pub quadric_decimation: Option<QuadricDecimationParameters<R>>,
+    /// Whether to keep vertices without connectivity during decimation and clamping (keeps vertex indices stable)
+    pub keep_vertices: bool,
+    /// Whether to compute surface normals and attach them as the point attribute `"normals"`
+    pub compute_normals: bool,
+    /// Whether to use SPH interpolation (instead of area weighted triangle normals) to compute the normals
+    pub sph_normals: bool,
+    /// Whether to compute discrete curvatures of the mesh and attach them as the point attributes `"mean_curvature"`, `"gaussian_curvature"` and `"principal_curvatures"`
+    pub compute_curvature: bool,
+    /// Whether to additionally compute the mean curvature using SPH interpolation and attach it as the point attribute `"sph_mean_curvature"` (only used if curvatures are computed)
+    pub sph_curvature: bool,
+    /// Number of Laplacian smoothing iterations applied to the normal field (no smoothing if `None`)
+    pub normals_smoothing_iters: Option<usize>,
+    /// Interpolation scheme used for the SPH normals and the interpolation of particle attributes
+    pub interpolation_mode: InterpolationMode,
+    /// Interpolation scheme used for integer particle attributes (e.g. IDs and labels)
+    pub integer_interpolation: CategoricalInterpolation,
+    /// Number of smoothing iterations applied to the mesh (no smoothing if `None`)
+    pub mesh_smoothing_iters: Option<usize>,
+    /// Smoothing scheme applied to the mesh (only used if mesh smoothing is enabled)
+    pub mesh_smoothing_method: MeshSmoothingMethod<R>,
+    /// Whether to restore the volume of the mesh before smoothing by displacing the vertices along their normals weighted by the smoothing weights (see [`par_volume_correction_inplace`])
+    pub mesh_smoothing_volume_correction: bool,
+    /// Relative tolerance of the volume after the volume correction
+    pub volume_correction_tolerance: R,
+    /// Parameters of the isotropic remeshing applied after the mesh smoothing (no remeshing if `None`, see [`isotropic_remeshing`])
+    pub remeshing: Option<IsotropicRemeshingParameters<R>>,
+    /// Parameters of the Loop subdivision applied after the remeshing (no subdivision if `None`, see [`loop_subdivision`])
+    pub subdivision: Option<LoopSubdivisionParameters>,
+    /// Whether to compute feature weights for the mesh smoothing according to "Weighted Laplacian Smoothing for Surface Reconstruction of Particle-based Fluids" (Löschner, Böttcher, Jeske, Bender 2023)
+    pub mesh_smoothing_weights: bool,
+    /// Normalization value from weighted number of neighbors to smoothing weights
+    pub mesh_smoothing_weights_normalization: R,
+    /// Whether to attach the smoothing weights as point attributes `"wnn"` and `"sw"` to the mesh
+    pub output_mesh_smoothing_weights: bool,
+    /// Whether to attach the unsmoothed normals as point attribute `"raw_normals"` to the mesh (only used if the normals are smoothed)
+    pub output_raw_normals: bool,
+    /// Bounding box the mesh is restricted to, all cells completely outside of it are removed (no clamping if `None`)
+    pub mesh_aabb: Option<Aabb3d<R>>,
+    /// Whether to clamp vertices outside of `mesh_aabb` to its boundary
+    pub mesh_aabb_clamp_vertices: bool,
+    /// Whether to clip the mesh at the faces of `mesh_aabb` and close the cuts with caps instead of removing cells and clamping vertices (see [`clip_with_aabb`])
+    pub mesh_aabb_clip: bool,
+    /// Parameters of the mesh repair applied as the last step (no repair if `None`, see [`repair_mesh`])
+    pub mesh_repair: Option<MeshRepairParameters<R>>,
+    /// Strategy to generate texture coordinates attached as point attribute `"uv"` to the final mesh (no texture coordinates if `None`, see [`UvGeneration`])
+    ///
+    /// Advected texture coordinates additionally attach the coordinates of the second layer as `"uv_1"`. The blend weights
+    /// of the layers are the same for all vertices and only depend on the time (see [`advected_uv_coordinates`]).
+    pub uv_generation: Option<UvGeneration<R>>,
+    /// Whether to attach the vertex provenance of the reconstruction (if available) as point attributes (see [`VertexProvenance::to_attributes`](crate::VertexProvenance::to_attributes))
+    pub output_vertex_provenance: bool,
+    /// Connected components with an absolute enclosed volume below this threshold are removed (see [`remove_small_components`])
+    pub min_component_volume: Option<R>,
+    /// Connected components with fewer triangles than this threshold are removed (see [`remove_small_components`])
+    pub min_component_triangles: Option<usize>,
+    /// Whether to attach the index of the connected component of each vertex as point attribute `"component_id"` to the final mesh
+    pub output_component_ids: bool,
+}
+
+impl<R: Real> Default for PostprocessingParameters<R> {
+    /// Returns parameters that disable all post-processing steps
+    fn default() -> Self {
+        Self {
+            mesh_cleanup: false,
+            decimate_barnacles: false,
+            quadric_decimation: None,
+            keep_vertices: false,
+            compute_normals: false,
+            sph_normals: false,
+            compute_curvature: false,
+            sph_curvature: false,
+            normals_smoothing_iters: None,
+            interpolation_mode: InterpolationMode::default(),
+            integer_interpolation: CategoricalInterpolation::default(),
+            mesh_smoothing_iters: None,
+            mesh_smoothing_method: MeshSmoothingMethod::default(),
+            mesh_smoothing_volume_correction: false,
+            volume_correction_tolerance: R::from_f64(1e-4).unwrap(),
+            remeshing: None,
+            subdivision: None,
+            mesh_smoothing_weights: false,
+            mesh_smoothing_weights_normalization: R::from_f64(13.0).unwrap(),
+            output_mesh_smoothing_weights: false,
+            output_raw_normals: false,
+            mesh_aabb: None,
+            mesh_aabb_clamp_vertices: false,
+            mesh_aabb_clip: false,
+            mesh_repair: None,
+            uv_generation: None,
+            output_vertex_provenance: false,
+            min_component_volume: None,
+            min_component_triangles: None,
+            output_component_ids: false,
+        }
+    }
+}
+
+impl<R: Real> PostprocessingParameters<R> {
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns `None` if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<PostprocessingParameters<T>> {
+        Some(PostprocessingParameters {
+            mesh_cleanup: self.mesh_cleanup,
+            decimate_barnacles: self.decimate_barnacles,
+            quadric_decimation: match &self.quadric_decimation {
+                Some(quadric_decimation) => Some(quadric_decimation.try_convert()?),
+                None => None,
+            },
+            keep_vertices: self.keep_vertices,
+            compute_normals: self.compute_normals,
+            sph_normals: self.sph_normals,
+            compute_curvature: self.compute_curvature,
+            sph_curvature: self.sph_curvature,
+            normals_smoothing_iters: self.normals_smoothing_iters,
+            interpolation_mode: self.interpolation_mode,
+            integer_interpolation: self.integer_interpolation,
+            mesh_smoothing_iters: self.mesh_smoothing_iters,
+            mesh_smoothing_method: self.mesh_smoothing_method.try_convert()?,
+            mesh_smoothing_volume_correction: self.mesh_smoothing_volume_correction,
+            volume_correction_tolerance: self.volume_correction_tolerance.try_convert()?,
+            remeshing: match &self.remeshing {
+                Some(remeshing) => Some(remeshing.try_convert()?),
+                None => None,
+            },
+            subdivision: self.subdivision.clone(),
+            mesh_smoothing_weights: self.mesh_smoothing_weights,
+            mesh_smoothing_weights_normalization: self
+                .mesh_smoothing_weights_normalization
+                .try_convert()?,
+            output_mesh_smoothing_weights: self.output_mesh_smoothing_weights,
+            output_raw_normals: self.output_raw_normals,
+            mesh_aabb: match &self.mesh_aabb {
+                Some(aabb) => Some(aabb.try_convert()?),
+                None => None,
+            },
+            mesh_aabb_clamp_vertices: self.mesh_aabb_clamp_vertices,
+            mesh_aabb_clip: self.mesh_aabb_clip,
+            mesh_repair: match &self.mesh_repair {
+                Some(mesh_repair) => Some(mesh_repair.try_convert()?),
+                None => None,
+            },
+            uv_generation: match &self.uv_generation {
+                Some(uv_generation) => Some(uv_generation.try_convert()?),
+                None => None,
+            },
+            output_vertex_provenance: self.output_vertex_provenance,
+            min_component_volume: match self.min_component_volume {
+                Some(min_component_volume) => Some(min_component_volume.try_convert()?),
+                None => None,
+            },
+            min_component_triangles: self.min_component_triangles,
+            output_component_ids: self.output_component_ids,
+        })
+    }
+
+    /// Returns whether an [`SphInterpolator`] has to be constructed for these parameters
+    fn interpolator_required(&self) -> bool {
+        self.mesh_smoothing_weights
+            || (self.compute_normals && self.sph_normals)
+            || self
+                .remeshing
+                .as_ref()
+                .is_some_and(|remeshing| remeshing.projection == RemeshingProjection::LevelSet)
+            || self
+                .subdivision
+                .as_ref()
+                .is_some_and(|subdivision| subdivision.level_set_projection)
+            || (self.compute_curvature && self.sph_curvature)
+    }
+}
+
+/// Post-processed mesh and the connected components removed from it
+type PostprocessedSurface<R> = (MeshWithData<R, TriMesh3d<R>>, Vec<MeshComponentInfo<R>>);
+
+/// Applies the post-processing pipeline to the mesh of a surface reconstruction
 ///
-/// Move each vertex towards the mean position of its neighbors.
-/// Factor beta in \[0;1] proportional to amount of smoothing (for beta=1 each vertex is placed at the mean position).
-/// Additionally, feature weights can be specified to apply a varying amount of smoothing over the mesh.
-pub fn par_laplacian_smoothing_inplace<R: Real>(
-    mesh: &mut TriMesh3d<R>,
-    vertex_connectivity: &[Vec<usize>],
-    iterations: usize,
-    beta: R,
-    weights: &[R],
-) {
-    profile!("laplacian_smoothing");
+/// The steps are performed in the following order (each step only if enabled in the parameters):
+///  1. marching cubes cleanup and barnacle decimation (and attaching the vertex provenance),
+///  2. computation of the smoothing weights, (weighted) mesh smoothing with the configured [`MeshSmoothingMethod`] and volume correction,
+///  3. isotropic remeshing (discards all point attributes computed in the previous steps),
+///  4. Loop subdivision (interpolating all point attributes computed in the previous steps),
+///  5. computation (and smoothing) of the surface normals,
+///  6. computation of the surface curvatures,
+///  7. SPH interpolation of the given particle attributes to the mesh vertices,
+///  8. quadric error metric decimation (carrying over all point attributes),
+///  9. removal and clamping of all cells outside of the mesh AABB or watertight clipping at the mesh AABB,
+///  10. repair of the mesh (removal of degenerate and non-manifold configurations and hole filling),
+///  11. generation of texture coordinates (using the normals computed before if available),
+///  12. removal of small connected components,
+///  13. labelling of the connected components of the final mesh.
+///
+/// Returns the post-processed mesh and the statistics of all connected components removed in step 12 (empty if the
+/// removal is disabled), e.g. to convert them into particles with [`components_to_particles`].
+/// The reconstruction is consumed such that its mesh can be post-processed without copying it.
+/// The particle positions have to be the same positions that were used for the reconstruction.
+/// The particle densities of the reconstruction are used for all SPH interpolation steps.
+/// Real scalar and vector attributes are interpolated using the configured [`InterpolationMode`], integer attributes
+/// using the configured [`CategoricalInterpolation`]. Tensor attributes are averaged with [`TensorAveraging::LogEuclidean`]
+/// if all tensors are symmetric positive definite and component-wise otherwise.
+pub fn postprocess_surface<I: Index, R: Real>(
+    mut reconstruction: SurfaceReconstruction<I, R>,
+    particle_positions: &[Vector3<R>],
+    particle_attributes: Vec<MeshAttribute<R>>,
+    parameters: &Parameters<R>,
+    postprocessing: &PostprocessingParameters<R>,
+) -> Result<PostprocessedSurface<R>, anyhow::Error> {
+    profile!("postprocessing");
+
+    let mut mesh_with_data = MeshWithData::new(std::mem::take(&mut reconstruction.mesh));
+    let mut vertex_connectivity = None;
+
+    if postprocessing.mesh_cleanup {
+        info!("Post-processing: Performing mesh cleanup");
+        let tris_before = mesh_with_data.mesh.triangles.len();
+        let verts_before = mesh_with_data.mesh.vertices.len();
+        vertex_connectivity = Some(marching_cubes_cleanup(
+            &mut mesh_with_data.mesh,
+            reconstruction.grid(),
+            5,
+            postprocessing.keep_vertices,
+        ));
+        let tris_after = mesh_with_data.mesh.triangles.len();
+        let verts_after = mesh_with_data.mesh.vertices.len();
+        info!("Post-processing: Cleanup reduced number of vertices to {:.2}% and number of triangles to {:.2}% of original mesh.", (verts_after as f64 / verts_before as f64) * 100.0, (tris_after as f64 / tris_before as f64) * 100.0)
+    }
 
-    let mut vertex_buffer = mesh.vertices.clone();
+    // Decimate mesh if requested
+    if postprocessing.decimate_barnacles {
+        info!("Post-processing: Performing decimation");
+        vertex_connectivity = Some(decimation(
+            &mut mesh_with_data.mesh,
+            postprocessing.keep_vertices,
+        ));
+    }
 
-    for _ in 0..iterations {
-        profile!("laplacian_smoothing iter");
+    // Attach vertex provenance if requested, only possible if the vertex indices were not changed by the cleanup
+    if postprocessing.output_vertex_provenance {
+        let vertex_indices_changed = (postprocessing.mesh_cleanup
+            || postprocessing.decimate_barnacles)
+            && !postprocessing.keep_vertices;
+        match reconstruction.vertex_provenance() {
+            Some(_) if vertex_indices_changed => warn!("Post-processing: Vertex provenance cannot be attached to the mesh because mesh cleanup or decimation changed the vertex indices (keep vertices to prevent this)"),
+            Some(provenance) => mesh_with_data
+                .point_attributes
+                .extend(provenance.to_attributes()),
+            None => warn!("Post-processing: Vertex provenance was requested but not computed by the surface reconstruction"),
+        }
+    }
 
-        std::mem::swap(&mut vertex_buffer, &mut mesh.vertices);
+    // Initialize SPH interpolator if required later
+    let interpolator = if postprocessing.interpolator_required() || !particle_attributes.is_empty()
+    {
+        profile!("initialize interpolator");
+        info!("Post-processing: Initializing interpolator...");
 
-        mesh.vertices
-            .par_iter_mut()
-            .enumerate()
-            .for_each(|(i, vertex_i)| {
-                let beta_eff = beta * weights[i];
+        info!(
+            "Constructing global acceleration structure for SPH interpolation to {} vertices...",
+            mesh_with_data.vertices().len()
+        );
+
+        let particle_rest_density = parameters.rest_density;
+        let particle_rest_volume = R::from_f64((4.0 / 3.0) * std::f64::consts::PI).unwrap()
+            * parameters.particle_radius.powi(3);
+        let particle_rest_mass = particle_rest_volume * particle_rest_density;
+
+        let particle_densities = reconstruction
+            .particle_densities()
+            .ok_or_else(|| anyhow!("Particle densities were not returned by surface reconstruction but are required for SPH interpolation"))?
+            .as_slice();
+        if particle_positions.len() != particle_densities.len() {
+            return Err(anyhow!(
+                "There has to be one density value per particle (got {} particles and {} densities)",
+                particle_positions.len(),
+                particle_densities.len()
+            ));
+        }
+
+        Some(SphInterpolator::new(
+            particle_positions,
+            particle_densities,
+            particle_rest_mass,
+            parameters.compact_support_radius,
+        ))
+    } else {
+        None
+    };
+
+    // Compute mesh vertex-vertex connectivity map if required later
+    let vertex_connectivity_required = postprocessing.normals_smoothing_iters.is_some()
+        || postprocessing.mesh_smoothing_iters.is_some();
+    if vertex_connectivity.is_none() && vertex_connectivity_required {
+        vertex_connectivity = Some(mesh_with_data.mesh.vertex_vertex_connectivity());
+    }
+
+    // Compute smoothing weights if requested
+    let smoothing_weights = if postprocessing.mesh_smoothing_weights {
+        profile!("compute smoothing weights");
+        info!("Post-processing: Computing smoothing weights...");
+
+        let interpolator = interpolator.as_ref().expect("interpolator is required");
+        let (vertex_weighted_num_neighbors, smoothing_weights) = compute_smoothing_weights::<I, R>(
+            &reconstruction,
+            particle_positions,
+            interpolator,
+            mesh_with_data.vertices(),
+            parameters.compact_support_radius,
+            postprocessing.mesh_smoothing_weights_normalization,
+        );
+
+        if postprocessing.output_mesh_smoothing_weights {
+            // Raw distance-weighted number of neighbors value per vertex (can be used to determine normalization value)
+            mesh_with_data.point_attributes.push(MeshAttribute::new(
+                "wnn".to_string(),
+                AttributeData::ScalarReal(vertex_weighted_num_neighbors),
+            ));
+            // Final smoothing weights per vertex
+            mesh_with_data.point_attributes.push(MeshAttribute::new(
+                "sw".to_string(),
+                AttributeData::ScalarReal(smoothing_weights.clone()),
+            ));
+        }
+
+        Some(smoothing_weights)
+    } else {
+        None
+    };
 
-                // Compute mean position of neighboring vertices
-                let mut vertex_sum = Vector3::zeros();
-                for j in vertex_connectivity[i].iter() {
-                    vertex_sum += vertex_buffer[j.clone()];
+    // Perform smoothing if requested
+    if let Some(mesh_smoothing_iters) = postprocessing.mesh_smoothing_iters {
+        profile!("mesh smoothing");
+        info!("Post-processing: Smoothing mesh...");
+
+        let smoothing_weights =
+            smoothing_weights.unwrap_or_else(|| vec![R::one(); mesh_with_data.vertices().len()]);
+
+        let volume_before = postprocessing
+            .mesh_smoothing_volume_correction
+            .then(|| mesh_with_data.mesh.signed_volume());
+
+        par_smoothing_inplace(
+            &mut mesh_with_data.mesh,
+            vertex_connectivity
+                .as_ref()
+                .expect("vertex connectivity is required"),
+            mesh_smoothing_iters,
+            postprocessing.mesh_smoothing_method,
+            &smoothing_weights,
+        );
+
+        if let Some(volume_before) = volume_before {
+            info!("Post-processing: Correcting volume of smoothed mesh...");
+
+            let volume_after = mesh_with_data.mesh.signed_volume();
+            let remaining_error = par_volume_correction_inplace(
+                &mut mesh_with_data.mesh,
+                volume_before,
+                &smoothing_weights,
+                postprocessing.volume_correction_tolerance,
+                10,
+            );
+            info!(
+                "Post-processing: Smoothing changed the volume by {:.3}%, remaining relative volume error after correction: {:.3e}",
+                ((volume_after - volume_before) / volume_before).to_f64().unwrap_or(f64::NAN) * 100.0,
+                remaining_error.to_f64().unwrap_or(f64::NAN)
+            );
+            if remaining_error > postprocessing.volume_correction_tolerance {
+                warn!("Post-processing: Volume correction did not reach the requested tolerance (is the mesh closed?)");
+            }
+        }
+    }
+
+    // Remesh surface if requested
+    if let Some(remeshing) = &postprocessing.remeshing {
+        profile!("isotropic remeshing");
+        info!("Post-processing: Performing isotropic remeshing...");
+
+        if !mesh_with_data.point_attributes.is_empty() {
+            warn!("Post-processing: Point attributes computed before the remeshing (smoothing weights, vertex provenance) are discarded because the remeshing replaces the vertices");
+            mesh_with_data.point_attributes.clear();
+        }
+
+        let level_set = interpolator
+            .as_ref()
+            .map(|interpolator| (interpolator, parameters.iso_surface_threshold));
+        vertex_connectivity = Some(isotropic_remeshing(
+            &mut mesh_with_data.mesh,
+            remeshing,
+            level_set,
+        )?);
+    }
+
+    // Subdivide surface if requested
+    if let Some(subdivision) = &postprocessing.subdivision {
+        profile!("loop subdivision");
+        info!("Post-processing: Performing Loop subdivision...");
+
+        let level_set = interpolator
+            .as_ref()
+            .map(|interpolator| (interpolator, parameters.iso_surface_threshold));
+        mesh_with_data = loop_subdivision(&mesh_with_data, subdivision, level_set)?;
+        info!(
+            "Post-processing: Subdivided mesh has {} vertices and {} triangles.",
+            mesh_with_data.mesh.vertices.len(),
+            mesh_with_data.mesh.triangles.len()
+        );
+
+        vertex_connectivity = postprocessing
+            .normals_smoothing_iters
+            .map(|_| mesh_with_data.mesh.vertex_vertex_connectivity());
+    }
+
+    // Add normals to mesh if requested
+    if postprocessing.compute_normals {
+        profile!("compute normals");
+        info!("Post-processing: Computing surface normals...");
+
+        // Compute normals
+        let normals = if postprocessing.sph_normals {
+            info!("Using SPH interpolation to compute surface normals");
+
+            let sph_normals = interpolator
+                .as_ref()
+                .expect("interpolator is required")
+                .interpolate_normals_with_mode(
+                    mesh_with_data.vertices(),
+                    postprocessing.interpolation_mode,
+                );
+            bytemuck::allocation::cast_vec::<Unit<Vector3<R>>, Vector3<R>>(sph_normals)
+        } else {
+            info!("Using area weighted triangle normals for surface normals");
+            profile!("mesh.par_vertex_normals");
+            let tri_normals = mesh_with_data.mesh.par_vertex_normals();
+
+            // Convert unit vectors to plain vectors
+            bytemuck::allocation::cast_vec::<Unit<Vector3<R>>, Vector3<R>>(tri_normals)
+        };
+
+        // Smooth normals
+        if let Some(smoothing_iters) = postprocessing.normals_smoothing_iters {
+            info!("Post-processing: Smoothing normals...");
+
+            let mut smoothed_normals = normals.clone();
+            par_laplacian_smoothing_normals_inplace(
+                &mut smoothed_normals,
+                vertex_connectivity
+                    .as_ref()
+                    .expect("vertex connectivity is required"),
+                smoothing_iters,
+            );
+
+            mesh_with_data.point_attributes.push(MeshAttribute::new(
+                "normals".to_string(),
+                AttributeData::Vector3Real(smoothed_normals),
+            ));
+            if postprocessing.output_raw_normals {
+                mesh_with_data.point_attributes.push(MeshAttribute::new(
+                    "raw_normals".to_string(),
+                    AttributeData::Vector3Real(normals),
+                ));
+            }
+        } else {
+            mesh_with_data.point_attributes.push(MeshAttribute::new(
+                "normals".to_string(),
+                AttributeData::Vector3Real(normals),
+            ));
+        }
+    }
+
+    // Add curvatures to mesh if requested
+    if postprocessing.compute_curvature {
+        profile!("compute curvature");
+        info!("Post-processing: Computing surface curvatures...");
+
+        let curvatures = mesh_with_data.mesh.vertex_curvatures();
+        mesh_with_data.point_attributes.push(MeshAttribute::new(
+            "mean_curvature".to_string(),
+            AttributeData::ScalarReal(curvatures.mean),
+        ));
+        mesh_with_data.point_attributes.push(MeshAttribute::new(
+            "gaussian_curvature".to_string(),
+            AttributeData::ScalarReal(curvatures.gaussian),
+        ));
+        mesh_with_data.point_attributes.push(MeshAttribute::new(
+            "principal_curvatures".to_string(),
+            AttributeData::Vector2Real(
+                curvatures
+                    .principal
+                    .into_iter()
+                    .map(Vector2::from)
+                    .collect(),
+            ),
+        ));
+
+        if postprocessing.sph_curvature {
+            info!("Using SPH interpolation to compute the mean curvature");
+
+            let sph_mean_curvature = interpolator
+                .as_ref()
+                .expect("interpolator is required")
+                .interpolate_mean_curvature(mesh_with_data.vertices());
+            mesh_with_data.point_attributes.push(MeshAttribute::new(
+                "sph_mean_curvature".to_string(),
+                AttributeData::ScalarReal(sph_mean_curvature),
+            ));
+        }
+    }
+
+    // Interpolate attributes if requested
+    if !particle_attributes.is_empty() {
+        profile!("interpolate attributes");
+        info!("Post-processing: Interpolating attributes...");
+        let interpolator = interpolator.as_ref().expect("interpolator is required");
+
+        for attribute in particle_attributes.into_iter() {
+            info!("Interpolating attribute \"{}\"...", attribute.name);
+
+            let vertices = mesh_with_data.vertices();
+            let mode = postprocessing.interpolation_mode;
+            let integer_interpolation = postprocessing.integer_interpolation;
+            let interpolated_data = match attribute.data {
+                AttributeData::ScalarU64(values) => {
+                    AttributeData::ScalarU64(interpolator.interpolate_categorical_quantity(
+                        values.as_slice(),
+                        vertices,
+                        integer_interpolation,
+                    ))
                 }
-                if vertex_connectivity[i].len() > 0 {
-                    let n = R::from_usize(vertex_connectivity[i].len()).unwrap();
-                    vertex_sum /= n;
+                AttributeData::ScalarI64(values) => {
+                    AttributeData::ScalarI64(interpolator.interpolate_categorical_quantity(
+                        values.as_slice(),
+                        vertices,
+                        integer_interpolation,
+                    ))
                 }
+                AttributeData::ScalarReal(values) => AttributeData::ScalarReal(
+                    interpolator.interpolate_scalar_quantity(values.as_slice(), vertices, mode),
+                ),
+                AttributeData::Vector2Real(values) => AttributeData::Vector2Real(
+                    interpolator.interpolate_vector_quantity(values.as_slice(), vertices, mode),
+                ),
+                AttributeData::Vector3Real(values) => AttributeData::Vector3Real(
+                    interpolator.interpolate_vector_quantity(values.as_slice(), vertices, mode),
+                ),
+                AttributeData::Vector4Real(values) => AttributeData::Vector4Real(
+                    interpolator.interpolate_vector_quantity(values.as_slice(), vertices, mode),
+                ),
+                AttributeData::Matrix3Real(values) => {
+                    let averaging = if values.par_iter().all(is_symmetric_positive_definite) {
+                        TensorAveraging::LogEuclidean
+                    } else {
+                        TensorAveraging::Linear
+                    };
+                    AttributeData::Matrix3Real(interpolator.interpolate_tensor_quantity(
+                        values.as_slice(),
+                        vertices,
+                        mode,
+                        averaging,
+                    ))
+                }
+            };
 
-                *vertex_i = vertex_i.scale(R::one() - beta_eff) + vertex_sum.scale(beta_eff);
-            });
+            mesh_with_data
+                .point_attributes
+                .push(MeshAttribute::new(attribute.name, interpolated_data));
+        }
     }
+
+    // Simplify mesh to the target triangle count or error threshold if requested
+    if let Some(quadric_decimation) = &postprocessing.quadric_decimation {
+        profile!("quadric decimation");
+        info!("Post-processing: Performing quadric decimation...");
+
+        let tris_before = mesh_with_data.mesh.triangles.len();
+        mesh_with_data = quadric_decimation_with_data(
+            &mesh_with_data,
+            quadric_decimation,
+            postprocessing.keep_vertices,
+        );
+        let tris_after = mesh_with_data.mesh.triangles.len();
+        info!(
+            "Post-processing: Quadric decimation reduced number of triangles to {:.2}% of original mesh.",
+            (tris_after as f64 / tris_before as f64) * 100.0
+        );
+    }
+
+    // Clip mesh at AABB or remove and clamp cells outside of AABB
+    let mesh_with_data = if let Some(mesh_aabb) = postprocessing
+        .mesh_aabb
+        .as_ref()
+        .filter(|_| postprocessing.mesh_aabb_clip)
+    {
+        info!("Post-processing: Clipping mesh at AABB...");
+        let mesh_with_data =
+            clip_with_aabb(&mesh_with_data, mesh_aabb, postprocessing.keep_vertices);
+        info!(
+            "Post-processing: Clipped mesh has {} triangles.",
+            mesh_with_data.mesh.triangles.len()
+        );
+        mesh_with_data
+    } else if let Some(mesh_aabb) = &postprocessing.mesh_aabb {
+        profile!("clamp mesh to aabb");
+        info!("Post-processing: Clamping mesh to AABB...");
+
+        mesh_with_data.par_clamp_with_aabb(
+            mesh_aabb,
+            postprocessing.mesh_aabb_clamp_vertices,
+            postprocessing.keep_vertices,
+        )
+    } else {
+        mesh_with_data
+    };
+
+    // Repair mesh
+    let mut mesh_with_data = if let Some(mesh_repair) = &postprocessing.mesh_repair {
+        info!("Post-processing: Repairing mesh...");
+        let (mesh_with_data, report) =
+            repair_mesh(&mesh_with_data, mesh_repair, postprocessing.keep_vertices);
+        info!(
+            "Post-processing: Mesh repair removed {} duplicate, {} degenerate and {} non-manifold edge triangles, split {} non-manifold vertices and filled {} holes.",
+            report.removed_duplicate_triangles,
+            report.removed_degenerate_triangles,
+            report.removed_non_manifold_edge_triangles,
+            report.split_non_manifold_vertices,
+            report.filled_holes
+        );
+        if !report.is_closed_manifold() {
+            warn!(
+                "Post-processing: Repaired mesh is not a closed manifold ({} unfilled holes, {} boundary edges, {} non-manifold edges, {} non-manifold vertices remain).",
+                report.unfilled_holes,
+                report.remaining_boundary_edges,
+                report.remaining_non_manifold_edges,
+                report.remaining_non_manifold_vertices
+            );
+        }
+        mesh_with_data
+    } else {
+        mesh_with_data
+    };
+
+    // Generate texture coordinates if requested
+    if let Some(uv_generation) = &postprocessing.uv_generation {
+        profile!("uv generation");
+        info!("Post-processing: Generating texture coordinates...");
+
+        let normals = match mesh_with_data
+            .point_attributes
+            .iter()
+            .find(|a| a.name == "normals")
+        {
+            Some(MeshAttribute {
+                data: AttributeData::Vector3Real(normals),
+                ..
+            }) => normals.clone(),
+            _ => bytemuck::allocation::cast_vec::<Unit<Vector3<R>>, Vector3<R>>(
+                mesh_with_data.mesh.par_vertex_normals(),
+            ),
+        };
+
+        match uv_generation {
+            UvGeneration::Triplanar { scale } => {
+                let uv = triplanar_uv_coordinates(mesh_with_data.vertices(), &normals, *scale);
+                mesh_with_data
+                    .point_attributes
+                    .push(MeshAttribute::new("uv", AttributeData::Vector2Real(uv)));
+            }
+            UvGeneration::Advected(parameters) => {
+                let velocities = match mesh_with_data
+                    .point_attributes
+                    .iter()
+                    .find(|a| a.name == parameters.velocity_attribute)
+                {
+                    Some(MeshAttribute {
+                        data: AttributeData::Vector3Real(velocities),
+                        ..
+                    }) => velocities,
+                    Some(_) => {
+                        return Err(anyhow!(
+                            "Velocity attribute \"{}\" for advected texture coordinates has to be a 3D vector attribute",
+                            parameters.velocity_attribute
+                        ))
+                    }
+                    None => {
+                        return Err(anyhow!(
+                            "Velocity attribute \"{}\" for advected texture coordinates was not found on the mesh (it has to be interpolated from the particles)",
+                            parameters.velocity_attribute
+                        ))
+                    }
+                };
+
+                let AdvectedUvCoordinates {
+                    uv: [uv_0, uv_1],
+                    weights,
+                } = advected_uv_coordinates(
+                    mesh_with_data.vertices(),
+                    &normals,
+                    velocities,
+                    parameters,
+                );
+                info!(
+                    "Post-processing: Blend weights of the advected texture coordinate layers \"uv\" and \"uv_1\": {:.4} and {:.4}",
+                    weights[0].to_f64().unwrap_or(f64::NAN),
+                    weights[1].to_f64().unwrap_or(f64::NAN)
+                );
+                mesh_with_data.point_attributes.extend([
+                    MeshAttribute::new("uv", AttributeData::Vector2Real(uv_0)),
+                    MeshAttribute::new("uv_1", AttributeData::Vector2Real(uv_1)),
+                ]);
+            }
+        }
+    }
+
+    // Remove small connected components if requested
+    let (mesh_with_data, removed_components) = if postprocessing.min_component_volume.is_some()
+        || postprocessing.min_component_triangles.is_some()
+    {
+        profile!("remove small components");
+        info!("Post-processing: Removing small connected components...");
+
+        remove_small_components(
+            &mesh_with_data,
+            postprocessing.min_component_volume,
+            postprocessing.min_component_triangles,
+            postprocessing.keep_vertices,
+        )
+    } else {
+        (mesh_with_data, Vec::new())
+    };
+
+    // Attach the connected component index of each vertex if requested
+    let mesh_with_data = if postprocessing.output_component_ids {
+        let components = mesh_with_data.mesh.connected_components();
+        info!(
+            "Post-processing: Surface mesh has {} connected components.",
+            components.len()
+        );
+
+        let component_ids = components
+            .vertex_components
+            .into_iter()
+            .map(|c| c as u64)
+            .collect();
+        mesh_with_data.with_point_data(MeshAttribute::new(
+            "component_id",
+            AttributeData::ScalarU64(component_ids),
+        ))
+    } else {
+        mesh_with_data
+    };
+
+    Ok((mesh_with_data, removed_components))
+}
+
+/// Returns whether the given matrix is symmetric (up to a relative tolerance) and positive definite
+fn is_symmetric_positive_definite<R: Real>(m: &Matrix3<R>) -> bool {
+    let tolerance = R::default_epsilon().sqrt() * m.norm();
+    (m - m.transpose()).amax() <= tolerance && m.cholesky().is_some()
+}
+
+/// Computes the feature weights for weighted Laplacian smoothing of the given mesh vertices
+///
+/// Implements the smoothing weights from "Weighted Laplacian Smoothing for Surface Reconstruction of Particle-based Fluids" (Löschner, Böttcher, Jeske, Bender 2023).
+/// Returns the distance-weighted number of neighbors interpolated to the vertices and the final smoothing weights per vertex.
+/// The particle neighborhood lists of the reconstruction are re-used if available.
+pub fn compute_smoothing_weights<I: Index, R: Real>(
+    reconstruction: &SurfaceReconstruction<I, R>,
+    particle_positions: &[Vector3<R>],
+    interpolator: &SphInterpolator<R>,
+    vertices: &[Vector3<R>],
+    compact_support_radius: R,
+    normalization: R,
+) -> (Vec<R>, Vec<R>) {
+    // Weight of a neighbor depending on its squared distance
+    let squared_r = compact_support_radius * compact_support_radius;
+    let neighbor_weight = |dist: R| R::one() - (dist / squared_r).clamp(R::zero(), R::one());
+
+    // Compute weighted neighbor count
+    let weighted_ncounts = if let Some(nl) = reconstruction.particle_neighbors() {
+        // Re-use the neighborhood lists of the reconstruction
+        nl.par_iter()
+            .enumerate()
+            .map(|(i, nl)| {
+                nl.iter()
+                    .copied()
+                    .map(|j| {
+                        neighbor_weight(
+                            (particle_positions[i] - particle_positions[j]).norm_squared(),
+                        )
+                    })
+                    .fold(R::zero(), R::add)
+            })
+            .collect::<Vec<_>>()
+    } else {
+        // Global neighborhood search, the squared distances are stored to avoid recomputing them
+        let search_radius = compact_support_radius;
+
+        let mut domain = Aabb3d::from_points(particle_positions);
+        domain.grow_uniformly(search_radius);
+
+        let mut nl = FlatNeighborhoodList::default();
+        let mut squared_distances = Vec::new();
+        neighborhood_search::neighborhood_search_spatial_hashing_flat_parallel::<I, R>(
+            &domain,
+            particle_positions,
+            search_radius,
+            &mut nl,
+            Some(&mut squared_distances),
+        );
+        assert_eq!(nl.len(), particle_positions.len());
+
+        nl.neighbor_ptr
+            .par_windows(2)
+            .map(|range| {
+                squared_distances[range[0]..range[1]]
+                    .iter()
+                    .copied()
+                    .map(neighbor_weight)
+                    .fold(R::zero(), R::add)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let vertex_weighted_num_neighbors = {
+        profile!("interpolate weighted neighbor counts");
+        interpolator.interpolate_scalar_quantity(
+            weighted_ncounts.as_slice(),
+            vertices,
+            InterpolationMode::Shepard,
+        )
+    };
+
+    let offset = R::zero();
+    let normalization = normalization - offset;
+
+    // Normalize number of neighbors
+    let smoothing_weights = vertex_weighted_num_neighbors
+        .par_iter()
+        .copied()
+        .map(|n| (n - offset).max(R::zero()))
+        .map(|n| (n / normalization).min(R::one()))
+        // Smooth-Step function
+        .map(|x| x.powi(5).times(6) - x.powi(4).times(15) + x.powi(3).times(10))
+        .collect::<Vec<_>>();
+
+    (vertex_weighted_num_neighbors, smoothing_weights)
 }
 
 /// Laplacian smoothing of a normal field
@@ -222,6 +1076,82 @@ pub fn merge_barnacle_configurations<R: Real>(mesh: &mut HalfEdgeTriMesh<R>) {
     merge_double_barnacle_configurations_he(mesh);
 }
 
+/// Removes all connected components of the mesh with an enclosed volume or a number of triangles below the given thresholds
+///
+/// Returns the filtered mesh (point and cell attributes are filtered accordingly) and the statistics of all removed
+/// components, e.g. to convert them into particles with [`components_to_particles`]. The absolute value of the enclosed
+/// volume is compared with the threshold, i.e. the threshold is also applied to inverted components. Components without
+/// triangles (isolated vertices) are always removed unless `keep_vertices` is set.
+pub fn remove_small_components<R: Real>(
+    mesh_with_data: &MeshWithData<R, TriMesh3d<R>>,
+    min_volume: Option<R>,
+    min_triangles: Option<usize>,
+    keep_vertices: bool,
+) -> (MeshWithData<R, TriMesh3d<R>>, Vec<MeshComponentInfo<R>>) {
+    profile!("remove_small_components");
+
+    let components = mesh_with_data.mesh.connected_components();
+    let is_small = components
+        .components
+        .iter()
+        .map(|c| {
+            min_volume.is_some_and(|min_volume| c.volume.abs() < min_volume)
+                || min_triangles.is_some_and(|min_triangles| c.num_triangles < min_triangles)
+        })
+        .collect::<Vec<_>>();
+
+    let remaining_triangles = components
+        .triangle_components
+        .iter()
+        .enumerate()
+        .filter(|(_, &c)| !is_small[c])
+        .map(|(tri_idx, _)| tri_idx)
+        .collect::<Vec<_>>();
+    let removed_components = components
+        .components
+        .into_iter()
+        .zip(is_small)
+        .filter(|(c, is_small)| *is_small && c.num_triangles > 0)
+        .map(|(c, _)| c)
+        .collect::<Vec<_>>();
+
+    info!(
+        "Small component removal: Removed {} connected components with {} triangles in total",
+        removed_components.len(),
+        mesh_with_data.mesh.triangles.len() - remaining_triangles.len()
+    );
+
+    (
+        mesh_with_data.keep_cells(&remaining_triangles, keep_vertices),
+        removed_components,
+    )
+}
+
+/// Converts mesh components into particles located at their centroids
+///
+/// The particles get the point attributes `"volume"` (absolute enclosed volume of the component), `"radius"` (radius
+/// of a sphere with the same volume) and `"area"` (surface area of the component).
+pub fn components_to_particles<R: Real>(
+    components: &[MeshComponentInfo<R>],
+) -> MeshWithData<R, PointCloud3d<R>> {
+    let volumes = components
+        .iter()
+        .map(|c| c.volume.abs())
+        .collect::<Vec<_>>();
+    let radii = volumes
+        .iter()
+        .map(|&v| (v / (R::from_i32(4).unwrap() * R::frac_pi_3())).cbrt())
+        .collect::<Vec<_>>();
+    let areas = components.iter().map(|c| c.area).collect::<Vec<_>>();
+
+    MeshWithData::new(PointCloud3d::new(
+        components.iter().map(|c| c.centroid).collect::<Vec<_>>(),
+    ))
+    .with_point_data(MeshAttribute::new_real_scalar("volume", volumes))
+    .with_point_data(MeshAttribute::new_real_scalar("radius", radii))
+    .with_point_data(MeshAttribute::new_real_scalar("area", areas))
+}
+
 #[allow(unused)]
 fn find_small_triangles<R: Real>(mesh: &HalfEdgeTriMesh<R>, area_limit: R) -> Vec<usize> {
     profile!("find_small_triangles");
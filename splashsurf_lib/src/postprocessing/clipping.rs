@@ -0,0 +1,568 @@
+//! Watertight clipping of triangle meshes with planes and AABBs
+
+use crate::mesh::{AttributeData, Mesh3d, MeshAttribute, MeshWithData, TriMesh3d};
+use crate::{profile, Aabb3d, MapType, Real};
+use log::warn;
+use nalgebra::{Unit, Vector2, Vector3};
+use std::cmp::Ordering;
+
+/// Clips the mesh at the given plane and closes the cut with planar cap polygons, keeps the half-space `normal · (x - point) <= 0`
+///
+/// Triangles crossing the plane are split at their intersection with the plane, new vertices are created on the
+/// intersected edges and their point attributes are interpolated linearly (integer attributes are taken from the closer
+/// vertex). Every closed loop of the cut is triangulated (taking nested loops into account) and added as cap triangles
+/// with the cell attributes of an adjacent triangle, so a closed and manifold input mesh stays closed and manifold.
+/// Open cut polylines (e.g. when clipping an open mesh) are not capped. Triangles lying in the plane are replaced by the caps.
+pub fn clip_with_plane<R: Real>(
+    mesh_with_data: &MeshWithData<R, TriMesh3d<R>>,
+    point: &Vector3<R>,
+    normal: &Vector3<R>,
+    keep_vertices: bool,
+) -> MeshWithData<R, TriMesh3d<R>> {
+    profile!("clip_with_plane");
+    let normal = Unit::new_normalize(*normal);
+    let offset = normal.dot(point);
+    clip_with_plane_impl(mesh_with_data, &normal, offset, keep_vertices)
+}
+
+/// Clips the mesh at all faces of the given AABB and closes the cuts with planar cap polygons
+///
+/// See [`clip_with_plane`] for details. The AABB is clipped face by face, the cut vertices are placed exactly on the
+/// faces of the AABB, so caps of adjacent faces are joined without gaps at the edges and corners of the AABB.
+pub fn clip_with_aabb<R: Real>(
+    mesh_with_data: &MeshWithData<R, TriMesh3d<R>>,
+    aabb: &Aabb3d<R>,
+    keep_vertices: bool,
+) -> MeshWithData<R, TriMesh3d<R>> {
+    profile!("clip_with_aabb");
+
+    let mut clipped_mesh = mesh_with_data.clone();
+    for axis in 0..3 {
+        let mut normal = Vector3::zeros();
+
+        normal[axis] = R::one();
+        let normal = Unit::new_unchecked(normal);
+        clipped_mesh =
+            clip_with_plane_impl(&clipped_mesh, &normal, aabb.max()[axis], keep_vertices);
+
+        let normal = -normal;
+        clipped_mesh =
+            clip_with_plane_impl(&clipped_mesh, &normal, -aabb.min()[axis], keep_vertices);
+    }
+
+    clipped_mesh
+}
+
+/// Clips the mesh at the plane `normal · x = offset`, keeps the half-space `normal · x <= offset`
+fn clip_with_plane_impl<R: Real>(
+    mesh_with_data: &MeshWithData<R, TriMesh3d<R>>,
+    normal: &Unit<Vector3<R>>,
+    offset: R,
+    keep_vertices: bool,
+) -> MeshWithData<R, TriMesh3d<R>> {
+    let mesh = &mesh_with_data.mesh;
+
+    // Coordinate axis of axis-aligned planes, used to place new vertices exactly onto the plane
+    let plane_axis = (0..3).find(|&k| normal[k].abs() == R::one());
+    let project_to_plane = |p: &Vector3<R>| -> Vector3<R> {
+        if let Some(k) = plane_axis {
+            let mut p = *p;
+            p[k] = offset * normal[k];
+            p
+        } else {
+            p - normal.scale(normal.dot(p) - offset)
+        }
+    };
+
+    let mut signed_distances = mesh
+        .vertices
+        .iter()
+        .map(|v| normal.dot(v) - offset)
+        .collect::<Vec<_>>();
+
+    // Nothing to clip if all vertices are inside of the half-space
+    if signed_distances.iter().all(|&s| s <= R::zero()) {
+        return mesh_with_data.clone();
+    }
+
+    // Vertices closer to the plane than the tolerance are moved onto the plane
+    let max_distance = signed_distances
+        .iter()
+        .fold(offset.abs(), |max, s| max.max((*s + offset).abs()));
+    let tolerance = max_distance * R::default_epsilon() * R::from_i32(64).unwrap();
+
+    let mut vertices = mesh.vertices.clone();
+    for (v, s) in vertices.iter_mut().zip(signed_distances.iter_mut()) {
+        if s.abs() <= tolerance {
+            *s = R::zero();
+            *v = project_to_plane(v);
+        }
+    }
+
+    let mut on_plane = signed_distances
+        .iter()
+        .map(|&s| s == R::zero())
+        .collect::<Vec<_>>();
+
+    // New vertices on intersected edges: (first vertex, second vertex, interpolation parameter)
+    let mut edge_points = Vec::new();
+    let mut edge_point_map = MapType::default();
+    let mut edge_point = |a: usize, b: usize| -> usize {
+        if signed_distances[a] == R::zero() {
+            return a;
+        } else if signed_distances[b] == R::zero() {
+            return b;
+        }
+
+        let (a, b) = (a.min(b), a.max(b));
+        *edge_point_map.entry((a, b)).or_insert_with(|| {
+            let t = signed_distances[a] / (signed_distances[a] - signed_distances[b]);
+            let p = vertices[a] + (vertices[b] - vertices[a]).scale(t);
+            vertices.push(project_to_plane(&p));
+            on_plane.push(true);
+            edge_points.push((a, b, t));
+            vertices.len() - 1
+        })
+    };
+
+    // Clip all triangles against the plane (Sutherland–Hodgman), the parent triangle of each new triangle is stored for the cell attributes
+    let mut triangles = Vec::with_capacity(mesh.triangles.len());
+    let mut parent_triangles = Vec::with_capacity(mesh.triangles.len());
+    for (tri_idx, tri) in mesh.triangles.iter().enumerate() {
+        let s = tri.map(|v| signed_distances[v]);
+
+        if s.iter().all(|&s| s <= R::zero()) {
+            // Triangles in the plane are replaced by the caps
+            if s.iter().any(|&s| s < R::zero()) {
+                triangles.push(*tri);
+                parent_triangles.push(tri_idx);
+            }
+            continue;
+        } else if s.iter().all(|&s| s >= R::zero()) {
+            continue;
+        }
+
+        let mut polygon = Vec::with_capacity(4);
+        let mut push_vertex = |v: usize| {
+            if polygon.last() != Some(&v) && polygon.first() != Some(&v) {
+                polygon.push(v);
+            }
+        };
+        for i in 0..3 {
+            let (a, b) = (tri[i], tri[(i + 1) % 3]);
+            let (a_inside, b_inside) = (s[i] <= R::zero(), s[(i + 1) % 3] <= R::zero());
+
+            if a_inside != b_inside {
+                push_vertex(edge_point(a, b));
+            }
+            if b_inside {
+                push_vertex(b);
+            }
+        }
+
+        for i in 1..polygon.len().saturating_sub(1) {
+            triangles.push([polygon[0], polygon[i], polygon[i + 1]]);
+            parent_triangles.push(tri_idx);
+        }
+    }
+
+    // Boundary edges in the plane (without an opposite edge) are the boundaries of the caps
+    let plane_edges = triangles
+        .iter()
+        .enumerate()
+        .flat_map(|(tri_idx, tri)| (0..3).map(move |i| ((tri[i], tri[(i + 1) % 3]), tri_idx)))
+        .filter(|((a, b), _)| on_plane[*a] && on_plane[*b])
+        .collect::<MapType<_, _>>();
+
+    // The caps are oriented opposite to their boundary edges
+    let mut cap_edges = MapType::<usize, Vec<(usize, usize)>>::default();
+    for (&(a, b), &tri_idx) in plane_edges.iter() {
+        if !plane_edges.contains_key(&(b, a)) {
+            cap_edges.entry(b).or_default().push((a, tri_idx));
+        }
+    }
+
+    let cap_loops = collect_edge_loops(cap_edges);
+    let num_open_chains = cap_loops.iter().filter(|l| l.is_none()).count();
+    if num_open_chains > 0 {
+        warn!(
+            "Mesh clipping: {} open cut polylines were not capped",
+            num_open_chains
+        );
+    }
+    let cap_loops = cap_loops.into_iter().flatten().collect::<Vec<_>>();
+
+    // Triangulate the caps in a 2D coordinate system of the plane
+    let u = if normal.x.abs() < normal.y.abs() && normal.x.abs() < normal.z.abs() {
+        normal.cross(&Vector3::x())
+    } else if normal.y.abs() < normal.z.abs() {
+        normal.cross(&Vector3::y())
+    } else {
+        normal.cross(&Vector3::z())
+    }
+    .normalize();
+    let v = normal.cross(&u);
+
+    let polygons = cap_loops
+        .iter()
+        .map(|(vertex_loop, _)| {
+            vertex_loop
+                .iter()
+                .map(|&i| (i, Vector2::new(u.dot(&vertices[i]), v.dot(&vertices[i]))))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    for (cap_triangles, loop_idx) in triangulate_planar_polygons(polygons) {
+        let parent_tri = parent_triangles[cap_loops[loop_idx].1];
+        parent_triangles.resize(parent_triangles.len() + cap_triangles.len(), parent_tri);
+        triangles.extend(cap_triangles);
+    }
+
+    let point_attributes = mesh_with_data
+        .point_attributes
+        .iter()
+        .map(|attribute| MeshAttribute {
+            name: attribute.name.clone(),
+            data: interpolate_edge_points(&attribute.data, &edge_points),
+        })
+        .collect();
+    let cell_attributes = mesh_with_data
+        .cell_attributes
+        .iter()
+        .map(|attribute| MeshAttribute {
+            name: attribute.name.clone(),
+            data: attribute.data.select(&parent_triangles),
+        })
+        .collect();
+
+    let num_triangles = triangles.len();
+    let clipped_mesh = MeshWithData {
+        mesh: TriMesh3d {
+            vertices,
+            triangles,
+        },
+        point_attributes,
+        cell_attributes,
+    };
+    clipped_mesh.keep_cells(&(0..num_triangles).collect::<Vec<_>>(), keep_vertices)
+}
+
+/// Chains directed edges (map from start vertex to end vertices with an adjacent triangle) to loops
+///
+/// Returns each closed loop with the adjacent triangle of its first edge, open chains are returned as `None`.
+pub(super) fn collect_edge_loops(
+    mut cap_edges: MapType<usize, Vec<(usize, usize)>>,
+) -> Vec<Option<(Vec<usize>, usize)>> {
+    let mut start_vertices = cap_edges.keys().copied().collect::<Vec<_>>();
+    start_vertices.sort_unstable();
+
+    let mut loops = Vec::new();
+    for start in start_vertices {
+        while let Some((mut next, tri_idx)) = cap_edges.get_mut(&start).and_then(|e| e.pop()) {
+            let mut vertex_loop = vec![start];
+            while next != start {
+                vertex_loop.push(next);
+                match cap_edges.get_mut(&next).and_then(|e| e.pop()) {
+                    Some((v, _)) => next = v,
+                    None => break,
+                }
+            }
+
+            if next == start && vertex_loop.len() >= 3 {
+                loops.push(Some((vertex_loop, tri_idx)));
+            } else {
+                loops.push(None);
+            }
+        }
+    }
+
+    loops
+}
+
+/// Appends the linearly interpolated attribute values of new vertices on edges (integer attributes are taken from the closer vertex)
+fn interpolate_edge_points<R: Real>(
+    data: &AttributeData<R>,
+    edge_points: &[(usize, usize, R)],
+) -> AttributeData<R> {
+    fn append<T: Clone, R: Real>(
+        data: &[T],
+        edge_points: &[(usize, usize, R)],
+        f: impl Fn(&T, &T, R) -> T,
+    ) -> Vec<T> {
+        let mut new_data = data.to_vec();
+        new_data.extend(
+            edge_points
+                .iter()
+                .map(|&(a, b, t)| f(&data[a], &data[b], t)),
+        );
+        new_data
+    }
+
+    fn nearest<T: Clone, R: Real>(a: &T, b: &T, t: R) -> T {
+        if t < R::from_f64(0.5).unwrap() {
+            a.clone()
+        } else {
+            b.clone()
+        }
+    }
+
+    match data {
+        AttributeData::ScalarU64(d) => AttributeData::ScalarU64(append(d, edge_points, nearest)),
+        AttributeData::ScalarI64(d) => AttributeData::ScalarI64(append(d, edge_points, nearest)),
+        AttributeData::ScalarReal(d) => {
+            AttributeData::ScalarReal(append(d, edge_points, |a, b, t| *a + (*b - *a) * t))
+        }
+        AttributeData::Vector2Real(d) => {
+            AttributeData::Vector2Real(append(d, edge_points, |a, b, t| a + (b - a).scale(t)))
+        }
+        AttributeData::Vector3Real(d) => {
+            AttributeData::Vector3Real(append(d, edge_points, |a, b, t| a + (b - a).scale(t)))
+        }
+        AttributeData::Vector4Real(d) => {
+            AttributeData::Vector4Real(append(d, edge_points, |a, b, t| a + (b - a).scale(t)))
+        }
+        AttributeData::Matrix3Real(d) => {
+            AttributeData::Matrix3Real(append(d, edge_points, |a, b, t| a + (b - a).scale(t)))
+        }
+    }
+}
+
+/// Orientation of the point `c` relative to the line from `a` to `b` (positive if `c` is left of the line)
+fn orient_2d<R: Real>(a: &Vector2<R>, b: &Vector2<R>, c: &Vector2<R>) -> R {
+    (b - a).perp(&(c - a))
+}
+
+/// Returns whether the closed segments `a-b` and `c-d` intersect
+fn segments_intersect<R: Real>(
+    a: &Vector2<R>,
+    b: &Vector2<R>,
+    c: &Vector2<R>,
+    d: &Vector2<R>,
+) -> bool {
+    let d1 = orient_2d(c, d, a);
+    let d2 = orient_2d(c, d, b);
+    let d3 = orient_2d(a, b, c);
+    let d4 = orient_2d(a, b, d);
+
+    if d1 == R::zero() && d2 == R::zero() && d3 == R::zero() && d4 == R::zero() {
+        // Collinear segments: check overlap of their projections
+        let dir = b - a;
+        let (ta, tb) = (R::zero(), dir.norm_squared());
+        let (tc, td) = (dir.dot(&(c - a)), dir.dot(&(d - a)));
+        tc.max(td) >= ta && tc.min(td) <= tb
+    } else {
+        d1 * d2 <= R::zero() && d3 * d4 <= R::zero()
+    }
+}
+
+/// Returns whether the point lies inside of the polygon (crossing number test)
+fn point_in_polygon<R: Real>(p: &Vector2<R>, polygon: &[(usize, Vector2<R>)]) -> bool {
+    let mut inside = false;
+    for i in 0..polygon.len() {
+        let a = &polygon[i].1;
+        let b = &polygon[(i + 1) % polygon.len()].1;
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (b.x - a.x) * (p.y - a.y) / (b.y - a.y) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Signed area of the polygon (positive if counter-clockwise)
+fn signed_polygon_area<R: Real>(polygon: &[(usize, Vector2<R>)]) -> R {
+    let mut area = R::zero();
+    for i in 0..polygon.len() {
+        area += polygon[i].1.perp(&polygon[(i + 1) % polygon.len()].1);
+    }
+    area * R::from_f64(0.5).unwrap()
+}
+
+/// Triangulates planar polygons (vertex indices with 2D coordinates) that may be nested into each other
+///
+/// The orientation of the polygons with the largest total area is considered as the orientation of the outer boundaries,
+/// polygons with the opposite orientation are holes of the smallest outer polygon containing them. Holes are connected
+/// to their outer polygon by bridge edges and the resulting simple polygons are triangulated by ear clipping.
+/// The triangles have the same orientation as the outer polygons. Returns the triangles of every outer polygon
+/// together with the index of the outer polygon.
+fn triangulate_planar_polygons<R: Real>(
+    mut polygons: Vec<Vec<(usize, Vector2<R>)>>,
+) -> Vec<(Vec<[usize; 3]>, usize)> {
+    let total_area = polygons
+        .iter()
+        .map(|polygon| signed_polygon_area(polygon))
+        .fold(R::zero(), |sum, area| sum + area);
+
+    // Mirror all polygons if the outer boundaries are oriented clockwise
+    if total_area < R::zero() {
+        polygons.iter_mut().flatten().for_each(|(_, p)| p.y = -p.y);
+    }
+
+    let areas = polygons
+        .iter()
+        .map(|polygon| signed_polygon_area(polygon))
+        .collect::<Vec<_>>();
+
+    let mut holes_of_outer = vec![Vec::new(); polygons.len()];
+    for (hole_idx, hole) in polygons.iter().enumerate() {
+        if areas[hole_idx] >= R::zero() {
+            continue;
+        }
+
+        let containing_outer = (0..polygons.len())
+            .filter(|&i| areas[i] > R::zero() && point_in_polygon(&hole[0].1, &polygons[i]))
+            .min_by(|&i, &j| areas[i].partial_cmp(&areas[j]).unwrap_or(Ordering::Equal));
+        match containing_outer {
+            Some(outer_idx) => holes_of_outer[outer_idx].push(hole_idx),
+            None => warn!("Mesh clipping: Skipping cap hole without surrounding cap polygon"),
+        }
+    }
+
+    let mut triangulations = Vec::new();
+    for (outer_idx, hole_indices) in holes_of_outer.into_iter().enumerate() {
+        if areas[outer_idx] <= R::zero() {
+            continue;
+        }
+
+        let mut holes = hole_indices
+            .into_iter()
+            .map(|hole_idx| polygons[hole_idx].clone())
+            .collect::<Vec<_>>();
+        let max_x = |polygon: &[(usize, Vector2<R>)]| {
+            polygon.iter().fold(polygon[0].1.x, |m, p| m.max(p.1.x))
+        };
+        holes.sort_by(|a, b| max_x(b).partial_cmp(&max_x(a)).unwrap_or(Ordering::Equal));
+
+        let mut polygon = polygons[outer_idx].clone();
+        for i in 0..holes.len() {
+            let (hole, remaining_holes) = holes[i..].split_first().unwrap();
+            bridge_hole(&mut polygon, hole, remaining_holes);
+        }
+
+        triangulations.push((ear_clipping(&polygon), outer_idx));
+    }
+
+    triangulations
+}
+
+/// Connects the hole (clockwise) to the polygon (counter-clockwise) with a bridge edge that does not intersect any other edge
+fn bridge_hole<R: Real>(
+    polygon: &mut Vec<(usize, Vector2<R>)>,
+    hole: &[(usize, Vector2<R>)],
+    remaining_holes: &[Vec<(usize, Vector2<R>)>],
+) {
+    let m = (0..hole.len())
+        .max_by(|&i, &j| {
+            hole[i]
+                .1
+                .x
+                .partial_cmp(&hole[j].1.x)
+                .unwrap_or(Ordering::Equal)
+        })
+        .unwrap();
+    let m_pos = hole[m].1;
+
+    let is_visible = |j: usize| -> bool {
+        let p = &polygon[j].1;
+        let prev = &polygon[(j + polygon.len() - 1) % polygon.len()].1;
+        let next = &polygon[(j + 1) % polygon.len()].1;
+
+        // The bridge has to start into the interior angle of the polygon vertex
+        let in_wedge = if orient_2d(prev, p, next) >= R::zero() {
+            orient_2d(prev, p, &m_pos) > R::zero() && orient_2d(p, next, &m_pos) > R::zero()
+        } else {
+            orient_2d(prev, p, &m_pos) > R::zero() || orient_2d(p, next, &m_pos) > R::zero()
+        };
+        if !in_wedge {
+            return false;
+        }
+
+        std::iter::once(polygon.as_slice())
+            .chain(std::iter::once(hole))
+            .chain(remaining_holes.iter().map(|h| h.as_slice()))
+            .all(|edges| {
+                (0..edges.len()).all(|i| {
+                    let c = &edges[i].1;
+                    let d = &edges[(i + 1) % edges.len()].1;
+                    // Edges incident to the end points of the bridge are ignored
+                    *c == m_pos
+                        || *d == m_pos
+                        || c == p
+                        || d == p
+                        || !segments_intersect(&m_pos, p, c, d)
+                })
+            })
+    };
+
+    let mut candidates = (0..polygon.len()).collect::<Vec<_>>();
+    candidates.sort_by(|&i, &j| {
+        let di = (polygon[i].1 - m_pos).norm_squared();
+        let dj = (polygon[j].1 - m_pos).norm_squared();
+        di.partial_cmp(&dj).unwrap_or(Ordering::Equal)
+    });
+    let j = candidates
+        .iter()
+        .copied()
+        .find(|&j| is_visible(j))
+        .unwrap_or(candidates[0]);
+
+    let mut bridged_polygon = Vec::with_capacity(polygon.len() + hole.len() + 2);
+    bridged_polygon.extend_from_slice(&polygon[..=j]);
+    bridged_polygon.extend_from_slice(&hole[m..]);
+    bridged_polygon.extend_from_slice(&hole[..=m]);
+    bridged_polygon.extend_from_slice(&polygon[j..]);
+    *polygon = bridged_polygon;
+}
+
+/// Triangulates a simple counter-clockwise polygon (which may contain bridge edges) by ear clipping
+fn ear_clipping<R: Real>(polygon: &[(usize, Vector2<R>)]) -> Vec<[usize; 3]> {
+    let mut triangles = Vec::with_capacity(polygon.len().saturating_sub(2));
+    let mut remaining = (0..polygon.len()).collect::<Vec<_>>();
+
+    let mut push_triangle = |a: usize, b: usize, c: usize| {
+        let tri = [polygon[a].0, polygon[b].0, polygon[c].0];
+        if tri[0] != tri[1] && tri[1] != tri[2] && tri[2] != tri[0] {
+            triangles.push(tri);
+        }
+    };
+
+    let mut i = 0;
+    let mut steps_without_ear = 0;
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let (a, b, c) = (
+            remaining[(i + n - 1) % n],
+            remaining[i % n],
+            remaining[(i + 1) % n],
+        );
+        let (pa, pb, pc) = (&polygon[a].1, &polygon[b].1, &polygon[c].1);
+
+        let is_ear = orient_2d(pa, pb, pc) > R::zero()
+            && remaining.iter().all(|&k| {
+                let q = &polygon[k].1;
+                q == pa
+                    || q == pb
+                    || q == pc
+                    || orient_2d(pa, pb, q) < R::zero()
+                    || orient_2d(pb, pc, q) < R::zero()
+                    || orient_2d(pc, pa, q) < R::zero()
+            });
+
+        // Clip the vertex anyway if no ear can be found (degenerate polygon)
+        if is_ear || steps_without_ear > n {
+            push_triangle(a, b, c);
+            remaining.remove(i % n);
+            i %= n - 1;
+            steps_without_ear = 0;
+        } else {
+            i = (i + 1) % n;
+            steps_without_ear += 1;
+        }
+    }
+
+    if remaining.len() == 3 {
+        push_triangle(remaining[0], remaining[1], remaining[2]);
+    }
+
+    triangles
+}
@@ -0,0 +1,274 @@
+//! Quadric error metric (Garland–Heckbert) based mesh simplification
+
+use crate::halfedge_mesh::HalfEdgeTriMesh;
+use crate::mesh::{Mesh3d, MeshWithData, TriMesh3d, TriMesh3dExt};
+use crate::{profile, Real, RealConvert};
+use log::{info, warn};
+use nalgebra::{Matrix4, Vector4};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Parameters for the quadric error metric based mesh simplification (see [`quadric_decimation`])
+#[derive(Clone, Debug)]
+pub struct QuadricDecimationParameters<R: Real> {
+    /// Number of triangles at which the simplification stops (no triangle budget if `None`)
+    pub target_triangle_count: Option<usize>,
+    /// Maximum quadric error (sum of squared distances to the planes of the original triangles) of a collapse (no error threshold if `None`)
+    pub max_error: Option<R>,
+    /// Maximum angle (in radians) by which the normal of a triangle may rotate due to a single collapse
+    pub max_normal_change: R,
+    /// Maximum factor by which the aspect ratio of a triangle may grow due to a single collapse
+    pub max_aspect_ratio_change: R,
+}
+
+impl<R: Real> Default for QuadricDecimationParameters<R> {
+    /// Returns parameters without target triangle count and error threshold (at least one of them has to be set)
+    fn default() -> Self {
+        Self {
+            target_triangle_count: None,
+            max_error: None,
+            max_normal_change: R::frac_pi_4(),
+            max_aspect_ratio_change: R::from_f64(4.0).unwrap(),
+        }
+    }
+}
+
+impl<R: Real> QuadricDecimationParameters<R> {
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns `None` if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<QuadricDecimationParameters<T>> {
+        Some(QuadricDecimationParameters {
+            target_triangle_count: self.target_triangle_count,
+            max_error: match self.max_error {
+                Some(max_error) => Some(max_error.try_convert()?),
+                None => None,
+            },
+            max_normal_change: self.max_normal_change.try_convert()?,
+            max_aspect_ratio_change: self.max_aspect_ratio_change.try_convert()?,
+        })
+    }
+}
+
+/// Simplifies the given mesh using quadric error metric based edge collapses, returns the vertex-vertex connectivity of the simplified mesh
+///
+/// See [`quadric_decimation_he`] for details.
+pub fn quadric_decimation<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    parameters: &QuadricDecimationParameters<R>,
+    keep_vertices: bool,
+) -> Vec<Vec<usize>> {
+    let mut half_edge_mesh = HalfEdgeTriMesh::from(std::mem::take(mesh));
+    quadric_decimation_he(&mut half_edge_mesh, parameters);
+
+    let (new_mesh, vertex_map) = half_edge_mesh.into_parts(keep_vertices);
+    *mesh = new_mesh;
+    vertex_map
+}
+
+/// Simplifies the given mesh using quadric error metric based edge collapses and carries over all point and cell attributes
+///
+/// As every collapse keeps one of the two edge vertices in place, the point attributes are filtered to the remaining
+/// vertices and the cell attributes to the remaining triangles. See [`quadric_decimation_he`] for details.
+pub fn quadric_decimation_with_data<R: Real>(
+    mesh_with_data: &MeshWithData<R, TriMesh3d<R>>,
+    parameters: &QuadricDecimationParameters<R>,
+    keep_vertices: bool,
+) -> MeshWithData<R, TriMesh3d<R>> {
+    let mut half_edge_mesh = HalfEdgeTriMesh::from(mesh_with_data.mesh.clone());
+    quadric_decimation_he(&mut half_edge_mesh, parameters);
+
+    let remaining_triangles = (0..half_edge_mesh.triangles.len())
+        .filter(|&tri_idx| half_edge_mesh.is_valid_triangle(tri_idx))
+        .collect::<Vec<_>>();
+
+    // Triangles of the half-edge mesh are updated in place, so their indices still match the cell attributes
+    let collapsed_mesh = MeshWithData {
+        mesh: TriMesh3d {
+            vertices: std::mem::take(&mut half_edge_mesh.vertices),
+            triangles: std::mem::take(&mut half_edge_mesh.triangles),
+        },
+        point_attributes: mesh_with_data.point_attributes.clone(),
+        cell_attributes: mesh_with_data.cell_attributes.clone(),
+    };
+    collapsed_mesh.keep_cells(&remaining_triangles, keep_vertices)
+}
+
+/// Simplifies the given half-edge mesh using quadric error metric based edge collapses, returns the number of performed collapses
+///
+/// Implements the simplification of Garland and Heckbert: ["Surface Simplification Using Quadric Error Metrics"](https://doi.org/10.1145/258734.258849) (1997)
+/// restricted to half-edge collapses: every edge is collapsed into the one of its two vertices with the smaller error
+/// ("subset placement"), so the positions of all remaining vertices are unchanged.
+/// Collapses are performed in order of increasing error until the target triangle count is reached or the error of the
+/// next collapse exceeds the maximum error. Boundary vertices are never removed, i.e. mesh boundaries are preserved exactly.
+/// Collapses that are topologically illegal, rotate a triangle normal by more than the maximum normal change or increase
+/// the aspect ratio of a triangle by more than the maximum aspect ratio change are skipped.
+pub fn quadric_decimation_he<R: Real>(
+    mesh: &mut HalfEdgeTriMesh<R>,
+    parameters: &QuadricDecimationParameters<R>,
+) -> usize {
+    profile!("quadric_decimation");
+
+    if parameters.target_triangle_count.is_none() && parameters.max_error.is_none() {
+        warn!("Quadric decimation: Neither a target triangle count nor a maximum error was specified, skipping simplification");
+        return 0;
+    }
+
+    let target_triangle_count = parameters.target_triangle_count.unwrap_or(0);
+    let mut num_triangles = (0..mesh.triangles.len())
+        .filter(|&tri_idx| mesh.is_valid_triangle(tri_idx))
+        .count();
+
+    // Error quadrics of all vertices, initialized with the plane quadrics of their incident triangles
+    let mut quadrics = vec![Matrix4::<R>::zeros(); mesh.vertices.len()];
+    for (tri_idx, tri) in mesh.triangles.iter().enumerate() {
+        if !mesh.is_valid_triangle(tri_idx) || mesh.tri_area_ijk::<R>(tri) <= R::default_epsilon() {
+            continue;
+        }
+
+        let n = mesh.tri_normal_ijk::<R>(tri);
+        let plane = Vector4::new(n.x, n.y, n.z, -n.dot(&mesh.vertices[tri[0]]));
+        let plane_quadric = plane * plane.transpose();
+        for &v in tri {
+            quadrics[v] += plane_quadric;
+        }
+    }
+
+    // Version stamps to detect outdated candidates (incremented whenever the quadric of a vertex changes)
+    let mut vertex_versions = vec![0_usize; mesh.vertices.len()];
+    let mut candidates = BinaryHeap::new();
+
+    for v in 0..mesh.vertices.len() {
+        if !mesh.is_valid_vertex(v) {
+            continue;
+        }
+        for he in mesh.outgoing_half_edges(v) {
+            // Every edge is visited from both sides, both collapse directions are added at once
+            if v < he.to {
+                push_collapse_candidates(
+                    &mut candidates,
+                    mesh,
+                    &quadrics,
+                    &vertex_versions,
+                    v,
+                    he.to,
+                );
+            }
+        }
+    }
+
+    let mut num_collapses = 0;
+    while num_triangles > target_triangle_count {
+        let Some(candidate) = candidates.pop() else {
+            break;
+        };
+
+        let (from, to) = (candidate.from, candidate.to);
+        if !mesh.is_valid_vertex(from)
+            || !mesh.is_valid_vertex(to)
+            || candidate.from_version != vertex_versions[from]
+            || candidate.to_version != vertex_versions[to]
+        {
+            continue;
+        }
+
+        if let Some(max_error) = parameters.max_error {
+            if candidate.error > max_error {
+                break;
+            }
+        }
+
+        let Some(he) = mesh.half_edge(from, to) else {
+            continue;
+        };
+        if mesh.is_boundary_edge(he)
+            || mesh.half_edge_collapse_max_normal_change(he) > parameters.max_normal_change
+            || mesh.half_edge_collapse_max_aspect_ratio_change(he)
+                > parameters.max_aspect_ratio_change
+        {
+            continue;
+        }
+        if mesh.try_half_edge_collapse(he).is_err() {
+            continue;
+        }
+
+        num_collapses += 1;
+        num_triangles = num_triangles.saturating_sub(2);
+
+        // The remaining vertex inherits the quadric of the removed vertex
+        let from_quadric = quadrics[from];
+        quadrics[to] += from_quadric;
+        vertex_versions[to] += 1;
+
+        if mesh.is_valid_vertex(to) {
+            for n in mesh.vertex_one_ring(to).collect::<Vec<_>>() {
+                push_collapse_candidates(&mut candidates, mesh, &quadrics, &vertex_versions, to, n);
+            }
+        }
+    }
+
+    info!(
+        "Quadric decimation: Performed {} edge collapses, {} triangles remaining",
+        num_collapses, num_triangles
+    );
+
+    num_collapses
+}
+
+/// Adds both collapse directions of the edge between the given vertices to the queue (except for collapses that remove a boundary vertex)
+fn push_collapse_candidates<R: Real>(
+    candidates: &mut BinaryHeap<CollapseCandidate<R>>,
+    mesh: &HalfEdgeTriMesh<R>,
+    quadrics: &[Matrix4<R>],
+    vertex_versions: &[usize],
+    v0: usize,
+    v1: usize,
+) {
+    for (from, to) in [(v0, v1), (v1, v0)] {
+        if mesh.is_boundary_vertex(from) {
+            continue;
+        }
+
+        let p = &mesh.vertices[to];
+        let p = Vector4::new(p.x, p.y, p.z, R::one());
+        let error = p.dot(&((quadrics[from] + quadrics[to]) * p));
+
+        candidates.push(CollapseCandidate {
+            error,
+            from,
+            to,
+            from_version: vertex_versions[from],
+            to_version: vertex_versions[to],
+        });
+    }
+}
+
+/// Half-edge collapse candidate of the quadric decimation, ordered such that a [`BinaryHeap`] pops the candidate with the smallest error first
+struct CollapseCandidate<R: Real> {
+    error: R,
+    from: usize,
+    to: usize,
+    from_version: usize,
+    to_version: usize,
+}
+
+impl<R: Real> Ord for CollapseCandidate<R> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .error
+            .partial_cmp(&self.error)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl<R: Real> PartialOrd for CollapseCandidate<R> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<R: Real> PartialEq for CollapseCandidate<R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<R: Real> Eq for CollapseCandidate<R> {}
@@ -0,0 +1,411 @@
+//! Isotropic remeshing with optional projection onto the input mesh or the SPH level set
+
+use crate::halfedge_mesh::{HalfEdge, HalfEdgeTriMesh};
+use crate::mesh::{TriMesh3d, TriMesh3dExt};
+use crate::sph_interpolation::SphInterpolator;
+use crate::{profile, Real, RealConvert};
+use anyhow::anyhow;
+use log::info;
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use rstar::{PointDistance, RTree, RTreeObject, AABB};
+
+/// Surface onto which the vertices are projected in every iteration of the isotropic remeshing
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum RemeshingProjection {
+    /// No projection, the surface is only approximately preserved by the tangential relaxation
+    None,
+    /// Projection of every vertex onto the closest point of the mesh before remeshing
+    #[default]
+    InputMesh,
+    /// Projection onto the iso-surface of the SPH level set of the particles (see [`SphInterpolator::project_to_level_set_inplace`])
+    LevelSet,
+}
+
+/// Parameters for the isotropic remeshing (see [`isotropic_remeshing`])
+#[derive(Clone, Debug)]
+pub struct IsotropicRemeshingParameters<R: Real> {
+    /// Target length of all edges of the remeshed surface
+    pub target_edge_length: R,
+    /// Number of remeshing iterations (each consisting of splits, collapses, flips, tangential relaxation and projection)
+    pub iterations: usize,
+    /// Surface onto which the vertices are projected in every iteration
+    pub projection: RemeshingProjection,
+    /// Maximum angle (in radians) by which the normal of a triangle may rotate due to a single collapse or flip
+    pub max_normal_change: R,
+}
+
+impl<R: Real> IsotropicRemeshingParameters<R> {
+    /// Returns parameters for the given target edge length with five iterations and projection onto the input mesh
+    pub fn new(target_edge_length: R) -> Self {
+        Self {
+            target_edge_length,
+            iterations: 5,
+            projection: RemeshingProjection::default(),
+            max_normal_change: R::frac_pi_4(),
+        }
+    }
+
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns `None` if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<IsotropicRemeshingParameters<T>> {
+        Some(IsotropicRemeshingParameters {
+            target_edge_length: self.target_edge_length.try_convert()?,
+            iterations: self.iterations,
+            projection: self.projection,
+            max_normal_change: self.max_normal_change.try_convert()?,
+        })
+    }
+}
+
+/// Isotropically remeshes the given mesh, returns the vertex-vertex connectivity of the remeshed mesh
+///
+/// See [`isotropic_remeshing_he`] for details.
+pub fn isotropic_remeshing<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    parameters: &IsotropicRemeshingParameters<R>,
+    level_set: Option<(&SphInterpolator<R>, R)>,
+) -> Result<Vec<Vec<usize>>, anyhow::Error> {
+    let mut half_edge_mesh = HalfEdgeTriMesh::from(std::mem::take(mesh));
+    let result = isotropic_remeshing_he(&mut half_edge_mesh, parameters, level_set);
+
+    let (new_mesh, vertex_map) = half_edge_mesh.into_parts(false);
+    *mesh = new_mesh;
+    result.map(|_| vertex_map)
+}
+
+/// Isotropically remeshes the given half-edge mesh towards the target edge length
+///
+/// Implements the incremental remeshing of Botsch and Kobbelt: ["A Remeshing Approach to Multiresolution Modeling"](https://doi.org/10.1145/1057432.1057457) (2004).
+/// Every iteration
+///  1. splits all edges longer than `4/3` of the target edge length at their midpoint,
+///  2. collapses all edges shorter than `4/5` of the target edge length (unless this creates edges longer than `4/3` of the target edge length),
+///  3. flips edges if this brings the valences of the affected vertices closer to six,
+///  4. moves all vertices towards the centroid of their one-ring in their tangent plane ("tangential relaxation"),
+///  5. projects all vertices back onto the surface given by the configured [`RemeshingProjection`].
+///
+/// For the projection onto the level set, the interpolator of the particles and the iso-value of the level set (i.e. the
+/// iso-surface threshold of the reconstruction) have to be provided. Collapses and flips that rotate a triangle normal by
+/// more than the maximum normal change are skipped. Boundary edges are neither split nor collapsed and boundary vertices
+/// are never moved, i.e. mesh boundaries are preserved exactly.
+pub fn isotropic_remeshing_he<R: Real>(
+    mesh: &mut HalfEdgeTriMesh<R>,
+    parameters: &IsotropicRemeshingParameters<R>,
+    level_set: Option<(&SphInterpolator<R>, R)>,
+) -> Result<(), anyhow::Error> {
+    profile!("isotropic_remeshing");
+
+    if parameters.projection == RemeshingProjection::LevelSet && level_set.is_none() {
+        return Err(anyhow!(
+            "Isotropic remeshing: Projection onto the level set requires an SPH interpolator"
+        ));
+    }
+
+    let high = parameters.target_edge_length * R::from_f64(4.0 / 3.0).unwrap();
+    let low = parameters.target_edge_length * R::from_f64(4.0 / 5.0).unwrap();
+
+    // Copy of the input surface for the closest point projection
+    let input_surface = (parameters.projection == RemeshingProjection::InputMesh).then(|| {
+        let triangles = (0..mesh.triangles.len())
+            .filter(|&tri_idx| mesh.is_valid_triangle(tri_idx))
+            .map(|tri_idx| mesh.triangles[tri_idx])
+            .filter(|tri| mesh.tri_area_ijk::<R>(tri) > R::default_epsilon())
+            .map(|tri| ProjectionTriangle {
+                vertices: tri.map(|i| mesh.vertices[i]),
+            })
+            .collect();
+        RTree::bulk_load(triangles)
+    });
+
+    let (mut num_splits, mut num_collapses, mut num_flips) = (0, 0, 0);
+    for _ in 0..parameters.iterations {
+        num_splits += split_long_edges(mesh, high);
+        num_collapses += collapse_short_edges(mesh, low, high, parameters.max_normal_change);
+        num_flips += equalize_valences(mesh, parameters.max_normal_change);
+
+        // Only interior vertices are moved by the relaxation and projection
+        let movable = (0..mesh.vertices.len())
+            .map(|v| {
+                mesh.is_valid_vertex(v)
+                    && mesh.vertex_one_ring_len(v) > 0
+                    && !mesh.is_boundary_vertex(v)
+            })
+            .collect::<Vec<_>>();
+
+        tangential_relaxation(mesh, &movable);
+
+        match parameters.projection {
+            RemeshingProjection::None => {}
+            RemeshingProjection::InputMesh => {
+                let input_surface = input_surface.as_ref().expect("input surface is required");
+                mesh.vertices
+                    .par_iter_mut()
+                    .zip(movable.par_iter())
+                    .filter(|(_, movable)| **movable)
+                    .for_each(|(v, _)| {
+                        if let Some(tri) = input_surface.nearest_neighbor(&[v.x, v.y, v.z]) {
+                            *v = closest_point_on_triangle(v, &tri.vertices);
+                        }
+                    });
+            }
+            RemeshingProjection::LevelSet => {
+                let (interpolator, iso_value) = level_set.expect("level set is required");
+                let movable_vertices = (0..mesh.vertices.len())
+                    .filter(|&v| movable[v])
+                    .collect::<Vec<_>>();
+                let mut points = movable_vertices
+                    .iter()
+                    .map(|&v| mesh.vertices[v])
+                    .collect::<Vec<_>>();
+                interpolator.project_to_level_set_inplace(&mut points, iso_value, 10);
+                for (&v, p) in movable_vertices.iter().zip(points) {
+                    mesh.vertices[v] = p;
+                }
+            }
+        }
+    }
+
+    info!(
+        "Isotropic remeshing: Performed {} edge splits, {} edge collapses and {} edge flips in {} iterations",
+        num_splits, num_collapses, num_flips, parameters.iterations
+    );
+
+    Ok(())
+}
+
+/// Returns the indices of all valid interior edges (represented by the half-edge with the smaller index) of the mesh
+fn interior_edges<R: Real>(mesh: &HalfEdgeTriMesh<R>) -> Vec<usize> {
+    mesh.half_edges
+        .iter()
+        .filter(|he| {
+            he.idx < he.opposite && mesh.is_valid_half_edge(he.idx) && !mesh.is_boundary_edge(**he)
+        })
+        .map(|he| he.idx)
+        .collect()
+}
+
+/// Returns the squared length of the given half-edge
+fn half_edge_length_squared<R: Real>(mesh: &HalfEdgeTriMesh<R>, half_edge: HalfEdge) -> R {
+    let from = mesh.opposite(half_edge).to;
+    (mesh.vertices[half_edge.to] - mesh.vertices[from]).norm_squared()
+}
+
+/// Splits all interior edges longer than the given length at their midpoint, returns the number of splits
+fn split_long_edges<R: Real>(mesh: &mut HalfEdgeTriMesh<R>, max_length: R) -> usize {
+    let max_length_squared = max_length * max_length;
+
+    let mut num_splits = 0;
+    for he_idx in interior_edges(mesh) {
+        // Splits do not change other edges, so the edges only have to be checked once
+        let he = mesh.half_edges[he_idx];
+        if half_edge_length_squared(mesh, he) > max_length_squared
+            && mesh.try_edge_split(he).is_ok()
+        {
+            num_splits += 1;
+        }
+    }
+
+    num_splits
+}
+
+/// Collapses all interior edges shorter than `min_length` unless this creates edges longer than `max_length`, returns the number of collapses
+fn collapse_short_edges<R: Real>(
+    mesh: &mut HalfEdgeTriMesh<R>,
+    min_length: R,
+    max_length: R,
+    max_normal_change: R,
+) -> usize {
+    let min_length_squared = min_length * min_length;
+    let max_length_squared = max_length * max_length;
+
+    let mut num_collapses = 0;
+    for he_idx in interior_edges(mesh) {
+        if !mesh.is_valid_half_edge(he_idx) {
+            continue;
+        }
+
+        let he = mesh.half_edges[he_idx];
+        if mesh.is_boundary_edge(he) || half_edge_length_squared(mesh, he) >= min_length_squared {
+            continue;
+        }
+
+        // Try to collapse the edge in both directions (the vertex at the start of the half-edge is removed)
+        for he in [he, mesh.opposite(he)] {
+            let from = mesh.opposite(he).to;
+            let to = he.to;
+            if mesh.is_boundary_vertex(from) {
+                continue;
+            }
+
+            let creates_long_edge = mesh.vertex_one_ring(from).any(|n| {
+                (mesh.vertices[n] - mesh.vertices[to]).norm_squared() > max_length_squared
+            });
+            if creates_long_edge
+                || mesh.half_edge_collapse_max_normal_change(he) > max_normal_change
+            {
+                continue;
+            }
+
+            if mesh.try_half_edge_collapse(he).is_ok() {
+                num_collapses += 1;
+                break;
+            }
+        }
+    }
+
+    num_collapses
+}
+
+/// Flips all interior edges where this reduces the deviation of the valences of the affected vertices from the optimal valence, returns the number of flips
+///
+/// The optimal valence is six for interior vertices and four for boundary vertices.
+fn equalize_valences<R: Real>(mesh: &mut HalfEdgeTriMesh<R>, max_normal_change: R) -> usize {
+    let target_valence = |mesh: &HalfEdgeTriMesh<R>, v: usize| -> isize {
+        if mesh.is_boundary_vertex(v) {
+            4
+        } else {
+            6
+        }
+    };
+
+    let mut num_flips = 0;
+    for he_idx in interior_edges(mesh) {
+        let he = mesh.half_edges[he_idx];
+        let he_o = mesh.opposite(he);
+
+        // Edge (a, b) is flipped to edge (c, d)
+        let a = he_o.to;
+        let b = he.to;
+        let c = mesh.next(he).to;
+        let d = mesh.next(he_o).to;
+
+        let mut deviation_before = 0;
+        let mut deviation_after = 0;
+        for (v, valence_change) in [(a, -1), (b, -1), (c, 1), (d, 1)] {
+            let valence = mesh.vertex_one_ring_len(v) as isize;
+            let target = target_valence(mesh, v);
+            deviation_before += (valence - target).pow(2);
+            deviation_after += (valence + valence_change - target).pow(2);
+        }
+
+        if deviation_after < deviation_before
+            && mesh.is_flip_ok(he).is_ok()
+            && mesh.edge_flip_max_normal_change(he) <= max_normal_change
+        {
+            mesh.edge_flip(he);
+            num_flips += 1;
+        }
+    }
+
+    num_flips
+}
+
+/// Moves all movable vertices towards the centroid of their one-ring, projected into their tangent plane
+fn tangential_relaxation<R: Real>(mesh: &mut HalfEdgeTriMesh<R>, movable: &[bool]) {
+    let new_positions = (0..mesh.vertices.len())
+        .into_par_iter()
+        .map(|v| {
+            let p = mesh.vertices[v];
+            if !movable[v] {
+                return p;
+            }
+
+            let mut centroid = Vector3::zeros();
+            for n in mesh.vertex_one_ring(v) {
+                centroid += mesh.vertices[n];
+            }
+            centroid /= R::from_usize(mesh.vertex_one_ring_len(v)).unwrap();
+
+            // Area weighted vertex normal
+            let mut normal = Vector3::zeros();
+            for face in mesh.incident_faces(v) {
+                let [v0, v1, v2] = mesh.triangles[face].map(|i| mesh.vertices[i]);
+                normal += (v1 - v0).cross(&(v2 - v0));
+            }
+
+            let displacement = centroid - p;
+            match normal.try_normalize(R::default_epsilon()) {
+                Some(normal) => p + displacement - normal.scale(normal.dot(&displacement)),
+                None => p,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    mesh.vertices = new_positions;
+}
+
+/// Triangle of the input mesh stored in the R-tree for the closest point projection of the isotropic remeshing
+struct ProjectionTriangle<R: Real> {
+    vertices: [Vector3<R>; 3],
+}
+
+impl<R: Real> RTreeObject for ProjectionTriangle<R> {
+    type Envelope = AABB<[R; 3]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        let points = self.vertices.map(|v| [v.x, v.y, v.z]);
+        AABB::from_points(points.iter())
+    }
+}
+
+impl<R: Real> PointDistance for ProjectionTriangle<R> {
+    fn distance_2(&self, point: &[R; 3]) -> R {
+        let point = Vector3::from(*point);
+        (closest_point_on_triangle(&point, &self.vertices) - point).norm_squared()
+    }
+}
+
+/// Returns the point of the given (non-degenerate) triangle that is closest to the given point
+///
+/// See Ericson: "Real-Time Collision Detection" (2004), Section 5.1.5.
+fn closest_point_on_triangle<R: Real>(p: &Vector3<R>, [a, b, c]: &[Vector3<R>; 3]) -> Vector3<R> {
+    let zero = R::zero();
+
+    let ab = b - a;
+    let ac = c - a;
+
+    // Vertex region of a
+    let ap = p - a;
+    let d1 = ab.dot(&ap);
+    let d2 = ac.dot(&ap);
+    if d1 <= zero && d2 <= zero {
+        return *a;
+    }
+
+    // Vertex region of b
+    let bp = p - b;
+    let d3 = ab.dot(&bp);
+    let d4 = ac.dot(&bp);
+    if d3 >= zero && d4 <= d3 {
+        return *b;
+    }
+
+    // Edge region of ab
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= zero && d1 >= zero && d3 <= zero {
+        return a + ab.scale(d1 / (d1 - d3));
+    }
+
+    // Vertex region of c
+    let cp = p - c;
+    let d5 = ab.dot(&cp);
+    let d6 = ac.dot(&cp);
+    if d6 >= zero && d5 <= d6 {
+        return *c;
+    }
+
+    // Edge region of ac
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= zero && d2 >= zero && d6 <= zero {
+        return a + ac.scale(d2 / (d2 - d6));
+    }
+
+    // Edge region of bc
+    let va = d3 * d6 - d5 * d4;
+    if va <= zero && (d4 - d3) >= zero && (d5 - d6) >= zero {
+        return b + (c - b).scale((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    // Face region
+    let denom = R::one() / (va + vb + vc);
+    a + ab.scale(vb * denom) + ac.scale(vc * denom)
+}
@@ -0,0 +1,583 @@
+//! Mesh repair (duplicate, degenerate and non-manifold elements and hole filling)
+
+use super::clipping::collect_edge_loops;
+use crate::mesh::{Mesh3d, MeshWithData, TriMesh3d};
+use crate::utils::UnionFind;
+use crate::{profile, MapType, Real, RealConvert, SetType};
+use log::warn;
+use nalgebra::Vector3;
+use std::cmp::Ordering;
+
+/// Parameters for the mesh repair ([`repair_mesh`])
+#[derive(Clone, Debug)]
+pub struct MeshRepairParameters<R: Real> {
+    /// Triangles with a height over their longest edge below this tolerance (relative to the mean edge length of the mesh) are considered degenerate
+    pub degenerate_tolerance: R,
+    /// Maximum number of boundary edges of holes that are filled (all holes are filled if `None`)
+    pub max_hole_edges: Option<usize>,
+}
+
+impl<R: Real> Default for MeshRepairParameters<R> {
+    fn default() -> Self {
+        Self {
+            degenerate_tolerance: R::from_f64(1e-6).unwrap(),
+            max_hole_edges: None,
+        }
+    }
+}
+
+impl<R: Real> MeshRepairParameters<R> {
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns `None` if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<MeshRepairParameters<T>> {
+        Some(MeshRepairParameters {
+            degenerate_tolerance: self.degenerate_tolerance.try_convert()?,
+            max_hole_edges: self.max_hole_edges,
+        })
+    }
+}
+
+/// Summary of the modifications performed by [`repair_mesh`] and the problems remaining after the repair
+#[derive(Clone, Debug, Default)]
+pub struct MeshRepairReport {
+    /// Number of removed duplicate triangles
+    pub removed_duplicate_triangles: usize,
+    /// Number of removed degenerate triangles (including triangles removed by welding the vertices of short edges)
+    pub removed_degenerate_triangles: usize,
+    /// Number of triangles removed because they were incident to non-manifold edges
+    pub removed_non_manifold_edge_triangles: usize,
+    /// Number of vertices that were added by splitting non-manifold vertices
+    pub split_non_manifold_vertices: usize,
+    /// Number of filled holes
+    pub filled_holes: usize,
+    /// Number of holes that could not be filled
+    pub unfilled_holes: usize,
+    /// Number of boundary edges remaining after the repair
+    pub remaining_boundary_edges: usize,
+    /// Number of non-manifold edges remaining after the repair
+    pub remaining_non_manifold_edges: usize,
+    /// Number of non-manifold vertices remaining after the repair
+    pub remaining_non_manifold_vertices: usize,
+}
+
+impl MeshRepairReport {
+    /// Returns whether the repaired mesh is closed and manifold
+    pub fn is_closed_manifold(&self) -> bool {
+        self.remaining_boundary_edges == 0
+            && self.remaining_non_manifold_edges == 0
+            && self.remaining_non_manifold_vertices == 0
+    }
+}
+
+/// Repairs the mesh to obtain a closed manifold mesh if possible
+///
+/// Performs the following steps (see the individual functions for details):
+///  1. removal of duplicate triangles ([`remove_duplicate_triangles`]),
+///  2. removal of degenerate triangles ([`remove_degenerate_triangles`]),
+///  3. removal of triangles incident to non-manifold edges ([`remove_non_manifold_edges`]),
+///  4. splitting of non-manifold vertices ([`split_non_manifold_vertices`]),
+///  5. filling of all holes ([`fill_holes`]).
+///
+/// Point and cell attributes are carried over. The returned report contains the number of performed modifications
+/// and the problems remaining after the repair, e.g. holes that could not be filled without creating non-manifold edges.
+pub fn repair_mesh<R: Real>(
+    mesh_with_data: &MeshWithData<R, TriMesh3d<R>>,
+    parameters: &MeshRepairParameters<R>,
+    keep_vertices: bool,
+) -> (MeshWithData<R, TriMesh3d<R>>, MeshRepairReport) {
+    profile!("repair_mesh");
+
+    let mut mesh_with_data = mesh_with_data.clone();
+    let mut report = MeshRepairReport::default();
+
+    let mesh = &mesh_with_data.mesh;
+    let mean_edge_length = if mesh.triangles.is_empty() {
+        R::zero()
+    } else {
+        mesh.triangles
+            .iter()
+            .flat_map(|tri| (0..3).map(move |i| (tri[i], tri[(i + 1) % 3])))
+            .map(|(a, b)| (mesh.vertices[a] - mesh.vertices[b]).norm())
+            .fold(R::zero(), |sum, l| sum + l)
+            / R::from_usize(3 * mesh.triangles.len()).unwrap()
+    };
+
+    report.removed_duplicate_triangles = remove_duplicate_triangles(&mut mesh_with_data);
+    report.removed_degenerate_triangles = remove_degenerate_triangles(
+        &mut mesh_with_data,
+        parameters.degenerate_tolerance * mean_edge_length,
+    );
+    // Welding vertices of short edges can result in new duplicates
+    report.removed_duplicate_triangles += remove_duplicate_triangles(&mut mesh_with_data);
+    report.removed_non_manifold_edge_triangles = remove_non_manifold_edges(&mut mesh_with_data);
+    report.split_non_manifold_vertices = split_non_manifold_vertices(&mut mesh_with_data);
+    (report.filled_holes, report.unfilled_holes) =
+        fill_holes(&mut mesh_with_data, parameters.max_hole_edges);
+
+    let num_triangles = mesh_with_data.mesh.triangles.len();
+    let mesh_with_data =
+        mesh_with_data.keep_cells(&(0..num_triangles).collect::<Vec<_>>(), keep_vertices);
+
+    let manifold_info = mesh_with_data.mesh.compute_manifold_information();
+    report.remaining_boundary_edges = manifold_info.boundary_edges.len();
+    report.remaining_non_manifold_edges = manifold_info.non_manifold_edges.len();
+    report.remaining_non_manifold_vertices = manifold_info.non_manifold_vertices.len();
+
+    (mesh_with_data, report)
+}
+
+/// Removes all triangles that are not marked to be kept and the corresponding cell attributes, returns the number of removed triangles
+fn retain_triangles<R: Real>(
+    mesh_with_data: &mut MeshWithData<R, TriMesh3d<R>>,
+    keep: &[bool],
+) -> usize {
+    let kept_triangles = (0..keep.len()).filter(|&i| keep[i]).collect::<Vec<_>>();
+    let num_removed = keep.len() - kept_triangles.len();
+    if num_removed > 0 {
+        let triangles = &mesh_with_data.mesh.triangles;
+        mesh_with_data.mesh.triangles = kept_triangles.iter().map(|&i| triangles[i]).collect();
+        for attribute in mesh_with_data.cell_attributes.iter_mut() {
+            attribute.data = attribute.data.select(&kept_triangles);
+        }
+    }
+    num_removed
+}
+
+/// Removes triangles that refer to the same set of vertices, returns the number of removed triangles
+///
+/// Of multiple triangles with the same orientation only the first one is kept. Pairs of triangles with opposite
+/// orientations enclose no volume and are both removed.
+pub fn remove_duplicate_triangles<R: Real>(
+    mesh_with_data: &mut MeshWithData<R, TriMesh3d<R>>,
+) -> usize {
+    profile!("remove_duplicate_triangles");
+
+    let triangles = &mesh_with_data.mesh.triangles;
+    let same_orientation = |a: &[usize; 3], b: &[usize; 3]| {
+        (0..3).any(|i| a[0] == b[i] && a[1] == b[(i + 1) % 3] && a[2] == b[(i + 2) % 3])
+    };
+
+    let mut keep = vec![true; triangles.len()];
+    let mut first_occurrences = MapType::default();
+    for (tri_idx, tri) in triangles.iter().enumerate() {
+        let mut key = *tri;
+        key.sort_unstable();
+
+        if let Some(&first_idx) = first_occurrences.get(&key) {
+            keep[tri_idx] = false;
+            if !same_orientation(tri, &triangles[first_idx]) {
+                keep[first_idx] = false;
+                first_occurrences.remove(&key);
+            }
+        } else {
+            first_occurrences.insert(key, tri_idx);
+        }
+    }
+
+    retain_triangles(mesh_with_data, &keep)
+}
+
+/// Removes degenerate triangles with a height over their longest edge below the given (absolute) tolerance, returns the number of removed triangles
+///
+/// Triangles with repeated vertex indices are removed. For degenerate triangles with an edge shorter than the tolerance
+/// ("needles"), the vertices of the edge are welded, which removes the triangle and its neighbor over the edge.
+/// Other degenerate triangles ("caps") are removed together with their neighbor over the longest edge, the resulting
+/// holes can be closed using [`fill_holes`] which replaces them with a non-degenerate triangulation.
+pub fn remove_degenerate_triangles<R: Real>(
+    mesh_with_data: &mut MeshWithData<R, TriMesh3d<R>>,
+    tolerance: R,
+) -> usize {
+    profile!("remove_degenerate_triangles");
+
+    let mesh = &mut mesh_with_data.mesh;
+    let mut welded_to = UnionFind::new(mesh.vertices.len());
+    let mut cap_edges = MapType::default();
+    let mut keep = vec![true; mesh.triangles.len()];
+
+    for (tri_idx, tri) in mesh.triangles.iter().enumerate() {
+        if tri[0] == tri[1] || tri[1] == tri[2] || tri[2] == tri[0] {
+            continue;
+        }
+
+        let edge_lengths =
+            [0, 1, 2].map(|i| (mesh.vertices[tri[(i + 1) % 3]] - mesh.vertices[tri[i]]).norm());
+        let area = (mesh.vertices[tri[1]] - mesh.vertices[tri[0]])
+            .cross(&(mesh.vertices[tri[2]] - mesh.vertices[tri[0]]))
+            .norm();
+        let longest = (0..3)
+            .max_by(|&i, &j| {
+                edge_lengths[i]
+                    .partial_cmp(&edge_lengths[j])
+                    .unwrap_or(Ordering::Equal)
+            })
+            .unwrap();
+        let shortest = (0..3)
+            .min_by(|&i, &j| {
+                edge_lengths[i]
+                    .partial_cmp(&edge_lengths[j])
+                    .unwrap_or(Ordering::Equal)
+            })
+            .unwrap();
+
+        // The area is twice the triangle area, so this is the height over the longest edge
+        if area > tolerance * edge_lengths[longest] {
+            continue;
+        }
+
+        if edge_lengths[shortest] <= tolerance {
+            welded_to.union(tri[shortest], tri[(shortest + 1) % 3]);
+        } else {
+            let (a, b) = (tri[longest], tri[(longest + 1) % 3]);
+            cap_edges.insert((a.min(b), a.max(b)), tri_idx);
+        }
+    }
+
+    // Remove caps and their neighbors over the longest edge
+    if !cap_edges.is_empty() {
+        for (tri_idx, tri) in mesh.triangles.iter().enumerate() {
+            for i in 0..3 {
+                let (a, b) = (tri[i], tri[(i + 1) % 3]);
+                if cap_edges.contains_key(&(a.min(b), a.max(b))) {
+                    keep[tri_idx] = false;
+                }
+            }
+        }
+    }
+
+    // Apply the vertex welding and remove the collapsed triangles
+    for (tri, keep) in mesh.triangles.iter_mut().zip(keep.iter_mut()) {
+        for v in tri.iter_mut() {
+            *v = welded_to.find(*v);
+        }
+        if tri[0] == tri[1] || tri[1] == tri[2] || tri[2] == tri[0] {
+            *keep = false;
+        }
+    }
+
+    retain_triangles(mesh_with_data, &keep)
+}
+
+/// Removes all triangles incident to non-manifold edges (edges with more than two incident triangles), returns the number of removed triangles
+///
+/// The resulting holes can be closed using [`fill_holes`] (after splitting non-manifold vertices).
+pub fn remove_non_manifold_edges<R: Real>(
+    mesh_with_data: &mut MeshWithData<R, TriMesh3d<R>>,
+) -> usize {
+    profile!("remove_non_manifold_edges");
+
+    let non_manifold_edges = mesh_with_data
+        .mesh
+        .compute_edge_information()
+        .iter()
+        .filter(|e| e.incident_faces > 2)
+        .map(|e| e.edge_sorted)
+        .collect::<SetType<_>>();
+    if non_manifold_edges.is_empty() {
+        return 0;
+    }
+
+    let keep = mesh_with_data
+        .mesh
+        .triangles
+        .iter()
+        .map(|tri| {
+            (0..3).all(|i| {
+                let (a, b) = (tri[i], tri[(i + 1) % 3]);
+                !non_manifold_edges.contains(&[a.min(b), a.max(b)])
+            })
+        })
+        .collect::<Vec<_>>();
+
+    retain_triangles(mesh_with_data, &keep)
+}
+
+/// Splits all non-manifold vertices (vertices with more than one fan of triangles), returns the number of added vertices
+///
+/// Every additional fan of a vertex gets its own copy of the vertex (including its point attributes).
+pub fn split_non_manifold_vertices<R: Real>(
+    mesh_with_data: &mut MeshWithData<R, TriMesh3d<R>>,
+) -> usize {
+    profile!("split_non_manifold_vertices");
+
+    let mesh = &mut mesh_with_data.mesh;
+    let num_vertices = mesh.vertices.len();
+    let vertex_triangles = mesh.vertex_cell_connectivity();
+
+    let mut split_sources = Vec::new();
+    for (v, triangles) in vertex_triangles.iter().enumerate() {
+        if triangles.len() < 2 {
+            continue;
+        }
+
+        // Triangles sharing an edge incident to the vertex belong to the same fan
+        let mut fans = UnionFind::new(triangles.len());
+        let mut edge_triangle = MapType::default();
+        for (local_idx, &tri_idx) in triangles.iter().enumerate() {
+            for &w in mesh.triangles[tri_idx].iter().filter(|&&w| w != v) {
+                if let Some(&other_idx) = edge_triangle.get(&w) {
+                    fans.union(other_idx, local_idx);
+                } else {
+                    edge_triangle.insert(w, local_idx);
+                }
+            }
+        }
+
+        // The fan of the first triangle keeps the original vertex
+        let mut fan_vertices = MapType::default();
+        fan_vertices.insert(fans.find(0), v);
+        for (local_idx, &tri_idx) in triangles.iter().enumerate() {
+            let fan = fans.find(local_idx);
+            let fan_vertex = *fan_vertices.entry(fan).or_insert_with(|| {
+                mesh.vertices.push(mesh.vertices[v]);
+                split_sources.push(v);
+                mesh.vertices.len() - 1
+            });
+            mesh.triangles[tri_idx]
+                .iter_mut()
+                .filter(|w| **w == v)
+                .for_each(|w| *w = fan_vertex);
+        }
+    }
+
+    if !split_sources.is_empty() {
+        let source_vertices = (0..num_vertices)
+            .chain(split_sources.iter().copied())
+            .collect::<Vec<_>>();
+        for attribute in mesh_with_data.point_attributes.iter_mut() {
+            attribute.data = attribute.data.select(&source_vertices);
+        }
+    }
+
+    split_sources.len()
+}
+
+/// Fills all holes of the mesh, returns the number of filled holes and the number of holes that could not be filled
+///
+/// The boundary loops are triangulated without adding vertices. Holes with up to 200 edges are triangulated such that
+/// the maximum dihedral angle between adjacent triangles and then the area are minimized (see Liepa:
+/// ["Filling Holes in Meshes"](https://doi.org/10.2312/SGP/SGP03/200-206), 2003), larger holes are filled by
+/// successively clipping the vertex with the smallest angle. Triangulations that would duplicate existing edges are
+/// rejected, i.e. such holes and holes larger than `max_hole_edges` remain open. The new triangles get the cell
+/// attributes of a triangle adjacent to the hole. The mesh has to be free of non-manifold edges and vertices.
+pub fn fill_holes<R: Real>(
+    mesh_with_data: &mut MeshWithData<R, TriMesh3d<R>>,
+    max_hole_edges: Option<usize>,
+) -> (usize, usize) {
+    profile!("fill_holes");
+
+    let mesh = &mut mesh_with_data.mesh;
+    let mut edges = mesh
+        .triangles
+        .iter()
+        .flat_map(|tri| (0..3).map(move |i| (tri[i], tri[(i + 1) % 3])))
+        .collect::<SetType<_>>();
+
+    // Boundary edges (without opposite edge) with the opposite vertex of their triangle, the holes are oriented opposite to them
+    let mut boundary_edges = MapType::default();
+    let mut hole_edges = MapType::<usize, Vec<(usize, usize)>>::default();
+    for (tri_idx, tri) in mesh.triangles.iter().enumerate() {
+        for i in 0..3 {
+            let (a, b) = (tri[i], tri[(i + 1) % 3]);
+            if !edges.contains(&(b, a)) {
+                boundary_edges.insert((a, b), tri[(i + 2) % 3]);
+                hole_edges.entry(b).or_default().push((a, tri_idx));
+            }
+        }
+    }
+
+    let mut num_filled = 0;
+    let mut num_unfilled = 0;
+    let mut parent_triangles = (0..mesh.triangles.len()).collect::<Vec<_>>();
+    for hole in collect_edge_loops(hole_edges) {
+        let Some((hole, tri_idx)) = hole else {
+            warn!("Hole filling: Skipping open boundary (mesh is not manifold)");
+            num_unfilled += 1;
+            continue;
+        };
+
+        if max_hole_edges.is_some_and(|max_hole_edges| hole.len() > max_hole_edges) {
+            num_unfilled += 1;
+            continue;
+        }
+
+        let opposite_vertices = (0..hole.len())
+            .map(|i| boundary_edges[&(hole[(i + 1) % hole.len()], hole[i])])
+            .collect::<Vec<_>>();
+        let has_edge = |a: usize, b: usize| edges.contains(&(a, b)) || edges.contains(&(b, a));
+        let hole_triangles = if hole.len() <= 200 {
+            triangulate_hole(&mesh.vertices, &hole, &opposite_vertices, has_edge)
+        } else {
+            triangulate_hole_greedy(&mesh.vertices, &hole, has_edge)
+        };
+
+        match hole_triangles {
+            Some(hole_triangles) => {
+                for tri in hole_triangles.iter() {
+                    edges.extend((0..3).map(|i| (tri[i], tri[(i + 1) % 3])));
+                }
+                parent_triangles.resize(parent_triangles.len() + hole_triangles.len(), tri_idx);
+                mesh.triangles.extend(hole_triangles);
+                num_filled += 1;
+            }
+            None => {
+                warn!(
+                    "Hole filling: Hole with {} edges cannot be filled without creating non-manifold edges",
+                    hole.len()
+                );
+                num_unfilled += 1;
+            }
+        }
+    }
+
+    if num_filled > 0 {
+        for attribute in mesh_with_data.cell_attributes.iter_mut() {
+            attribute.data = attribute.data.select(&parent_triangles);
+        }
+    }
+
+    (num_filled, num_unfilled)
+}
+
+/// Normal of the triangle `a, b, c` (not normalized, zero for degenerate triangles)
+fn triangle_normal<R: Real>(a: &Vector3<R>, b: &Vector3<R>, c: &Vector3<R>) -> Vector3<R> {
+    (b - a).cross(&(c - a))
+}
+
+/// Angle between two (not normalized) normals, zero if one of them is degenerate
+fn normal_angle<R: Real>(n1: &Vector3<R>, n2: &Vector3<R>) -> R {
+    let denom = n1.norm() * n2.norm();
+    if denom > R::zero() {
+        (n1.dot(n2) / denom).clamp(-R::one(), R::one()).acos()
+    } else {
+        R::zero()
+    }
+}
+
+/// Triangulates a hole minimizing the maximum dihedral angle and then the total area by dynamic programming
+///
+/// The hole is given by its vertices in the orientation of the new triangles and the opposite vertices of the mesh
+/// triangles adjacent to each hole edge `hole[i] -> hole[i + 1]`. Returns `None` if every triangulation would contain
+/// an existing edge of the mesh.
+fn triangulate_hole<R: Real>(
+    vertices: &[Vector3<R>],
+    hole: &[usize],
+    opposite_vertices: &[usize],
+    has_edge: impl Fn(usize, usize) -> bool,
+) -> Option<Vec<[usize; 3]>> {
+    let n = hole.len();
+    let p = |i: usize| &vertices[hole[i]];
+
+    // Normals of the mesh triangles adjacent to the hole edges
+    let boundary_normals = (0..n)
+        .map(|i| triangle_normal(p((i + 1) % n), p(i), &vertices[opposite_vertices[i]]))
+        .collect::<Vec<_>>();
+
+    // Weight (maximum dihedral angle, area) and optimal middle vertex of the triangulation of the polygon i..=k
+    let mut weights = vec![None; n * n];
+    let mut middle = vec![0; n * n];
+    for i in 0..n - 1 {
+        weights[i * n + i + 1] = Some((R::zero(), R::zero()));
+    }
+
+    for gap in 2..n {
+        for i in 0..n - gap {
+            let k = i + gap;
+            // The diagonal i-k must not be an existing edge (except for the boundary edge of the last polygon)
+            if (k != n - 1 || i != 0) && has_edge(hole[i], hole[k]) {
+                continue;
+            }
+
+            let mut best: Option<(R, R)> = None;
+            for m in i + 1..k {
+                let (Some(w_im), Some(w_mk)) = (weights[i * n + m], weights[m * n + k]) else {
+                    continue;
+                };
+
+                let normal = triangle_normal(p(i), p(m), p(k));
+                let adjacent_normal = |a: usize, b: usize| {
+                    if b == a + 1 {
+                        boundary_normals[a]
+                    } else {
+                        triangle_normal(p(a), p(middle[a * n + b]), p(b))
+                    }
+                };
+
+                let mut max_angle = w_im.0.max(w_mk.0);
+                max_angle = max_angle.max(normal_angle(&normal, &adjacent_normal(i, m)));
+                max_angle = max_angle.max(normal_angle(&normal, &adjacent_normal(m, k)));
+                if i == 0 && k == n - 1 {
+                    max_angle = max_angle.max(normal_angle(&normal, &boundary_normals[n - 1]));
+                }
+                let area = w_im.1 + w_mk.1 + normal.norm() * R::from_f64(0.5).unwrap();
+
+                let is_better = match best {
+                    Some(best) => (max_angle, area) < best,
+                    None => true,
+                };
+                if is_better {
+                    best = Some((max_angle, area));
+                    middle[i * n + k] = m;
+                }
+            }
+            weights[i * n + k] = best;
+        }
+    }
+
+    weights[n - 1]?;
+
+    let mut triangles = Vec::with_capacity(n - 2);
+    let mut stack = vec![(0, n - 1)];
+    while let Some((i, k)) = stack.pop() {
+        if k - i < 2 {
+            continue;
+        }
+        let m = middle[i * n + k];
+        triangles.push([hole[i], hole[m], hole[k]]);
+        stack.push((i, m));
+        stack.push((m, k));
+    }
+
+    Some(triangles)
+}
+
+/// Triangulates a hole by successively clipping the vertex with the smallest angle, returns `None` if the hole cannot be closed without duplicating existing edges
+fn triangulate_hole_greedy<R: Real>(
+    vertices: &[Vector3<R>],
+    hole: &[usize],
+    has_edge: impl Fn(usize, usize) -> bool,
+) -> Option<Vec<[usize; 3]>> {
+    let mut remaining = hole.to_vec();
+    let mut new_edges = SetType::default();
+    let mut triangles = Vec::with_capacity(hole.len() - 2);
+
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let (i, _) = (0..n)
+            .filter_map(|i| {
+                let (a, b, c) = (
+                    remaining[(i + n - 1) % n],
+                    remaining[i],
+                    remaining[(i + 1) % n],
+                );
+                if has_edge(a, c) || new_edges.contains(&(a.min(c), a.max(c))) {
+                    return None;
+                }
+                Some((
+                    i,
+                    normal_angle(&(vertices[a] - vertices[b]), &(vertices[c] - vertices[b])),
+                ))
+            })
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))?;
+
+        let (a, b, c) = (
+            remaining[(i + n - 1) % n],
+            remaining[i],
+            remaining[(i + 1) % n],
+        );
+        triangles.push([a, b, c]);
+        new_edges.insert((a.min(c), a.max(c)));
+        remaining.remove(i);
+    }
+
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    Some(triangles)
+}
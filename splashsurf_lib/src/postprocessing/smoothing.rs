@@ -0,0 +1,394 @@
+//! Mesh smoothing schemes (Laplacian, cotangent, Taubin and HC smoothing) and volume correction
+
+use crate::mesh::{Mesh3d, TriMesh3d};
+use crate::{profile, Real, RealConvert};
+use log::warn;
+use nalgebra::Vector3;
+use rayon::prelude::*;
+
+/// Mesh smoothing schemes supported by [`par_smoothing_inplace`]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum MeshSmoothingMethod<R: Real> {
+    /// Uniform ("umbrella") Laplacian smoothing that moves each vertex to the mean position of its neighbors (see [`par_laplacian_smoothing_inplace`])
+    ///
+    /// Shrinks the mesh and thin features under strong smoothing.
+    #[default]
+    Laplacian,
+    /// Laplacian smoothing with cotangent weights that moves vertices mostly normal to the surface (see [`par_cotangent_laplacian_smoothing_inplace`])
+    ///
+    /// Avoids the tangential drift of the uniform Laplacian on irregular meshes but still shrinks the mesh.
+    Cotangent,
+    /// Taubin λ/μ smoothing alternating a shrinking (`lambda > 0`) and an inflating (`mu < -lambda`) Laplacian step (see [`par_taubin_smoothing_inplace`])
+    Taubin {
+        /// Factor of the shrinking step
+        lambda: R,
+        /// Factor of the inflating step
+        mu: R,
+    },
+    /// Humphrey's Classes (HC) smoothing that pushes the Laplacian result back towards the original and previous positions (see [`par_hc_smoothing_inplace`])
+    Hc {
+        /// Weight of the original positions (in contrast to the previous positions) for the push-back
+        alpha: R,
+        /// Weight of the vertex itself (in contrast to its neighbors) for the push-back
+        beta: R,
+    },
+}
+
+impl<R: Real> MeshSmoothingMethod<R> {
+    /// Returns Taubin smoothing with the parameters `lambda = 0.5` and `mu = -0.53`
+    pub fn taubin() -> Self {
+        Self::Taubin {
+            lambda: R::from_f64(0.5).unwrap(),
+            mu: R::from_f64(-0.53).unwrap(),
+        }
+    }
+
+    /// Returns HC smoothing with the parameters `alpha = 0.1` and `beta = 0.6`
+    pub fn hc() -> Self {
+        Self::Hc {
+            alpha: R::from_f64(0.1).unwrap(),
+            beta: R::from_f64(0.6).unwrap(),
+        }
+    }
+
+    /// Tries to convert the method from one [Real] type to another [Real] type, returns `None` if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<MeshSmoothingMethod<T>> {
+        Some(match *self {
+            Self::Laplacian => MeshSmoothingMethod::Laplacian,
+            Self::Cotangent => MeshSmoothingMethod::Cotangent,
+            Self::Taubin { lambda, mu } => MeshSmoothingMethod::Taubin {
+                lambda: lambda.try_convert()?,
+                mu: mu.try_convert()?,
+            },
+            Self::Hc { alpha, beta } => MeshSmoothingMethod::Hc {
+                alpha: alpha.try_convert()?,
+                beta: beta.try_convert()?,
+            },
+        })
+    }
+}
+
+/// Smooths the mesh with the given method and feature weights
+///
+/// The feature weights (e.g. from [`compute_smoothing_weights`](super::compute_smoothing_weights)) scale the displacement of each vertex per iteration,
+/// a weight of zero keeps the vertex in place. The Laplacian and cotangent methods are applied with `beta = 1`.
+pub fn par_smoothing_inplace<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    vertex_connectivity: &[Vec<usize>],
+    iterations: usize,
+    method: MeshSmoothingMethod<R>,
+    weights: &[R],
+) {
+    match method {
+        MeshSmoothingMethod::Laplacian => par_laplacian_smoothing_inplace(
+            mesh,
+            vertex_connectivity,
+            iterations,
+            R::one(),
+            weights,
+        ),
+        MeshSmoothingMethod::Cotangent => {
+            let vertex_cell_connectivity = mesh.vertex_cell_connectivity();
+            par_cotangent_laplacian_smoothing_inplace(
+                mesh,
+                &vertex_cell_connectivity,
+                iterations,
+                R::one(),
+                weights,
+            )
+        }
+        MeshSmoothingMethod::Taubin { lambda, mu } => {
+            par_taubin_smoothing_inplace(mesh, vertex_connectivity, iterations, lambda, mu, weights)
+        }
+        MeshSmoothingMethod::Hc { alpha, beta } => {
+            par_hc_smoothing_inplace(mesh, vertex_connectivity, iterations, alpha, beta, weights)
+        }
+    }
+}
+
+/// Laplacian Smoothing with feature weights
+///
+/// Move each vertex towards the mean position of its neighbors.
+/// Factor beta in \[0;1] proportional to amount of smoothing (for beta=1 each vertex is placed at the mean position).
+/// Additionally, feature weights can be specified to apply a varying amount of smoothing over the mesh.
+pub fn par_laplacian_smoothing_inplace<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    vertex_connectivity: &[Vec<usize>],
+    iterations: usize,
+    beta: R,
+    weights: &[R],
+) {
+    profile!("laplacian_smoothing");
+
+    let mut vertex_buffer = mesh.vertices.clone();
+
+    for _ in 0..iterations {
+        profile!("laplacian_smoothing iter");
+
+        std::mem::swap(&mut vertex_buffer, &mut mesh.vertices);
+        par_laplacian_step(
+            &mut mesh.vertices,
+            &vertex_buffer,
+            vertex_connectivity,
+            beta,
+            weights,
+        );
+    }
+}
+
+/// Taubin λ/μ smoothing with feature weights
+///
+/// Every iteration consists of a Laplacian step with the positive factor `lambda` (shrinking) followed by a Laplacian
+/// step with the negative factor `mu` (inflating), the factors have to satisfy `0 < lambda < -mu`. This acts as a
+/// low-pass filter that removes noise without shrinking the mesh.
+/// See Taubin: ["A Signal Processing Approach to Fair Surface Design"](https://doi.org/10.1145/218380.218473) (1995).
+/// The feature weights scale the amount of smoothing per vertex like in [`par_laplacian_smoothing_inplace`].
+pub fn par_taubin_smoothing_inplace<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    vertex_connectivity: &[Vec<usize>],
+    iterations: usize,
+    lambda: R,
+    mu: R,
+    weights: &[R],
+) {
+    profile!("taubin_smoothing");
+
+    let mut vertex_buffer = mesh.vertices.clone();
+
+    for _ in 0..iterations {
+        profile!("taubin_smoothing iter");
+
+        for factor in [lambda, mu] {
+            std::mem::swap(&mut vertex_buffer, &mut mesh.vertices);
+            par_laplacian_step(
+                &mut mesh.vertices,
+                &vertex_buffer,
+                vertex_connectivity,
+                factor,
+                weights,
+            );
+        }
+    }
+}
+
+/// Humphrey's Classes (HC) smoothing with feature weights
+///
+/// Every iteration performs a uniform Laplacian step and afterwards pushes the vertices back by the weighted difference to
+/// the original (weight `alpha`) and previous (weight `1 - alpha`) positions, averaged between the vertex itself (weight `beta`)
+/// and its neighbors (weight `1 - beta`). This largely prevents the shrinkage of the mesh.
+/// See Vollmer, Mencl and Müller: ["Improved Laplacian Smoothing of Noisy Surface Meshes"](https://doi.org/10.1111/1467-8659.00334) (1999).
+/// The feature weights scale the resulting displacement of each vertex per iteration.
+pub fn par_hc_smoothing_inplace<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    vertex_connectivity: &[Vec<usize>],
+    iterations: usize,
+    alpha: R,
+    beta: R,
+    weights: &[R],
+) {
+    profile!("hc_smoothing");
+
+    let original_vertices = mesh.vertices.clone();
+    let mut previous_vertices = mesh.vertices.clone();
+    let mut differences = vec![Vector3::zeros(); mesh.vertices.len()];
+
+    for _ in 0..iterations {
+        profile!("hc_smoothing iter");
+
+        std::mem::swap(&mut previous_vertices, &mut mesh.vertices);
+        par_laplacian_step(
+            &mut mesh.vertices,
+            &previous_vertices,
+            vertex_connectivity,
+            R::one(),
+            &[],
+        );
+
+        // Difference of the smoothed positions to the blend of original and previous positions
+        differences.par_iter_mut().enumerate().for_each(|(i, b_i)| {
+            *b_i = mesh.vertices[i]
+                - (original_vertices[i].scale(alpha)
+                    + previous_vertices[i].scale(R::one() - alpha));
+        });
+
+        mesh.vertices
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(i, vertex_i)| {
+                let neighbors = &vertex_connectivity[i];
+                let mut neighbor_mean = Vector3::zeros();
+                for &j in neighbors {
+                    neighbor_mean += differences[j];
+                }
+                if !neighbors.is_empty() {
+                    neighbor_mean /= R::from_usize(neighbors.len()).unwrap();
+                }
+
+                let hc_vertex =
+                    *vertex_i - (differences[i].scale(beta) + neighbor_mean.scale(R::one() - beta));
+                let w = weights.get(i).copied().unwrap_or(R::one());
+                *vertex_i = previous_vertices[i] + (hc_vertex - previous_vertices[i]).scale(w);
+            });
+    }
+}
+
+/// Laplacian smoothing with cotangent weights and feature weights
+///
+/// Moves each vertex towards the cotangent weighted mean position of its neighbors, i.e. the weight of the edge `(i,j)` is
+/// `cot(α_ij) + cot(β_ij)` with the two angles opposite to the edge. Negative cotangents of obtuse angles are clamped
+/// to zero to keep the weighted mean inside of the one-ring. The weights are recomputed in every iteration.
+/// The factor beta and the feature weights are used like in [`par_laplacian_smoothing_inplace`], the vertex-cell
+/// connectivity can be obtained with [`Mesh3d::vertex_cell_connectivity`].
+pub fn par_cotangent_laplacian_smoothing_inplace<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    vertex_cell_connectivity: &[Vec<usize>],
+    iterations: usize,
+    beta: R,
+    weights: &[R],
+) {
+    profile!("cotangent_laplacian_smoothing");
+
+    let mut vertex_buffer = mesh.vertices.clone();
+
+    for _ in 0..iterations {
+        profile!("cotangent_laplacian_smoothing iter");
+
+        std::mem::swap(&mut vertex_buffer, &mut mesh.vertices);
+
+        let triangles = &mesh.triangles;
+        mesh.vertices
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(i, vertex_i)| {
+                let beta_eff = beta * weights.get(i).copied().unwrap_or(R::one());
+                let p_i = vertex_buffer[i];
+
+                // Accumulate the cotangent weighted neighbor positions over all incident triangles
+                let mut weighted_sum = Vector3::zeros();
+                let mut weight_sum = R::zero();
+                for &tri_idx in &vertex_cell_connectivity[i] {
+                    let tri = triangles[tri_idx];
+                    let local = tri.iter().position(|&v| v == i).unwrap();
+                    let j = tri[(local + 1) % 3];
+                    let k = tri[(local + 2) % 3];
+                    let (p_j, p_k) = (vertex_buffer[j], vertex_buffer[k]);
+
+                    // The angle at k is opposite to the edge (i,j) and vice versa
+                    let cot_k = cotangent(&(p_i - p_k), &(p_j - p_k)).max(R::zero());
+                    let cot_j = cotangent(&(p_i - p_j), &(p_k - p_j)).max(R::zero());
+
+                    weighted_sum += p_j.scale(cot_k) + p_k.scale(cot_j);
+                    weight_sum += cot_k + cot_j;
+                }
+
+                if weight_sum > R::default_epsilon() {
+                    let weighted_mean = weighted_sum.unscale(weight_sum);
+                    *vertex_i = p_i.scale(R::one() - beta_eff) + weighted_mean.scale(beta_eff);
+                } else {
+                    *vertex_i = p_i;
+                }
+            });
+    }
+}
+
+/// Cotangent of the angle between the two given vectors
+fn cotangent<R: Real>(a: &Vector3<R>, b: &Vector3<R>) -> R {
+    let sin_norm = a.cross(b).norm();
+    if sin_norm > R::default_epsilon() {
+        a.dot(b) / sin_norm
+    } else {
+        R::zero()
+    }
+}
+
+/// Single uniform Laplacian step from `vertices_in` to `vertices_out` with per-vertex factors `beta * weights[i]` (weights of one if `weights` is empty)
+fn par_laplacian_step<R: Real>(
+    vertices_out: &mut [Vector3<R>],
+    vertices_in: &[Vector3<R>],
+    vertex_connectivity: &[Vec<usize>],
+    beta: R,
+    weights: &[R],
+) {
+    vertices_out
+        .par_iter_mut()
+        .enumerate()
+        .for_each(|(i, vertex_i)| {
+            let beta_eff = beta * weights.get(i).copied().unwrap_or(R::one());
+
+            // Compute mean position of neighboring vertices
+            let mut vertex_sum = Vector3::zeros();
+            for j in vertex_connectivity[i].iter() {
+                vertex_sum += vertices_in[j.clone()];
+            }
+            if vertex_connectivity[i].len() > 0 {
+                let n = R::from_usize(vertex_connectivity[i].len()).unwrap();
+                vertex_sum /= n;
+            } else {
+                vertex_sum = vertices_in[i];
+            }
+
+            *vertex_i = vertices_in[i].scale(R::one() - beta_eff) + vertex_sum.scale(beta_eff);
+        });
+}
+
+/// Restores the given volume of a closed mesh by displacing its vertices along their normals, returns the remaining relative volume error
+///
+/// Intended to compensate the volume loss of mesh smoothing: the target volume should be the [`TriMesh3d::signed_volume`]
+/// of the mesh before smoothing. Each vertex `i` is displaced by `s * weights[i]` along its area weighted vertex normal,
+/// i.e. with the smoothing weights the correction is concentrated on the regions that were smoothed the most (weights
+/// of one are used if `weights` is empty). The common displacement `s` is determined by Newton iterations on the
+/// signed volume until the relative volume error is below `tolerance` or `max_iterations` were performed.
+pub fn par_volume_correction_inplace<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    target_volume: R,
+    weights: &[R],
+    tolerance: R,
+    max_iterations: usize,
+) -> R {
+    profile!("volume_correction");
+
+    if target_volume.abs() <= R::default_epsilon() {
+        warn!("Volume correction: Target volume is zero, skipping correction");
+        return R::zero();
+    }
+
+    let relative_error = |volume: R| ((volume - target_volume) / target_volume).abs();
+    let six = R::from_i32(6).unwrap();
+
+    let mut volume = mesh.signed_volume();
+    for _ in 0..max_iterations {
+        if relative_error(volume) <= tolerance {
+            break;
+        }
+
+        // The gradient of the volume w.r.t. a vertex is 1/6 of its (unnormalized) area weighted normal direction
+        let normal_directions = mesh.vertex_normal_directions();
+        let volume_derivative = normal_directions
+            .par_iter()
+            .enumerate()
+            .map(|(i, n)| weights.get(i).copied().unwrap_or(R::one()) * n.norm() / six)
+            .reduce(R::zero, |a, b| a + b);
+
+        if volume_derivative <= R::default_epsilon() {
+            warn!("Volume correction: Vertices cannot be displaced (all weights or normals are zero), skipping correction");
+            break;
+        }
+
+        let displacement = (target_volume - volume) / volume_derivative;
+        mesh.vertices
+            .par_iter_mut()
+            .zip(normal_directions.par_iter())
+            .enumerate()
+            .for_each(|(i, (vertex_i, n))| {
+                let norm = n.norm();
+                if norm > R::default_epsilon() {
+                    let w = weights.get(i).copied().unwrap_or(R::one());
+                    *vertex_i += n.scale(displacement * w / norm);
+                }
+            });
+
+        volume = mesh.signed_volume();
+    }
+
+    relative_error(volume)
+}
@@ -0,0 +1,251 @@
+//! Loop subdivision of triangle meshes with attribute interpolation
+
+use crate::mesh::{AttributeData, MeshAttribute, MeshWithData, TriMesh3d};
+use crate::sph_interpolation::SphInterpolator;
+use crate::{profile, MapType, Real};
+use anyhow::anyhow;
+use nalgebra::{Matrix3, Vector2, Vector3, Vector4};
+use rayon::prelude::*;
+use std::cmp::Ordering;
+
+/// Parameters for the Loop subdivision (see [`loop_subdivision`])
+#[derive(Clone, Debug)]
+pub struct LoopSubdivisionParameters {
+    /// Number of subdivision steps (every step quadruples the number of triangles)
+    pub iterations: usize,
+    /// Whether to project all vertices onto the SPH level set of the particles after every subdivision step
+    pub level_set_projection: bool,
+}
+
+impl Default for LoopSubdivisionParameters {
+    /// Returns parameters for a single subdivision step without projection
+    fn default() -> Self {
+        Self {
+            iterations: 1,
+            level_set_projection: false,
+        }
+    }
+}
+
+/// Refines the mesh using Loop subdivision, point attributes are interpolated with the subdivision stencils
+///
+/// Implements the subdivision scheme of Loop: ["Smooth Subdivision Surfaces Based on Triangles"](https://www.microsoft.com/en-us/research/publication/smooth-subdivision-surfaces-based-on-triangles/) (1987)
+/// with the vertex weights of Warren. Every step splits each triangle into four triangles by inserting a vertex on every
+/// edge. Boundary edges and non-manifold edges are treated as creases (the curve is subdivided as cubic B-spline),
+/// vertices with more than two incident crease edges are kept fixed.
+///
+/// Real point attributes are interpolated with the same weights as the vertex positions, integer attributes are taken
+/// from the vertex with the largest weight (e.g. the closer vertex of an edge). Note that interpolated normals are not
+/// normalized. Cell attributes of a triangle are copied to its four child triangles.
+///
+/// If the level set projection is enabled, all vertices are projected onto the iso-surface of the SPH level set after
+/// every step, which requires the interpolator of the particles and the iso-value of the level set (i.e. the iso-surface
+/// threshold of the reconstruction). This counteracts the shrinkage of the subdivision and adds surface details of the
+/// particle data.
+pub fn loop_subdivision<R: Real>(
+    mesh_with_data: &MeshWithData<R, TriMesh3d<R>>,
+    parameters: &LoopSubdivisionParameters,
+    level_set: Option<(&SphInterpolator<R>, R)>,
+) -> Result<MeshWithData<R, TriMesh3d<R>>, anyhow::Error> {
+    profile!("loop_subdivision");
+
+    if parameters.level_set_projection && level_set.is_none() {
+        return Err(anyhow!(
+            "Loop subdivision: Projection onto the level set requires an SPH interpolator"
+        ));
+    }
+
+    let mut mesh_with_data = mesh_with_data.clone();
+    for _ in 0..parameters.iterations {
+        let (triangles, stencils) = loop_subdivision_stencils(&mesh_with_data.mesh);
+
+        let mut vertices = stencils.apply(&mesh_with_data.mesh.vertices, |data, stencil| {
+            stencil
+                .iter()
+                .fold(Vector3::zeros(), |sum, &(i, w)| sum + data[i].scale(w))
+        });
+        if parameters.level_set_projection {
+            if let Some((interpolator, iso_value)) = level_set {
+                interpolator.project_to_level_set_inplace(&mut vertices, iso_value, 10);
+            }
+        }
+
+        let parent_triangles = (0..mesh_with_data.mesh.triangles.len())
+            .flat_map(|tri_idx| [tri_idx; 4])
+            .collect::<Vec<_>>();
+
+        mesh_with_data = MeshWithData {
+            mesh: TriMesh3d {
+                vertices,
+                triangles,
+            },
+            point_attributes: mesh_with_data
+                .point_attributes
+                .iter()
+                .map(|attribute| MeshAttribute {
+                    name: attribute.name.clone(),
+                    data: stencils.apply_to_attribute(&attribute.data),
+                })
+                .collect(),
+            cell_attributes: mesh_with_data
+                .cell_attributes
+                .iter()
+                .map(|attribute| MeshAttribute {
+                    name: attribute.name.clone(),
+                    data: attribute.data.select(&parent_triangles),
+                })
+                .collect(),
+        };
+    }
+
+    Ok(mesh_with_data)
+}
+
+/// Weights of the vertices of a mesh contributing to every vertex of the subdivided mesh
+struct SubdivisionStencils<R: Real> {
+    /// Range of the entries of each new vertex
+    offsets: Vec<usize>,
+    /// Source vertex and weight of all stencils
+    entries: Vec<(usize, R)>,
+}
+
+impl<R: Real> SubdivisionStencils<R> {
+    fn stencil(&self, i: usize) -> &[(usize, R)] {
+        &self.entries[self.offsets[i]..self.offsets[i + 1]]
+    }
+
+    /// Evaluates the given function with the stencil of every new vertex
+    fn apply<T: Send + Sync>(
+        &self,
+        data: &[T],
+        f: impl Fn(&[T], &[(usize, R)]) -> T + Sync,
+    ) -> Vec<T> {
+        (0..self.offsets.len() - 1)
+            .into_par_iter()
+            .map(|i| f(data, self.stencil(i)))
+            .collect()
+    }
+
+    /// Interpolates the attribute values of all new vertices (values of integer attributes are taken from the vertex with the largest weight)
+    fn apply_to_attribute(&self, data: &AttributeData<R>) -> AttributeData<R> {
+        fn largest_weight<T: Clone, R: Real>(data: &[T], stencil: &[(usize, R)]) -> T {
+            let (i, _) = stencil
+                .iter()
+                .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+                .unwrap();
+            data[*i].clone()
+        }
+
+        match data {
+            AttributeData::ScalarU64(d) => AttributeData::ScalarU64(self.apply(d, largest_weight)),
+            AttributeData::ScalarI64(d) => AttributeData::ScalarI64(self.apply(d, largest_weight)),
+            AttributeData::ScalarReal(d) => AttributeData::ScalarReal(self.apply(d, |d, s| {
+                s.iter().fold(R::zero(), |sum, &(i, w)| sum + d[i] * w)
+            })),
+            AttributeData::Vector2Real(d) => AttributeData::Vector2Real(self.apply(d, |d, s| {
+                s.iter()
+                    .fold(Vector2::zeros(), |sum, &(i, w)| sum + d[i].scale(w))
+            })),
+            AttributeData::Vector3Real(d) => AttributeData::Vector3Real(self.apply(d, |d, s| {
+                s.iter()
+                    .fold(Vector3::zeros(), |sum, &(i, w)| sum + d[i].scale(w))
+            })),
+            AttributeData::Vector4Real(d) => AttributeData::Vector4Real(self.apply(d, |d, s| {
+                s.iter()
+                    .fold(Vector4::zeros(), |sum, &(i, w)| sum + d[i].scale(w))
+            })),
+            AttributeData::Matrix3Real(d) => AttributeData::Matrix3Real(self.apply(d, |d, s| {
+                s.iter()
+                    .fold(Matrix3::zeros(), |sum, &(i, w)| sum + d[i].scale(w))
+            })),
+        }
+    }
+}
+
+/// Computes the triangles and the vertex stencils of a single Loop subdivision step
+///
+/// The new mesh contains all original vertices (at the same indices) followed by one vertex per edge. The four child
+/// triangles of every triangle are stored consecutively.
+fn loop_subdivision_stencils<R: Real>(
+    mesh: &TriMesh3d<R>,
+) -> (Vec<[usize; 3]>, SubdivisionStencils<R>) {
+    let num_vertices = mesh.vertices.len();
+
+    // New vertex index and opposite vertices of every edge
+    let mut edges = MapType::default();
+    let mut edge_list = Vec::new();
+    let mut triangles = Vec::with_capacity(4 * mesh.triangles.len());
+    for tri in mesh.triangles.iter() {
+        let edge_vertices = [0, 1, 2].map(|i| {
+            let (a, b) = (tri[i], tri[(i + 1) % 3]);
+            let edge_idx = *edges.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                edge_list.push(((a.min(b), a.max(b)), Vec::with_capacity(2)));
+                edge_list.len() - 1
+            });
+            edge_list[edge_idx].1.push(tri[(i + 2) % 3]);
+            num_vertices + edge_idx
+        });
+
+        let [ab, bc, ca] = edge_vertices;
+        triangles.push([tri[0], ab, ca]);
+        triangles.push([tri[1], bc, ab]);
+        triangles.push([tri[2], ca, bc]);
+        triangles.push([ab, bc, ca]);
+    }
+
+    // Neighbors of every vertex and neighbors over crease edges (boundary or non-manifold edges)
+    let mut neighbors = vec![Vec::new(); num_vertices];
+    let mut crease_neighbors = vec![Vec::new(); num_vertices];
+    for ((a, b), opposite) in edge_list.iter() {
+        neighbors[*a].push(*b);
+        neighbors[*b].push(*a);
+        if opposite.len() != 2 {
+            crease_neighbors[*a].push(*b);
+            crease_neighbors[*b].push(*a);
+        }
+    }
+
+    let from_f64 = |v: f64| R::from_f64(v).unwrap();
+    let mut offsets = Vec::with_capacity(num_vertices + edge_list.len() + 1);
+    let mut entries = Vec::new();
+    offsets.push(0);
+
+    // Stencils of the original vertices
+    for v in 0..num_vertices {
+        match crease_neighbors[v].as_slice() {
+            [] if !neighbors[v].is_empty() => {
+                let n = neighbors[v].len();
+                let beta = if n == 3 {
+                    from_f64(3.0 / 16.0)
+                } else {
+                    from_f64(3.0 / 8.0) / R::from_usize(n).unwrap()
+                };
+                entries.push((v, R::one() - beta * R::from_usize(n).unwrap()));
+                entries.extend(neighbors[v].iter().map(|&w| (w, beta)));
+            }
+            [b0, b1] => {
+                entries.push((v, from_f64(0.75)));
+                entries.push((*b0, from_f64(0.125)));
+                entries.push((*b1, from_f64(0.125)));
+            }
+            _ => entries.push((v, R::one())),
+        }
+        offsets.push(entries.len());
+    }
+
+    // Stencils of the new edge vertices
+    for ((a, b), opposite) in edge_list.iter() {
+        if let [c, d] = opposite.as_slice() {
+            entries.push((*a, from_f64(0.375)));
+            entries.push((*b, from_f64(0.375)));
+            entries.push((*c, from_f64(0.125)));
+            entries.push((*d, from_f64(0.125)));
+        } else {
+            entries.push((*a, from_f64(0.5)));
+            entries.push((*b, from_f64(0.5)));
+        }
+        offsets.push(entries.len());
+    }
+
+    (triangles, SubdivisionStencils { offsets, entries })
+}
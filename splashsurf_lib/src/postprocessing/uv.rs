@@ -0,0 +1,166 @@
+//! Generation of texture coordinates for the mesh vertices
+
+use crate::{Real, RealConvert};
+use nalgebra::{Vector2, Vector3};
+use rayon::prelude::*;
+
+/// Strategies to generate texture coordinates for the mesh vertices (see [`triplanar_uv_coordinates`] and [`advected_uv_coordinates`])
+#[derive(Clone, Debug)]
+pub enum UvGeneration<R: Real> {
+    /// Triplanar projection of the vertex positions onto the coordinate plane given by the dominant axis of the vertex normal
+    Triplanar {
+        /// Number of texture repetitions per unit length
+        scale: R,
+    },
+    /// Triplanar projection of the vertex positions advected backwards with the particle velocity, blended between two layers that are periodically reset
+    Advected(AdvectedUvParameters<R>),
+}
+
+impl<R: Real> UvGeneration<R> {
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns `None` if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<UvGeneration<T>> {
+        Some(match self {
+            UvGeneration::Triplanar { scale } => UvGeneration::Triplanar {
+                scale: scale.try_convert()?,
+            },
+            UvGeneration::Advected(parameters) => UvGeneration::Advected(parameters.try_convert()?),
+        })
+    }
+}
+
+/// Parameters for the generation of advected texture coordinates (see [`advected_uv_coordinates`])
+#[derive(Clone, Debug)]
+pub struct AdvectedUvParameters<R: Real> {
+    /// Number of texture repetitions per unit length
+    pub scale: R,
+    /// Simulation time of the current frame
+    pub time: R,
+    /// Time after which the advection of each of the two layers is reset
+    pub reset_period: R,
+    /// Name of the point attribute with the velocities at the mesh vertices (e.g. an interpolated particle attribute)
+    pub velocity_attribute: String,
+}
+
+impl<R: Real> AdvectedUvParameters<R> {
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns `None` if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<AdvectedUvParameters<T>> {
+        Some(AdvectedUvParameters {
+            scale: self.scale.try_convert()?,
+            time: self.time.try_convert()?,
+            reset_period: self.reset_period.try_convert()?,
+            velocity_attribute: self.velocity_attribute.clone(),
+        })
+    }
+}
+
+/// Texture coordinates of the two layers of [`advected_uv_coordinates`] and their blend weights
+#[derive(Clone, Debug)]
+pub struct AdvectedUvCoordinates<R: Real> {
+    /// Texture coordinates of the two layers per vertex
+    pub uv: [Vec<Vector2<R>>; 2],
+    /// Blend weights of the two layers (sum up to one)
+    pub weights: [R; 2],
+}
+
+/// Computes texture coordinates by a triplanar projection of the given points
+///
+/// Every point is projected onto the coordinate plane orthogonal to the dominant axis of its normal, i.e. `(y, z)`,
+/// `(z, x)` or `(x, y)` for the x-, y- or z-axis. The first coordinate is negated for normals pointing in negative
+/// axis direction to avoid mirrored textures. The coordinates are multiplied by `scale` (number of texture repetitions
+/// per unit length). Note that the coordinates are discontinuous where the dominant axis of the normals changes.
+pub fn triplanar_uv_coordinates<R: Real>(
+    points: &[Vector3<R>],
+    normals: &[Vector3<R>],
+    scale: R,
+) -> Vec<Vector2<R>> {
+    assert_eq!(
+        points.len(),
+        normals.len(),
+        "there has to be one normal per point"
+    );
+
+    points
+        .par_iter()
+        .zip(normals.par_iter())
+        .map(|(p, n)| triplanar_projection(p, n).scale(scale))
+        .collect()
+}
+
+/// Projects the point onto the coordinate plane orthogonal to the dominant axis of the normal (see [`triplanar_uv_coordinates`])
+fn triplanar_projection<R: Real>(p: &Vector3<R>, n: &Vector3<R>) -> Vector2<R> {
+    let abs = n.abs();
+    let (axis, sign) = if abs.x >= abs.y && abs.x >= abs.z {
+        (0, n.x.signum())
+    } else if abs.y >= abs.z {
+        (1, n.y.signum())
+    } else {
+        (2, n.z.signum())
+    };
+
+    // Tangent plane (u, v) with u x v pointing along the positive axis
+    let u = p[(axis + 1) % 3];
+    let v = p[(axis + 2) % 3];
+    Vector2::new(u * sign, v)
+}
+
+/// Computes texture coordinates advected with the given velocities using two layers that are periodically reset
+///
+/// Follows the moving texture approach of Max and Becker: "Flow Visualization Using Moving Textures" (1995) that is also
+/// commonly used for the rendering of flowing water. As the surface meshes of different frames are not related, the
+/// coordinates are not integrated over time. Instead, every vertex is moved backwards along its velocity by the time
+/// since the last reset of a layer and projected like in [`triplanar_uv_coordinates`]. The resets of the two layers
+/// are offset by half of the reset period. The blend weight of a layer increases linearly from zero at its reset to one
+/// at half of the period and decreases to zero until the next reset, such that the resets are not visible when both
+/// layers are blended. Every reset cycle additionally shifts the coordinates of the layer by a constant offset to hide
+/// the periodic pattern.
+pub fn advected_uv_coordinates<R: Real>(
+    points: &[Vector3<R>],
+    normals: &[Vector3<R>],
+    velocities: &[Vector3<R>],
+    parameters: &AdvectedUvParameters<R>,
+) -> AdvectedUvCoordinates<R> {
+    assert_eq!(
+        points.len(),
+        normals.len(),
+        "there has to be one normal per point"
+    );
+    assert_eq!(
+        points.len(),
+        velocities.len(),
+        "there has to be one velocity per point"
+    );
+
+    let half = R::from_f64(0.5).unwrap();
+    let two = R::from_f64(2.0).unwrap();
+    // Offset per reset cycle (golden ratio conjugates to avoid repeating offsets)
+    let cycle_offset = Vector2::new(
+        R::from_f64(0.618_033_988_75).unwrap(),
+        R::from_f64(0.381_966_011_25).unwrap(),
+    );
+
+    let layer = |k: usize| {
+        let s = parameters.time / parameters.reset_period + R::from_usize(k).unwrap() * half;
+        let cycle = s.floor();
+        let phase = s - cycle;
+        let age = phase * parameters.reset_period;
+        let offset = (cycle_offset * cycle).map(|c| c - c.floor());
+
+        let uv = points
+            .par_iter()
+            .zip(normals.par_iter())
+            .zip(velocities.par_iter())
+            .map(|((p, n), v)| {
+                triplanar_projection(&(p - v.scale(age)), n).scale(parameters.scale) + offset
+            })
+            .collect::<Vec<_>>();
+        let weight = R::one() - (two * phase - R::one()).abs();
+        (uv, weight)
+    };
+
+    let (uv_0, weight_0) = layer(0);
+    let (uv_1, _) = layer(1);
+    AdvectedUvCoordinates {
+        uv: [uv_0, uv_1],
+        weights: [weight_0, R::one() - weight_0],
+    }
+}
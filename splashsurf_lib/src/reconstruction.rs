@@ -1,31 +1,37 @@
 use crate::dense_subdomains::{
-    compute_global_densities_and_neighbors, decomposition, initialize_parameters, reconstruction,
-    stitching, subdomain_classification::GhostMarginClassifier,
+    compute_global_densities_and_neighbors, decomposition, incremental_reconstruction,
+    initialize_parameters, reconstruction, stitching,
+    subdomain_classification::GhostMarginClassifier, subdomain_stats,
 };
 use crate::mesh::TriMesh3d;
+use crate::neighborhood_search::FlatNeighborhoodList;
 use crate::uniform_grid::UniformGrid;
 use crate::workspace::LocalReconstructionWorkspace;
 use crate::{
-    density_map, marching_cubes, neighborhood_search, profile, Index, Parameters, Real,
-    ReconstructionError, SurfaceReconstruction,
+    density_map, marching_cubes, neighborhood_search, profile, IncrementalParameters, Index,
+    Parameters, Real, ReconstructionError, ReconstructionStats, SurfaceReconstruction,
 };
-use anyhow::Context;
 use log::{info, trace};
 use nalgebra::Vector3;
+use rayon::prelude::*;
+use std::time::Instant;
 
 /// Performs a surface reconstruction with a regular grid for domain decomposition
+///
+/// If incremental parameters are given, the surface patches cached in the output surface are reused where possible.
 pub(crate) fn reconstruct_surface_subdomain_grid<'a, I: Index, R: Real>(
     particle_positions: &[Vector3<R>],
     parameters: &Parameters<R>,
+    incremental_parameters: Option<&IncrementalParameters<R>>,
     output_surface: &'a mut SurfaceReconstruction<I, R>,
-) -> Result<(), anyhow::Error> {
+) -> Result<(), ReconstructionError<I, R>> {
     profile!("surface reconstruction subdomain-grid");
 
+    let stage_start = Instant::now();
     let internal_parameters =
         initialize_parameters(parameters, &particle_positions, output_surface)?;
-    output_surface.grid = internal_parameters
-        .global_marching_cubes_grid()
-        .context("failed to convert global marching cubes grid")?;
+    output_surface.grid = internal_parameters.global_marching_cubes_grid()?;
+    output_surface.stats.record("initialization", stage_start);
 
     // Filter "narrow band"
     /*
@@ -33,8 +39,11 @@ pub(crate) fn reconstruct_surface_subdomain_grid<'a, I: Index, R: Real>(
     let particles = narrow_band_particles;
      */
 
+    let stage_start = Instant::now();
     let subdomains =
         decomposition::<I, R, GhostMarginClassifier<I>>(&internal_parameters, &particle_positions)?;
+    output_surface.stats.subdomains = Some(subdomain_stats(&internal_parameters, &subdomains)?);
+    output_surface.stats.record("decomposition", stage_start);
 
     /*
     {
@@ -47,20 +56,82 @@ pub(crate) fn reconstruct_surface_subdomain_grid<'a, I: Index, R: Real>(
     }
      */
 
-    let (particle_densities, particle_neighbors) = compute_global_densities_and_neighbors(
-        &internal_parameters,
-        &particle_positions,
-        &subdomains,
-    );
+    let stage_start = Instant::now();
+    let (particle_densities, particle_neighbors) = if internal_parameters.uses_local_coordinates() {
+        compute_global_densities_and_neighbors::<I, R, f32>(
+            &internal_parameters,
+            &particle_positions,
+            &subdomains,
+        )?
+    } else {
+        compute_global_densities_and_neighbors::<I, R, R>(
+            &internal_parameters,
+            &particle_positions,
+            &subdomains,
+        )?
+    };
+    output_surface
+        .stats
+        .record("density computation", stage_start);
 
-    let surface_patches = reconstruction(
-        &internal_parameters,
-        &particle_positions,
-        &particle_densities,
-        &subdomains,
-    );
+    let stage_start = Instant::now();
+    let (surface_patches, patch_cache) =
+        if let Some(incremental_parameters) = incremental_parameters {
+            let cache = output_surface.patch_cache.take();
+            let (cache, num_reused) = if internal_parameters.uses_local_coordinates() {
+                incremental_reconstruction::<I, R, f32>(
+                    &internal_parameters,
+                    incremental_parameters,
+                    &particle_positions,
+                    &particle_densities,
+                    &subdomains,
+                    cache,
+                )?
+            } else {
+                incremental_reconstruction::<I, R, R>(
+                    &internal_parameters,
+                    incremental_parameters,
+                    &particle_positions,
+                    &particle_densities,
+                    &subdomains,
+                    cache,
+                )?
+            };
+            if let Some(subdomain_stats) = output_surface.stats.subdomains.as_mut() {
+                subdomain_stats.num_reused_subdomains = num_reused;
+            }
+            (Vec::new(), Some(cache))
+        } else if internal_parameters.uses_local_coordinates() {
+            let surface_patches = reconstruction::<I, R, f32>(
+                &internal_parameters,
+                &particle_positions,
+                &particle_densities,
+                &subdomains,
+                None,
+            )?;
+            (surface_patches, None)
+        } else {
+            let surface_patches = reconstruction::<I, R, R>(
+                &internal_parameters,
+                &particle_positions,
+                &particle_densities,
+                &subdomains,
+                None,
+            )?;
+            (surface_patches, None)
+        };
+    output_surface.stats.record("reconstruction", stage_start);
 
-    let global_mesh = stitching(surface_patches);
+    let stage_start = Instant::now();
+    let (global_mesh, vertex_provenance) = stitching(
+        patch_cache
+            .as_ref()
+            .map(|cache| cache.patches())
+            .unwrap_or(&surface_patches),
+        internal_parameters.vertex_provenance_particles(),
+    );
+    output_surface.patch_cache = patch_cache;
+    output_surface.stats.record("stitching", stage_start);
     info!(
         "Global mesh has {} vertices and {} triangles.",
         global_mesh.vertices.len(),
@@ -68,6 +139,7 @@ pub(crate) fn reconstruct_surface_subdomain_grid<'a, I: Index, R: Real>(
     );
 
     output_surface.mesh = global_mesh;
+    output_surface.vertex_provenance = vertex_provenance;
     output_surface.particle_densities = Some(particle_densities);
     if parameters.global_neighborhood_list {
         output_surface.particle_neighbors = Some(particle_neighbors);
@@ -110,6 +182,7 @@ pub(crate) fn reconstruct_surface_global<'a, I: Index, R: Real>(
         particle_positions,
         parameters,
         &mut output_surface.mesh,
+        &mut output_surface.stats,
     )?;
 
     output_surface.particle_densities = Some(std::mem::take(&mut workspace.particle_densities));
@@ -132,24 +205,58 @@ pub(crate) fn compute_particle_densities_and_neighbors<I: Index, R: Real>(
         * parameters.particle_radius.powi(3);
     let particle_rest_mass = particle_rest_volume * particle_rest_density;
 
-    trace!("Starting neighborhood search...");
-    neighborhood_search::search_inplace::<I, R>(
-        &grid.aabb(),
-        particle_positions,
-        parameters.compact_support_radius,
-        parameters.enable_multi_threading,
-        particle_neighbor_lists,
-    );
+    if parameters.enable_multi_threading {
+        // Store the squared distances during the neighborhood search to avoid recomputing them for the densities
+        let mut flat_neighbor_lists = FlatNeighborhoodList::default();
+        let mut neighbor_squared_distances = Vec::new();
 
-    trace!("Computing particle densities...");
-    density_map::compute_particle_densities_inplace::<I, R>(
-        particle_positions,
-        particle_neighbor_lists.as_slice(),
-        parameters.compact_support_radius,
-        particle_rest_mass,
-        parameters.enable_multi_threading,
-        densities,
-    );
+        trace!("Starting neighborhood search...");
+        neighborhood_search::neighborhood_search_spatial_hashing_flat_parallel::<I, R>(
+            &grid.aabb(),
+            particle_positions,
+            parameters.compact_support_radius,
+            &mut flat_neighbor_lists,
+            Some(&mut neighbor_squared_distances),
+        );
+
+        trace!("Computing particle densities...");
+        density_map::parallel_compute_particle_densities_from_squared_distances(
+            &flat_neighbor_lists,
+            neighbor_squared_distances.as_slice(),
+            parameters.compact_support_radius,
+            particle_rest_mass,
+            densities,
+        );
+
+        // The neighbor lists are part of the reconstruction result, reuse their existing allocations
+        particle_neighbor_lists.resize_with(particle_positions.len(), Vec::new);
+        particle_neighbor_lists
+            .par_iter_mut()
+            .zip(flat_neighbor_lists.neighbor_ptr.par_windows(2))
+            .for_each(|(neighbors, range)| {
+                neighbors.clear();
+                neighbors.extend_from_slice(&flat_neighbor_lists.neighbors[range[0]..range[1]]);
+            });
+    } else {
+        trace!("Starting neighborhood search...");
+        neighborhood_search::search_inplace::<I, R>(
+            &grid.aabb(),
+            particle_positions,
+            parameters.compact_support_radius,
+            false,
+            particle_neighbor_lists,
+        );
+
+        trace!("Computing particle densities...");
+        density_map::compute_particle_densities_inplace::<I, R>(
+            particle_positions,
+            particle_neighbor_lists.as_slice(),
+            parameters.compact_support_radius,
+            particle_rest_mass,
+            false,
+            densities,
+        );
+    }
 }
 
 /// Reconstruct a surface, appends triangulation to the given mesh
@@ -159,12 +266,14 @@ pub(crate) fn reconstruct_single_surface_append<'a, I: Index, R: Real>(
     particle_positions: &[Vector3<R>],
     parameters: &Parameters<R>,
     output_mesh: &'a mut TriMesh3d<R>,
+    stats: &mut ReconstructionStats,
 ) -> Result<(), ReconstructionError<I, R>> {
     let particle_rest_density = parameters.rest_density;
     let particle_rest_volume = R::from_f64((4.0 / 3.0) * std::f64::consts::PI).unwrap()
         * parameters.particle_radius.powi(3);
     let particle_rest_mass = particle_rest_volume * particle_rest_density;
 
+    let stage_start = Instant::now();
     let particle_densities = {
         compute_particle_densities_and_neighbors(
             grid,
@@ -175,9 +284,11 @@ pub(crate) fn reconstruct_single_surface_append<'a, I: Index, R: Real>(
         );
         workspace.particle_densities.as_slice()
     };
+    stats.record("density computation", stage_start);
 
     // Create a new density map, reusing memory with the workspace is bad for cache efficiency
     // Alternatively one could reuse memory with a custom caching allocator
+    let stage_start = Instant::now();
     let mut density_map = Default::default();
     density_map::generate_sparse_density_map(
         grid,
@@ -190,13 +301,16 @@ pub(crate) fn reconstruct_single_surface_append<'a, I: Index, R: Real>(
         parameters.enable_multi_threading,
         &mut density_map,
     )?;
+    stats.record("density map", stage_start);
 
+    let stage_start = Instant::now();
     marching_cubes::triangulate_density_map_append(
         grid,
         &density_map,
         parameters.iso_surface_threshold,
         output_mesh,
     )?;
+    stats.record("marching cubes", stage_start);
 
     Ok(())
 }
@@ -0,0 +1,335 @@
+//! Space-filling curves (Morton/Z-order and Hilbert) used to reorder particles for better cache locality
+//!
+//! Sorting the particles along a space-filling curve places particles that are close in space also close
+//! in memory. This improves the cache efficiency of the neighborhood search, the density computation and
+//! the level-set evaluation, especially if the input particles are stored in an arbitrary order.
+
+use crate::mesh::{AttributeData, MeshAttribute};
+use crate::{profile, Aabb3d, Real};
+use nalgebra::Vector3;
+use rayon::prelude::*;
+
+/// Number of bits per coordinate axis used for the space-filling curve indices (3 * 21 = 63 bits in total)
+pub const BITS_PER_AXIS: u32 = 21;
+
+/// Type of space-filling curve that is used to order particles
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SpaceFillingCurve {
+    /// Morton (Z-order) curve, obtained by interleaving the bits of the quantized coordinates
+    Morton,
+    /// Hilbert curve, more expensive to compute than the Morton curve but without large jumps between consecutive cells
+    Hilbert,
+}
+
+impl SpaceFillingCurve {
+    /// Returns the index of the given quantized coordinates along this curve (only the lower [`BITS_PER_AXIS`] bits of each coordinate are used)
+    #[inline(always)]
+    pub fn index(&self, ijk: [u32; 3]) -> u64 {
+        match self {
+            SpaceFillingCurve::Morton => morton_index(ijk),
+            SpaceFillingCurve::Hilbert => hilbert_index(ijk, BITS_PER_AXIS),
+        }
+    }
+}
+
+/// Spreads the lower 21 bits of the value such that there are two zero bits between each of the bits
+#[inline(always)]
+fn spread_bits(v: u32) -> u64 {
+    let mut x = (v as u64) & 0x1f_ffff;
+    x = (x | x << 32) & 0x1f_0000_0000_ffff;
+    x = (x | x << 16) & 0x1f_0000_ff00_00ff;
+    x = (x | x << 8) & 0x100f_00f0_0f00_f00f;
+    x = (x | x << 4) & 0x10c3_0c30_c30c_30c3;
+    x = (x | x << 2) & 0x1249_2492_4924_9249;
+    x
+}
+
+/// Returns the Morton (Z-order) index of the given quantized coordinates (only the lower 21 bits of each coordinate are used)
+#[inline(always)]
+pub fn morton_index(ijk: [u32; 3]) -> u64 {
+    spread_bits(ijk[0]) | spread_bits(ijk[1]) << 1 | spread_bits(ijk[2]) << 2
+}
+
+/// Returns the Hilbert index of the given quantized coordinates using the given number of bits per coordinate (at most 21)
+///
+/// Implementation of the "transpose" algorithm from "Programming the Hilbert curve" (Skilling, 2004).
+#[inline]
+pub fn hilbert_index(ijk: [u32; 3], bits: u32) -> u64 {
+    assert!(
+        bits > 0 && bits <= BITS_PER_AXIS,
+        "number of bits per axis has to be in the range 1..={}",
+        BITS_PER_AXIS
+    );
+
+    let mask = (1u32 << bits) - 1;
+    let mut x = [ijk[0] & mask, ijk[1] & mask, ijk[2] & mask];
+    let m = 1u32 << (bits - 1);
+
+    // Inverse undo excess work
+    let mut q = m;
+    while q > 1 {
+        let p = q - 1;
+        for i in 0..3 {
+            if x[i] & q != 0 {
+                x[0] ^= p;
+            } else {
+                let t = (x[0] ^ x[i]) & p;
+                x[0] ^= t;
+                x[i] ^= t;
+            }
+        }
+        q >>= 1;
+    }
+
+    // Gray encode
+    x[1] ^= x[0];
+    x[2] ^= x[1];
+    let mut t = 0;
+    let mut q = m;
+    while q > 1 {
+        if x[2] & q != 0 {
+            t ^= q - 1;
+        }
+        q >>= 1;
+    }
+    x.iter_mut().for_each(|xi| *xi ^= t);
+
+    // The bits of the transposed index are interleaved with the first coordinate being the most significant
+    morton_index([x[2], x[1], x[0]])
+}
+
+/// Permutation of a set of particles along a space-filling curve
+///
+/// The ordering can be used to reorder particle positions and their attributes before performing computations
+/// on them and to restore per-particle results to the original order afterward.
+#[derive(Clone, Debug, Default)]
+pub struct ParticleOrdering {
+    /// Maps the index of a particle in the new order to its index in the original order
+    permutation: Vec<usize>,
+    /// Maps the index of a particle in the original order to its index in the new order
+    inverse_permutation: Vec<usize>,
+}
+
+impl ParticleOrdering {
+    /// Computes the order of the given particles along the space-filling curve, the AABB is used to quantize the particle coordinates
+    ///
+    /// Particles outside of the AABB are clamped to its boundary. Particles with the same curve index keep their relative order.
+    pub fn new<R: Real>(
+        particle_positions: &[Vector3<R>],
+        aabb: &Aabb3d<R>,
+        curve: SpaceFillingCurve,
+        enable_multi_threading: bool,
+    ) -> Self {
+        profile!("ParticleOrdering::new");
+
+        let max_cell = (1u32 << BITS_PER_AXIS) - 1;
+        let max_extent = aabb.max_extent();
+        let scale = if max_extent > R::zero() {
+            R::from_u32(max_cell).unwrap() / max_extent
+        } else {
+            R::zero()
+        };
+        let min = *aabb.min();
+
+        let quantize = |x: R| -> u32 {
+            // Also handles negative values and NaN by mapping them to zero
+            x.to_u32().unwrap_or(0).min(max_cell)
+        };
+        let curve_index = |(i, p): (usize, &Vector3<R>)| -> (u64, usize) {
+            let q = (p - min) * scale;
+            (
+                curve.index([quantize(q.x), quantize(q.y), quantize(q.z)]),
+                i,
+            )
+        };
+
+        let mut keys: Vec<(u64, usize)>;
+        if enable_multi_threading {
+            keys = particle_positions
+                .par_iter()
+                .enumerate()
+                .map(curve_index)
+                .collect();
+            keys.par_sort_unstable();
+        } else {
+            keys = particle_positions
+                .iter()
+                .enumerate()
+                .map(curve_index)
+                .collect();
+            keys.sort_unstable();
+        }
+
+        let permutation: Vec<usize> = keys.into_iter().map(|(_, i)| i).collect();
+        Self::from_permutation(permutation)
+    }
+
+    /// Constructs an ordering from a permutation that maps indices in the new order to indices in the original order, panics if it is not a valid permutation
+    pub fn from_permutation(permutation: Vec<usize>) -> Self {
+        let mut inverse_permutation = vec![usize::MAX; permutation.len()];
+        for (new_index, &old_index) in permutation.iter().enumerate() {
+            assert!(
+                old_index < permutation.len() && inverse_permutation[old_index] == usize::MAX,
+                "the given indices are not a permutation"
+            );
+            inverse_permutation[old_index] = new_index;
+        }
+
+        Self {
+            permutation,
+            inverse_permutation,
+        }
+    }
+
+    /// Returns the number of particles of the ordering
+    pub fn len(&self) -> usize {
+        self.permutation.len()
+    }
+
+    /// Returns whether the ordering is empty
+    pub fn is_empty(&self) -> bool {
+        self.permutation.is_empty()
+    }
+
+    /// Returns the permutation mapping the index of a particle in the new order to its index in the original order
+    pub fn permutation(&self) -> &[usize] {
+        self.permutation.as_slice()
+    }
+
+    /// Returns the permutation mapping the index of a particle in the original order to its index in the new order
+    pub fn inverse_permutation(&self) -> &[usize] {
+        self.inverse_permutation.as_slice()
+    }
+
+    /// Returns a copy of the per-particle data (given in the original order) in the new order
+    pub fn apply<T: Clone>(&self, data: &[T]) -> Vec<T> {
+        let mut reordered = Vec::new();
+        self.apply_into(data, &mut reordered);
+        reordered
+    }
+
+    /// Stores a copy of the per-particle data (given in the original order) in the new order in the output vector
+    pub fn apply_into<T: Clone>(&self, data: &[T], output: &mut Vec<T>) {
+        assert_eq!(data.len(), self.len());
+        output.clear();
+        output.extend(self.permutation.iter().map(|&i| data[i].clone()));
+    }
+
+    /// Returns a copy of the per-particle data (given in the new order) in the original order
+    pub fn restore<T: Clone>(&self, data: &[T]) -> Vec<T> {
+        assert_eq!(data.len(), self.len());
+        self.inverse_permutation
+            .iter()
+            .map(|&i| data[i].clone())
+            .collect()
+    }
+
+    /// Maps a particle index of the new order to the corresponding index of the original order
+    #[inline(always)]
+    pub fn original_index(&self, new_index: usize) -> usize {
+        self.permutation[new_index]
+    }
+
+    /// Restores per-particle neighbor lists computed in the new order to the original order, including the neighbor indices
+    pub fn restore_neighbor_lists(&self, mut neighbor_lists: Vec<Vec<usize>>) -> Vec<Vec<usize>> {
+        assert_eq!(neighbor_lists.len(), self.len());
+        neighbor_lists
+            .par_iter_mut()
+            .for_each(|neighbors| neighbors.iter_mut().for_each(|j| *j = self.permutation[*j]));
+
+        // Move the lists to their original positions
+        let mut restored = vec![Vec::new(); neighbor_lists.len()];
+        for (new_index, neighbors) in neighbor_lists.into_iter().enumerate() {
+            restored[self.permutation[new_index]] = neighbors;
+        }
+        restored
+    }
+
+    /// Returns a copy of the per-particle attribute data (given in the original order) in the new order
+    pub fn apply_to_attribute_data<R: Real>(&self, data: &AttributeData<R>) -> AttributeData<R> {
+        assert_eq!(data.len(), self.len());
+        data.select(&self.permutation)
+    }
+
+    /// Returns a copy of the per-particle attribute (given in the original order) in the new order
+    pub fn apply_to_attribute<R: Real>(&self, attribute: &MeshAttribute<R>) -> MeshAttribute<R> {
+        MeshAttribute {
+            name: attribute.name.clone(),
+            data: self.apply_to_attribute_data(&attribute.data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_morton_index() {
+        assert_eq!(morton_index([0, 0, 0]), 0);
+        assert_eq!(morton_index([1, 0, 0]), 1);
+        assert_eq!(morton_index([0, 1, 0]), 2);
+        assert_eq!(morton_index([0, 0, 1]), 4);
+        assert_eq!(morton_index([7, 7, 7]), 511);
+        assert_eq!(morton_index([0x1f_ffff; 3]), (1 << 63) - 1);
+    }
+
+    #[test]
+    fn test_hilbert_index_adjacency() {
+        for bits in 1..=4 {
+            let n = 1u32 << bits;
+            let mut cells = vec![None; (n * n * n) as usize];
+            for i in 0..n {
+                for j in 0..n {
+                    for k in 0..n {
+                        let index = hilbert_index([i, j, k], bits) as usize;
+                        assert!(cells[index].is_none(), "Hilbert index is not unique");
+                        cells[index] = Some([i, j, k]);
+                    }
+                }
+            }
+
+            // Consecutive cells along the Hilbert curve have to be face neighbors
+            for pair in cells.windows(2) {
+                let (a, b) = (pair[0].unwrap(), pair[1].unwrap());
+                let dist: u32 = (0..3).map(|d| a[d].abs_diff(b[d])).sum();
+                assert_eq!(dist, 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_particle_ordering_roundtrip() {
+        let particles: Vec<Vector3<f64>> = (0..1000)
+            .map(|i| {
+                let i = i as f64;
+                Vector3::new((i * 0.37).sin(), (i * 0.73).cos(), (i * 0.11).sin())
+            })
+            .collect();
+        let aabb = Aabb3d::from_points(&particles);
+
+        for curve in [SpaceFillingCurve::Morton, SpaceFillingCurve::Hilbert] {
+            let ordering = ParticleOrdering::new(&particles, &aabb, curve, true);
+            assert_eq!(
+                ordering.permutation(),
+                ParticleOrdering::new(&particles, &aabb, curve, false).permutation()
+            );
+
+            let reordered = ordering.apply(&particles);
+            assert_eq!(ordering.restore(&reordered), particles);
+
+            // Neighbor lists referring to the reordered particles are mapped back to the original indices
+            let neighbor_lists: Vec<Vec<usize>> = (0..particles.len())
+                .map(|i| vec![(i + 1) % particles.len()])
+                .collect();
+            let restored = ordering.restore_neighbor_lists(neighbor_lists);
+            for (i, neighbors) in restored.iter().enumerate() {
+                let new_index = ordering.inverse_permutation()[i];
+                assert_eq!(
+                    neighbors,
+                    &vec![ordering.original_index((new_index + 1) % particles.len())]
+                );
+            }
+        }
+    }
+}
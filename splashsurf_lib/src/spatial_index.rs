@@ -0,0 +1,148 @@
+//! Spatial index of particle sets for radius and k-nearest-neighbor queries of arbitrary points
+//!
+//! In contrast to the [`neighborhood_search`](crate::neighborhood_search) module which computes the neighborhoods
+//! of the particles themselves, the [`SpatialIndex`] answers queries for arbitrary points (e.g. mesh vertices).
+//! The index can be built once per particle set and shared between all algorithms that need such queries.
+
+use crate::neighborhood_search::{build_flat_neighborhood_list, FlatNeighborhoodList};
+use crate::{profile, Real};
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use rstar::primitives::GeomWithData;
+use rstar::RTree;
+
+/// Particle type that is stored in the R-tree (position with the index of the particle)
+type IndexedParticle<R> = GeomWithData<[R; 3], usize>;
+
+/// R-tree based spatial index of a set of particles for radius and k-nearest-neighbor queries of arbitrary points
+///
+/// All query results refer to the particles by their index in the slice that was used to build the index.
+/// The functions with a `par_` prefix process many query points in parallel.
+pub struct SpatialIndex<R: Real> {
+    tree: RTree<IndexedParticle<R>>,
+}
+
+impl<R: Real> SpatialIndex<R> {
+    /// Builds the spatial index for the given particles, this is a relatively expensive operation (builds an R-tree)
+    pub fn new(particle_positions: &[Vector3<R>]) -> Self {
+        profile!("SpatialIndex::new");
+
+        let particles = particle_positions
+            .iter()
+            .enumerate()
+            .map(|(i, p)| IndexedParticle::new(bytemuck::cast(*p), i))
+            .collect();
+
+        Self {
+            tree: RTree::bulk_load(particles),
+        }
+    }
+
+    /// Returns the number of particles in the index
+    pub fn len(&self) -> usize {
+        self.tree.size()
+    }
+
+    /// Returns whether the index does not contain any particles
+    pub fn is_empty(&self) -> bool {
+        self.tree.size() == 0
+    }
+
+    /// Calls the given closure with the index and position of every particle within the given radius of the query point
+    ///
+    /// Particles at a distance of exactly `radius` are not included (consistent with the neighborhood search).
+    pub fn for_each_within_radius(
+        &self,
+        query_point: &Vector3<R>,
+        radius: R,
+        mut f: impl FnMut(usize, &Vector3<R>),
+    ) {
+        let squared_radius = radius * radius;
+        for p in self
+            .tree
+            .locate_within_distance(bytemuck::cast::<_, [R; 3]>(*query_point), squared_radius)
+        {
+            let position = bytemuck::cast_ref::<_, Vector3<R>>(p.geom());
+            // The R-tree also returns particles exactly at the given distance
+            if (position - query_point).norm_squared() < squared_radius {
+                f(p.data, position)
+            }
+        }
+    }
+
+    /// Appends the indices of all particles within the given radius of the query point to the given vector
+    pub fn within_radius(&self, query_point: &Vector3<R>, radius: R, neighbors: &mut Vec<usize>) {
+        self.for_each_within_radius(query_point, radius, |i, _| neighbors.push(i));
+    }
+
+    /// Appends the indices and squared distances of the (up to) `k` particles closest to the query point to the given vector, ordered by increasing distance
+    pub fn nearest_neighbors(
+        &self,
+        query_point: &Vector3<R>,
+        k: usize,
+        neighbors: &mut Vec<(usize, R)>,
+    ) {
+        let query_point = bytemuck::cast::<_, [R; 3]>(*query_point);
+        neighbors.extend(
+            self.tree
+                .nearest_neighbor_iter_with_distance_2(&query_point)
+                .take(k)
+                .map(|(p, squared_distance)| (p.data, squared_distance)),
+        );
+    }
+
+    /// Computes the particles within the given radius of all query points in parallel
+    pub fn par_within_radius(
+        &self,
+        query_points: &[Vector3<R>],
+        radius: R,
+        neighborhood_list: &mut FlatNeighborhoodList,
+    ) {
+        profile!("SpatialIndex::par_within_radius");
+        build_flat_neighborhood_list(
+            query_points.len(),
+            true,
+            neighborhood_list,
+            |i, neighbors| self.within_radius(&query_points[i], radius, neighbors),
+        );
+    }
+
+    /// Computes the (up to) `k` particles closest to each query point in parallel, the neighbors are ordered by increasing distance
+    pub fn par_nearest_neighbors(
+        &self,
+        query_points: &[Vector3<R>],
+        k: usize,
+        neighborhood_list: &mut FlatNeighborhoodList,
+    ) {
+        profile!("SpatialIndex::par_nearest_neighbors");
+        build_flat_neighborhood_list(
+            query_points.len(),
+            true,
+            neighborhood_list,
+            |i, neighbors| {
+                let query_point = bytemuck::cast::<_, [R; 3]>(query_points[i]);
+                neighbors.extend(
+                    self.tree
+                        .nearest_neighbor_iter(&query_point)
+                        .take(k)
+                        .map(|p| p.data),
+                );
+            },
+        );
+    }
+
+    /// Returns the index and squared distance of the closest particle for each query point in parallel (`None` if the index is empty)
+    pub fn par_nearest_neighbor(&self, query_points: &[Vector3<R>]) -> Vec<Option<(usize, R)>> {
+        profile!("SpatialIndex::par_nearest_neighbor");
+        query_points
+            .par_iter()
+            .map(|q| {
+                let query_point = bytemuck::cast::<_, [R; 3]>(*q);
+                self.tree
+                    .nearest_neighbor_iter_with_distance_2(&query_point)
+                    .next()
+                    .map(|(p, squared_distance)| (p.data, squared_distance))
+            })
+            .collect()
+    }
+}
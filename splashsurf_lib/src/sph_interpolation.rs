@@ -2,29 +2,62 @@
 
 use crate::kernel::SymmetricKernel3d;
 use crate::profile;
+use crate::spatial_index::SpatialIndex;
 use crate::Real;
 use crate::{kernel, ThreadSafe};
-use nalgebra::{SVector, Unit, Vector3};
+use nalgebra::{Matrix3, Matrix4, SMatrix, SVector, Unit, Vector3, Vector4};
 use rayon::prelude::*;
-use rstar::primitives::GeomWithData;
-use rstar::RTree;
 use std::ops::AddAssign;
 
 /// Acceleration structure for interpolating field quantities of the fluid to arbitrary points using SPH interpolation
 pub struct SphInterpolator<R: Real> {
     compact_support_radius: R,
-    tree: RTree<Particle<R>>,
+    /// Spatial index of the particles for fast SPH neighbor queries
+    index: SpatialIndex<R>,
+    /// Volume associated with each particle which is needed to evaluate the SPH density field
+    particle_volumes: Vec<R>,
 }
 
-/// Particle type that is stored in the R-tree for fast SPH neighbor queries
-type Particle<R> = GeomWithData<[R; 3], ParticleData<R>>;
+/// Interpolation schemes supported by the [`SphInterpolator`]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum InterpolationMode {
+    /// Standard SPH sum `A(x) = sum_j V_j A_j W(x - x_j)` without any correction
+    ///
+    /// Values and normals are biased close to the free surface where the kernel support is not completely filled with particles.
+    Sph,
+    /// Shepard-normalized SPH sum `A(x) = sum_j V_j A_j W(x - x_j) / sum_j V_j W(x - x_j)`
+    ///
+    /// Exactly reproduces constant fields (zeroth-order consistency). Normals are computed like with [`InterpolationMode::Sph`].
+    #[default]
+    Shepard,
+    /// Moving least squares (MLS) interpolation with a linear basis, i.e. the SPH weights are corrected to exactly reproduce linear fields
+    ///
+    /// Normals are computed using the kernel gradient correction of Bonet and Lok (1999).
+    /// Points with less than four particles in a non-degenerate configuration in their neighborhood fall back to [`InterpolationMode::Shepard`].
+    MovingLeastSquares,
+}
 
-/// Data associated with each particle that is stored in the R-tree
-struct ParticleData<R: Real> {
-    /// Index of the particle in the global particle list
-    index: usize,
-    /// Volume associated with each particle which is needed to evaluate the SPH density field
-    volume: R,
+/// Averaging schemes for tensor quantities supported by [`SphInterpolator::interpolate_tensor_quantity`]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum TensorAveraging {
+    /// Component-wise weighted average of the tensors (suitable for arbitrary tensors)
+    #[default]
+    Linear,
+    /// Weighted average of the matrix logarithms of the tensors followed by the matrix exponential (Arsigny et al. 2006)
+    ///
+    /// Only suitable for symmetric positive definite tensors (e.g. anisotropy or diffusion tensors). The result is always
+    /// symmetric positive definite and does not suffer from the "swelling" of the determinant of the linear average.
+    LogEuclidean,
+}
+
+/// Interpolation schemes for integer quantities like IDs and labels where averaging is not meaningful
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum CategoricalInterpolation {
+    /// Value of the particle closest to the interpolation point
+    Nearest,
+    /// Value with the largest sum of SPH weights of all particles within the compact support (falls back to the nearest particle if there are no particles within the support)
+    #[default]
+    MajorityVote,
 }
 
 /// Trait for per-particle quantities that can be interpolated using SPH
@@ -45,7 +78,7 @@ impl<R: Real> InterpolationQuantity<R> for R {
     }
 }
 
-impl<R: Real, const D: usize> InterpolationQuantity<R> for SVector<R, D> {
+impl<R: Real, const M: usize, const N: usize> InterpolationQuantity<R> for SMatrix<R, M, N> {
     fn zero() -> Self {
         Self::zeros()
     }
@@ -65,50 +98,104 @@ impl<R: Real> SphInterpolator<R> {
     ) -> Self {
         assert_eq!(particle_positions.len(), particle_densities.len());
 
-        let tree = build_rtree(particle_positions, particle_densities, particle_rest_mass);
+        Self::from_spatial_index(
+            SpatialIndex::new(particle_positions),
+            particle_densities,
+            particle_rest_mass,
+            compact_support_radius,
+        )
+    }
+
+    /// Initializes the interpolator using an existing spatial index of the fluid particles
+    pub fn from_spatial_index(
+        index: SpatialIndex<R>,
+        particle_densities: &[R],
+        particle_rest_mass: R,
+        compact_support_radius: R,
+    ) -> Self {
+        assert_eq!(index.len(), particle_densities.len());
+
+        let particle_volumes = particle_densities
+            .par_iter()
+            .map(|rho_i| particle_rest_mass / *rho_i)
+            .collect();
 
         Self {
             compact_support_radius,
-            tree,
+            index,
+            particle_volumes,
         }
     }
 
+    /// Returns the spatial index of the particles used by this interpolator (e.g. to run further queries without building another index)
+    pub fn spatial_index(&self) -> &SpatialIndex<R> {
+        &self.index
+    }
+
     /// Interpolates surface normals (i.e. normalized SPH gradient of the indicator function) of the fluid to the given points using SPH interpolation, appends to the given vector
     pub fn interpolate_normals_inplace(
         &self,
         interpolation_points: &[Vector3<R>],
         normals: &mut Vec<Unit<Vector3<R>>>,
+    ) {
+        self.interpolate_normals_with_mode_inplace(
+            interpolation_points,
+            normals,
+            InterpolationMode::Sph,
+        )
+    }
+
+    /// Interpolates surface normals of the fluid to the given points using the given interpolation mode, appends to the given vector
+    pub fn interpolate_normals_with_mode_inplace(
+        &self,
+        interpolation_points: &[Vector3<R>],
+        normals: &mut Vec<Unit<Vector3<R>>>,
+        mode: InterpolationMode,
     ) {
         profile!("interpolate_normals_inplace");
 
-        let squared_support = self.compact_support_radius * self.compact_support_radius;
         let kernel = kernel::CubicSplineKernel::new(self.compact_support_radius);
+        let enable_gradient_correction = mode == InterpolationMode::MovingLeastSquares;
 
         interpolation_points
             .par_iter()
             .map(|x_i| {
                 // Compute the gradient of the particle density field which points in the same direction as surface normals
                 let mut density_grad = Vector3::zeros();
-
-                // SPH: Iterate over all other particles within the squared support radius
-                let query_point = bytemuck::cast::<_, [R; 3]>(*x_i);
-                for p_j in self
-                    .tree
-                    .locate_within_distance(query_point, squared_support)
-                {
-                    // Volume of the neighbor particle
-                    let vol_j = p_j.data.volume;
-                    // Position of the neighbor particle
-                    let x_j = bytemuck::cast_ref::<_, Vector3<R>>(p_j.geom());
-
-                    // Relative position `dx` and distance `r` of the neighbor particle
-                    let dx = x_j - x_i;
-                    let r = dx.norm();
-
-                    // Compute the contribution of the neighbor to the gradient of the density field
-                    // TODO: Replace this by a discrete gradient norm evaluation
-                    let kernel_grad = dx.unscale(r) * kernel.evaluate_gradient_norm(r);
-                    density_grad += kernel_grad * vol_j;
+                // Kernel gradient correction matrix (only used for MLS)
+                let mut correction_matrix = Matrix3::zeros();
+
+                // SPH: Iterate over all other particles within the support radius
+                self.index
+                    .for_each_within_radius(x_i, self.compact_support_radius, |j, x_j| {
+                        // Volume of the neighbor particle
+                        let vol_j = self.particle_volumes[j];
+
+                        // Relative position `dx` and distance `r` of the neighbor particle
+                        let dx = x_j - x_i;
+                        let r = dx.norm();
+                        if r <= R::zero() {
+                            // The kernel gradient vanishes at the particle position
+                            return;
+                        }
+
+                        // Compute the contribution of the neighbor to the gradient of the density field
+                        // TODO: Replace this by a discrete gradient norm evaluation
+                        let kernel_grad = dx.unscale(r) * kernel.evaluate_gradient_norm(r);
+                        density_grad += kernel_grad * vol_j;
+
+                        if enable_gradient_correction {
+                            correction_matrix -= (kernel_grad * dx.transpose()).scale(vol_j);
+                        }
+                    });
+
+                if enable_gradient_correction {
+                    if let Some(inverse) = correction_matrix.try_inverse() {
+                        let corrected_grad = inverse * density_grad;
+                        if corrected_grad.iter().all(|v| v.is_finite()) {
+                            density_grad = corrected_grad;
+                        }
+                    }
                 }
 
                 // Normalize the gradient to get the surface normal
@@ -127,6 +214,139 @@ impl<R: Real> SphInterpolator<R> {
         normals
     }
 
+    /// Interpolates surface normals of the fluid to the given points using the given interpolation mode
+    pub fn interpolate_normals_with_mode(
+        &self,
+        interpolation_points: &[Vector3<R>],
+        mode: InterpolationMode,
+    ) -> Vec<Unit<Vector3<R>>> {
+        let mut normals = Vec::with_capacity(interpolation_points.len());
+        self.interpolate_normals_with_mode_inplace(interpolation_points, &mut normals, mode);
+        normals
+    }
+
+    /// Computes the mean curvature of the fluid surface at the given points from the derivatives of the SPH level set function
+    ///
+    /// The mean curvature is half of the divergence of the surface normal field `n = -grad(phi) / |grad(phi)|` of the level
+    /// set function `phi` (see [`SphInterpolator::evaluate_level_set`]). It is evaluated directly from the gradient and the
+    /// Hessian of the level set, i.e. `H = -(tr(Hess(phi)) - n^T Hess(phi) n) / (2 |grad(phi)|)`, so no normals of interior
+    /// particles (where the gradient vanishes and its direction is dominated by noise) are involved.
+    /// Points where the gradient vanishes (e.g. far away from the fluid) get a curvature of zero.
+    /// The sign convention is the same as in [`TriMesh3d::vertex_curvatures`](crate::mesh::TriMesh3d::vertex_curvatures),
+    /// i.e. the mean curvature is positive for convex regions of the fluid.
+    pub fn interpolate_mean_curvature(&self, interpolation_points: &[Vector3<R>]) -> Vec<R> {
+        profile!("interpolate_mean_curvature");
+
+        let kernel = kernel::CubicSplineKernel::new(self.compact_support_radius);
+        let two = R::one() + R::one();
+        // Gradients below this norm are considered to vanish (the gradient at the surface is in the order of the inverse support radius)
+        let min_gradient_norm = R::default_epsilon().sqrt() / self.compact_support_radius;
+
+        interpolation_points
+            .par_iter()
+            .map(|x| {
+                let mut gradient = Vector3::zeros();
+                let mut hessian = Matrix3::zeros();
+
+                // SPH: Iterate over all particles within the support radius
+                self.index
+                    .for_each_within_radius(x, self.compact_support_radius, |j, x_j| {
+                        let vol_j = self.particle_volumes[j];
+                        let dx = x - x_j;
+                        let r = dx.norm();
+                        if r <= R::zero() {
+                            // The Hessian of the kernel at its center is isotropic and the gradient vanishes
+                            hessian += Matrix3::identity()
+                                .scale(kernel.evaluate_second_derivative(R::zero()) * vol_j);
+                            return;
+                        }
+
+                        // Gradient and Hessian of the radially symmetric kernel
+                        let dir = dx.unscale(r);
+                        let dw = kernel.evaluate_gradient_norm(r);
+                        let ddw = kernel.evaluate_second_derivative(r);
+                        gradient += dir.scale(dw * vol_j);
+                        hessian += ((dir * dir.transpose()).scale(ddw - dw / r)
+                            + Matrix3::identity().scale(dw / r))
+                        .scale(vol_j);
+                    });
+
+                let gradient_norm = gradient.norm();
+                if gradient_norm <= min_gradient_norm {
+                    return R::zero();
+                }
+
+                // Divergence of the outward normal, i.e. the normalized negative gradient of the level set
+                let gradient_dir = gradient.unscale(gradient_norm);
+                let divergence = -(hessian.trace() - gradient_dir.dot(&(hessian * gradient_dir)))
+                    / gradient_norm;
+                divergence / two
+            })
+            .collect()
+    }
+
+    /// Evaluates the SPH level set function `phi(x) = sum_j V_j W(x - x_j)` of the fluid and its gradient at the given point
+    ///
+    /// This is the scalar field whose iso-surface is extracted by the surface reconstruction.
+    pub fn evaluate_level_set(&self, x: &Vector3<R>) -> (R, Vector3<R>) {
+        let kernel = kernel::CubicSplineKernel::new(self.compact_support_radius);
+
+        let mut value = R::zero();
+        let mut gradient = Vector3::zeros();
+        self.index
+            .for_each_within_radius(x, self.compact_support_radius, |j, x_j| {
+                let vol_j = self.particle_volumes[j];
+                let dx = x_j - x;
+                let r = dx.norm();
+
+                value += kernel.evaluate(r) * vol_j;
+                if r > R::zero() {
+                    // Gradient of the kernel with respect to the evaluation point
+                    gradient -= dx.unscale(r) * (kernel.evaluate_gradient_norm(r) * vol_j);
+                }
+            });
+
+        (value, gradient)
+    }
+
+    /// Projects the given points onto the iso-surface `phi(x) = iso_value` of the SPH level set (see [`SphInterpolator::evaluate_level_set`])
+    ///
+    /// Performs up to `max_iterations` Newton steps along the gradient of the level set per point. The length of each
+    /// step is limited to a quarter of the compact support radius, points where the gradient vanishes (e.g. far away
+    /// from all particles) are left unchanged.
+    pub fn project_to_level_set_inplace(
+        &self,
+        points: &mut [Vector3<R>],
+        iso_value: R,
+        max_iterations: usize,
+    ) {
+        profile!("project_to_level_set_inplace");
+
+        let max_step = self.compact_support_radius / R::from_f64(4.0).unwrap();
+        let tolerance = self.compact_support_radius * R::from_f64(1e-6).unwrap();
+
+        points.par_iter_mut().for_each(|x| {
+            for _ in 0..max_iterations {
+                let (value, gradient) = self.evaluate_level_set(x);
+                let gradient_norm_squared = gradient.norm_squared();
+                if gradient_norm_squared <= R::default_epsilon() {
+                    break;
+                }
+
+                let mut step = gradient.scale((iso_value - value) / gradient_norm_squared);
+                let step_length = step.norm();
+                if step_length > max_step {
+                    step *= max_step / step_length;
+                }
+
+                *x += step;
+                if step_length <= tolerance {
+                    break;
+                }
+            }
+        });
+    }
+
     /// Interpolates a scalar per particle quantity to the given points, panics if the there are less per-particles values than particles, appends to the given vector
     #[allow(non_snake_case)]
     fn interpolate_scalar_quantity_inplace(
@@ -134,13 +354,13 @@ impl<R: Real> SphInterpolator<R> {
         particle_quantity: &[R],
         interpolation_points: &[Vector3<R>],
         interpolated_values: &mut Vec<R>,
-        first_order_correction: bool,
+        mode: InterpolationMode,
     ) {
         self.interpolate_quantity_inplace(
             particle_quantity,
             interpolation_points,
             interpolated_values,
-            first_order_correction,
+            mode,
         )
     }
 
@@ -149,14 +369,14 @@ impl<R: Real> SphInterpolator<R> {
         &self,
         particle_quantity: &[R],
         interpolation_points: &[Vector3<R>],
-        first_order_correction: bool,
+        mode: InterpolationMode,
     ) -> Vec<R> {
         let mut values = Vec::with_capacity(interpolation_points.len());
         self.interpolate_scalar_quantity_inplace(
             particle_quantity,
             interpolation_points,
             &mut values,
-            first_order_correction,
+            mode,
         );
         values
     }
@@ -168,13 +388,13 @@ impl<R: Real> SphInterpolator<R> {
         particle_quantity: &[SVector<R, D>],
         interpolation_points: &[Vector3<R>],
         interpolated_values: &mut Vec<SVector<R, D>>,
-        first_order_correction: bool,
+        mode: InterpolationMode,
     ) {
         self.interpolate_quantity_inplace(
             particle_quantity,
             interpolation_points,
             interpolated_values,
-            first_order_correction,
+            mode,
         )
     }
 
@@ -183,18 +403,125 @@ impl<R: Real> SphInterpolator<R> {
         &self,
         particle_quantity: &[SVector<R, D>],
         interpolation_points: &[Vector3<R>],
-        first_order_correction: bool,
+        mode: InterpolationMode,
     ) -> Vec<SVector<R, D>> {
         let mut values = Vec::with_capacity(interpolation_points.len());
         self.interpolate_vector_quantity_inplace(
             particle_quantity,
             interpolation_points,
             &mut values,
-            first_order_correction,
+            mode,
         );
         values
     }
 
+    /// Interpolates a 3x3 tensor per particle quantity to the given points using the given averaging scheme, panics if the there are less per-particles values than particles
+    ///
+    /// For [`TensorAveraging::LogEuclidean`], the tensors are assumed to be symmetric positive definite. Only the
+    /// symmetric part of the tensors is used and eigenvalues below a small threshold are clamped to keep the logarithm finite.
+    pub fn interpolate_tensor_quantity(
+        &self,
+        particle_quantity: &[Matrix3<R>],
+        interpolation_points: &[Vector3<R>],
+        mode: InterpolationMode,
+        averaging: TensorAveraging,
+    ) -> Vec<Matrix3<R>> {
+        let mut values = Vec::with_capacity(interpolation_points.len());
+        match averaging {
+            TensorAveraging::Linear => self.interpolate_quantity_inplace(
+                particle_quantity,
+                interpolation_points,
+                &mut values,
+                mode,
+            ),
+            TensorAveraging::LogEuclidean => {
+                let min_eigenvalue = R::default_epsilon().sqrt();
+                let log_tensors = particle_quantity
+                    .par_iter()
+                    .map(|t| map_symmetric_eigenvalues(t, |lambda| lambda.max(min_eigenvalue).ln()))
+                    .collect::<Vec<_>>();
+                self.interpolate_quantity_inplace(
+                    &log_tensors,
+                    interpolation_points,
+                    &mut values,
+                    mode,
+                );
+                values
+                    .par_iter_mut()
+                    .for_each(|t| *t = map_symmetric_eigenvalues(t, |lambda| lambda.exp()));
+            }
+        }
+        values
+    }
+
+    /// Interpolates a per particle quantity to the given points by taking the value of the closest particle, panics if the there are less per-particles values than particles
+    pub fn interpolate_nearest_quantity<T: Clone + ThreadSafe>(
+        &self,
+        particle_quantity: &[T],
+        interpolation_points: &[Vector3<R>],
+    ) -> Vec<T> {
+        profile!("interpolate_nearest_quantity");
+        assert_eq!(particle_quantity.len(), self.index.len());
+
+        self.index
+            .par_nearest_neighbor(interpolation_points)
+            .into_par_iter()
+            .map(|nearest| {
+                let (j, _) = nearest.expect("at least one particle is required for interpolation");
+                particle_quantity[j].clone()
+            })
+            .collect()
+    }
+
+    /// Interpolates a categorical per particle quantity (e.g. IDs or labels) to the given points using the given scheme, panics if the there are less per-particles values than particles
+    pub fn interpolate_categorical_quantity<T: Copy + Eq + ThreadSafe>(
+        &self,
+        particle_quantity: &[T],
+        interpolation_points: &[Vector3<R>],
+        scheme: CategoricalInterpolation,
+    ) -> Vec<T> {
+        if scheme == CategoricalInterpolation::Nearest {
+            return self.interpolate_nearest_quantity(particle_quantity, interpolation_points);
+        }
+
+        profile!("interpolate_categorical_quantity");
+        assert_eq!(particle_quantity.len(), self.index.len());
+
+        let kernel = kernel::CubicSplineKernel::new(self.compact_support_radius);
+
+        interpolation_points
+            .par_iter()
+            .map_init(Vec::new, |votes: &mut Vec<(T, R)>, x_i| {
+                // Accumulate the SPH weights of all particles within the support radius per value
+                votes.clear();
+                self.index
+                    .for_each_within_radius(x_i, self.compact_support_radius, |j, x_j| {
+                        let w_j = self.particle_volumes[j] * kernel.evaluate((x_j - x_i).norm());
+                        let value = particle_quantity[j];
+                        match votes.iter_mut().find(|(v, _)| *v == value) {
+                            Some((_, weight)) => *weight += w_j,
+                            None => votes.push((value, w_j)),
+                        }
+                    });
+
+                // The first value with the largest weight wins, fall back to the nearest particle without votes
+                let winner = votes
+                    .iter()
+                    .copied()
+                    .reduce(|a, b| if b.1 > a.1 { b } else { a });
+                winner.map(|(value, _)| value).unwrap_or_else(|| {
+                    let mut nearest = Vec::with_capacity(1);
+                    self.index.nearest_neighbors(x_i, 1, &mut nearest);
+                    let (j, _) = nearest
+                        .first()
+                        .copied()
+                        .expect("at least one particle is required for interpolation");
+                    particle_quantity[j]
+                })
+            })
+            .collect()
+    }
+
     /// Interpolates a per particle quantity to the given points, panics if the there are less per-particles values than particles, appends to the given vector
     #[allow(non_snake_case)]
     fn interpolate_quantity_inplace<T: InterpolationQuantity<R>>(
@@ -202,85 +529,78 @@ impl<R: Real> SphInterpolator<R> {
         particle_quantity: &[T],
         interpolation_points: &[Vector3<R>],
         interpolated_values: &mut Vec<T>,
-        first_order_correction: bool,
+        mode: InterpolationMode,
     ) {
         profile!("interpolate_quantity_inplace");
-        assert_eq!(particle_quantity.len(), self.tree.size());
+        assert_eq!(particle_quantity.len(), self.index.len());
 
-        let squared_support = self.compact_support_radius * self.compact_support_radius;
         let kernel = kernel::CubicSplineKernel::new(self.compact_support_radius);
 
-        let enable_correction = if first_order_correction {
-            R::one()
-        } else {
-            R::zero()
-        };
-
         interpolation_points
             .par_iter()
-            .map(|x_i| {
+            .map_init(Vec::new, |neighbors, x_i| {
+                // Collect all particles within the support radius with their relative position (scaled by the support radius
+                // for a well conditioned MLS moment matrix) and SPH weight `V_j * W_ij`
+                neighbors.clear();
+                self.index
+                    .for_each_within_radius(x_i, self.compact_support_radius, |j, x_j| {
+                        // Relative position `dx` and distance `r` of the neighbor particle
+                        let dx = x_j - x_i;
+                        let r = dx.norm();
+
+                        let vol_j = self.particle_volumes[j];
+                        let W_ij = kernel.evaluate(r);
+                        neighbors.push((j, dx.unscale(self.compact_support_radius), vol_j * W_ij));
+                    });
+
+                // Sum of all SPH weights for the Shepard normalization
+                let weight_sum = neighbors
+                    .iter()
+                    .map(|(_, _, w_j)| *w_j)
+                    .fold(R::zero(), R::add);
+
+                // Optional MLS correction of the weights: `w_j * (M^-1 p_j)[0]` with the linear basis `p_j = [1, dx]`
+                let mls_correction = if mode == InterpolationMode::MovingLeastSquares {
+                    let mut moment_matrix = Matrix4::zeros();
+                    for (_, dx, w_j) in neighbors.iter() {
+                        let p_j = Vector4::new(R::one(), dx.x, dx.y, dx.z);
+                        moment_matrix += (p_j * p_j.transpose()).scale(*w_j);
+                    }
+                    // First row of the inverse moment matrix (equal to the first column as the matrix is symmetric)
+                    moment_matrix
+                        .try_inverse()
+                        .map(|inverse| inverse.column(0).into_owned())
+                        .filter(|c| c.iter().all(|v| v.is_finite()))
+                } else {
+                    None
+                };
+
                 let mut interpolated_value = T::zero();
-                let mut correction = R::zero();
-
-                // SPH: Iterate over all other particles within the squared support radius
-                let query_point = bytemuck::cast::<_, [R; 3]>(*x_i);
-                for p_j in self
-                    .tree
-                    .locate_within_distance(query_point, squared_support)
-                {
-                    // Volume of the neighbor particle
-                    let vol_j = p_j.data.volume;
-                    // Position of the neighbor particle
-                    let x_j = bytemuck::cast_ref::<_, Vector3<R>>(p_j.geom());
-
-                    // Relative position `dx` and distance `r` of the neighbor particle
-                    let dx = x_j - x_i;
-                    let r = dx.norm();
+                for (j, dx, w_j) in neighbors.iter() {
+                    let weight = match (mode, &mls_correction) {
+                        (InterpolationMode::MovingLeastSquares, Some(c)) => {
+                            *w_j * (c.x + c.y * dx.x + c.z * dx.y + c.w * dx.z)
+                        }
+                        (InterpolationMode::Sph, _) => *w_j,
+                        // Shepard normalization (also used as fallback for MLS)
+                        _ => *w_j / weight_sum,
+                    };
 
                     // Unchecked access is fine as we asserted before that the slice has the correct length
-                    let A_j = unsafe { particle_quantity.get_unchecked(p_j.data.index).clone() };
-                    let W_ij = kernel.evaluate(r);
-
-                    interpolated_value += A_j.scale(vol_j * W_ij);
-                    correction += vol_j * W_ij;
+                    let A_j = unsafe { particle_quantity.get_unchecked(*j).clone() };
+                    interpolated_value += A_j.scale(weight);
                 }
 
-                let correction_factor =
-                    enable_correction * correction.recip() + (R::one() - enable_correction);
-                interpolated_value.scale(correction_factor)
+                interpolated_value
             })
             .collect_into_vec(interpolated_values);
     }
 }
 
-/// Constructs an R-Tree for the given particles, storing each particle's index and volume in the tree
-fn build_rtree<R: Real>(
-    particle_positions: &[Vector3<R>],
-    particle_densities: &[R],
-    particle_rest_mass: R,
-) -> RTree<Particle<R>> {
-    assert_eq!(particle_positions.len(), particle_densities.len());
-
-    // Prepare data for R-tree insertion:
-    // Collect all particles with their position and compute their volume
-    let particles = particle_positions
-        .iter()
-        .zip(particle_densities.iter().copied())
-        .enumerate()
-        .map(|(i, (p, rho_i))| {
-            let data = ParticleData {
-                index: i,
-                volume: particle_rest_mass / rho_i,
-            };
-            Particle::new(bytemuck::cast(*p), data)
-        })
-        .collect();
-
-    // Build the R-tree to accelerate SPH neighbor queries near the interpolation points
-    let tree = {
-        profile!("build R-tree");
-        RTree::bulk_load(particles)
-    };
-
-    tree
+/// Applies the given function to the eigenvalues of the symmetric part of the given matrix
+fn map_symmetric_eigenvalues<R: Real>(m: &Matrix3<R>, f: impl Fn(R) -> R) -> Matrix3<R> {
+    let symmetric = (m + m.transpose()).scale(R::from_f64(0.5).unwrap());
+    let mut eigen = symmetric.symmetric_eigen();
+    eigen.eigenvalues.apply(|lambda| *lambda = f(*lambda));
+    eigen.recompose()
 }
@@ -86,6 +86,37 @@ impl<'a, T> UnsafeSlice<'a, T> {
     }
 }
 
+/// Disjoint-set forest over the indices `0..n` with path halving
+#[derive(Clone, Debug)]
+pub(crate) struct UnionFind {
+    parents: Vec<usize>,
+}
+
+impl UnionFind {
+    /// Creates `n` singleton sets
+    pub fn new(n: usize) -> Self {
+        Self {
+            parents: (0..n).collect(),
+        }
+    }
+
+    /// Returns the representative of the set containing `i`
+    pub fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+        i
+    }
+
+    /// Merges the sets containing `a` and `b`, the representative of the set of `a` becomes the representative of the merged set
+    pub fn union(&mut self, a: usize, b: usize) -> usize {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parents[b] = a;
+        a
+    }
+}
+
 /// Ensures that at least the specified total capacity is reserved for the given vector
 pub(crate) fn reserve_total<T>(vec: &mut Vec<T>, total_capacity: usize) {
     if total_capacity > vec.capacity() {
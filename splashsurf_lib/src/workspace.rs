@@ -12,6 +12,8 @@ use thread_local::ThreadLocal;
 pub struct ReconstructionWorkspace<R: Real> {
     /// Temporary storage for storing a filtered set of the user provided particles
     filtered_particles: Vec<Vector3<R>>,
+    /// Temporary storage for the particles reordered along a space-filling curve
+    reordered_particles: Vec<Vector3<R>>,
     local_workspaces: ThreadLocal<RefCell<LocalReconstructionWorkspace<R>>>,
 }
 
@@ -21,6 +23,11 @@ impl<R: Real> ReconstructionWorkspace<R> {
         &mut self.filtered_particles
     }
 
+    /// Returns a mutable reference to the global reordered particles vector
+    pub(crate) fn reordered_particles_mut(&mut self) -> &mut Vec<Vector3<R>> {
+        &mut self.reordered_particles
+    }
+
     /// Returns a reference to a thread local workspace, initializes it with the given capacity if not already initialized
     pub(crate) fn get_local_with_capacity(
         &self,
@@ -3,3 +3,4 @@ pub mod test_full;
 #[cfg(feature = "io")]
 pub mod test_mesh;
 pub mod test_neighborhood_search;
+pub mod test_sph_interpolation;
@@ -2,9 +2,14 @@ use nalgebra::Vector3;
 use splashsurf_lib::io::particles_from_file;
 use splashsurf_lib::io::vtk_format::write_vtk;
 use splashsurf_lib::marching_cubes::check_mesh_consistency;
+use splashsurf_lib::mesh::AttributeData;
+use splashsurf_lib::postprocessing::{postprocess_surface, PostprocessingParameters};
+use splashsurf_lib::space_filling_curve::SpaceFillingCurve;
 use splashsurf_lib::{
-    reconstruct_surface, Aabb3d, GridDecompositionParameters, Parameters, Real,
-    SpatialDecomposition,
+    reconstruct_surface, reconstruct_surface_incremental, Aabb3d, GridDecompositionParameters,
+    IncrementalParameters, Parameters, Real, ReconstructionError, SpatialDecomposition,
+    SubdomainGridError, SurfaceReconstruction, TabulatedKernelParameters,
+    VertexProvenanceParameters,
 };
 use std::path::Path;
 
@@ -37,6 +42,9 @@ fn params_with_aabb<R: Real>(
         enable_multi_threading: false,
         spatial_decomposition: None,
         global_neighborhood_list: false,
+        vertex_provenance: None,
+        tabulated_kernel: None,
+        particle_reordering: None,
     };
 
     match strategy {
@@ -45,6 +53,9 @@ fn params_with_aabb<R: Real>(
             parameters.spatial_decomposition = Some(SpatialDecomposition::UniformGrid(
                 GridDecompositionParameters {
                     subdomain_num_cubes_per_dim: 64,
+                    enable_simd: true,
+                    local_f32_coordinates: false,
+                    auto_tuning: false,
                 },
             ))
         }
@@ -154,3 +165,475 @@ generate_test!(f32, surface_reconstruction_knot_grid, "sailors_knot_19539_partic
 
 generate_test!(f32, surface_reconstruction_free_particles_01, "free_particles_1000_particles.vtk" => "reconstruct_surface_free_particles_01_global.vtk", params(0.5, 4.0, 1.5, 0.45, Strategy::Global), 21000, 25000);
 generate_test!(f32, surface_reconstruction_free_particles_02, "free_particles_125_particles.vtk" => "reconstruct_surface_free_particles_02_global.vtk", params_with_aabb(0.5, 4.0, 1.5, 0.45, Some(Aabb3d::new(Vector3::new(-10.0, -10.0, -10.0), Vector3::new(210.0, 210.0, 210.0))), Strategy::Global), 1500, 1600);
+
+#[test]
+fn surface_reconstruction_knot_grid_postprocessing() {
+    let input_file = Path::new("../data/").join("sailors_knot_19539_particles.vtk");
+    let particle_positions: Vec<Vector3<f32>> = particles_from_file(input_file).unwrap();
+
+    let mut parameters = params(0.025, 4.0, 1.1, 0.6, Strategy::SubdomainGrid);
+    parameters.global_neighborhood_list = true;
+
+    let reconstruction =
+        reconstruct_surface::<i64, _>(particle_positions.as_slice(), &parameters).unwrap();
+
+    let postprocessing = PostprocessingParameters {
+        compute_normals: true,
+        sph_normals: true,
+        mesh_smoothing_iters: Some(25),
+        mesh_smoothing_weights: true,
+        output_mesh_smoothing_weights: true,
+        ..Default::default()
+    };
+
+    let grid = reconstruction.grid().clone();
+    let (mesh_with_data, _) = postprocess_surface(
+        reconstruction,
+        particle_positions.as_slice(),
+        Vec::new(),
+        &parameters,
+        &postprocessing,
+    )
+    .unwrap();
+
+    let num_vertices = mesh_with_data.mesh.vertices.len();
+    assert!(num_vertices > 0);
+    for name in ["wnn", "sw", "normals"] {
+        let attribute = mesh_with_data
+            .point_attributes
+            .iter()
+            .find(|a| a.name == name)
+            .unwrap_or_else(|| panic!("Mesh is missing point attribute \"{}\"", name));
+        assert_eq!(attribute.data.len(), num_vertices);
+    }
+
+    let sw = mesh_with_data
+        .point_attributes
+        .iter()
+        .find_map(|a| match &a.data {
+            AttributeData::ScalarReal(values) if a.name == "sw" => Some(values),
+            _ => None,
+        })
+        .unwrap();
+    assert!(sw.iter().all(|&w| (0.0..=1.0).contains(&w)));
+
+    if let Err(e) = check_mesh_consistency(&grid, &mesh_with_data.mesh, true, true, true) {
+        eprintln!("{}", e);
+        panic!("Post-processed mesh contains topological/manifold errors");
+    }
+}
+
+#[test]
+fn surface_reconstruction_knot_grid_vertex_provenance() {
+    let input_file = Path::new("../data/").join("sailors_knot_19539_particles.vtk");
+    let particle_positions: Vec<Vector3<f32>> = particles_from_file(input_file).unwrap();
+
+    let mut parameters = params(0.025, 4.0, 1.1, 0.6, Strategy::SubdomainGrid);
+    parameters.vertex_provenance = Some(VertexProvenanceParameters { num_particles: 3 });
+
+    let reconstruction =
+        reconstruct_surface::<i64, _>(particle_positions.as_slice(), &parameters).unwrap();
+
+    let mesh = reconstruction.mesh();
+    let provenance = reconstruction.vertex_provenance().unwrap();
+    assert_eq!(provenance.len(), mesh.vertices.len());
+    assert_eq!(provenance.particle_indices.len(), 3 * mesh.vertices.len());
+    assert_eq!(provenance.particle_weights.len(), 3 * mesh.vertices.len());
+
+    let squared_support = parameters.compact_support_radius * parameters.compact_support_radius;
+    for (v, vertex) in mesh.vertices.iter().enumerate() {
+        // Every vertex is generated by at least one particle
+        let nearest = provenance.particles(v)[0];
+        assert!(nearest < particle_positions.len());
+        assert!(provenance.weights(v)[0] > 0.0);
+
+        let mut last_dist_sq = 0.0;
+        for &p in provenance.particles(v).iter().filter(|&&p| p != usize::MAX) {
+            let dist_sq = (particle_positions[p] - vertex).norm_squared();
+            assert!(dist_sq < squared_support);
+            assert!(dist_sq >= last_dist_sq);
+            last_dist_sq = dist_sq;
+        }
+    }
+
+    let attributes = provenance.to_attributes();
+    assert_eq!(attributes.len(), 1 + 2 * 3);
+    assert!(attributes
+        .iter()
+        .all(|a| a.data.len() == mesh.vertices.len()));
+}
+
+#[test]
+fn surface_reconstruction_knot_stats() {
+    let input_file = Path::new("../data/").join("sailors_knot_19539_particles.vtk");
+    let particle_positions: Vec<Vector3<f32>> = particles_from_file(input_file).unwrap();
+
+    let parameters = params(0.025, 4.0, 1.1, 0.6, Strategy::SubdomainGrid);
+    let reconstruction =
+        reconstruct_surface::<i64, _>(particle_positions.as_slice(), &parameters).unwrap();
+
+    let stats = reconstruction.stats();
+    assert_eq!(stats.num_input_particles, particle_positions.len());
+    assert_eq!(stats.num_particles, particle_positions.len());
+    assert_eq!(stats.num_vertices, reconstruction.mesh().vertices.len());
+    assert_eq!(stats.num_triangles, reconstruction.mesh().triangles.len());
+
+    let subdomains = stats.subdomains.as_ref().unwrap();
+    assert!(subdomains.num_nonempty_subdomains <= subdomains.num_subdomains);
+    assert_eq!(
+        subdomains.num_dense_subdomains + subdomains.num_sparse_subdomains,
+        subdomains.num_nonempty_subdomains
+    );
+    assert!(subdomains.total_subdomain_particles >= particle_positions.len());
+    assert!(subdomains.largest_subdomain_particles > subdomains.sparse_particle_limit);
+    assert!(subdomains.ghost_margin_volume_fraction > 0.0);
+
+    for stage in [
+        "grid construction",
+        "initialization",
+        "decomposition",
+        "density computation",
+        "reconstruction",
+        "stitching",
+    ] {
+        assert!(stats.timing(stage).is_some(), "missing timing of {stage}");
+    }
+    let stage_sum = stats
+        .timings
+        .iter()
+        .map(|t| t.duration)
+        .sum::<std::time::Duration>();
+    assert!(stats.total_duration >= stage_sum);
+
+    let parameters = params(0.025, 4.0, 1.1, 0.6, Strategy::Global);
+    let reconstruction =
+        reconstruct_surface::<i64, _>(particle_positions.as_slice(), &parameters).unwrap();
+    let stats = reconstruction.stats();
+    assert!(stats.subdomains.is_none());
+    assert_eq!(stats.num_vertices, reconstruction.mesh().vertices.len());
+    assert!(stats.timing("marching cubes").is_some());
+}
+
+#[test]
+fn surface_reconstruction_knot_grid_ghost_margin_error() {
+    let input_file = Path::new("../data/").join("sailors_knot_19539_particles.vtk");
+    let particle_positions: Vec<Vector3<f32>> = particles_from_file(input_file).unwrap();
+
+    // The ghost margin (5 cubes, rounded up) is thicker than half of the subdomain extent (2 cubes)
+    let mut parameters = params(0.025, 4.0, 1.0, 0.6, Strategy::SubdomainGrid);
+    parameters.spatial_decomposition = Some(SpatialDecomposition::UniformGrid(
+        GridDecompositionParameters {
+            subdomain_num_cubes_per_dim: 4,
+            enable_simd: true,
+            local_f32_coordinates: false,
+            auto_tuning: false,
+        },
+    ));
+
+    let result = reconstruct_surface::<i64, _>(particle_positions.as_slice(), &parameters);
+    match result {
+        Err(ReconstructionError::SubdomainGridError(SubdomainGridError::GhostMarginTooThick {
+            ghost_margin_cubes,
+            subdomain_cubes,
+        })) => {
+            assert_eq!(ghost_margin_cubes, 5);
+            assert_eq!(subdomain_cubes, 4);
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+}
+
+fn test_grid_simd_consistency<R: Real>(particle_positions: &[Vector3<R>]) {
+    let mut parameters = params(
+        R::from_f64(0.025).unwrap(),
+        R::from_f64(4.0).unwrap(),
+        R::from_f64(0.8).unwrap(),
+        R::from_f64(0.6).unwrap(),
+        Strategy::SubdomainGrid,
+    );
+
+    let mut meshes = Vec::new();
+    for enable_simd in [false, true] {
+        parameters.spatial_decomposition = Some(SpatialDecomposition::UniformGrid(
+            GridDecompositionParameters {
+                subdomain_num_cubes_per_dim: 32,
+                enable_simd,
+                local_f32_coordinates: false,
+                auto_tuning: false,
+            },
+        ));
+        let reconstruction =
+            reconstruct_surface::<i64, _>(particle_positions, &parameters).unwrap();
+        meshes.push(reconstruction.mesh().clone());
+    }
+
+    // The SIMD and the scalar level-set evaluation have to give bitwise identical results
+    assert_eq!(meshes[0].vertices, meshes[1].vertices);
+    assert_eq!(meshes[0].triangles, meshes[1].triangles);
+}
+
+#[test]
+fn surface_reconstruction_knot_grid_simd_consistency() {
+    let input_file = Path::new("../data/").join("sailors_knot_19539_particles.vtk");
+    let particle_positions: Vec<Vector3<f32>> = particles_from_file(input_file).unwrap();
+
+    test_grid_simd_consistency(&particle_positions);
+
+    let particle_positions: Vec<Vector3<f64>> = particle_positions
+        .iter()
+        .map(|p| p.map(|x| x as f64))
+        .collect();
+    test_grid_simd_consistency(&particle_positions);
+}
+
+#[test]
+fn surface_reconstruction_knot_grid_tabulated_kernel() {
+    let input_file = Path::new("../data/").join("sailors_knot_19539_particles.vtk");
+    let particle_positions: Vec<Vector3<f32>> = particles_from_file(input_file).unwrap();
+
+    let mut parameters = params(0.025, 4.0, 0.8, 0.6, Strategy::SubdomainGrid);
+    let exact = reconstruct_surface::<i64, _>(&particle_positions, &parameters).unwrap();
+
+    parameters.tabulated_kernel = Some(TabulatedKernelParameters {
+        resolution: 1024,
+        max_relative_error: Some(1e-4),
+    });
+    let tabulated = reconstruct_surface::<i64, _>(&particle_positions, &parameters).unwrap();
+
+    if let Err(e) = check_mesh_consistency(tabulated.grid(), tabulated.mesh(), true, true, true) {
+        eprintln!("{}", e);
+        panic!("Mesh contains topological/manifold errors");
+    }
+
+    // A small interpolation error of the kernel should only slightly shift the surface
+    let num_exact = exact.mesh().vertices.len() as f64;
+    let num_tabulated = tabulated.mesh().vertices.len() as f64;
+    assert!((num_exact - num_tabulated).abs() / num_exact < 0.01);
+}
+
+#[test]
+fn surface_reconstruction_knot_particle_reordering() {
+    let input_file = Path::new("../data/").join("sailors_knot_19539_particles.vtk");
+    let particle_positions: Vec<Vector3<f64>> = particles_from_file(input_file).unwrap();
+
+    for strategy in [Strategy::Global, Strategy::SubdomainGrid] {
+        let mut parameters = params(0.025, 4.0, 0.8, 0.6, strategy);
+        parameters.global_neighborhood_list = true;
+        let reference = reconstruct_surface::<i64, _>(&particle_positions, &parameters).unwrap();
+
+        for curve in [SpaceFillingCurve::Morton, SpaceFillingCurve::Hilbert] {
+            parameters.particle_reordering = Some(curve);
+            let reconstruction =
+                reconstruct_surface::<i64, _>(&particle_positions, &parameters).unwrap();
+
+            // Per-particle outputs have to be returned in the input order
+            let densities = reconstruction.particle_densities().unwrap();
+            let reference_densities = reference.particle_densities().unwrap();
+            assert_eq!(densities.len(), particle_positions.len());
+            for (rho, rho_ref) in densities.iter().zip(reference_densities.iter()) {
+                assert!((rho - rho_ref).abs() <= 1e-9 * rho_ref.abs());
+            }
+
+            if let (Some(neighbors), Some(reference_neighbors)) = (
+                reconstruction.particle_neighbors(),
+                reference.particle_neighbors(),
+            ) {
+                for (nl, nl_ref) in neighbors.iter().zip(reference_neighbors.iter()) {
+                    let mut nl = nl.clone();
+                    let mut nl_ref = nl_ref.clone();
+                    nl.sort_unstable();
+                    nl_ref.sort_unstable();
+                    assert_eq!(nl, nl_ref);
+                }
+            }
+
+            // The reconstructed surface only differs due to the summation order of the densities
+            let num_vertices = reconstruction.mesh().vertices.len() as f64;
+            let num_vertices_ref = reference.mesh().vertices.len() as f64;
+            assert!((num_vertices - num_vertices_ref).abs() / num_vertices_ref < 0.001);
+        }
+    }
+}
+
+#[test]
+fn surface_reconstruction_knot_grid_local_f32_coordinates() {
+    let input_file = Path::new("../data/").join("sailors_knot_19539_particles.vtk");
+    let particle_positions: Vec<Vector3<f32>> = particles_from_file(input_file).unwrap();
+
+    // Move the particles far away from the origin where f32 coordinates lose most of their precision
+    let offset = Vector3::new(10_000.0, -20_000.0, 5_000.0);
+    let particle_positions: Vec<Vector3<f64>> = particle_positions
+        .iter()
+        .map(|p| p.map(|x| x as f64) + offset)
+        .collect();
+
+    let mut parameters = params(0.025, 4.0, 0.8, 0.6, Strategy::SubdomainGrid);
+    let reference = reconstruct_surface::<i64, _>(&particle_positions, &parameters).unwrap();
+
+    parameters.spatial_decomposition = Some(SpatialDecomposition::UniformGrid(
+        GridDecompositionParameters {
+            subdomain_num_cubes_per_dim: 64,
+            enable_simd: true,
+            local_f32_coordinates: true,
+            auto_tuning: false,
+        },
+    ));
+    let reconstruction = reconstruct_surface::<i64, _>(&particle_positions, &parameters).unwrap();
+
+    if let Err(e) = check_mesh_consistency(
+        reconstruction.grid(),
+        reconstruction.mesh(),
+        true,
+        true,
+        true,
+    ) {
+        eprintln!("{}", e);
+        panic!("Mesh contains topological/manifold errors");
+    }
+
+    // The densities only differ by the single precision rounding relative to the subdomain origins
+    let densities = reconstruction.particle_densities().unwrap();
+    let reference_densities = reference.particle_densities().unwrap();
+    for (rho, rho_ref) in densities.iter().zip(reference_densities.iter()) {
+        assert!((rho - rho_ref).abs() <= 1e-5 * rho_ref.abs());
+    }
+
+    let num_vertices = reconstruction.mesh().vertices.len() as f64;
+    let num_vertices_ref = reference.mesh().vertices.len() as f64;
+    assert!((num_vertices - num_vertices_ref).abs() / num_vertices_ref < 0.001);
+
+    // The vertices are returned in global coordinates
+    let aabb = reference.grid().aabb();
+    assert!(reconstruction
+        .mesh()
+        .vertices
+        .iter()
+        .all(|v| aabb.contains_point(v)));
+}
+
+#[test]
+fn surface_reconstruction_knot_grid_incremental() {
+    let input_file = Path::new("../data/").join("sailors_knot_19539_particles.vtk");
+    let particle_positions: Vec<Vector3<f32>> = particles_from_file(input_file).unwrap();
+
+    let mut parameters = params(0.025, 4.0, 0.8, 0.6, Strategy::SubdomainGrid);
+    parameters.spatial_decomposition = Some(SpatialDecomposition::UniformGrid(
+        GridDecompositionParameters {
+            subdomain_num_cubes_per_dim: 16,
+            ..Default::default()
+        },
+    ));
+
+    let exact = IncrementalParameters {
+        position_tolerance: 0.0,
+        density_tolerance: 0.0,
+    };
+    let mut surface = SurfaceReconstruction::default();
+    reconstruct_surface_incremental::<i64, _>(
+        &particle_positions,
+        &parameters,
+        &exact,
+        &mut surface,
+    )
+    .unwrap();
+
+    // Move the particles of a small region of the knot
+    let aabb = Aabb3d::par_from_points(&particle_positions);
+    let x_split = aabb.max().x - 0.1 * aabb.extents().x;
+    let moved_positions = particle_positions
+        .iter()
+        .map(|p| {
+            if p.x > x_split {
+                p + Vector3::new(0.0, 0.005, 0.0)
+            } else {
+                *p
+            }
+        })
+        .collect::<Vec<_>>();
+
+    reconstruct_surface_incremental(&moved_positions, &parameters, &exact, &mut surface).unwrap();
+    let subdomain_stats = surface.stats().subdomains.clone().unwrap();
+    assert!(subdomain_stats.num_reused_subdomains > 0);
+    assert!(subdomain_stats.num_reused_subdomains < subdomain_stats.num_nonempty_subdomains);
+
+    // Without tolerances, the reused patches are identical to recomputed patches
+    let reference = reconstruct_surface::<i64, _>(&moved_positions, &parameters).unwrap();
+    assert_eq!(surface.mesh().vertices, reference.mesh().vertices);
+    assert_eq!(surface.mesh().triangles, reference.mesh().triangles);
+
+    // Reused patches have to be stitched to the recomputed patches without holes
+    let tolerances = IncrementalParameters {
+        position_tolerance: 0.001,
+        density_tolerance: 0.01,
+    };
+    let jittered_positions = moved_positions
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let offset = (i % 7) as f32 / 7.0 - 0.5;
+            let mut p = p + Vector3::new(offset, -offset, 0.5 * offset) * 0.0005;
+            if p.x > x_split {
+                p.y += 0.005;
+            }
+            p
+        })
+        .collect::<Vec<_>>();
+    reconstruct_surface_incremental(&jittered_positions, &parameters, &tolerances, &mut surface)
+        .unwrap();
+    assert!(
+        surface
+            .stats()
+            .subdomains
+            .as_ref()
+            .unwrap()
+            .num_reused_subdomains
+            > 0
+    );
+
+    if let Err(e) = check_mesh_consistency(surface.grid(), surface.mesh(), true, true, true) {
+        eprintln!("{}", e);
+        panic!("Mesh contains topological/manifold errors");
+    }
+}
+
+#[test]
+fn surface_reconstruction_knot_grid_auto_tuning() {
+    let input_file = Path::new("../data/").join("sailors_knot_19539_particles.vtk");
+    let particle_positions: Vec<Vector3<f32>> = particles_from_file(input_file).unwrap();
+
+    let mut parameters = params(0.025, 4.0, 0.8, 0.6, Strategy::SubdomainGrid);
+    let reference = reconstruct_surface::<i64, _>(&particle_positions, &parameters).unwrap();
+
+    parameters.spatial_decomposition = Some(SpatialDecomposition::UniformGrid(
+        GridDecompositionParameters {
+            // Ignored in favor of the automatically selected size
+            subdomain_num_cubes_per_dim: 1,
+            auto_tuning: true,
+            ..Default::default()
+        },
+    ));
+    let reconstruction = reconstruct_surface::<i64, _>(&particle_positions, &parameters).unwrap();
+
+    if let Err(e) = check_mesh_consistency(
+        reconstruction.grid(),
+        reconstruction.mesh(),
+        true,
+        true,
+        true,
+    ) {
+        eprintln!("{}", e);
+        panic!("Mesh contains topological/manifold errors");
+    }
+
+    let stats = reconstruction.stats().subdomains.as_ref().unwrap();
+    assert!(stats.subdomain_cubes as f64 >= 2.0 * stats.ghost_margin_cubes);
+    assert_eq!(
+        stats.num_dense_subdomains + stats.num_sparse_subdomains,
+        stats.num_nonempty_subdomains
+    );
+
+    // The reconstructed surface only differs due to the summation order of the densities
+    let num_vertices = reconstruction.mesh().vertices.len() as f64;
+    let num_vertices_ref = reference.mesh().vertices.len() as f64;
+    assert!((num_vertices - num_vertices_ref).abs() / num_vertices_ref < 0.001);
+}
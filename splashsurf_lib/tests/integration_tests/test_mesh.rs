@@ -1,6 +1,17 @@
-use splashsurf_lib::halfedge_mesh::HalfEdgeTriMesh;
+use nalgebra::{Vector2, Vector3};
+use splashsurf_lib::halfedge_mesh::{HalfEdgeTriMesh, IllegalEdgeFlip, IllegalEdgeSplit};
 use splashsurf_lib::io;
-use splashsurf_lib::mesh::{Mesh3d, MeshWithData};
+use splashsurf_lib::mesh::{AttributeData, Mesh3d, MeshAttribute, MeshWithData, TriMesh3d};
+use splashsurf_lib::postprocessing::{
+    advected_uv_coordinates, clip_with_aabb, clip_with_plane, components_to_particles,
+    isotropic_remeshing, loop_subdivision, par_smoothing_inplace, par_volume_correction_inplace,
+    postprocess_surface, quadric_decimation, quadric_decimation_with_data, remove_small_components,
+    repair_mesh, split_non_manifold_vertices, triplanar_uv_coordinates, AdvectedUvParameters,
+    IsotropicRemeshingParameters, LoopSubdivisionParameters, MeshRepairParameters,
+    MeshSmoothingMethod, PostprocessingParameters, QuadricDecimationParameters,
+    RemeshingProjection,
+};
+use splashsurf_lib::{reconstruct_surface, Aabb3d, Parameters};
 
 #[test]
 fn test_halfedge_ico() -> Result<(), anyhow::Error> {
@@ -64,3 +75,1033 @@ fn test_halfedge_plane() -> Result<(), anyhow::Error> {
     io::obj_format::mesh_to_obj(&MeshWithData::new(tri_mesh), "../out/plane_new_1.obj")?;
     Ok(())
 }
+
+#[test]
+fn test_vertex_curvatures_sphere() -> Result<(), anyhow::Error> {
+    let mesh = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/icosphere.obj")?.mesh;
+    let curvatures = mesh.vertex_curvatures();
+
+    // Unit sphere: k1 = k2 = H = K = 1 (up to the discretization error of the coarse mesh)
+    for i in 0..mesh.vertices.len() {
+        assert!(
+            (curvatures.mean[i] - 1.0).abs() < 0.2,
+            "{}",
+            curvatures.mean[i]
+        );
+        assert!(
+            (curvatures.gaussian[i] - 1.0).abs() < 0.3,
+            "{}",
+            curvatures.gaussian[i]
+        );
+        let [k1, k2] = curvatures.principal[i];
+        assert!(k1 >= k2);
+        assert!((k1 + k2 - 2.0 * curvatures.mean[i]).abs() < 1e-12);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_vertex_curvatures_plane() -> Result<(), anyhow::Error> {
+    let mesh = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/plane.obj")?.mesh;
+    let curvatures = mesh.vertex_curvatures();
+
+    // Flat plane: all curvatures vanish (boundary vertices are set to zero)
+    assert!(curvatures.mean.iter().all(|h| h.abs() < 1e-10));
+    assert!(curvatures.gaussian.iter().all(|k| k.abs() < 1e-10));
+
+    Ok(())
+}
+
+#[test]
+fn test_quadric_decimation_plane() -> Result<(), anyhow::Error> {
+    let mut mesh = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/plane.obj")?.mesh;
+    let boundary_edges_before = mesh.compute_edge_information().count_boundary_edges();
+
+    let parameters = QuadricDecimationParameters {
+        target_triangle_count: Some(400),
+        ..Default::default()
+    };
+    quadric_decimation(&mut mesh, &parameters, false);
+
+    assert!(mesh.triangles.len() <= 400);
+    // Boundary has to be preserved and the mesh has to stay flat without flipped triangles
+    assert_eq!(
+        mesh.compute_edge_information().count_boundary_edges(),
+        boundary_edges_before
+    );
+    assert!(mesh.vertices.iter().all(|v| v.y.abs() < 1e-12));
+    for tri in &mesh.triangles {
+        let [a, b, c] = tri.map(|i| mesh.vertices[i]);
+        assert!((b - a).cross(&(c - a)).y > 0.0);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_quadric_decimation_max_error() -> Result<(), anyhow::Error> {
+    let mut mesh = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/plane.obj")?.mesh;
+
+    // Make mesh curved
+    for v in mesh.vertices.iter_mut() {
+        v.y = -0.1 * (v.x * v.x + v.z * v.z);
+    }
+
+    let decimate = |max_error: f64| {
+        let mut mesh = mesh.clone();
+        let parameters = QuadricDecimationParameters {
+            max_error: Some(max_error),
+            ..Default::default()
+        };
+        quadric_decimation(&mut mesh, &parameters, false);
+        mesh.triangles.len()
+    };
+
+    let num_triangles_fine = decimate(1e-4);
+    let num_triangles_coarse = decimate(1e-2);
+    assert!(num_triangles_fine < mesh.triangles.len());
+    assert!(num_triangles_coarse < num_triangles_fine);
+
+    Ok(())
+}
+
+#[test]
+fn test_quadric_decimation_with_data() -> Result<(), anyhow::Error> {
+    let mesh = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/icosphere.obj")?.mesh;
+    let num_vertices = mesh.vertices.len();
+    let num_triangles = mesh.triangles.len();
+
+    let mesh_with_data = MeshWithData::new(mesh.clone())
+        .with_point_data(MeshAttribute::new(
+            "id",
+            AttributeData::ScalarU64((0..num_vertices as u64).collect()),
+        ))
+        .with_cell_data(MeshAttribute::new(
+            "tri_id",
+            AttributeData::ScalarU64((0..num_triangles as u64).collect()),
+        ));
+
+    let parameters = QuadricDecimationParameters {
+        target_triangle_count: Some(60),
+        ..Default::default()
+    };
+    let decimated = quadric_decimation_with_data(&mesh_with_data, &parameters, false);
+
+    assert_eq!(decimated.mesh.triangles.len(), 60);
+
+    // Remaining vertices keep their position and attributes
+    let ids = match &decimated.point_attributes[0].data {
+        AttributeData::ScalarU64(ids) => ids,
+        _ => panic!("unexpected attribute type"),
+    };
+    assert_eq!(ids.len(), decimated.mesh.vertices.len());
+    for (v, &id) in decimated.mesh.vertices.iter().zip(ids.iter()) {
+        assert_eq!(*v, mesh.vertices[id as usize]);
+    }
+
+    // Cell attributes are filtered to the remaining triangles
+    let tri_ids = match &decimated.cell_attributes[0].data {
+        AttributeData::ScalarU64(tri_ids) => tri_ids,
+        _ => panic!("unexpected attribute type"),
+    };
+    assert_eq!(tri_ids.len(), 60);
+    assert!(tri_ids
+        .iter()
+        .all(|&tri_id| (tri_id as usize) < num_triangles));
+
+    Ok(())
+}
+
+#[test]
+fn test_mesh_smoothing_methods() -> Result<(), anyhow::Error> {
+    let mesh = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/icosphere.obj")?.mesh;
+    let vertex_connectivity = mesh.vertex_vertex_connectivity();
+    let weights = vec![1.0; mesh.vertices.len()];
+
+    let mean_radius = |mesh: &TriMesh3d<f64>| {
+        mesh.vertices.iter().map(|v| v.norm()).sum::<f64>() / mesh.vertices.len() as f64
+    };
+    let smooth = |mesh: &TriMesh3d<f64>, method, weights: &[f64]| {
+        let mut mesh = mesh.clone();
+        par_smoothing_inplace(&mut mesh, &vertex_connectivity, 10, method, weights);
+        mesh
+    };
+
+    // Uniform and cotangent Laplacian smoothing shrink the unit sphere, Taubin and HC smoothing preserve it
+    let laplacian = smooth(&mesh, MeshSmoothingMethod::Laplacian, &weights);
+    let cotangent = smooth(&mesh, MeshSmoothingMethod::Cotangent, &weights);
+    let taubin = smooth(&mesh, MeshSmoothingMethod::taubin(), &weights);
+    let hc = smooth(&mesh, MeshSmoothingMethod::hc(), &weights);
+    assert!(mean_radius(&laplacian) < 0.5);
+    assert!(mean_radius(&cotangent) < 0.5);
+    assert!(mean_radius(&taubin) > 0.95);
+    assert!(mean_radius(&hc) > 0.9);
+
+    // All methods reduce noise of the radius
+    let mut noisy_mesh = mesh.clone();
+    for (i, v) in noisy_mesh.vertices.iter_mut().enumerate() {
+        *v *= 1.0 + 0.05 * (7.0 * i as f64).sin();
+    }
+    let max_deviation = |mesh: &TriMesh3d<f64>| {
+        let r = mean_radius(mesh);
+        mesh.vertices
+            .iter()
+            .map(|v| (v.norm() - r).abs())
+            .fold(0.0, f64::max)
+    };
+    for method in [
+        MeshSmoothingMethod::Laplacian,
+        MeshSmoothingMethod::Cotangent,
+        MeshSmoothingMethod::taubin(),
+        MeshSmoothingMethod::hc(),
+    ] {
+        let smoothed = smooth(&noisy_mesh, method, &weights);
+        assert!(max_deviation(&smoothed) < max_deviation(&noisy_mesh));
+
+        // Vertices with zero feature weight are not moved
+        let fixed = smooth(&noisy_mesh, method, &vec![0.0; mesh.vertices.len()]);
+        assert_eq!(fixed.vertices, noisy_mesh.vertices);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_volume_correction() -> Result<(), anyhow::Error> {
+    let mesh = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/icosphere.obj")?.mesh;
+    let volume = mesh.signed_volume();
+    // Inscribed polyhedron of the unit sphere with outward oriented triangles
+    assert!(volume > 0.85 * 4.0 / 3.0 * std::f64::consts::PI);
+    assert!(volume < 4.0 / 3.0 * std::f64::consts::PI);
+
+    let mut smoothed_mesh = mesh.clone();
+    let vertex_connectivity = mesh.vertex_vertex_connectivity();
+    let weights = mesh
+        .vertices
+        .iter()
+        .map(|v| if v.y > 0.5 { 0.0 } else { 1.0 })
+        .collect::<Vec<_>>();
+    par_smoothing_inplace(
+        &mut smoothed_mesh,
+        &vertex_connectivity,
+        3,
+        MeshSmoothingMethod::Laplacian,
+        &weights,
+    );
+    assert!(smoothed_mesh.signed_volume() < 0.9 * volume);
+
+    let fixed_vertices = smoothed_mesh.vertices.clone();
+    let remaining_error =
+        par_volume_correction_inplace(&mut smoothed_mesh, volume, &weights, 1e-6, 20);
+    assert!(remaining_error <= 1e-6);
+    assert!(((smoothed_mesh.signed_volume() - volume) / volume).abs() <= 1e-6);
+
+    // Vertices with zero weight are not displaced
+    for (i, w) in weights.iter().enumerate() {
+        if *w == 0.0 {
+            assert_eq!(smoothed_mesh.vertices[i], fixed_vertices[i]);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_halfedge_split_and_flip() -> Result<(), anyhow::Error> {
+    let mesh = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/icosphere.obj")?.mesh;
+    let num_vertices = mesh.vertices.len();
+    let num_triangles = mesh.triangles.len();
+    let mut he_mesh = HalfEdgeTriMesh::from(mesh.clone());
+
+    // Split an edge at its midpoint
+    let [a, b, _] = mesh.triangles[0];
+    let m = he_mesh.try_edge_split(he_mesh.half_edge(a, b).unwrap())?;
+    assert_eq!(m, num_vertices);
+    assert_eq!(
+        he_mesh.vertices[m],
+        (mesh.vertices[a] + mesh.vertices[b]) / 2.0
+    );
+    assert_eq!(he_mesh.vertex_one_ring_len(m), 4);
+    assert!(he_mesh.half_edge(a, b).is_none());
+    assert!(he_mesh.half_edge(a, m).is_some() && he_mesh.half_edge(m, b).is_some());
+
+    // Flip one of the new edges back to the opposite vertices of the split edge
+    let c = he_mesh
+        .vertex_one_ring(m)
+        .find(|&v| v != a && v != b)
+        .unwrap();
+    let valence_c = he_mesh.vertex_one_ring_len(c);
+    he_mesh.try_edge_flip(he_mesh.half_edge(m, c).unwrap())?;
+    assert!(he_mesh.half_edge(m, c).is_none());
+    assert_eq!(he_mesh.vertex_one_ring_len(m), 3);
+    assert_eq!(he_mesh.vertex_one_ring_len(c), valence_c - 1);
+
+    // Vertices of valence three cannot lose another edge
+    let n = he_mesh.vertex_one_ring(m).next().unwrap();
+    assert_eq!(
+        he_mesh.try_edge_flip(he_mesh.half_edge(m, n).unwrap()),
+        Err(IllegalEdgeFlip::MinimalValence)
+    );
+
+    let (tri_mesh, _vertex_map) = he_mesh.into_parts(false);
+    assert_eq!(tri_mesh.vertices.len(), num_vertices + 1);
+    assert_eq!(tri_mesh.triangles.len(), num_triangles + 2);
+    assert!(tri_mesh.compute_manifold_information().is_closed());
+    assert!(tri_mesh.compute_manifold_information().is_manifold());
+    assert!(tri_mesh.signed_volume() > 0.0);
+
+    // Boundary edges can neither be split nor flipped
+    let plane = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/plane.obj")?.mesh;
+    let mut he_plane = HalfEdgeTriMesh::from(plane.clone());
+    let [a, b] = plane
+        .compute_edge_information()
+        .boundary_edges()
+        .next()
+        .unwrap();
+    let boundary_half_edge = he_plane
+        .half_edge(a, b)
+        .or_else(|| he_plane.half_edge(b, a))
+        .unwrap();
+    assert_eq!(
+        he_plane.try_edge_split(boundary_half_edge),
+        Err(IllegalEdgeSplit::BoundaryEdge)
+    );
+    assert_eq!(
+        he_plane.try_edge_flip(boundary_half_edge),
+        Err(IllegalEdgeFlip::BoundaryEdge)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_isotropic_remeshing() -> Result<(), anyhow::Error> {
+    let edge_lengths = |mesh: &TriMesh3d<f64>| {
+        mesh.compute_edge_information()
+            .iter()
+            .map(|e| (mesh.vertices[e.edge[0]] - mesh.vertices[e.edge[1]]).norm())
+            .collect::<Vec<_>>()
+    };
+
+    let mesh = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/icosphere.obj")?.mesh;
+    let initial_edge_lengths = edge_lengths(&mesh);
+    let initial_mean = initial_edge_lengths.iter().sum::<f64>() / initial_edge_lengths.len() as f64;
+
+    // Refine the mesh to half of the initial edge length
+    let target_edge_length = 0.5 * initial_mean;
+    let mut remeshed = mesh.clone();
+    isotropic_remeshing(
+        &mut remeshed,
+        &IsotropicRemeshingParameters::new(target_edge_length),
+        None,
+    )?;
+
+    assert!(remeshed.triangles.len() > 3 * mesh.triangles.len());
+    assert!(remeshed.compute_manifold_information().is_closed());
+    assert!(remeshed.compute_manifold_information().is_manifold());
+
+    let remeshed_edge_lengths = edge_lengths(&remeshed);
+    let mean = remeshed_edge_lengths.iter().sum::<f64>() / remeshed_edge_lengths.len() as f64;
+    assert!((mean - target_edge_length).abs() < 0.2 * target_edge_length);
+    assert!(remeshed_edge_lengths
+        .iter()
+        .all(|&l| l < 4.0 / 3.0 * target_edge_length + 1e-10));
+
+    // All vertices are projected onto the input mesh (inscribed polyhedron of the unit sphere)
+    assert!(remeshed
+        .vertices
+        .iter()
+        .all(|v| v.norm() <= 1.0 + 1e-10 && v.norm() > 0.75));
+
+    // Without projection, the vertices leave the input surface
+    let mut unprojected = mesh.clone();
+    isotropic_remeshing(
+        &mut unprojected,
+        &IsotropicRemeshingParameters {
+            projection: RemeshingProjection::None,
+            ..IsotropicRemeshingParameters::new(target_edge_length)
+        },
+        None,
+    )?;
+    assert!(unprojected.compute_manifold_information().is_closed());
+
+    // The level set projection requires an interpolator
+    let mut level_set = mesh.clone();
+    assert!(isotropic_remeshing(
+        &mut level_set,
+        &IsotropicRemeshingParameters {
+            projection: RemeshingProjection::LevelSet,
+            ..IsotropicRemeshingParameters::new(target_edge_length)
+        },
+        None,
+    )
+    .is_err());
+
+    // Boundaries of open meshes are preserved
+    let plane = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/plane.obj")?.mesh;
+    let plane_edge_lengths = edge_lengths(&plane);
+    let plane_mean = plane_edge_lengths.iter().sum::<f64>() / plane_edge_lengths.len() as f64;
+    let mut remeshed_plane = plane.clone();
+    isotropic_remeshing(
+        &mut remeshed_plane,
+        &IsotropicRemeshingParameters::new(1.5 * plane_mean),
+        None,
+    )?;
+
+    assert!(remeshed_plane.triangles.len() < plane.triangles.len());
+    assert!(remeshed_plane.compute_manifold_information().is_manifold());
+    assert_eq!(
+        remeshed_plane
+            .compute_edge_information()
+            .count_boundary_edges(),
+        plane.compute_edge_information().count_boundary_edges()
+    );
+    assert!(remeshed_plane.vertices.iter().all(|v| v.y.abs() < 1e-10));
+
+    Ok(())
+}
+
+#[test]
+fn test_connected_components() -> Result<(), anyhow::Error> {
+    let sphere = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/icosphere.obj")?.mesh;
+    let sphere_volume = sphere.signed_volume();
+
+    // Unit sphere and a small "droplet" sphere
+    let mut mesh = sphere.clone();
+    let offset = Vector3::new(3.0, 0.0, 0.0);
+    let mut droplet = sphere.clone();
+    droplet
+        .vertices
+        .iter_mut()
+        .for_each(|v| *v = *v * 0.1 + offset);
+    mesh.append(&mut droplet);
+
+    let components = mesh.connected_components();
+    assert_eq!(components.len(), 2);
+    assert!(components.vertex_components[..sphere.vertices.len()]
+        .iter()
+        .all(|&c| c == 0));
+    assert!(components.triangle_components[sphere.triangles.len()..]
+        .iter()
+        .all(|&c| c == 1));
+    for (c, scale) in components.components.iter().zip([1.0, 0.1]) {
+        assert_eq!(c.num_vertices, sphere.vertices.len());
+        assert_eq!(c.num_triangles, sphere.triangles.len());
+        assert!((c.volume - sphere_volume * scale * scale * scale).abs() < 1e-10);
+    }
+    assert!(components.components[0].centroid.norm() < 1e-10);
+    assert!((components.components[1].centroid - offset).norm() < 1e-10);
+
+    // Remove the droplet and convert it into a particle
+    let mesh_with_data = MeshWithData::new(mesh.clone()).with_point_data(MeshAttribute::new(
+        "id",
+        AttributeData::ScalarU64((0..mesh.vertices.len() as u64).collect()),
+    ));
+    let (filtered, removed) =
+        remove_small_components(&mesh_with_data, Some(0.01 * sphere_volume), None, false);
+    assert_eq!(filtered.mesh.vertices, sphere.vertices);
+    assert_eq!(filtered.mesh.triangles, sphere.triangles);
+    assert_eq!(
+        filtered.point_attributes[0].data.len(),
+        sphere.vertices.len()
+    );
+    assert_eq!(removed.len(), 1);
+
+    let particles = components_to_particles(&removed);
+    assert_eq!(particles.vertices().len(), 1);
+    assert!((particles.vertices()[0] - offset).norm() < 1e-10);
+
+    // Thresholds on the triangle count
+    let (filtered, removed) = remove_small_components(
+        &mesh_with_data,
+        None,
+        Some(sphere.triangles.len() + 1),
+        false,
+    );
+    assert!(filtered.mesh.triangles.is_empty());
+    assert_eq!(removed.len(), 2);
+
+    // Collapsed elements of a half-edge mesh are not part of any component
+    let mut he_mesh = HalfEdgeTriMesh::from(mesh);
+    he_mesh.try_half_edge_collapse(he_mesh.half_edge(12, 0).unwrap())?;
+    let he_components = he_mesh.connected_components();
+    assert_eq!(he_components.len(), 2);
+    assert_eq!(he_components.vertex_components[12], usize::MAX);
+    assert_eq!(
+        he_components.components[0].num_triangles,
+        sphere.triangles.len() - 2
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_postprocess_surface_small_components() -> Result<(), anyhow::Error> {
+    let particle_radius = 0.025;
+    let spacing = 2.0 * particle_radius;
+
+    // Large block of fluid and a small droplet far away from it
+    let block = |n: usize, offset: Vector3<f64>| {
+        let mut particles = Vec::new();
+        for i in 0..n {
+            for j in 0..n {
+                for k in 0..n {
+                    particles.push(offset + Vector3::new(i as f64, j as f64, k as f64) * spacing);
+                }
+            }
+        }
+        particles
+    };
+    let mut particles = block(10, Vector3::zeros());
+    particles.extend(block(2, Vector3::new(2.0, 0.0, 0.0)));
+
+    let parameters = Parameters {
+        particle_radius,
+        rest_density: 1000.0,
+        compact_support_radius: 4.0 * particle_radius,
+        cube_size: 0.5 * particle_radius,
+        iso_surface_threshold: 0.6,
+        particle_aabb: None,
+        enable_multi_threading: true,
+        spatial_decomposition: None,
+        global_neighborhood_list: false,
+        vertex_provenance: None,
+        tabulated_kernel: None,
+        particle_reordering: None,
+    };
+    let reconstruction = reconstruct_surface::<i64, f64>(&particles, &parameters)?;
+    assert_eq!(reconstruction.mesh().connected_components().len(), 2);
+
+    let postprocessing = PostprocessingParameters {
+        min_component_volume: Some(0.01),
+        output_component_ids: true,
+        ..Default::default()
+    };
+    let (mesh_with_data, removed) = postprocess_surface(
+        reconstruction,
+        &particles,
+        Vec::new(),
+        &parameters,
+        &postprocessing,
+    )?;
+
+    // Only the droplet is removed and all remaining vertices belong to the block
+    assert_eq!(removed.len(), 1);
+    assert!((removed[0].centroid - Vector3::new(2.025, 0.025, 0.025)).norm() < 1e-2);
+    let component_ids = mesh_with_data
+        .point_attributes
+        .iter()
+        .find(|a| a.name == "component_id")
+        .expect("mesh is missing the component IDs");
+    match &component_ids.data {
+        AttributeData::ScalarU64(ids) => {
+            assert_eq!(ids.len(), mesh_with_data.vertices().len());
+            assert!(ids.iter().all(|&id| id == 0));
+        }
+        _ => panic!("component IDs have to be integers"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_clip_with_plane() -> Result<(), anyhow::Error> {
+    let sphere = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/icosphere.obj")?.mesh;
+    let sphere_volume = sphere.signed_volume();
+
+    let x_coordinates = sphere.vertices.iter().map(|v| v.x).collect::<Vec<_>>();
+    let mesh = MeshWithData::new(sphere.clone())
+        .with_point_data(MeshAttribute::new_real_scalar("x", x_coordinates))
+        .with_cell_data(MeshAttribute::new(
+            "id",
+            AttributeData::ScalarU64((0..sphere.triangles.len() as u64).collect()),
+        ));
+
+    let clipped = clip_with_plane(
+        &mesh,
+        &Vector3::new(0.0, 0.0, 0.1),
+        &Vector3::new(0.0, 0.0, 2.0),
+        false,
+    );
+    let manifold_info = clipped.mesh.compute_manifold_information();
+    assert!(manifold_info.is_closed());
+    assert!(manifold_info.is_manifold());
+    assert!(clipped.mesh.vertices.iter().all(|v| v.z <= 0.1));
+    assert!(clipped.mesh.vertices.iter().any(|v| v.z == 0.1));
+
+    let volume = clipped.mesh.signed_volume();
+    assert!(volume.signum() == sphere_volume.signum());
+    assert!(volume.abs() > 0.5 * sphere_volume.abs() && volume.abs() < 0.7 * sphere_volume.abs());
+
+    // Point attributes are interpolated linearly, cell attributes are inherited
+    match &clipped.point_attributes[0].data {
+        AttributeData::ScalarReal(x) => {
+            assert_eq!(x.len(), clipped.mesh.vertices.len());
+            for (x, v) in x.iter().zip(clipped.mesh.vertices.iter()) {
+                assert!((x - v.x).abs() < 1e-12);
+            }
+        }
+        _ => panic!("unexpected attribute type"),
+    }
+    assert_eq!(
+        clipped.cell_attributes[0].data.len(),
+        clipped.mesh.triangles.len()
+    );
+
+    // Mesh completely inside of the half-space is not modified
+    let unclipped = clip_with_plane(&mesh, &Vector3::new(0.0, 0.0, 2.0), &Vector3::z(), false);
+    assert_eq!(unclipped.mesh.triangles, sphere.triangles);
+
+    Ok(())
+}
+
+#[test]
+fn test_clip_hollow_sphere() -> Result<(), anyhow::Error> {
+    let sphere = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/icosphere.obj")?.mesh;
+    let sphere_volume = sphere.signed_volume();
+
+    // Spherical shell: the inner sphere is inverted, the cut results in an annulus
+    let mut mesh = sphere.clone();
+    let mut inner_sphere = sphere.clone();
+    inner_sphere.vertices.iter_mut().for_each(|v| *v *= 0.5);
+    inner_sphere
+        .triangles
+        .iter_mut()
+        .for_each(|tri| tri.swap(1, 2));
+    mesh.append(&mut inner_sphere);
+    let shell_volume = mesh.signed_volume();
+    assert!((shell_volume - 0.875 * sphere_volume).abs() < 1e-10);
+
+    let clipped = clip_with_plane(
+        &MeshWithData::new(mesh),
+        &Vector3::new(0.0, 0.0, 0.0),
+        &Vector3::new(0.3, -0.2, 1.0),
+        false,
+    );
+    let manifold_info = clipped.mesh.compute_manifold_information();
+    assert!(manifold_info.is_closed());
+    assert!(manifold_info.is_manifold());
+    assert_eq!(clipped.mesh.connected_components().len(), 1);
+
+    let volume = clipped.mesh.signed_volume();
+    assert!((volume - 0.5 * shell_volume).abs() < 0.05 * shell_volume.abs());
+
+    Ok(())
+}
+
+#[test]
+fn test_clip_with_aabb() -> Result<(), anyhow::Error> {
+    let sphere = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/icosphere.obj")?.mesh;
+
+    let aabb = Aabb3d::new(Vector3::new(-2.0, -0.3, -2.0), Vector3::new(0.2, 0.4, 0.5));
+    let clipped = clip_with_aabb(&MeshWithData::new(sphere.clone()), &aabb, false);
+
+    let manifold_info = clipped.mesh.compute_manifold_information();
+    assert!(manifold_info.is_closed());
+    assert!(manifold_info.is_manifold());
+    assert!(clipped
+        .mesh
+        .vertices
+        .iter()
+        .all(|v| aabb.contains_point(v) || (0..3).any(|k| v[k] == aabb.max()[k])));
+    assert!(clipped.mesh.signed_volume().abs() < sphere.signed_volume().abs());
+
+    // The corner of the AABB inside of the sphere is a vertex of the caps
+    assert!(clipped
+        .mesh
+        .vertices
+        .iter()
+        .any(|v| *v == Vector3::new(0.2, 0.4, 0.5)));
+
+    Ok(())
+}
+
+#[test]
+fn test_repair_mesh_fill_holes() -> Result<(), anyhow::Error> {
+    let sphere = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/icosphere.obj")?.mesh;
+    let sphere_volume = sphere.signed_volume();
+
+    // Remove the fan of a vertex and a larger cap of the sphere
+    let mut mesh = sphere.clone();
+    let vertices = &mesh.vertices;
+    mesh.triangles
+        .retain(|tri| !tri.contains(&0) && !tri.iter().all(|&v| vertices[v].z > 0.75));
+    assert!(!mesh.compute_manifold_information().is_closed());
+
+    let (repaired, report) = repair_mesh(
+        &MeshWithData::new(mesh),
+        &MeshRepairParameters::default(),
+        false,
+    );
+    assert_eq!(report.filled_holes, 2);
+    assert_eq!(report.unfilled_holes, 0);
+    assert!(report.is_closed_manifold());
+
+    let manifold_info = repaired.mesh.compute_manifold_information();
+    assert!(manifold_info.is_closed());
+    assert!(manifold_info.is_manifold());
+    // Closed genus zero triangle mesh
+    assert_eq!(
+        repaired.mesh.triangles.len(),
+        2 * repaired.mesh.vertices.len() - 4
+    );
+    assert!((repaired.mesh.signed_volume() - sphere_volume).abs() < 0.1 * sphere_volume.abs());
+
+    // Holes larger than the limit are not filled
+    let mut mesh = sphere.clone();
+    mesh.triangles.retain(|tri| !tri.contains(&0));
+    let (_, report) = repair_mesh(
+        &MeshWithData::new(mesh),
+        &MeshRepairParameters {
+            max_hole_edges: Some(3),
+            ..Default::default()
+        },
+        false,
+    );
+    assert_eq!(report.filled_holes, 0);
+    assert_eq!(report.unfilled_holes, 1);
+    assert!(!report.is_closed_manifold());
+
+    Ok(())
+}
+
+#[test]
+fn test_repair_mesh_non_manifold() -> Result<(), anyhow::Error> {
+    let sphere = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/icosphere.obj")?.mesh;
+    let [a, b, c] = sphere.triangles[0];
+
+    let mut separate_triangles = sphere
+        .triangles
+        .iter()
+        .filter(|tri| tri.iter().all(|v| ![a, b, c].contains(v)));
+    let tri_1 = *separate_triangles.next().unwrap();
+    let tri_2 = *separate_triangles
+        .find(|tri| tri.iter().all(|v| !tri_1.contains(v)))
+        .unwrap();
+
+    let mut mesh = sphere.clone();
+    // Duplicate triangle with the same orientation
+    mesh.triangles.push(tri_1);
+    // Duplicate triangle with the opposite orientation
+    let [d, e, f] = tri_2;
+    mesh.triangles.push([d, f, e]);
+    // Fin triangle at a non-manifold edge
+    mesh.vertices.push(Vector3::new(2.0, 2.0, 2.0));
+    mesh.triangles.push([a, b, mesh.vertices.len() - 1]);
+    // Needle triangles at a collapsed edge
+    mesh.vertices[c] = mesh.vertices[a];
+
+    let x_coordinates = mesh.vertices.iter().map(|v| v.x).collect::<Vec<_>>();
+    let mesh_with_data = MeshWithData::new(mesh)
+        .with_point_data(MeshAttribute::new_real_scalar("x", x_coordinates))
+        .with_cell_data(MeshAttribute::new(
+            "id",
+            AttributeData::ScalarU64((0..sphere.triangles.len() as u64 + 3).collect()),
+        ));
+
+    let (repaired, report) = repair_mesh(&mesh_with_data, &MeshRepairParameters::default(), false);
+    assert_eq!(report.removed_duplicate_triangles, 3);
+    assert_eq!(report.removed_degenerate_triangles, 2);
+    assert!(report.removed_non_manifold_edge_triangles > 0);
+    assert!(report.filled_holes > 0);
+    assert!(report.is_closed_manifold());
+
+    let manifold_info = repaired.mesh.compute_manifold_information();
+    assert!(manifold_info.is_closed());
+    assert!(manifold_info.is_manifold());
+    assert_eq!(
+        repaired.point_attributes[0].data.len(),
+        repaired.mesh.vertices.len()
+    );
+    assert_eq!(
+        repaired.cell_attributes[0].data.len(),
+        repaired.mesh.triangles.len()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_split_non_manifold_vertices() -> Result<(), anyhow::Error> {
+    let sphere = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/icosphere.obj")?.mesh;
+
+    // Two spheres touching in a single vertex
+    let (pinch_vertex, pinch_position) = sphere
+        .vertices
+        .iter()
+        .copied()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.x.partial_cmp(&b.x).unwrap())
+        .unwrap();
+    let mut mesh = sphere.clone();
+    let mut other_sphere = sphere.clone();
+    other_sphere
+        .vertices
+        .iter_mut()
+        .for_each(|v| *v = pinch_position * 2.0 - *v);
+    let offset = mesh.vertices.len();
+    mesh.append(&mut other_sphere);
+    for v in mesh.triangles.iter_mut().flatten() {
+        if *v == offset + pinch_vertex {
+            *v = pinch_vertex;
+        }
+    }
+    assert_eq!(
+        mesh.compute_manifold_information().non_manifold_vertices,
+        vec![pinch_vertex]
+    );
+
+    let mut mesh_with_data = MeshWithData::new(mesh);
+    assert_eq!(split_non_manifold_vertices(&mut mesh_with_data), 1);
+
+    let manifold_info = mesh_with_data.mesh.compute_manifold_information();
+    assert!(manifold_info.is_closed());
+    assert!(manifold_info.is_manifold());
+    // The unreferenced copy of the pinch vertex of the second sphere is still part of the mesh
+    assert_eq!(
+        mesh_with_data.mesh.vertices.len(),
+        2 * sphere.vertices.len() + 1
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_loop_subdivision() -> Result<(), anyhow::Error> {
+    let sphere = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/icosphere.obj")?.mesh;
+    let num_edges = sphere.compute_edge_information().iter().count();
+
+    let x_coordinates = sphere.vertices.iter().map(|v| v.x).collect::<Vec<_>>();
+    let mesh = MeshWithData::new(sphere.clone())
+        .with_point_data(MeshAttribute::new_real_scalar("x", x_coordinates))
+        .with_point_data(MeshAttribute::new(
+            "id",
+            AttributeData::ScalarU64((0..sphere.vertices.len() as u64).collect()),
+        ))
+        .with_cell_data(MeshAttribute::new(
+            "tri_id",
+            AttributeData::ScalarU64((0..sphere.triangles.len() as u64).collect()),
+        ));
+
+    let subdivided = loop_subdivision(&mesh, &LoopSubdivisionParameters::default(), None)?;
+    assert_eq!(
+        subdivided.mesh.vertices.len(),
+        sphere.vertices.len() + num_edges
+    );
+    assert_eq!(subdivided.mesh.triangles.len(), 4 * sphere.triangles.len());
+
+    let manifold_info = subdivided.mesh.compute_manifold_information();
+    assert!(manifold_info.is_closed());
+    assert!(manifold_info.is_manifold());
+
+    // The subdivision surface of a convex mesh lies inside of it
+    assert!(subdivided
+        .mesh
+        .vertices
+        .iter()
+        .all(|v| v.norm() <= 1.0 + 1e-12 && v.norm() > 0.9));
+
+    // Linear attributes are reproduced exactly, integer attributes are inherited
+    match (
+        &subdivided.point_attributes[0].data,
+        &subdivided.point_attributes[1].data,
+        &subdivided.cell_attributes[0].data,
+    ) {
+        (
+            AttributeData::ScalarReal(x),
+            AttributeData::ScalarU64(ids),
+            AttributeData::ScalarU64(tri_ids),
+        ) => {
+            for (x, v) in x.iter().zip(subdivided.mesh.vertices.iter()) {
+                assert!((x - v.x).abs() < 1e-12);
+            }
+            assert_eq!(ids.len(), subdivided.mesh.vertices.len());
+            assert!(ids[..sphere.vertices.len()]
+                .iter()
+                .enumerate()
+                .all(|(i, &id)| id == i as u64));
+            assert_eq!(tri_ids[4 * 7 + 3], 7);
+        }
+        _ => panic!("unexpected attribute types"),
+    }
+
+    let subdivided = loop_subdivision(
+        &mesh,
+        &LoopSubdivisionParameters {
+            iterations: 2,
+            ..Default::default()
+        },
+        None,
+    )?;
+    assert_eq!(subdivided.mesh.triangles.len(), 16 * sphere.triangles.len());
+    assert!(subdivided.mesh.compute_manifold_information().is_closed());
+
+    // Boundaries of open meshes are subdivided as curves
+    let plane = TriMesh3d {
+        vertices: vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        ],
+        triangles: vec![[0, 1, 2], [0, 2, 3]],
+    };
+    let subdivided = loop_subdivision(
+        &MeshWithData::new(plane.clone()),
+        &LoopSubdivisionParameters::default(),
+        None,
+    )?;
+    assert_eq!(subdivided.mesh.vertices[0], Vector3::new(0.125, 0.125, 0.0));
+    // The midpoint of the diagonal is the only interior vertex
+    assert_eq!(subdivided.mesh.vertices.len(), 9);
+    assert_eq!(
+        subdivided
+            .mesh
+            .vertices
+            .iter()
+            .filter(|v| **v == Vector3::new(0.5, 0.5, 0.0))
+            .count(),
+        1
+    );
+    assert!(subdivided.mesh.vertices.iter().all(|v| v.z == 0.0));
+
+    Ok(())
+}
+
+#[test]
+fn test_uv_coordinates() -> Result<(), anyhow::Error> {
+    let sphere = io::obj_format::surface_mesh_from_obj::<f64, _>("../data/icosphere.obj")?.mesh;
+    let normals = sphere
+        .vertex_normals()
+        .into_iter()
+        .map(|n| n.into_inner())
+        .collect::<Vec<_>>();
+
+    // Triplanar projection onto the plane of the dominant normal axis, mirrored for negative directions
+    let points = [Vector3::new(1.0, 2.0, 3.0); 3];
+    let uv = triplanar_uv_coordinates(
+        &points,
+        &[
+            Vector3::new(0.9, 0.1, 0.0),
+            Vector3::new(0.0, -1.0, 0.5),
+            Vector3::new(0.0, 0.0, 1.0),
+        ],
+        2.0,
+    );
+    assert_eq!(uv[0], Vector2::new(4.0, 6.0));
+    assert_eq!(uv[1], Vector2::new(-6.0, 2.0));
+    assert_eq!(uv[2], Vector2::new(2.0, 4.0));
+
+    // Advected coordinates at the reset of the first layer
+    let velocities = vec![Vector3::new(0.1, 0.2, 0.3); sphere.vertices.len()];
+    let mut parameters = AdvectedUvParameters {
+        scale: 1.0,
+        time: 0.0,
+        reset_period: 2.0,
+        velocity_attribute: "velocity".to_string(),
+    };
+    let advected = advected_uv_coordinates(&sphere.vertices, &normals, &velocities, &parameters);
+    let triplanar = triplanar_uv_coordinates(&sphere.vertices, &normals, 1.0);
+    assert_eq!(advected.weights, [0.0, 1.0]);
+    assert_eq!(advected.uv[0], triplanar);
+    // The second layer was reset half a period ago
+    let shifted = sphere
+        .vertices
+        .iter()
+        .zip(velocities.iter())
+        .map(|(p, v)| p - v)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        advected.uv[1],
+        triplanar_uv_coordinates(&shifted, &normals, 1.0)
+    );
+
+    // Weights change linearly and always sum up to one
+    for (time, weight) in [(0.5, 0.5), (1.0, 1.0), (1.5, 0.5), (3.0, 1.0)] {
+        parameters.time = time;
+        let advected =
+            advected_uv_coordinates(&sphere.vertices, &normals, &velocities, &parameters);
+        assert_eq!(advected.weights[0], weight);
+        assert_eq!(advected.weights[0] + advected.weights[1], 1.0);
+    }
+
+    // Texture coordinates are written to and read from OBJ files
+    let mesh_with_data = MeshWithData::new(sphere.clone()).with_point_data(MeshAttribute::new(
+        "uv",
+        AttributeData::Vector2Real(triplanar.clone()),
+    ));
+    io::obj_format::mesh_to_obj(&mesh_with_data, "../out/icosphere_uv.obj")?;
+    let mesh_read = io::obj_format::surface_mesh_from_obj::<f64, _>("../out/icosphere_uv.obj")?;
+    match &mesh_read.point_attributes[0] {
+        MeshAttribute {
+            name,
+            data: AttributeData::Vector2Real(uv),
+        } if name == "uv" => {
+            assert_eq!(uv.len(), triplanar.len());
+            for (a, b) in uv.iter().zip(triplanar.iter()) {
+                assert!((a - b).norm() < 1e-12);
+            }
+        }
+        _ => panic!("expected texture coordinates"),
+    }
+    assert_eq!(mesh_read.mesh.triangles, sphere.triangles);
+
+    Ok(())
+}
+
+#[test]
+fn test_obj_texture_coordinate_seams() -> Result<(), anyhow::Error> {
+    let quad_vertices = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    // Texture seam: the last corner uses a texture coordinate that is not indexed like its vertex
+    std::fs::write(
+        "../out/quad_uv_seam.obj",
+        format!(
+            "{quad_vertices}vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nvt 0.5 1\nf 1/1 2/2 3/3\nf 1/1 3/3 4/5\n"
+        ),
+    )?;
+    let mesh_read = io::obj_format::surface_mesh_from_obj::<f64, _>("../out/quad_uv_seam.obj")?;
+    assert_eq!(mesh_read.mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+    assert!(mesh_read.point_attributes.is_empty());
+
+    // The mesh without the texture coordinates can be written and read again
+    io::obj_format::mesh_to_obj(&mesh_read, "../out/quad_uv_seam_new.obj")?;
+    let mesh_reread =
+        io::obj_format::surface_mesh_from_obj::<f64, _>("../out/quad_uv_seam_new.obj")?;
+    assert_eq!(mesh_reread.mesh.vertices, mesh_read.mesh.vertices);
+    assert_eq!(mesh_reread.mesh.triangles, mesh_read.mesh.triangles);
+
+    // Texture coordinates indexed like the vertices with an optional second coordinate
+    std::fs::write(
+        "../out/quad_uv_1d.obj",
+        format!(
+            "{quad_vertices}vt 0\nvt 1\nvt 1 1\nvt 0 1 0\nf 1/1 2/2 3/3\nf 1/1/1 3/3/3 4/4/4\n"
+        ),
+    )?;
+    let mesh_read = io::obj_format::surface_mesh_from_obj::<f64, _>("../out/quad_uv_1d.obj")?;
+    match &mesh_read.point_attributes[..] {
+        [MeshAttribute {
+            name,
+            data: AttributeData::Vector2Real(uv),
+        }] if name == "uv" => assert_eq!(
+            uv,
+            &vec![
+                Vector2::new(0.0, 0.0),
+                Vector2::new(1.0, 0.0),
+                Vector2::new(1.0, 1.0),
+                Vector2::new(0.0, 1.0)
+            ]
+        ),
+        _ => panic!("expected texture coordinates"),
+    }
+
+    // Malformed texture coordinates are reported as errors
+    std::fs::write(
+        "../out/quad_uv_invalid.obj",
+        format!("{quad_vertices}vt 0 0 0 0\nf 1 2 3\n"),
+    )?;
+    assert!(io::obj_format::surface_mesh_from_obj::<f64, _>("../out/quad_uv_invalid.obj").is_err());
+
+    Ok(())
+}
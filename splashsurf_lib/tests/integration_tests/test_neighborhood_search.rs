@@ -1,5 +1,7 @@
 use nalgebra::Vector3;
+use splashsurf_lib::density_map;
 use splashsurf_lib::neighborhood_search::*;
+use splashsurf_lib::spatial_index::SpatialIndex;
 use splashsurf_lib::Aabb3d;
 
 fn sort_neighborhood_lists(neighborhood_list: &mut Vec<Vec<usize>>) {
@@ -174,6 +176,282 @@ fn test_neighborhood_search_spatial_hashing_parallel_simple() {
     }
 }
 
+/// Generates pseudo-random points in the cube `[0, 1]^3`
+fn generate_random_points(n: usize, seed: u64) -> Vec<Vector3<f32>> {
+    let mut state = seed;
+    let mut next = || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 40) as f32 / (1u64 << 24) as f32
+    };
+    (0..n)
+        .map(|_| Vector3::new(next(), next(), next()))
+        .collect()
+}
+
+#[test]
+fn test_neighborhood_search_persistent_simple() {
+    let search_radius: f32 = 0.3;
+
+    for (particles, mut solution) in generate_simple_test_cases(search_radius) {
+        let mut search = NeighborhoodSearch::new(search_radius, true);
+        let point_set = search.add_point_set(particles.as_slice());
+
+        let mut nl = FlatNeighborhoodList::default();
+        search.find_neighbors(point_set, point_set, &mut nl);
+        let mut nl = nl.to_vec_vec();
+
+        sort_neighborhood_lists(&mut nl);
+        sort_neighborhood_lists(&mut solution);
+
+        assert_eq!(
+            nl, solution,
+            "NeighborhoodSearch failed. Search radius: {}, input: {:?}",
+            search_radius, particles
+        );
+    }
+}
+
+#[test]
+fn test_neighborhood_search_persistent_update() {
+    let search_radius: f32 = 0.05;
+    let mut particles = generate_random_points(3000, 42);
+
+    let mut search = NeighborhoodSearch::new(search_radius, true);
+    let point_set = search.add_point_set(particles.as_slice());
+
+    let mut nl = FlatNeighborhoodList::default();
+    let mut nl_naive = Vec::new();
+    for frame in 0..3 {
+        if frame > 0 {
+            // Move the particles by up to a few cells and check that the search picks up the new positions
+            let displacements = generate_random_points(particles.len(), frame);
+            for (p, d) in particles.iter_mut().zip(displacements.iter()) {
+                *p += (d - Vector3::repeat(0.5)) * 0.1;
+            }
+            let num_moved = search.update_point_set(point_set, particles.as_slice());
+            assert!(num_moved > 0 && num_moved < particles.len());
+        }
+
+        search.find_neighbors(point_set, point_set, &mut nl);
+        neighborhood_search_naive(particles.as_slice(), search_radius, &mut nl_naive);
+
+        let mut nl = std::mem::take(&mut nl).to_vec_vec();
+        sort_neighborhood_lists(&mut nl);
+        sort_neighborhood_lists(&mut nl_naive);
+        assert_eq!(
+            nl, nl_naive,
+            "NeighborhoodSearch does not match neighborhood_search_naive after update {}",
+            frame
+        );
+    }
+
+    // Changing the number of particles and the search radius re-assigns all particles
+    particles.truncate(2000);
+    assert_eq!(search.update_point_set(point_set, &particles), 2000);
+    search.set_search_radius(0.08);
+    search.find_neighbors(point_set, point_set, &mut nl);
+    neighborhood_search_naive(particles.as_slice(), 0.08, &mut nl_naive);
+    let mut nl = nl.to_vec_vec();
+    sort_neighborhood_lists(&mut nl);
+    sort_neighborhood_lists(&mut nl_naive);
+    assert_eq!(nl, nl_naive);
+}
+
+#[test]
+fn test_neighborhood_search_persistent_multiple_point_sets() {
+    let search_radius: f32 = 0.1;
+    let fluid = generate_random_points(1000, 1);
+    let boundary = generate_random_points(500, 2);
+
+    let mut search = NeighborhoodSearch::new(search_radius, false);
+    let fluid_set = search.add_point_set(fluid.as_slice());
+    let boundary_set = search.add_point_set(boundary.as_slice());
+    assert_eq!(search.num_point_sets(), 2);
+
+    let mut nl = FlatNeighborhoodList::default();
+    search.find_neighbors(fluid_set, boundary_set, &mut nl);
+    assert_eq!(nl.len(), fluid.len());
+
+    let mut neighbors = Vec::new();
+    for (i, p) in fluid.iter().enumerate() {
+        let mut expected = boundary
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| (*b - p).norm_squared() < search_radius * search_radius)
+            .map(|(j, _)| j)
+            .collect::<Vec<_>>();
+        expected.sort_unstable();
+
+        let mut found = nl.get_neighbors(i).unwrap().to_vec();
+        found.sort_unstable();
+        assert_eq!(found, expected);
+
+        // Queries for arbitrary points do not exclude any point of the set
+        neighbors.clear();
+        search.find_neighbors_of_point(p, boundary_set, &mut neighbors);
+        neighbors.sort_unstable();
+        assert_eq!(neighbors, expected);
+    }
+}
+
+#[test]
+fn test_neighborhood_search_spatial_hashing_flat_parallel() {
+    let particles = generate_random_points(3000, 5);
+    let search_radius: f32 = 0.08;
+
+    let mut domain = Aabb3d::from_points(particles.as_slice());
+    domain.grow_uniformly(search_radius);
+
+    let mut nl_naive = Vec::new();
+    neighborhood_search_naive(particles.as_slice(), search_radius, &mut nl_naive);
+    sort_neighborhood_lists(&mut nl_naive);
+
+    let mut nl = FlatNeighborhoodList::default();
+    let mut squared_distances = Vec::new();
+    neighborhood_search_spatial_hashing_flat_parallel::<i32, f32>(
+        &domain,
+        particles.as_slice(),
+        search_radius,
+        &mut nl,
+        Some(&mut squared_distances),
+    );
+
+    assert_eq!(nl.len(), particles.len());
+    assert_eq!(squared_distances.len(), nl.neighbors.len());
+    for i in 0..nl.len() {
+        let range = nl.neighbor_ptr[i]..nl.neighbor_ptr[i + 1];
+        for (&j, &d) in nl.neighbors[range.clone()]
+            .iter()
+            .zip(squared_distances[range].iter())
+        {
+            assert_eq!(d, (particles[j] - particles[i]).norm_squared());
+        }
+    }
+
+    let mut nl_without_distances = FlatNeighborhoodList::default();
+    neighborhood_search_spatial_hashing_flat_parallel::<i32, f32>(
+        &domain,
+        particles.as_slice(),
+        search_radius,
+        &mut nl_without_distances,
+        None,
+    );
+    assert_eq!(nl_without_distances.neighbors, nl.neighbors);
+
+    let mut nl = nl.to_vec_vec();
+    sort_neighborhood_lists(&mut nl);
+    assert_eq!(nl, nl_naive);
+}
+
+#[test]
+fn test_particle_densities_from_squared_distances() {
+    let particles = generate_random_points(3000, 7);
+    let compact_support_radius: f32 = 0.08;
+    let particle_rest_mass: f32 = 0.5;
+
+    let mut domain = Aabb3d::from_points(particles.as_slice());
+    domain.grow_uniformly(compact_support_radius);
+
+    let mut nl = FlatNeighborhoodList::default();
+    let mut squared_distances = Vec::new();
+    neighborhood_search_spatial_hashing_flat_parallel::<i32, f32>(
+        &domain,
+        particles.as_slice(),
+        compact_support_radius,
+        &mut nl,
+        Some(&mut squared_distances),
+    );
+
+    let mut densities_from_distances = Vec::new();
+    density_map::parallel_compute_particle_densities_from_squared_distances(
+        &nl,
+        squared_distances.as_slice(),
+        compact_support_radius,
+        particle_rest_mass,
+        &mut densities_from_distances,
+    );
+
+    let nl_vec_vec = (0..nl.len())
+        .map(|i| nl.get_neighbors(i).unwrap().to_vec())
+        .collect::<Vec<_>>();
+    let mut densities = Vec::new();
+    density_map::parallel_compute_particle_densities::<i32, f32>(
+        particles.as_slice(),
+        nl_vec_vec.as_slice(),
+        compact_support_radius,
+        particle_rest_mass,
+        &mut densities,
+    );
+
+    assert_eq!(densities_from_distances.len(), particles.len());
+    for (a, b) in densities_from_distances.iter().zip(densities.iter()) {
+        assert!(
+            (a - b).abs() <= 1e-5 * b.abs(),
+            "densities differ: {} vs. {}",
+            a,
+            b
+        );
+    }
+}
+
+#[test]
+fn test_spatial_index_queries() {
+    let particles = generate_random_points(2000, 3);
+    let query_points = generate_random_points(300, 4);
+    let radius: f32 = 0.1;
+    let k = 8;
+
+    let index = SpatialIndex::new(particles.as_slice());
+    assert_eq!(index.len(), particles.len());
+
+    let mut within_radius = FlatNeighborhoodList::default();
+    index.par_within_radius(query_points.as_slice(), radius, &mut within_radius);
+    let mut nearest = FlatNeighborhoodList::default();
+    index.par_nearest_neighbors(query_points.as_slice(), k, &mut nearest);
+    let nearest_single = index.par_nearest_neighbor(query_points.as_slice());
+    assert_eq!(within_radius.len(), query_points.len());
+    assert_eq!(nearest.len(), query_points.len());
+
+    let mut knn = Vec::new();
+    for (i, q) in query_points.iter().enumerate() {
+        let mut by_distance = particles
+            .iter()
+            .enumerate()
+            .map(|(j, p)| (j, (p - q).norm_squared()))
+            .collect::<Vec<_>>();
+        by_distance.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let mut expected = by_distance
+            .iter()
+            .filter(|(_, d)| *d < radius * radius)
+            .map(|(j, _)| *j)
+            .collect::<Vec<_>>();
+        expected.sort_unstable();
+        let mut found = within_radius.get_neighbors(i).unwrap().to_vec();
+        found.sort_unstable();
+        assert_eq!(found, expected);
+
+        let expected_knn = by_distance[..k].iter().map(|(j, _)| *j).collect::<Vec<_>>();
+        assert_eq!(nearest.get_neighbors(i).unwrap(), expected_knn.as_slice());
+        assert_eq!(nearest_single[i], Some(by_distance[0]));
+
+        knn.clear();
+        index.nearest_neighbors(q, k, &mut knn);
+        assert_eq!(knn.as_slice(), &by_distance[..k]);
+    }
+
+    // Less than `k` particles in the index
+    let small_index = SpatialIndex::new(&particles[..3]);
+    small_index.par_nearest_neighbors(query_points.as_slice(), k, &mut nearest);
+    assert!(nearest.iter().all(|neighbors| neighbors.len() == 3));
+    assert!(SpatialIndex::<f32>::new(&[])
+        .par_nearest_neighbor(query_points.as_slice())
+        .iter()
+        .all(Option::is_none));
+}
+
 #[cfg(feature = "io")]
 mod tests_from_files {
     use super::*;
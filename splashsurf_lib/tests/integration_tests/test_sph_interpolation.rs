@@ -0,0 +1,318 @@
+use nalgebra::{Matrix3, Vector3};
+use splashsurf_lib::mesh::{MeshWithData, TriMesh3d};
+use splashsurf_lib::postprocessing::{loop_subdivision, LoopSubdivisionParameters};
+use splashsurf_lib::sph_interpolation::{
+    CategoricalInterpolation, InterpolationMode, SphInterpolator, TensorAveraging,
+};
+use splashsurf_lib::{reconstruct_surface, Parameters};
+
+/// Generates a cubic block of particles on a regular lattice with the given spacing
+fn particle_block(n: usize, spacing: f64) -> Vec<Vector3<f64>> {
+    let mut particles = Vec::with_capacity(n * n * n);
+    for i in 0..n {
+        for j in 0..n {
+            for k in 0..n {
+                particles.push(Vector3::new(i as f64, j as f64, k as f64) * spacing);
+            }
+        }
+    }
+    particles
+}
+
+#[test]
+fn test_interpolation_modes_linear_field() {
+    let spacing = 0.1;
+    let compact_support_radius = 4.0 * spacing;
+    let particles = particle_block(12, spacing);
+    let densities = vec![1000.0; particles.len()];
+    let rest_mass = 1000.0 * spacing.powi(3);
+
+    let interpolator =
+        SphInterpolator::new(&particles, &densities, rest_mass, compact_support_radius);
+
+    // Linear field and interpolation points close to the boundary and in the interior of the block
+    let field = |x: &Vector3<f64>| 2.0 + 3.0 * x.x - 1.5 * x.y + 0.5 * x.z;
+    let values = particles.iter().map(field).collect::<Vec<_>>();
+    let points = vec![
+        Vector3::new(0.03, 0.52, 0.57),
+        Vector3::new(0.55, 0.01, 0.02),
+        Vector3::new(0.56, 0.54, 0.53),
+    ];
+
+    let mls = interpolator.interpolate_scalar_quantity(
+        &values,
+        &points,
+        InterpolationMode::MovingLeastSquares,
+    );
+    for (x, v) in points.iter().zip(mls.iter()) {
+        assert!(
+            (v - field(x)).abs() < 1e-8,
+            "MLS does not reproduce linear field at {x:?}"
+        );
+    }
+
+    // Shepard normalization reproduces constant fields exactly
+    let constant = vec![4.0; particles.len()];
+    let shepard =
+        interpolator.interpolate_scalar_quantity(&constant, &points, InterpolationMode::Shepard);
+    assert!(shepard.iter().all(|v| (v - 4.0).abs() < 1e-10));
+
+    // The plain SPH sum is biased close to the boundary
+    let sph = interpolator.interpolate_scalar_quantity(&constant, &points, InterpolationMode::Sph);
+    assert!((sph[0] - 4.0).abs() > 0.1);
+
+    // The interior is not affected by the boundary, so the modes agree there
+    let shepard_linear =
+        interpolator.interpolate_scalar_quantity(&values, &points, InterpolationMode::Shepard);
+    assert!((shepard_linear[2] - mls[2]).abs() < 5e-2);
+
+    // The corrected normals point outwards at the boundary
+    let normals = interpolator
+        .interpolate_normals_with_mode(&points[..2], InterpolationMode::MovingLeastSquares);
+    assert!(normals[0].x < -0.9);
+    assert!(normals[1].y < -0.5 && normals[1].z < -0.5);
+}
+
+#[test]
+fn test_interpolation_categorical_and_tensors() {
+    let spacing = 0.1;
+    let compact_support_radius = 4.0 * spacing;
+    let particles = particle_block(10, spacing);
+    let densities = vec![1000.0; particles.len()];
+    let rest_mass = 1000.0 * spacing.powi(3);
+
+    let interpolator =
+        SphInterpolator::new(&particles, &densities, rest_mass, compact_support_radius);
+
+    // Two labeled regions separated at x = 0.35
+    let labels = particles
+        .iter()
+        .map(|x| if x.x < 0.35 { 7u64 } else { 3u64 })
+        .collect::<Vec<_>>();
+    let points = vec![
+        Vector3::new(0.21, 0.45, 0.45),
+        Vector3::new(0.52, 0.45, 0.45),
+        // Outside of the support of all particles
+        Vector3::new(-2.0, 0.45, 0.45),
+    ];
+
+    for scheme in [
+        CategoricalInterpolation::Nearest,
+        CategoricalInterpolation::MajorityVote,
+    ] {
+        let interpolated = interpolator.interpolate_categorical_quantity(&labels, &points, scheme);
+        assert_eq!(interpolated, vec![7, 3, 7], "scheme: {scheme:?}");
+    }
+
+    // Constant SPD tensor fields are reproduced by both averaging schemes
+    let tensor = Matrix3::new(2.0, 0.5, 0.0, 0.5, 1.0, 0.0, 0.0, 0.0, 3.0);
+    let tensors = vec![tensor; particles.len()];
+    for averaging in [TensorAveraging::Linear, TensorAveraging::LogEuclidean] {
+        let interpolated = interpolator.interpolate_tensor_quantity(
+            &tensors,
+            &points[..2],
+            InterpolationMode::Shepard,
+            averaging,
+        );
+        for t in interpolated {
+            assert!((t - tensor).amax() < 1e-8, "averaging: {averaging:?}");
+        }
+    }
+
+    // Log-Euclidean averaging of two tensors with equal determinants preserves the determinant
+    let tensors = particles
+        .iter()
+        .map(|x| {
+            if x.x < 0.45 {
+                Matrix3::from_diagonal(&Vector3::new(4.0, 1.0, 1.0))
+            } else {
+                Matrix3::from_diagonal(&Vector3::new(1.0, 4.0, 1.0))
+            }
+        })
+        .collect::<Vec<_>>();
+    let point = [Vector3::new(0.45, 0.45, 0.45)];
+    let log_euclidean = interpolator.interpolate_tensor_quantity(
+        &tensors,
+        &point,
+        InterpolationMode::Shepard,
+        TensorAveraging::LogEuclidean,
+    );
+    let linear = interpolator.interpolate_tensor_quantity(
+        &tensors,
+        &point,
+        InterpolationMode::Shepard,
+        TensorAveraging::Linear,
+    );
+    assert!((log_euclidean[0].determinant() - 4.0).abs() < 1e-8);
+    assert!(linear[0].determinant() > 4.5);
+}
+
+#[test]
+fn test_level_set_projection() {
+    let spacing = 0.1;
+    let compact_support_radius = 4.0 * spacing;
+    let particles = particle_block(12, spacing);
+    let densities = vec![1000.0; particles.len()];
+    let rest_mass = 1000.0 * spacing.powi(3);
+
+    let interpolator =
+        SphInterpolator::new(&particles, &densities, rest_mass, compact_support_radius);
+
+    // The level set is close to one in the interior and its gradient points inwards at the boundary
+    let (interior_value, _) = interpolator.evaluate_level_set(&Vector3::new(0.55, 0.55, 0.55));
+    assert!((interior_value - 1.0).abs() < 0.05);
+    let (_, boundary_gradient) = interpolator.evaluate_level_set(&Vector3::new(0.0, 0.55, 0.55));
+    assert!(boundary_gradient.normalize().x > 0.99);
+
+    // Points on both sides of the boundary are projected onto the iso-surface, points far away are not moved
+    let iso_value = 0.5;
+    let far_point = Vector3::new(-5.0, 0.55, 0.55);
+    let mut points = vec![
+        Vector3::new(-0.1, 0.55, 0.55),
+        Vector3::new(0.15, 0.55, 0.55),
+        far_point,
+    ];
+    interpolator.project_to_level_set_inplace(&mut points, iso_value, 20);
+
+    for p in &points[..2] {
+        let (value, _) = interpolator.evaluate_level_set(p);
+        assert!(
+            (value - iso_value).abs() < 1e-6,
+            "point {p:?} is not on the iso-surface"
+        );
+        assert!(p.x.abs() < spacing);
+        assert!((p.y - 0.55).abs() < 1e-6 && (p.z - 0.55).abs() < 1e-6);
+    }
+    assert!((points[0] - points[1]).norm() < 1e-6);
+    assert_eq!(points[2], far_point);
+}
+
+#[test]
+fn test_loop_subdivision_level_set_projection() -> Result<(), anyhow::Error> {
+    let spacing = 0.1;
+    let compact_support_radius = 4.0 * spacing;
+    let particles = particle_block(12, spacing);
+    let densities = vec![1000.0; particles.len()];
+    let rest_mass = 1000.0 * spacing.powi(3);
+
+    let interpolator =
+        SphInterpolator::new(&particles, &densities, rest_mass, compact_support_radius);
+
+    // Octahedron around the center of the particle block, close enough to its faces for the projection
+    let center = Vector3::new(0.55, 0.55, 0.55);
+    let octahedron = TriMesh3d {
+        vertices: [
+            Vector3::x(),
+            -Vector3::x(),
+            Vector3::y(),
+            -Vector3::y(),
+            Vector3::z(),
+            -Vector3::z(),
+        ]
+        .iter()
+        .map(|d| center + d * 0.8)
+        .collect(),
+        triangles: vec![
+            [0, 2, 4],
+            [2, 1, 4],
+            [1, 3, 4],
+            [3, 0, 4],
+            [2, 0, 5],
+            [1, 2, 5],
+            [3, 1, 5],
+            [0, 3, 5],
+        ],
+    };
+
+    let iso_value = 0.5;
+    let parameters = LoopSubdivisionParameters {
+        iterations: 2,
+        level_set_projection: true,
+    };
+    let subdivided = loop_subdivision(
+        &MeshWithData::new(octahedron.clone()),
+        &parameters,
+        Some((&interpolator, iso_value)),
+    )?;
+    assert_eq!(subdivided.mesh.triangles.len(), 16 * 8);
+
+    // All vertices are projected onto the iso-surface close to the faces of the particle block
+    for v in subdivided.mesh.vertices.iter() {
+        let (value, _) = interpolator.evaluate_level_set(v);
+        assert!((value - iso_value).abs() < 1e-6);
+    }
+
+    // The projection requires an interpolator
+    assert!(loop_subdivision(&MeshWithData::new(octahedron), &parameters, None).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_mean_curvature_sphere() {
+    let spacing = 0.05;
+    let particle_radius = 0.5 * spacing;
+    let sphere_radius: f64 = 0.5;
+    let center = Vector3::new(0.5, 0.5, 0.5);
+
+    // Particles on concentric spherical shells (quasi-uniform Fibonacci points per shell) inside of a sphere
+    let mut particles = vec![center];
+    let golden_angle = std::f64::consts::PI * (3.0 - 5.0f64.sqrt());
+    let mut shell_radius = sphere_radius;
+    while shell_radius > 0.5 * spacing {
+        let n = (4.0 * std::f64::consts::PI * (shell_radius / spacing).powi(2)).round() as usize;
+        for i in 0..n {
+            let z = 1.0 - 2.0 * (i as f64 + 0.5) / n as f64;
+            let rho = (1.0 - z * z).sqrt();
+            let phi = golden_angle * i as f64;
+            particles
+                .push(center + Vector3::new(rho * phi.cos(), rho * phi.sin(), z) * shell_radius);
+        }
+        shell_radius -= spacing;
+    }
+
+    let parameters = Parameters {
+        particle_radius,
+        rest_density: 1000.0,
+        compact_support_radius: 4.0 * particle_radius,
+        cube_size: 0.5 * particle_radius,
+        iso_surface_threshold: 0.6,
+        particle_aabb: None,
+        enable_multi_threading: true,
+        spatial_decomposition: None,
+        global_neighborhood_list: false,
+        vertex_provenance: None,
+        tabulated_kernel: None,
+        particle_reordering: None,
+    };
+    let reconstruction = reconstruct_surface::<i64, f64>(&particles, &parameters).unwrap();
+    let vertices = &reconstruction.mesh().vertices;
+    assert!(!vertices.is_empty());
+
+    let particle_rest_mass = 1000.0 * (4.0 / 3.0) * std::f64::consts::PI * particle_radius.powi(3);
+    let interpolator = SphInterpolator::new(
+        &particles,
+        reconstruction.particle_densities().unwrap(),
+        particle_rest_mass,
+        parameters.compact_support_radius,
+    );
+
+    // The curvature of a sphere is the inverse of its radius, the surface is slightly outside of the outermost particles
+    let mesh_radius =
+        vertices.iter().map(|v| (v - center).norm()).sum::<f64>() / vertices.len() as f64;
+    let curvatures = interpolator.interpolate_mean_curvature(vertices);
+    let mean_curvature = curvatures.iter().sum::<f64>() / curvatures.len() as f64;
+    assert!(curvatures.iter().all(|h| h.is_finite()));
+    assert!(
+        (mean_curvature * mesh_radius - 1.0).abs() < 0.05,
+        "mean curvature {} differs from the inverse radius {}",
+        mean_curvature,
+        1.0 / mesh_radius
+    );
+
+    // The curvature vanishes far away from the fluid
+    assert_eq!(
+        interpolator.interpolate_mean_curvature(&[Vector3::new(5.0, 5.0, 5.0)]),
+        vec![0.0]
+    );
+}